@@ -0,0 +1,312 @@
+use eyre::{eyre, ErrReport};
+use image::{GrayImage, Luma};
+
+use crate::byte;
+use crate::resource::ResourceFork;
+
+//
+// QuickDraw bitmap fonts. Each size of a font is an `NFNT` (or, in older files, `FONT`) resource
+// holding a "strike": every character's image side by side in one long bitmap, plus tables saying
+// where each character starts in the strike, how far to offset it and how far to advance after it.
+// A `FOND` resource ties the sizes (and styles) of a family together.
+//
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BitmapFont {
+    pub font_type: u16,
+    pub first_char: u8,
+    pub last_char: u8,
+    pub max_width: i16,
+    /// The furthest any character kerns to the left, as a negative number.
+    pub kern_max: i16,
+    pub rect_width: u16,
+    /// Height of the strike, which is also the ascent plus descent.
+    pub rect_height: u16,
+    pub ascent: i16,
+    pub descent: i16,
+    pub leading: i16,
+    /// Width of the strike in 16-bit words.
+    pub row_words: u16,
+    /// The strike, one bit per pixel, most significant bit on the left.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialize::base64_bytes"))]
+    pub strike: Vec<u8>,
+    /// Where each character's image starts in the strike, with one extra entry for the missing
+    /// character and one to mark where it ends.
+    pub locations: Vec<u16>,
+    /// Each character's offset (high byte) and advance width (low byte); 0xFFFF for characters
+    /// that aren't in the font.
+    pub offset_widths: Vec<u16>,
+}
+
+/// A single character from a `BitmapFont`.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BitmapGlyph {
+    /// Where the left edge of the image goes relative to the pen.
+    pub left: i32,
+    /// How far to move the pen afterwards.
+    pub advance: i32,
+    /// The character's image, `ascent + descent` high with the top row at the ascent. Black
+    /// pixels are 0, white pixels are 255.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialize::image_png"))]
+    pub image: GrayImage,
+}
+
+/// A font family, from a `FOND` resource.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FontFamily {
+    pub id: i16,
+    pub name: String,
+    pub ascent: i16,
+    pub descent: i16,
+    pub leading: i16,
+    pub entries: Vec<FontAssociation>,
+}
+
+/// One entry of a family's font association table: a size and style of the family, and the ID of
+/// the `NFNT` or `FONT` it's in. A size of 0 means an outline font.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FontAssociation {
+    pub size: u16,
+    pub style: u16,
+    pub id: i16,
+}
+
+impl BitmapFont {
+    pub fn from_bytes(b: &[u8]) -> Result<BitmapFont, ErrReport> {
+        if b.len() < 26 {
+            return Err(eyre!("Font is too short to be valid"));
+        }
+        let word = |offset: usize| byte::u16_from_u8(&b[offset..offset + 2]);
+        let font_type = word(0);
+        let first_char = word(2);
+        let last_char = word(4);
+        if first_char > last_char || last_char > 255 {
+            return Err(eyre!("Font has an invalid character range"));
+        }
+        let n_descent = word(10) as i16;
+        let rect_height = word(14);
+        let row_words = word(24);
+
+        let strike_start = 26;
+        let strike_end = strike_start + row_words as usize * 2 * rect_height as usize;
+        // one entry per character, one for the missing character, and one to mark the end.
+        let entries = (last_char - first_char) as usize + 3;
+        let locations_end = strike_end + entries * 2;
+        // the offset/width table is found by counting words from the owTLoc field itself; very
+        // large fonts keep the high word of that in nDescent.
+        let mut ow_offset = word(16) as usize;
+        if n_descent > 0 {
+            ow_offset |= (n_descent as usize) << 16;
+        }
+        let ow_start = 16 + ow_offset * 2;
+        if locations_end > b.len() || ow_start + entries * 2 > b.len() {
+            return Err(eyre!("Font tables are out of bounds"));
+        }
+
+        Ok(BitmapFont {
+            font_type,
+            first_char: first_char as u8,
+            last_char: last_char as u8,
+            max_width: word(6) as i16,
+            kern_max: word(8) as i16,
+            rect_width: word(12),
+            rect_height,
+            ascent: word(18) as i16,
+            descent: word(20) as i16,
+            leading: word(22) as i16,
+            row_words,
+            strike: b[strike_start..strike_end].to_vec(),
+            locations: (0..entries).map(|i| word(strike_end + i * 2)).collect(),
+            offset_widths: (0..entries).map(|i| word(ow_start + i * 2)).collect(),
+        })
+    }
+
+    /// The character with the given Mac OS Roman code, or the font's missing character symbol if
+    /// it doesn't have one. `None` only if the font doesn't have a missing character either.
+    pub fn glyph(&self, ch: u8) -> Option<BitmapGlyph> {
+        let missing = (self.last_char - self.first_char) as usize + 1;
+        let index = if ch >= self.first_char && ch <= self.last_char {
+            let index = (ch - self.first_char) as usize;
+            if self.offset_widths[index] == 0xFFFF {
+                missing
+            } else {
+                index
+            }
+        } else {
+            missing
+        };
+        let ow = self.offset_widths[index];
+        if ow == 0xFFFF {
+            return None;
+        }
+
+        let row_bytes = self.row_words as u32 * 2;
+        let start = (self.locations[index] as u32).min(row_bytes * 8);
+        let end = (self.locations[index + 1] as u32).clamp(start, row_bytes * 8);
+        let image = GrayImage::from_fn(end - start, self.rect_height as u32, |x, y| {
+            let bit = start + x;
+            let byte = self.strike[(y * row_bytes + bit / 8) as usize];
+            if byte & (0x80 >> (bit % 8)) != 0 {
+                Luma([0])
+            } else {
+                Luma([255])
+            }
+        });
+        Some(BitmapGlyph {
+            left: self.kern_max as i32 + (ow >> 8) as i32,
+            advance: (ow & 0xFF) as i32,
+            image,
+        })
+    }
+
+    /// Whether the font has its own image for a character, rather than the missing symbol.
+    pub fn has_char(&self, ch: u8) -> bool {
+        ch >= self.first_char
+            && ch <= self.last_char
+            && self.offset_widths[(ch - self.first_char) as usize] != 0xFFFF
+    }
+}
+
+impl FontFamily {
+    pub fn from_bytes(id: i16, name: &str, b: &[u8]) -> Result<FontFamily, ErrReport> {
+        if b.len() < 54 {
+            return Err(eyre!("Font family is too short to be valid"));
+        }
+        let word = |offset: usize| byte::u16_from_u8(&b[offset..offset + 2]);
+        // counts are stored minus one.
+        let count = word(52).wrapping_add(1) as usize;
+        if 54 + count * 6 > b.len() {
+            return Err(eyre!("Font association table is out of bounds"));
+        }
+        let entries = (0..count)
+            .map(|i| {
+                let entry = 54 + i * 6;
+                FontAssociation {
+                    size: word(entry),
+                    style: word(entry + 2),
+                    id: word(entry + 4) as i16,
+                }
+            })
+            .collect();
+        Ok(FontFamily {
+            id,
+            name: name.to_string(),
+            ascent: word(8) as i16,
+            descent: word(10) as i16,
+            leading: word(12) as i16,
+            entries,
+        })
+    }
+}
+
+impl ResourceFork {
+    /// Every font family in the fork.
+    pub fn font_families(&self) -> Vec<FontFamily> {
+        self.of_type("FOND")
+            .filter_map(|r| FontFamily::from_bytes(r.id, &r.name, &r.data).ok())
+            .collect()
+    }
+
+    /// The plain bitmap fonts of a family, by size. Families without a `FOND` are found the old
+    /// way, where the `FONT` for each size has the ID `family * 128 + size`.
+    pub fn bitmap_fonts(&self, family: &FontFamily) -> Vec<(u16, BitmapFont)> {
+        let mut fonts: Vec<(u16, BitmapFont)> = family
+            .entries
+            .iter()
+            .filter(|e| e.size != 0 && e.style == 0)
+            .filter_map(|e| {
+                let res = self.get("NFNT", e.id).or_else(|| self.get("FONT", e.id))?;
+                Some((e.size, BitmapFont::from_bytes(&res.data).ok()?))
+            })
+            .collect();
+        if fonts.is_empty() {
+            fonts = self
+                .of_type("FONT")
+                .filter(|r| r.id >= 0 && r.id as i32 / 128 == family.id as i32 && r.id % 128 != 0)
+                .filter_map(|r| Some(((r.id % 128) as u16, BitmapFont::from_bytes(&r.data).ok()?)))
+                .collect();
+        }
+        fonts.sort_by_key(|f| f.0);
+        fonts
+    }
+
+    /// The font family with the given name, including old-style families that are only a `FONT`
+    /// named after the family with ID `family * 128`.
+    pub fn font_family(&self, name: &str) -> Option<FontFamily> {
+        if let Some(fond) = self.get_named("FOND", name) {
+            return FontFamily::from_bytes(fond.id, &fond.name, &fond.data).ok();
+        }
+        let font = self
+            .of_type("FONT")
+            .find(|r| r.id % 128 == 0 && r.name.eq_ignore_ascii_case(name))?;
+        Some(FontFamily {
+            id: font.id / 128,
+            name: font.name.clone(),
+            ascent: 0,
+            descent: 0,
+            leading: 0,
+            entries: Vec::new(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Chicago 12, as bundled for drawing text.
+    fn system_12() -> BitmapFont {
+        BitmapFont::from_bytes(include_bytes!("../../hc-render/fonts/system-12.nfnt")).unwrap()
+    }
+
+    #[test]
+    fn reads_the_header() {
+        let font = system_12();
+        assert_eq!((font.first_char, font.last_char), (0, 255));
+        assert_eq!((font.ascent, font.descent, font.leading), (11, 3, 1));
+        assert_eq!(font.rect_height, 14);
+        assert_eq!(font.kern_max, -3);
+    }
+
+    #[test]
+    fn advance_and_left_come_from_the_offset_width_table() {
+        let font = system_12();
+        // `A` has an offset of 3 from the furthest kern, -3, and advances 9.
+        let a = font.glyph(b'A').unwrap();
+        assert_eq!((a.left, a.advance), (0, 9));
+        assert_eq!((a.image.width(), a.image.height()), (10, 14));
+        // `j` hangs a pixel to the left of the pen.
+        let j = font.glyph(b'j').unwrap();
+        assert_eq!((j.left, j.advance), (-1, 4));
+    }
+
+    #[test]
+    fn an_image_is_cut_from_the_strike() {
+        let i = system_12().glyph(b'i').unwrap();
+        assert_eq!(i.image.width(), 3);
+        // black down to the baseline, and white above the top of the tallest letters.
+        assert_eq!(i.image.get_pixel(0, 10).0, [0]);
+        assert_eq!(i.image.get_pixel(0, 0).0, [255]);
+        assert_eq!(i.image.get_pixel(2, 10).0, [255]);
+    }
+
+    #[test]
+    fn missing_characters_get_the_missing_symbol() {
+        let font = system_12();
+        assert!(!font.has_char(0x01));
+        let missing = font.glyph(0x01).unwrap();
+        assert_eq!((missing.left, missing.advance), (1, 8));
+        assert_eq!(missing.image.width(), 6);
+    }
+
+    #[test]
+    fn tables_past_the_end_are_an_error() {
+        let bytes = include_bytes!("../../hc-render/fonts/system-12.nfnt");
+        assert!(BitmapFont::from_bytes(&bytes[..bytes.len() / 2]).is_err());
+        assert!(BitmapFont::from_bytes(&bytes[..20]).is_err());
+    }
+}
@@ -1,13 +1,15 @@
 use eyre::ErrReport;
 
-use crate::byte::{self, byte_range};
+use crate::byte::byte_range;
 use crate::macroman::macroman_to_char;
 
 use super::data_layout::BackgroundLayout as ba;
-use super::part::Part;
+use super::part::{ContentEntry, Part};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Background {
+    pub id: u32,
     pub bitmap_id: u32,
     pub flags: u16,
 
@@ -22,6 +24,7 @@ pub struct Background {
 
 impl Background {
     pub fn from(b: &[u8]) -> Result<Self, ErrReport> {
+        let id = byte_range!(u32, b, ba::BlockID);
         let bitmap_id = byte_range!(u32, b, ba::BitmapID);
         let flags = byte_range!(u16, b, ba::Flags);
 
@@ -31,73 +34,48 @@ impl Background {
         let cards_num = byte_range!(u32, b, ba::CardNum);
 
         let part_num = byte_range!(u16, b, ba::PartNum);
+        let part_list_size = byte_range!(u32, b, ba::PartListSize);
 
         let part_content_num = byte_range!(u16, b, ba::PartContentNum);
         let part_content_list_size = byte_range!(u32, b, ba::PartContentListSize);
 
         let mut offset = ba::PartContentListSizeEnd();
-        let mut parts: Vec<Part> = Vec::new();
-        #[allow(unused_assignments)]
-        let mut part_size = 0;
-        for i in 0..part_num {
-            // i don't know
-            if offset + 0x02 >= parts.len() {
-                continue;
+        let (mut parts, _) = Part::vec_from(b, offset, part_num)?;
+        offset += part_list_size as usize;
+
+        // on a background, the content list only holds shared text.
+        let (contents, _) = ContentEntry::vec_from(b, offset, part_content_num)?;
+        offset += part_content_list_size as usize;
+        for entry in contents {
+            if let Some(part) = parts.iter_mut().find(|p| p.id == entry.id) {
+                part.contents.push(entry);
             }
-            part_size = byte::u16_from_u8(&b[offset..offset + 0x02]);
-            //println!("{}",part_size);
-            let part = match Part::from(
-                &b[(offset + (i * part_size) as usize)..(offset + ((i + 1) * part_size) as usize)],
-                part_content_num,
-                part_content_list_size,
-            ) {
-                // we have to manually check this error because there is an error this throws that we want to handle.
-                Ok(a) => a,
-                Err(err) => {
-                    if err.to_string() == "out of bounds" {
-                        // for some unknown reason, sometimes the part size is not correct.
-                        // this should be investigated. but for now, if it's ever problem, just load the rest of the block in.
-                        Part::from(
-                            &b[(offset + (i * part_size) as usize)..],
-                            part_content_num,
-                            part_content_list_size,
-                        )?
-                    } else {
-                        return Err(err.into());
-                    }
-                }
-            };
-            parts.push(part);
-            offset += part_size as usize;
-            // offset is now at the "optional OSA script data".
-            // skip this.
-            part_size = byte::u16_from_u8(&b[offset..offset + 0x02]);
-            offset += (part_size / 8) as usize;
         }
 
-        // name and stack script, both terminated by nil
+        // name and background script, both terminated by nil
         let mut stack: Vec<char> = Vec::new();
-        loop {
-            let ch = (&b)[offset];
+        while offset < b.len() {
+            let ch = b[offset];
+            offset += 1;
             if ch == 0 {
                 break;
             }
-            stack.push(macroman_to_char(ch)); // blindly unwrap because we can be certain it's a valid mac roman character
-            offset += 1;
+            stack.push(macroman_to_char(ch));
         }
-        let name: String = (&stack).iter().collect();
+        let name: String = stack.iter().collect();
         let mut stack: Vec<char> = Vec::new();
-        loop {
-            let ch = (&b)[offset];
+        while offset < b.len() {
+            let ch = b[offset];
             if ch == 0 {
                 break;
             }
-            stack.push(macroman_to_char(ch)); // blindly unwrap because we can be certain it's a valid mac roman character
+            stack.push(macroman_to_char(ch));
             offset += 1;
         }
-        let code: String = (&stack).iter().collect();
+        let code: String = stack.iter().collect();
 
         Ok(Background {
+            id,
             bitmap_id,
             flags,
             cards_num,
@@ -108,4 +86,9 @@ impl Background {
             script: code,
         })
     }
+
+    /// Whether the background's picture is shown.
+    pub fn show_picture(&self) -> bool {
+        self.flags & 0x2000 == 0
+    }
 }
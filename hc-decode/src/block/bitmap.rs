@@ -1,22 +1,80 @@
-use eyre::{eyre, ErrReport};
-use image::GrayImage;
+use image::{imageops, GrayImage, Luma};
 
 use std::error::Error;
 
+use crate::byte::byte_range;
+
 use super::data_layout::BitmapLayout as bi;
 use woba::decode;
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Bitmap {
+    /// (top, left, bottom, right)
+    pub card_rect: (u16, u16, u16, u16),
+    /// (top, left, bottom, right)
+    pub mask_rect: (u16, u16, u16, u16),
+    /// (top, left, bottom, right)
+    pub image_rect: (u16, u16, u16, u16),
+
+    /// Card-sized picture; black pixels are 0, white pixels are 255.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialize::image_png"))]
     pub image: GrayImage,
+    /// Card-sized mask; opaque pixels are 255, transparent pixels are 0.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialize::image_png"))]
+    pub mask: GrayImage,
 }
 
 impl Bitmap {
     pub fn from(b: &[u8]) -> Result<Self, Box<dyn Error>> {
+        let card_rect = (
+            byte_range!(u16, b, bi::CardTop),
+            byte_range!(u16, b, bi::CardLeft),
+            byte_range!(u16, b, bi::CardBottom),
+            byte_range!(u16, b, bi::CardRight),
+        );
+        let mask_rect = (
+            byte_range!(u16, b, bi::MaskTop),
+            byte_range!(u16, b, bi::MaskLeft),
+            byte_range!(u16, b, bi::MaskBottom),
+            byte_range!(u16, b, bi::MaskRight),
+        );
+        let image_rect = (
+            byte_range!(u16, b, bi::ImageTop),
+            byte_range!(u16, b, bi::ImageLeft),
+            byte_range!(u16, b, bi::ImageBottom),
+            byte_range!(u16, b, bi::ImageRight),
+        );
+
+        // the decoder reads the rectangles itself and paints the picture and mask into a buffer
+        // the size of the card rectangle, at the offsets given by the image and mask rectangles.
         let pic_chunk = &b[bi::Filler0Start()..];
-        let pic = decode(&pic_chunk)?;
+        let pic = decode(pic_chunk)?;
+
+        // the card rectangle is almost always at (0, 0), but if it isn't then the whole thing
+        // needs to be moved over so that it lines up with the card.
+        let (width, height) = (card_rect.3 as u32, card_rect.2 as u32);
+        let mut image = GrayImage::from_pixel(width, height, Luma([255]));
+        let mut mask = GrayImage::from_pixel(width, height, Luma([0]));
+        imageops::replace(
+            &mut image,
+            &pic.as_image()?,
+            card_rect.1 as i64,
+            card_rect.0 as i64,
+        );
+        imageops::replace(
+            &mut mask,
+            &pic.as_mask()?,
+            card_rect.1 as i64,
+            card_rect.0 as i64,
+        );
+
         Ok(Bitmap {
-            image: pic.as_image()?,
+            card_rect,
+            mask_rect,
+            image_rect,
+            image,
+            mask,
         })
     }
 }
@@ -1,18 +1,23 @@
-use eyre::{eyre, ErrReport};
-
 use std::error::Error;
 
 use super::data_layout::CardLayout as c;
-use super::part::Part;
-use crate::byte::{self, byte_range};
+use super::part::{ContentEntry, Part};
+use crate::byte::byte_range;
 use crate::macroman::macroman_to_char;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Card {
     pub id: u32,
     pub bitmap_block_id: u32,
+    pub background_id: u32,
     pub flags: u16,
     pub parts: Vec<Part>,
+    /// The text of background fields on this card, keyed by the background part's ID.
+    pub background_contents: Vec<ContentEntry>,
+    /// Whether the card is marked. This is kept in the stack's list of cards rather than the
+    /// card itself.
+    pub marked: bool,
 
     pub name: String,
     pub script: String,
@@ -20,85 +25,77 @@ pub struct Card {
 
 impl Card {
     pub fn from(b: &[u8]) -> Result<Self, Box<dyn Error>> {
-        /*for i in 0..b.len() {
-            println!("{:#09x}\t\t{}",i,b[i]);
-        }*/
         let id = byte_range!(u32, b, c::BlockID);
         let bitmap_block_id = byte_range!(u32, b, c::BitmapID);
         let flags = byte_range!(u16, b, c::Flags);
+        let background_id = byte_range!(u32, b, c::BackgroundID);
         let part_num = byte_range!(u16, b, c::PartNum);
+        let part_list_size = byte_range!(u32, b, c::PartListSize);
         let part_content_num = byte_range!(u16, b, c::PartContentNum);
         let part_content_list_size = byte_range!(u32, b, c::PartContentListSize);
 
         let mut offset = c::PartContentListSizeEnd();
-        let mut parts: Vec<Part> = Vec::new();
-        #[allow(unused_assignments)]
-        let mut part_size = 0;
-        for i in 0..part_num {
-            // i don't know
-            if offset + 0x02 >= parts.len() {
-                continue;
+        let (mut parts, _) = Part::vec_from(b, offset, part_num)?;
+        offset += part_list_size as usize;
+
+        // the content list holds the text of both our own parts and the background's fields.
+        let (contents, _) = ContentEntry::vec_from(b, offset, part_content_num)?;
+        offset += part_content_list_size as usize;
+        let mut background_contents = Vec::new();
+        for entry in contents {
+            if !entry.card_part {
+                background_contents.push(entry);
+            } else if let Some(part) = parts.iter_mut().find(|p| p.id == entry.id) {
+                part.contents.push(entry);
             }
-            part_size = byte::u16_from_u8(&b[offset..offset + 0x02]);
-            let part = match Part::from(
-                &b[(offset + (i * part_size) as usize)..(offset + ((i + 1) * part_size) as usize)],
-                part_content_num,
-                part_content_list_size,
-            ) {
-                // we have to manually check this error because there is an error this throws that we want to handle.
-                Ok(a) => a,
-                Err(err) => {
-                    if err.to_string() == "out of bounds" {
-                        // for some unknown reason, sometimes the part size is not correct.
-                        // this should be investigated. but for now, if it's ever problem, just load the rest of the block in.
-                        Part::from(
-                            &b[(offset + (i * part_size) as usize)..],
-                            part_content_num,
-                            part_content_list_size,
-                        )?
-                    } else {
-                        return Err(err.into());
-                    }
-                }
-            };
-            parts.push(part);
-            //offset += part_size as usize;
-            offset += part_size as usize;
-            // offset is now at the "optional OSA script data".
-            // skip this.
-            part_size = byte::u16_from_u8(&b[offset..offset + 0x02]);
-            offset += (part_size / 8) as usize;
         }
 
-        // name and stack script, both terminated by nil
+        // name and card script, both terminated by nil
         let mut stack: Vec<char> = Vec::new();
-        loop {
-            let ch = (&b)[offset];
+        while offset < b.len() {
+            let ch = b[offset];
+            offset += 1;
             if ch == 0 {
                 break;
             }
-            stack.push(macroman_to_char(ch)); // blindly unwrap because we can be certain it's a valid mac roman character
-            offset += 1;
+            stack.push(macroman_to_char(ch));
         }
-        let name: String = (&stack).iter().collect();
+        let name: String = stack.iter().collect();
         let mut stack: Vec<char> = Vec::new();
-        loop {
-            let ch = (&b)[offset];
+        while offset < b.len() {
+            let ch = b[offset];
             if ch == 0 {
                 break;
             }
-
-            stack.push(macroman_to_char(ch)); // blindly unwrap because we can be certain it's a valid mac roman character
+            stack.push(macroman_to_char(ch));
             offset += 1;
         }
-        let code: String = (&stack).iter().collect();
+        let code: String = stack.iter().collect();
         Ok(Card {
             id,
             bitmap_block_id,
+            background_id,
             flags,
             parts,
+            background_contents,
+            marked: false,
             name,
             script: code,
         })
     }
+
+    /// The contents of a part as seen on this card. `part` is one of this card's parts, or one of
+    /// its background's parts if `background` is set.
+    pub fn contents_of<'a>(&'a self, part: &'a Part, background: bool) -> Option<&'a ContentEntry> {
+        if background && !part.shared_text {
+            self.background_contents.iter().find(|c| c.id == part.id)
+        } else {
+            part.contents.first()
+        }
+    }
+
+    /// Whether the card's picture is shown.
+    pub fn show_picture(&self) -> bool {
+        self.flags & 0x2000 == 0
+    }
 }
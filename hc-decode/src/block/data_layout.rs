@@ -286,6 +286,60 @@ enum StyleTableLayout {
     NextStyleID,
 }
 
+#[layout(ListLayout)]
+enum ListLayout {
+    #[ahead(4)]
+    BlockSize,
+    #[ahead(4)]
+    BlockType,
+    #[ahead(4)]
+    BlockID,
+    #[ahead(4)]
+    Filler0,
+    #[ahead(4)]
+    PageNum,
+    #[ahead(4)]
+    PageSize,
+    #[ahead(4)]
+    CardNum,
+    #[ahead(2)]
+    CardReferenceSize,
+    #[ahead(18)]
+    Unknown,
+}
+
+#[layout(PageLayout)]
+enum PageLayout {
+    #[ahead(4)]
+    BlockSize,
+    #[ahead(4)]
+    BlockType,
+    #[ahead(4)]
+    BlockID,
+    #[ahead(4)]
+    Filler0,
+    #[ahead(4)]
+    ListID,
+    #[ahead(4)]
+    Checksum,
+}
+
+#[layout(FontTableLayout)]
+enum FontTableLayout {
+    #[ahead(4)]
+    BlockSize,
+    #[ahead(4)]
+    BlockType,
+    #[ahead(4)]
+    BlockID,
+    #[ahead(4)]
+    Filler0,
+    #[ahead(4)]
+    FontNum,
+    #[ahead(4)]
+    Filler1,
+}
+
 #[layout(StyleLayout)]
 enum StyleLayout {
     #[ahead(4)]
@@ -298,6 +352,8 @@ enum StyleLayout {
     StyleFlags,
     #[ahead(2)]
     FontSize,
+    #[ahead(6)]
+    Unknown2,
 }
 
 #[layout(BackgroundLayout)]
@@ -333,3 +389,100 @@ enum BackgroundLayout {
     #[ahead(4)]
     PartContentListSize,
 }
+
+// print settings (PRNT). Only the report template list is documented; the margins and spacing are
+// where HyperCard's "Print Stack" settings appear to be kept, and are a best guess.
+#[layout(PrintSettingsLayout)]
+enum PrintSettingsLayout {
+    #[ahead(4)]
+    BlockSize,
+    #[ahead(4)]
+    BlockType,
+    #[ahead(4)]
+    BlockID,
+    #[ahead(4)]
+    Filler0,
+    #[ahead(4)]
+    Unknown1,
+    #[ahead(2)]
+    MarginTop,
+    #[ahead(2)]
+    MarginLeft,
+    #[ahead(2)]
+    MarginBottom,
+    #[ahead(2)]
+    MarginRight,
+    #[ahead(2)]
+    SpacingHeight,
+    #[ahead(2)]
+    SpacingWidth,
+    #[ahead(16)]
+    Unknown2,
+    #[ahead(2)]
+    TemplateNum,
+}
+
+// report templates (PRST).
+#[layout(ReportTemplateLayout)]
+enum ReportTemplateLayout {
+    #[ahead(4)]
+    BlockSize,
+    #[ahead(4)]
+    BlockType,
+    #[ahead(4)]
+    BlockID,
+    #[ahead(4)]
+    Filler0,
+    #[ahead(4)]
+    Unknown1,
+    #[ahead(2)]
+    Units,
+    #[ahead(2)]
+    MarginTop,
+    #[ahead(2)]
+    MarginLeft,
+    #[ahead(2)]
+    MarginBottom,
+    #[ahead(2)]
+    MarginRight,
+    #[ahead(2)]
+    SpacingHeight,
+    #[ahead(2)]
+    SpacingWidth,
+    #[ahead(2)]
+    CellHeight,
+    #[ahead(2)]
+    CellWidth,
+    #[ahead(2)]
+    Flags,
+    #[ahead(256)]
+    Header,
+    #[ahead(2)]
+    ItemNum,
+}
+
+#[layout(ReportItemLayout)]
+enum ReportItemLayout {
+    #[ahead(2)]
+    ItemSize,
+    #[ahead(2)]
+    Top,
+    #[ahead(2)]
+    Left,
+    #[ahead(2)]
+    Bottom,
+    #[ahead(2)]
+    Right,
+    #[ahead(2)]
+    Flags,
+    #[ahead(4)]
+    Unknown1,
+    #[ahead(2)]
+    TextFont,
+    #[ahead(2)]
+    TextSize,
+    #[ahead(2)]
+    TextStyle,
+    #[ahead(2)]
+    TextAlign,
+}
@@ -1,5 +1,44 @@
+use eyre::{eyre, ErrReport};
+
+use crate::byte::{self, byte_range};
+use crate::macroman::macroman_to_char;
+
+use super::data_layout::FontTableLayout as ft;
+
+/// An entry in the stack's font table, which maps the font IDs used by parts and styles to the
+/// names of the fonts they were when the stack was saved.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Font {
     pub id: u16,
     pub name: String,
 }
+
+impl Font {
+    pub fn vec_from(b: &[u8]) -> Result<Vec<Self>, ErrReport> {
+        let font_num = byte_range!(u32, b, ft::FontNum);
+        let mut offset = ft::Filler1End();
+        let mut fonts = Vec::new();
+        for _ in 0..font_num {
+            if offset + 2 > b.len() {
+                return Err(eyre!("font table is shorter than its font count"));
+            }
+            let id = byte::u16_from_u8(&b[offset..offset + 2]);
+            offset += 2;
+            let mut name = String::new();
+            loop {
+                match b.get(offset) {
+                    Some(0) => break,
+                    Some(ch) => name.push(macroman_to_char(*ch)),
+                    None => return Err(eyre!("out of bounds")),
+                }
+                offset += 1;
+            }
+            // skip the nil, then pad to an even offset.
+            offset += 1;
+            offset += offset % 2;
+            fonts.push(Font { id, name });
+        }
+        Ok(fonts)
+    }
+}
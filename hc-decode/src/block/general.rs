@@ -1,17 +1,33 @@
 use std::collections::HashMap;
 
 use super::{
-    background::Background, bitmap::Bitmap, card::Card, font::Font, part::Part, style::Style,
+    background::Background,
+    bitmap::Bitmap,
+    card::Card,
+    font::Font,
+    part::Part,
+    print::{PrintFormat, PrintSettings, ReportTemplate},
+    style::Style,
 };
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Block {
     Background(Background),
     Bitmap(Bitmap),
     Card(Card),
-    Font(Font),
+    Font(Vec<Font>),
     Part(Part),
-    Style(HashMap<u32, Style>),
+    PrintFormat(PrintFormat),
+    PrintSettings(PrintSettings),
+    ReportTemplate(ReportTemplate),
+    Style(
+        #[cfg_attr(
+            feature = "serde",
+            serde(serialize_with = "crate::serialize::sorted_map")
+        )]
+        HashMap<u32, Style>,
+    ),
 }
 
 impl Block {
@@ -30,6 +46,12 @@ impl Block {
     pub fn is_part(&self) -> bool {
         matches!(&self, Block::Part(_))
     }
+    pub fn is_print_settings(&self) -> bool {
+        matches!(&self, Block::PrintSettings(_))
+    }
+    pub fn is_report_template(&self) -> bool {
+        matches!(&self, Block::ReportTemplate(_))
+    }
     pub fn is_style(&self) -> bool {
         matches!(&self, Block::Style(_))
     }
@@ -54,7 +76,7 @@ impl Block {
             panic!("not a card");
         }
     }
-    pub fn get_font<'a>(&self) -> Font {
+    pub fn get_font<'a>(&self) -> Vec<Font> {
         if let Block::Font(a) = &self {
             a.clone()
         } else {
@@ -68,6 +90,20 @@ impl Block {
             panic!("not a part");
         }
     }
+    pub fn get_print_settings(&self) -> PrintSettings {
+        if let Block::PrintSettings(a) = &self {
+            a.clone()
+        } else {
+            panic!("not print settings");
+        }
+    }
+    pub fn get_report_template(&self) -> ReportTemplate {
+        if let Block::ReportTemplate(a) = &self {
+            a.clone()
+        } else {
+            panic!("not a report template");
+        }
+    }
     pub fn get_style<'a>(&self) -> HashMap<u32, Style> {
         if let Block::Style(a) = &self {
             a.clone()
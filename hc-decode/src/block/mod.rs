@@ -7,6 +7,7 @@ pub mod data_layout;
 pub mod font;
 mod general;
 pub mod part;
+pub mod print;
 pub mod stack;
 pub mod style;
 pub use general::*;
@@ -8,9 +8,47 @@ use super::data_layout::PartContentEntryLayout as pc;
 use super::data_layout::PartContentEntryStyleLayout as st;
 use super::data_layout::PartLayout as p;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Part {
+    pub id: u16,
     pub ty: PartType,
+    pub visible: bool,
+    /// Buttons only; fields are always enabled.
+    pub enabled: bool,
+
+    /// Buttons only.
+    pub show_name: bool,
+    /// Buttons only.
+    pub hilite: bool,
+    /// Buttons only.
+    pub auto_hilite: bool,
+    /// Buttons only; 0 means no icon.
+    pub icon_id: i16,
+    /// Fields only.
+    pub lock_text: bool,
+    /// Fields only.
+    pub dont_wrap: bool,
+    /// Fields only.
+    pub dont_search: bool,
+    /// Fields only.
+    pub shared_text: bool,
+    /// Fields only.
+    pub fixed_line_height: bool,
+    /// Fields only.
+    pub auto_tab: bool,
+    /// Fields only.
+    pub auto_select: bool,
+    /// Fields only.
+    pub show_lines: bool,
+    /// Fields only.
+    pub wide_margins: bool,
+    /// Fields only.
+    pub multiple_lines: bool,
+    /// The first selected line of a field, or the selected item of a popup button, starting at 1.
+    pub selected_line: u16,
+    /// Scrolling fields only: how many pixels the text is scrolled up by.
+    pub scroll: u16,
 
     pub position: (u16, u16, u16, u16),
     pub style: PartStyle,
@@ -20,31 +58,42 @@ pub struct Part {
 
     pub font_id: u16,
     pub font_size: u16,
+    /// QuickDraw text face: bold, italic, underline, outline, shadow, condense, extend.
+    pub text_style: u8,
     pub text_flags: u8,
 
     pub line_height: u16,
     pub name: String,
     pub script: String,
 
+    /// Contents stored alongside the part in its own layer. For background fields this is the
+    /// shared text; the text that differs from card to card lives in `Card::background_contents`.
     pub contents: Vec<ContentEntry>,
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ContentEntry {
-    id: u16,
-    styles: Option<Vec<ContentEntryStyle>>,
-    text: String,
+    pub id: u16,
+    /// Whether this belongs to a card part, as opposed to a background part.
+    pub card_part: bool,
+    pub styles: Option<Vec<ContentEntryStyle>>,
+    pub text: String,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PartType {
+    #[default]
     Button,
     Field,
     Unknown,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum PartStyle {
+    #[default]
     Transparent,
     Opaque,
     Rectangle,
@@ -60,8 +109,10 @@ pub enum PartStyle {
     Unknown,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TextAlignment {
+    #[default]
     Left,
     Center,
     Right,
@@ -72,22 +123,55 @@ pub enum TextAlignment {
 }
 
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ContentEntryStyle {
-    text_position: u16,
-    id: u16,
+    pub text_position: u16,
+    pub id: u16,
 }
 
 impl Part {
-    pub fn from(
+    /// Parse `part_num` parts from the part list starting at `offset`, returning them along with
+    /// the offset of whatever follows the list.
+    pub fn vec_from(
         b: &[u8],
-        part_content_num: u16,
-        part_content_list_size: u32,
-    ) -> Result<Self, ErrReport> {
-        let ty = match byte_range!(u16, b, p::PartID) {
-            0 => PartType::Button,
-            1 => PartType::Field,
+        mut offset: usize,
+        part_num: u16,
+    ) -> Result<(Vec<Self>, usize), ErrReport> {
+        let mut parts: Vec<Part> = Vec::new();
+        for _ in 0..part_num {
+            if offset + 0x02 >= b.len() {
+                return Err(eyre!("out of bounds"));
+            }
+            let part_size = byte::u16_from_u8(&b[offset..offset + 0x02]) as usize;
+            if offset + part_size > b.len() {
+                return Err(eyre!("out of bounds"));
+            }
+            parts.push(Part::from(&b[offset..offset + part_size])?);
+            offset += part_size;
+        }
+        Ok((parts, offset))
+    }
+
+    pub fn from(b: &[u8]) -> Result<Self, ErrReport> {
+        let id = byte_range!(u16, b, p::PartID);
+        let ty = match &b[p::PartTypeStart()] {
+            1 => PartType::Button,
+            2 => PartType::Field,
             _ => PartType::Unknown,
         };
+        let flags = b[p::FlagsStart()];
+        let visible = flags & 0x80 == 0;
+        let is_button = matches!(ty, PartType::Button);
+        let is_field = matches!(ty, PartType::Field);
+        let enabled = !(is_button && flags & 0x01 != 0);
+        let lock_text = is_field && flags & 0x01 != 0;
+        let auto_tab = is_field && flags & 0x02 != 0;
+        // this one's stored inverted.
+        let fixed_line_height = is_field && flags & 0x04 == 0;
+        let shared_text = is_field && flags & 0x08 != 0;
+        let dont_search = is_field && flags & 0x10 != 0;
+        let dont_wrap = is_field && flags & 0x20 != 0;
+
         let position = (
             byte_range!(u16, b, p::PartRectTop),
             byte_range!(u16, b, p::PartRectLeft),
@@ -125,102 +209,176 @@ impl Part {
 
         let font_id = byte_range!(u16, b, p::TextFontID);
         let font_size = byte_range!(u16, b, p::TextSize);
-        let text_flags = *&b[p::TextFlagsStart()];
+        let text_style = b[p::TextStyleFlagsStart()];
+        let text_flags = b[p::TextFlagsStart()];
+        let show_name = is_button && text_flags & 0x80 != 0;
+        let hilite = is_button && text_flags & 0x40 != 0;
+        let auto_hilite = is_button && text_flags & 0x20 != 0;
+        let auto_select = is_field && text_flags & 0x80 != 0;
+        let show_lines = is_field && text_flags & 0x40 != 0;
+        let wide_margins = is_field && text_flags & 0x20 != 0;
+        let multiple_lines = is_field && text_flags & 0x10 != 0;
+
+        // buttons keep their icon where fields keep the first selected line, and popups (which
+        // can't have an icon) keep their selected item.
+        let icon_or_line = byte_range!(u16, b, p::IconIDOrFirstSelectedLine);
+        let is_popup = matches!(style, PartStyle::Popup);
+        let icon_id = if is_button && !is_popup {
+            icon_or_line as i16
+        } else {
+            0
+        };
+        let selected_line = if is_button && !is_popup {
+            0
+        } else {
+            icon_or_line
+        };
+
+        // a scrolling field keeps its scroll where a popup keeps its title width.
+        let scroll = if is_field && matches!(style, PartStyle::Scrolling) {
+            title_width
+        } else {
+            0
+        };
 
         let line_height = byte_range!(u16, b, p::LineHeight);
 
         let mut offset = p::LineHeightEnd();
-        // name and stack script, both terminated by nil
+        // name and script, both terminated by nil, with a filler byte in between.
         let mut stack: Vec<char> = Vec::new();
         loop {
             if offset >= b.len() {
                 return Err(eyre!("out of bounds"));
             }
-            let ch = (&b)[offset];
+            let ch = b[offset];
             if ch == 0 {
                 break;
             }
             stack.push(macroman_to_char(ch));
             offset += 1;
         }
-        println!("\n");
-        let name: String = (&stack).iter().collect();
+        offset += 2;
+        let name: String = stack.iter().collect();
         let mut stack: Vec<char> = Vec::new();
-        loop {
-            if offset >= b.len() {
-                return Err(eyre!("out of bounds"));
-            }
-            let ch = (&b)[offset];
+        while offset < b.len() {
+            let ch = b[offset];
             if ch == 0 {
                 break;
             }
             stack.push(macroman_to_char(ch));
             offset += 1;
         }
-        let code: String = (&stack).iter().collect();
+        let code: String = stack.iter().collect();
 
-        let mut content_entries: Vec<ContentEntry> = Vec::new();
+        Ok(Part {
+            id,
+            ty,
+            visible,
+            enabled,
+            show_name,
+            hilite,
+            auto_hilite,
+            icon_id,
+            lock_text,
+            dont_wrap,
+            dont_search,
+            shared_text,
+            fixed_line_height,
+            auto_tab,
+            auto_select,
+            show_lines,
+            wide_margins,
+            multiple_lines,
+            selected_line,
+            scroll,
+            position,
+            style,
+            title_width,
+            text_alignment,
+            font_id,
+            font_size,
+            text_style,
+            text_flags,
+            line_height,
+            name,
+            script: code,
+            contents: Vec::new(),
+        })
+    }
+
+    /// The part with the given ID in a layer's part list.
+    pub fn find(parts: &[Part], id: u16) -> Option<&Part> {
+        parts.iter().find(|p| p.id == id)
+    }
 
-        // part content entry
+    pub fn find_mut(parts: &mut [Part], id: u16) -> Option<&mut Part> {
+        parts.iter_mut().find(|p| p.id == id)
+    }
+}
+
+impl ContentEntry {
+    /// Parse `part_content_num` entries from the part content list starting at `offset`,
+    /// returning them along with the offset of whatever follows the list.
+    pub fn vec_from(
+        b: &[u8],
+        mut offset: usize,
+        part_content_num: u16,
+    ) -> Result<(Vec<Self>, usize), ErrReport> {
+        let mut content_entries: Vec<ContentEntry> = Vec::new();
         for _ in 0..part_content_num {
-            let block = &b[offset..offset + part_content_list_size as usize];
-            let id = byte_range!(u16, block, pc::PartID);
+            if offset + pc::StyleLengthByte2End() > b.len() {
+                return Err(eyre!("out of bounds"));
+            }
+            let block = &b[offset..];
+            // card parts are stored with their ID negated.
+            let raw_id = byte_range!(u16, block, pc::PartID) as i16;
+            let (id, card_part) = (raw_id.unsigned_abs(), raw_id < 0);
+            let entry_length = byte_range!(u16, block, pc::EntryLength) as usize;
+            let data_start = pc::EntryLengthEnd();
+            let data_end = data_start + entry_length;
+            if data_end > block.len() {
+                return Err(eyre!("out of bounds"));
+            }
 
-            let tmp = &b[pc::PlainTextMarkerOrStyleLengthByte1Start()];
             // if that first byte is 0 then there's no styles.
             let mut styles: Option<Vec<ContentEntryStyle>> = None;
-            let mut noffset = offset;
-            if *tmp != 0 {
-                noffset += pc::StyleLengthByte2End();
-                let mut tstyles: Vec<ContentEntryStyle> = Vec::new();
+            let mut noffset = data_start + 1;
+            if block[pc::PlainTextMarkerOrStyleLengthByte1Start()] != 0 {
                 // get the 'style length'; but negate the first bit because it's always set.
                 let mut style_length = byte::u16_from_u8(
-                    &b[pc::PlainTextMarkerOrStyleLengthByte1Start()..pc::StyleLengthByte2End()],
+                    &block[pc::PlainTextMarkerOrStyleLengthByte1Start()..pc::StyleLengthByte2End()],
                 );
                 style_length &= i16::MAX as u16;
-                style_length /= 4;
-                for _ in 0..style_length {
-                    let styleblock = &b[noffset..noffset + 0x04 as usize];
+                noffset = data_start + style_length as usize;
+                let mut tstyles: Vec<ContentEntryStyle> = Vec::new();
+                let mut soffset = pc::StyleLengthByte2End();
+                while soffset + 0x04 <= noffset.min(data_end) {
+                    let styleblock = &block[soffset..soffset + 0x04];
                     let text_position = byte_range!(u16, styleblock, st::TextPosition);
                     let id2 = byte_range!(u16, styleblock, st::StyleID);
                     tstyles.push(ContentEntryStyle {
                         text_position,
                         id: id2,
                     });
-                    noffset += 0x04 as usize;
+                    soffset += 0x04;
                 }
                 styles = Some(tstyles);
             }
-            // name, both terminated by nil
-            let mut stack: Vec<char> = Vec::new();
-            loop {
-                let ch = (&b)[noffset];
-                if ch == 0 {
-                    break;
-                }
-                stack.push(macroman_to_char(ch)); // blindly unwrap because we can be certain it's a valid mac roman character
-                noffset += 1;
-            }
-            let text: String = (&stack).iter().collect();
-            content_entries.push(ContentEntry { id, styles, text });
-            offset += part_content_list_size as usize;
+            // the text runs to the end of the entry, and may or may not be terminated by nil.
+            let text: String = block[noffset.min(data_end)..data_end]
+                .iter()
+                .take_while(|ch| **ch != 0)
+                .map(|ch| macroman_to_char(*ch))
+                .collect();
+            content_entries.push(ContentEntry {
+                id,
+                card_part,
+                styles,
+                text,
+            });
+            // entries are padded to an even length.
+            offset += data_end + (data_end % 2);
         }
-
-        println!("\n===========");
-
-        Ok(Part {
-            ty,
-            position,
-            style,
-            title_width,
-            text_alignment,
-            font_id,
-            font_size,
-            text_flags,
-            line_height,
-            name,
-            script: code,
-            contents: content_entries,
-        })
+        Ok((content_entries, offset))
     }
 }
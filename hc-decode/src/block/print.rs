@@ -0,0 +1,227 @@
+use eyre::{eyre, ErrReport};
+
+use crate::byte::{self, byte_range};
+use crate::macroman::macroman_to_char;
+
+use super::data_layout::PrintSettingsLayout as pr;
+use super::data_layout::ReportItemLayout as ri;
+use super::data_layout::ReportTemplateLayout as rt;
+
+//
+// Printing. The stack's print settings (PRNT) hold how "Print Stack" lays cards out on the page
+// and the list of report templates, and each report template (PRST) is a block of its own with
+// the items that make up one cell of the report. PRFT blocks are also to do with printing but
+// aren't documented at all, so they're kept as they are.
+//
+// No stack we have to test against has a report template, so that part follows the published
+// notes on the format and hasn't been checked against a real file.
+//
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PrintSettings {
+    pub id: u32,
+    /// (top, left, bottom, right), in points.
+    pub margins: (u16, u16, u16, u16),
+    /// Space between cards printed several to a page: (height, width), in points.
+    pub spacing: (u16, u16),
+    pub templates: Vec<ReportTemplateEntry>,
+}
+
+/// An entry in the print settings' list of report templates.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReportTemplateEntry {
+    pub id: u32,
+    pub name: String,
+}
+
+/// The units a report template was designed in. Measurements are stored in points whatever the
+/// units are; this is only what the report dialog shows.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ReportUnits {
+    Centimeters,
+    Millimeters,
+    Inches,
+    Points,
+    Unknown(u16),
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReportTemplate {
+    pub id: u32,
+    /// From the print settings' list of templates.
+    pub name: String,
+    pub units: ReportUnits,
+    /// (top, left, bottom, right), in points.
+    pub margins: (u16, u16, u16, u16),
+    /// Space between cells: (height, width), in points.
+    pub spacing: (u16, u16),
+    /// The size of the cell each card is printed in: (height, width), in points.
+    pub cell_size: (u16, u16),
+    pub flags: u16,
+    /// Printed at the top of every page.
+    pub header: String,
+    pub items: Vec<ReportItem>,
+}
+
+/// One column of a report: where in the cell it goes, what it shows and how.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReportItem {
+    /// (top, left, bottom, right), relative to the cell.
+    pub rect: (u16, u16, u16, u16),
+    pub flags: u16,
+    pub font: u16,
+    pub size: u16,
+    pub style: u16,
+    /// 0 for left, 1 for center, -1 for right, like a field's textAlign.
+    pub align: i16,
+    /// The HyperTalk expression the item shows, usually a field, e.g. `field "Name"`.
+    pub contents: String,
+}
+
+/// A printing block that hasn't been worked out.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PrintFormat {
+    pub id: u32,
+    /// Everything after the block header.
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialize::base64_bytes"))]
+    pub data: Vec<u8>,
+}
+
+// each entry in the template list is the template's ID and its name, as a 31 character Pascal
+// string.
+const TEMPLATE_ENTRY_SIZE: usize = 36;
+
+impl PrintSettings {
+    pub fn from(b: &[u8]) -> Result<Self, ErrReport> {
+        if b.len() < pr::TemplateNumEnd() {
+            return Err(eyre!("print settings block is too short"));
+        }
+        let template_num = byte_range!(u16, b, pr::TemplateNum) as usize;
+        let mut templates = Vec::new();
+        for i in 0..template_num {
+            let entry = pr::TemplateNumEnd() + i * TEMPLATE_ENTRY_SIZE;
+            if entry + TEMPLATE_ENTRY_SIZE > b.len() {
+                return Err(eyre!(
+                    "print settings are shorter than their template count"
+                ));
+            }
+            templates.push(ReportTemplateEntry {
+                id: byte::u32_from_u8(&b[entry..entry + 4]),
+                name: pascal_string(&b[entry + 4..entry + TEMPLATE_ENTRY_SIZE]),
+            });
+        }
+        Ok(PrintSettings {
+            id: byte_range!(u32, b, pr::BlockID),
+            margins: (
+                byte_range!(u16, b, pr::MarginTop),
+                byte_range!(u16, b, pr::MarginLeft),
+                byte_range!(u16, b, pr::MarginBottom),
+                byte_range!(u16, b, pr::MarginRight),
+            ),
+            spacing: (
+                byte_range!(u16, b, pr::SpacingHeight),
+                byte_range!(u16, b, pr::SpacingWidth),
+            ),
+            templates,
+        })
+    }
+}
+
+impl ReportTemplate {
+    pub fn from(b: &[u8]) -> Result<Self, ErrReport> {
+        if b.len() < rt::ItemNumEnd() {
+            return Err(eyre!("report template block is too short"));
+        }
+        let item_num = byte_range!(u16, b, rt::ItemNum) as usize;
+        let mut offset = rt::ItemNumEnd();
+        let mut items = Vec::new();
+        for _ in 0..item_num {
+            if offset + ri::TextAlignEnd() > b.len() {
+                return Err(eyre!("report template is shorter than its item count"));
+            }
+            let item = &b[offset..];
+            let size = byte_range!(u16, item, ri::ItemSize) as usize;
+            if size < ri::TextAlignEnd() || offset + size > b.len() {
+                return Err(eyre!("report item has an invalid size"));
+            }
+            items.push(ReportItem {
+                rect: (
+                    byte_range!(u16, item, ri::Top),
+                    byte_range!(u16, item, ri::Left),
+                    byte_range!(u16, item, ri::Bottom),
+                    byte_range!(u16, item, ri::Right),
+                ),
+                flags: byte_range!(u16, item, ri::Flags),
+                font: byte_range!(u16, item, ri::TextFont),
+                size: byte_range!(u16, item, ri::TextSize),
+                style: byte_range!(u16, item, ri::TextStyle),
+                align: byte_range!(i16, item, ri::TextAlign),
+                contents: c_string(&item[ri::TextAlignEnd()..size]),
+            });
+            // items are padded to an even length.
+            offset += size + size % 2;
+        }
+
+        Ok(ReportTemplate {
+            id: byte_range!(u32, b, rt::BlockID),
+            name: String::new(),
+            units: match byte_range!(u16, b, rt::Units) {
+                0 => ReportUnits::Centimeters,
+                1 => ReportUnits::Millimeters,
+                2 => ReportUnits::Inches,
+                3 => ReportUnits::Points,
+                n => ReportUnits::Unknown(n),
+            },
+            margins: (
+                byte_range!(u16, b, rt::MarginTop),
+                byte_range!(u16, b, rt::MarginLeft),
+                byte_range!(u16, b, rt::MarginBottom),
+                byte_range!(u16, b, rt::MarginRight),
+            ),
+            spacing: (
+                byte_range!(u16, b, rt::SpacingHeight),
+                byte_range!(u16, b, rt::SpacingWidth),
+            ),
+            cell_size: (
+                byte_range!(u16, b, rt::CellHeight),
+                byte_range!(u16, b, rt::CellWidth),
+            ),
+            flags: byte_range!(u16, b, rt::Flags),
+            header: c_string(byte_range!(all, b, rt::Header)),
+            items,
+        })
+    }
+}
+
+impl PrintFormat {
+    pub fn from(b: &[u8]) -> Result<Self, ErrReport> {
+        if b.len() < pr::Filler0End() {
+            return Err(eyre!("print block is too short"));
+        }
+        Ok(PrintFormat {
+            id: byte_range!(u32, b, pr::BlockID),
+            data: b[pr::Filler0End()..].to_vec(),
+        })
+    }
+}
+
+fn pascal_string(b: &[u8]) -> String {
+    let len = (*b.first().unwrap_or(&0) as usize).min(b.len().saturating_sub(1));
+    b[1..1 + len]
+        .iter()
+        .map(|ch| macroman_to_char(*ch))
+        .collect()
+}
+
+fn c_string(b: &[u8]) -> String {
+    b.iter()
+        .take_while(|ch| **ch != 0)
+        .map(|ch| macroman_to_char(*ch))
+        .collect()
+}
@@ -12,23 +12,29 @@ use crate::block::bitmap::Bitmap;
 use crate::byte;
 use crate::byte::byte_range;
 use crate::macroman::macroman_to_char;
+use crate::resource::ResourceFork;
 
 use super::background::Background;
 use super::card::Card;
 use super::font::Font;
+use super::print::{PrintFormat, PrintSettings, ReportTemplate};
 use super::style::Style;
 
 use super::data_layout::BlockLayoutGeneric as gen;
+use super::data_layout::ListLayout as li;
+use super::data_layout::PageLayout as pg;
 use super::data_layout::StackDataLayout as st;
 
 use super::general::Block;
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum StackFormat {
     NotHyperCard,
     PreReleaseHyperCard1x,
     HyperCard1x,
     PreReleaseHyperCard2x,
+    #[default]
     HyperCard2x,
     Unsupported,
 }
@@ -67,6 +73,8 @@ impl Display for HyperCardVersionState {
     }
 }
 
+#[derive(Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct HypercardVersion(u32);
 impl HypercardVersion {
     pub fn whole(&self) -> u32 {
@@ -123,13 +131,18 @@ impl Debug for HypercardVersion {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Stack {
     pub format: StackFormat,
 
     pub backgrounds: Vec<Background>,
     pub first_background: Background,
 
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::serialize::sorted_map")
+    )]
     pub objects: HashMap<u32, Block>,
 
     pub cards: Vec<Card>,
@@ -152,27 +165,37 @@ pub struct Stack {
     pub coords: (u16, u16),
 
     pub fonts: Vec<Font>,
+    #[cfg_attr(
+        feature = "serde",
+        serde(serialize_with = "crate::serialize::sorted_map")
+    )]
     pub styles: HashMap<u32, Style>,
 
+    pub print_settings: Option<PrintSettings>,
+    pub report_templates: Vec<ReportTemplate>,
+
     /// width, height
     pub size: (u16, u16),
 
     pub script: String,
+
+    /// The stack's resource fork, if one could be found next to it.
+    pub resources: Option<ResourceFork>,
 }
 
 impl Stack {
     pub fn from_path(path: &Path) -> Result<Stack, ErrReport> {
-        Self::from_file(File::open(path)?)
+        let mut stack = Self::from_file(File::open(path)?)?;
+        stack.resources = ResourceFork::for_path(path).ok();
+        Ok(stack)
     }
     pub fn from_file(mut file: File) -> Result<Stack, ErrReport> {
-        println!("from_file");
         let len = file.metadata()?.len();
         let b: &mut Vec<u8> = &mut vec![0; len as usize];
         File::read(&mut file, b)?;
         Self::from_bytes(&b)
     }
     pub fn from_bytes(bytes: &[u8]) -> Result<Stack, ErrReport> {
-        println!("from_bytes");
         // if the size of the file isn't even 8 bytes, it's invalid.
         if bytes.len() < 8 {
             return Err(eyre!(
@@ -190,10 +213,6 @@ impl Stack {
             ));
         }
 
-        // ...or was it?
-        let checksum = bytes[0..0x5FF].iter().map(|f| *f as i32).sum::<i32>();
-        println!("Checksum is {}", checksum);
-
         let stack_size = byte_range!(u32, bytes, st::BlockSize);
 
         let format_raw = byte_range!(u32, bytes, st::HyperCardFormat);
@@ -207,16 +226,14 @@ impl Stack {
             11..=u32::MAX => StackFormat::Unsupported,
         };
 
-        println!("Loading a {:?} formatted stack", format);
-
         let first_background_id = byte_range!(u32, bytes, st::FirstBackgroundID);
         let first_card_id = byte_range!(u32, bytes, st::FirstCardID);
+        let print_block_id = byte_range!(u32, bytes, st::PrintBlockID);
 
         // get any values that don't need to be malformed or "changed" later here, in the order they
         // appear in the file. this improves load times a bit on older hard drives.
 
         // version
-        println!("{:#x}", st::HyperCardVersionAtCreationEnd());
         let version_raw = (
             byte_range!(u32, bytes, st::HyperCardVersionAtCreation),
             byte_range!(u32, bytes, st::HyperCardVersionAtLastCompacting),
@@ -285,11 +302,12 @@ impl Stack {
         offset += 0x20;
 
         // collect the table and parse it.
-        let mut master_table: HashMap<u8, u32> = HashMap::new();
-        let master_table_raw = &bytes[offset..offset + (block_size as usize) / 2];
+        // the id byte is only the low byte of the block's ID, so it isn't unique; keep every entry.
+        let mut master_table: Vec<(u8, u32)> = Vec::new();
+        let master_table_raw = &bytes[offset..offset + (block_size as usize - 0x20)];
 
         // store the master IDs
-        for i in 0_u32..(block_size / 8) as u32 {
+        for i in 0_u32..(block_size - 0x20) / 4 {
             let item = &master_table_raw[(i * 4) as usize..((i + 1) * 4) as usize];
             // first 24 bits is the offset (as a multiple of 32). last 8 is block's "ID number"
             let location = byte::u24_from_u8(&item[0..3]) * 32;
@@ -299,9 +317,12 @@ impl Stack {
                 continue;
             }
 
-            master_table.insert(id, location);
+            master_table.push((id, location));
         }
         let mut objects: HashMap<u32, Block> = HashMap::new();
+        // the card list and its pages, which are the only record of the order the cards are in.
+        let mut list: Option<&[u8]> = None;
+        let mut pages: HashMap<u32, &[u8]> = HashMap::new();
         // loop through all the pointers we got and construct blocks off of them.
         // we construct futures that do this so that we can use multithreading
         for (id, location) in &master_table {
@@ -310,12 +331,19 @@ impl Stack {
             let block_type = match str::from_utf8(j) {
                 Ok(a) => a,
                 Err(err) => unsafe {
-                    println!("Invalid block type '{}'", str::from_utf8_unchecked(j));
+                    eprintln!("Invalid block type '{}'", str::from_utf8_unchecked(j));
                     continue;
                 },
             };
             let block_size = byte_range!(u32, bytes, *location as usize, gen::BlockSize);
             let chunk = &bytes[*location as usize..*location as usize + block_size as usize];
+            match block_type {
+                "LIST" => list = Some(chunk),
+                "PAGE" => {
+                    pages.insert(byte_range!(u32, chunk, gen::BlockID), chunk);
+                }
+                _ => {}
+            }
             if let Some((k, v)) = stack_parse(*location, *id, block_type.to_string(), chunk) {
                 objects.insert(k, v);
             };
@@ -329,12 +357,26 @@ impl Stack {
         let (mut first_card, mut cards) = (None, Vec::new());
         let mut fonts = Vec::new();
         let mut styles = None;
+        let (print_settings, report_templates);
         {
             (first_background, backgrounds) = filter_backgrounds(&objects, first_background_id);
         }
         {
             (first_card, cards) = filter_cards(&objects, first_card_id);
+            if let Some(list) = list {
+                let order = card_order(list, &pages);
+                cards.sort_by_key(|c| {
+                    order
+                        .iter()
+                        .position(|(id, _)| *id == c.id)
+                        .unwrap_or(usize::MAX)
+                });
+                for card in &mut cards {
+                    card.marked = order.iter().any(|(id, marked)| *id == card.id && *marked);
+                }
+            }
             fonts = filter_fonts(&objects);
+            (print_settings, report_templates) = filter_print(&objects, print_block_id);
             styles = match filter_styles(&objects) {
                 Some(a) => Some(a),
                 None => return Err(eyre!("No style table found.")),
@@ -355,10 +397,44 @@ impl Stack {
             coords,
             fonts,
             styles: styles.unwrap(),
+            print_settings,
+            report_templates,
             size,
             script,
+            resources: None,
         })
     }
+
+    /// The background with the given ID.
+    pub fn background(&self, id: u32) -> Option<&Background> {
+        self.backgrounds.iter().find(|b| b.id == id)
+    }
+
+    /// The card with the given ID.
+    pub fn card(&self, id: u32) -> Option<&Card> {
+        self.cards.iter().find(|c| c.id == id)
+    }
+
+    pub fn background_mut(&mut self, id: u32) -> Option<&mut Background> {
+        self.backgrounds.iter_mut().find(|b| b.id == id)
+    }
+
+    pub fn card_mut(&mut self, id: u32) -> Option<&mut Card> {
+        self.cards.iter_mut().find(|c| c.id == id)
+    }
+
+    /// The font table entry for a font ID.
+    pub fn font(&self, id: u16) -> Option<&Font> {
+        self.fonts.iter().find(|f| f.id == id)
+    }
+
+    /// The bitmap with the given block ID.
+    pub fn bitmap(&self, id: u32) -> Option<&Bitmap> {
+        match self.objects.get(&id) {
+            Some(Block::Bitmap(a)) => Some(a),
+            _ => None,
+        }
+    }
 }
 
 fn filter_backgrounds(
@@ -366,7 +442,7 @@ fn filter_backgrounds(
     first_background_id: u32,
 ) -> (Option<Background>, Vec<Background>) {
     let mut first_background: Option<Background> = None;
-    let backgrounds = objects
+    let mut backgrounds: Vec<Background> = objects
         .into_iter()
         .filter(|f| f.1.is_background())
         .map(|f| {
@@ -376,6 +452,26 @@ fn filter_backgrounds(
             f.1.get_background()
         })
         .collect();
+    // the backgrounds form a ring through `next`; put them in that order, starting from the
+    // first, with any that aren't in it after.
+    let mut order = Vec::new();
+    let mut id = first_background_id;
+    while let Some(background) = backgrounds.iter().find(|b| b.id == id) {
+        if order.contains(&id) {
+            break;
+        }
+        order.push(id);
+        id = background.next;
+    }
+    backgrounds.sort_by_key(|b| {
+        (
+            order
+                .iter()
+                .position(|id| *id == b.id)
+                .unwrap_or(usize::MAX),
+            b.id,
+        )
+    });
     (first_background, backgrounds)
 }
 fn filter_cards(objects: &HashMap<u32, Block>, first_card_id: u32) -> (Option<Card>, Vec<Card>) {
@@ -392,13 +488,81 @@ fn filter_cards(objects: &HashMap<u32, Block>, first_card_id: u32) -> (Option<Ca
         .collect();
     (first_card, cards)
 }
+// The IDs of the cards in stack order, from the card list and its pages, with whether each is
+// marked.
+fn card_order(list: &[u8], pages: &HashMap<u32, &[u8]>) -> Vec<(u32, bool)> {
+    let mut order = Vec::new();
+    if list.len() < li::UnknownEnd() {
+        return order;
+    }
+    let page_num = byte_range!(u32, list, li::PageNum) as usize;
+    let reference_size = byte_range!(u16, list, li::CardReferenceSize) as usize;
+    // each page entry is the page's block ID and how many cards are on it.
+    for i in 0..page_num {
+        let entry = li::UnknownEnd() + i * 6;
+        if entry + 6 > list.len() || reference_size < 4 {
+            break;
+        }
+        let page_id = byte::u32_from_u8(&list[entry..entry + 4]);
+        let card_num = byte::u16_from_u8(&list[entry + 4..entry + 6]) as usize;
+        let page = match pages.get(&page_id) {
+            Some(a) => a,
+            None => continue,
+        };
+        for j in 0..card_num {
+            let reference = pg::ChecksumEnd() + j * reference_size;
+            if reference + 4 > page.len() {
+                break;
+            }
+            // the byte after the ID is the card's flags, where 0x10 is set if it's marked.
+            let marked = page
+                .get(reference + 4)
+                .is_some_and(|flags| flags & 0x10 != 0);
+            order.push((byte::u32_from_u8(&page[reference..reference + 4]), marked));
+        }
+    }
+    order
+}
+
 fn filter_fonts(objects: &HashMap<u32, Block>) -> Vec<Font> {
     objects
         .into_iter()
         .filter(|f| f.1.is_font())
-        .map(|f| f.1.get_font())
+        .flat_map(|f| f.1.get_font())
         .collect()
 }
+// The print settings the stack header points to, and the report templates they list, named.
+fn filter_print(
+    objects: &HashMap<u32, Block>,
+    print_block_id: u32,
+) -> (Option<PrintSettings>, Vec<ReportTemplate>) {
+    let settings = match objects.get(&print_block_id) {
+        Some(Block::PrintSettings(a)) => Some(a.clone()),
+        _ => None,
+    };
+    let mut templates: Vec<ReportTemplate> = objects
+        .values()
+        .filter(|b| b.is_report_template())
+        .map(|b| b.get_report_template())
+        .collect();
+    templates.sort_by_key(|t| t.id);
+    if let Some(settings) = &settings {
+        for template in &mut templates {
+            if let Some(entry) = settings.templates.iter().find(|e| e.id == template.id) {
+                template.name = entry.name.clone();
+            }
+        }
+        // keep them in the order the settings list them.
+        templates.sort_by_key(|t| {
+            settings
+                .templates
+                .iter()
+                .position(|e| e.id == t.id)
+                .unwrap_or(usize::MAX)
+        });
+    }
+    (settings, templates)
+}
 fn filter_styles(objects: &HashMap<u32, Block>) -> Option<HashMap<u32, Style>> {
     for obj in objects {
         if obj.1.is_style() {
@@ -412,9 +576,7 @@ fn stack_parse(location: u32, id: u8, block_type: String, chunk: &[u8]) -> Optio
     let block_id = byte_range!(u32, chunk, gen::BlockID);
     match block_type.as_str() {
         "LIST" | "PAGE" => {
-            // redundant data that speeds up insertion/deletions in the original tools
-            // but we read these cards as read only and thus this is about as useful  as
-            // the master block
+            // the card list; only used for the order of the cards, which is read separately.
             None
         }
         "BMAP" => {
@@ -435,6 +597,37 @@ fn stack_parse(location: u32, id: u8, block_type: String, chunk: &[u8]) -> Optio
             });
             Some((block_id, Block::Style(s)))
         }
+        "FTBL" => match Font::vec_from(chunk) {
+            Ok(f) => Some((block_id, Block::Font(f))),
+            // without the font table, text is drawn in the fonts its IDs stand for on their own.
+            Err(f) => {
+                eprintln!("error parsing font table, leaving it out\n{}", f);
+                None
+            }
+        },
+        "PRNT" => match PrintSettings::from(chunk) {
+            Ok(p) => Some((block_id, Block::PrintSettings(p))),
+            // the stack can be shown without what's for printing it, so a block that can't be
+            // read is left out.
+            Err(f) => {
+                eprintln!("error parsing print settings, leaving them out\n{}", f);
+                None
+            }
+        },
+        "PRST" => match ReportTemplate::from(chunk) {
+            Ok(r) => Some((block_id, Block::ReportTemplate(r))),
+            Err(f) => {
+                eprintln!("error parsing report template, leaving it out\n{}", f);
+                None
+            }
+        },
+        "PRFT" => match PrintFormat::from(chunk) {
+            Ok(p) => Some((block_id, Block::PrintFormat(p))),
+            Err(f) => {
+                eprintln!("error parsing print block, leaving it out\n{}", f);
+                None
+            }
+        },
         "BKGD" => {
             let b = Background::from(chunk).unwrap_or_else(|f| {
                 panic!("error parsing background\n{}", f);
@@ -442,7 +635,7 @@ fn stack_parse(location: u32, id: u8, block_type: String, chunk: &[u8]) -> Optio
             Some((block_id, Block::Background(b)))
         }
         _ => {
-            println!(
+            eprintln!(
                 "Unimplemented: block {} '{}' at {:#08x}",
                 id, block_type, location
             );
@@ -6,7 +6,9 @@ use crate::byte::byte_range;
 use super::data_layout::StyleLayout as s;
 use super::data_layout::StyleTableLayout as st;
 
+/// A run style from the style table. Any value of -1 means the run uses the field's own setting.
 #[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Style {
     pub font: i16,
     pub style_flags: i16,
@@ -16,10 +18,14 @@ pub struct Style {
 impl Style {
     pub fn vec_from(b: &[u8]) -> Result<HashMap<u32, Self>, ErrReport> {
         let style_num = byte_range!(u32, b, st::StyleNum);
-        let offset = st::NextStyleIDEnd();
+        let mut offset = st::NextStyleIDEnd();
         let mut styles: HashMap<u32, Self> = HashMap::new();
         for _ in 0..style_num {
-            let chunk = &b[offset..offset + 23];
+            if offset + s::Unknown2End() > b.len() {
+                return Err(eyre!("style table is shorter than its style count"));
+            }
+            let chunk = &b[offset..offset + s::Unknown2End()];
+            offset += s::Unknown2End();
             let id = byte_range!(u32, chunk, s::StyleID);
             let font = byte_range!(u16, chunk, s::FontID) as i16;
             let style_flags = byte_range!(u16, chunk, s::StyleFlags) as i16;
@@ -35,4 +41,13 @@ impl Style {
         }
         Ok(styles)
     }
+
+    /// The QuickDraw text face (bold, italic and so on) of the run, if it sets one.
+    pub fn face(&self) -> Option<u8> {
+        if self.style_flags == -1 {
+            None
+        } else {
+            Some((self.style_flags >> 8) as u8)
+        }
+    }
 }
@@ -1,4 +1,8 @@
+pub mod bitmap_font;
 mod block;
 pub(crate) mod byte;
-pub(crate) mod macroman;
+pub mod macroman;
+pub mod resource;
+#[cfg(feature = "serde")]
+pub mod serialize;
 pub use block::*;
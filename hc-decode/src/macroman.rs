@@ -131,3 +131,11 @@ pub fn macroman_to_char(macroman: u8) -> char {
         255 => 'ˇ', // caron
     }
 }
+
+/// The Mac OS Roman code for a character, if it has one.
+pub fn char_to_macroman(ch: char) -> Option<u8> {
+    if (ch as u32) < 128 {
+        return Some(ch as u8);
+    }
+    (128..=255).find(|b| macroman_to_char(*b) == ch)
+}
@@ -0,0 +1,157 @@
+use eyre::{eyre, ErrReport};
+use image::{GrayImage, Luma};
+
+use std::fs;
+use std::path::Path;
+
+use crate::byte;
+use crate::macroman::macroman_to_char;
+
+//
+// Classic Mac OS resource forks. Stacks keep their icons, fonts, sounds, XCMDs and so on in here.
+// Off of a Mac the fork has to be found next to the data fork somehow; see `ResourceFork::for_path`.
+//
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ResourceFork {
+    pub resources: Vec<Resource>,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Resource {
+    pub ty: String,
+    pub id: i16,
+    pub name: String,
+    #[cfg_attr(feature = "serde", serde(with = "crate::serialize::base64_bytes"))]
+    pub data: Vec<u8>,
+}
+
+impl ResourceFork {
+    /// Find and read the resource fork belonging to the file at `path`. This understands the
+    /// `.rsrc` folders that netatalk and most archives use, AppleDouble `._` files, and the
+    /// real thing on macOS.
+    pub fn for_path(path: &Path) -> Result<ResourceFork, ErrReport> {
+        let name = path
+            .file_name()
+            .ok_or_else(|| eyre!("Path has no file name"))?;
+        let dir = path.parent().unwrap_or(Path::new("."));
+
+        let rsrc = dir.join(".rsrc").join(name);
+        if rsrc.is_file() {
+            return Self::from_bytes(&fs::read(rsrc)?);
+        }
+        let named_fork = path.join("..namedfork").join("rsrc");
+        if named_fork.is_file() {
+            return Self::from_bytes(&fs::read(named_fork)?);
+        }
+        let mut apple_double_name = std::ffi::OsString::from("._");
+        apple_double_name.push(name);
+        let apple_double = dir.join(apple_double_name);
+        if apple_double.is_file() {
+            return Self::from_apple_double(&fs::read(apple_double)?);
+        }
+        Err(eyre!("No resource fork found for {}", path.display()))
+    }
+
+    /// Pull the resource fork out of an AppleDouble header file.
+    pub fn from_apple_double(b: &[u8]) -> Result<ResourceFork, ErrReport> {
+        if b.len() < 26 || byte::u32_from_u8(&b[0..4]) != 0x00051607 {
+            return Err(eyre!("Not an AppleDouble file"));
+        }
+        let entries = byte::u16_from_u8(&b[24..26]) as usize;
+        for i in 0..entries {
+            let entry = &b[26 + i * 12..26 + (i + 1) * 12];
+            // entry ID 2 is the resource fork.
+            if byte::u32_from_u8(&entry[0..4]) == 2 {
+                let offset = byte::u32_from_u8(&entry[4..8]) as usize;
+                let length = byte::u32_from_u8(&entry[8..12]) as usize;
+                return Self::from_bytes(&b[offset..offset + length]);
+            }
+        }
+        Err(eyre!("AppleDouble file has no resource fork"))
+    }
+
+    pub fn from_bytes(b: &[u8]) -> Result<ResourceFork, ErrReport> {
+        if b.len() < 16 {
+            return Err(eyre!("Resource fork is too short to be valid"));
+        }
+        let data_offset = byte::u32_from_u8(&b[0..4]) as usize;
+        let map_offset = byte::u32_from_u8(&b[4..8]) as usize;
+        let map_length = byte::u32_from_u8(&b[12..16]) as usize;
+        if map_offset + map_length > b.len() || map_length < 30 {
+            return Err(eyre!("Resource map is out of bounds"));
+        }
+        let map = &b[map_offset..map_offset + map_length];
+        let type_list_offset = byte::u16_from_u8(&map[24..26]) as usize;
+        let name_list_offset = byte::u16_from_u8(&map[26..28]) as usize;
+        let type_list = &map[type_list_offset..];
+
+        let mut resources = Vec::new();
+        // counts in the map are stored minus one.
+        let type_num = byte::u16_from_u8(&type_list[0..2]).wrapping_add(1) as usize;
+        for i in 0..type_num {
+            let entry = &type_list[2 + i * 8..2 + (i + 1) * 8];
+            let ty: String = entry[0..4].iter().map(|c| macroman_to_char(*c)).collect();
+            let count = byte::u16_from_u8(&entry[4..6]) as usize + 1;
+            let ref_list_offset = byte::u16_from_u8(&entry[6..8]) as usize;
+            for j in 0..count {
+                let r = &type_list[ref_list_offset + j * 12..ref_list_offset + (j + 1) * 12];
+                let id = byte::u16_from_u8(&r[0..2]) as i16;
+                let name_offset = byte::u16_from_u8(&r[2..4]);
+                let offset = data_offset + byte::u24_from_u8(&r[5..8]) as usize;
+                let length = byte::u32_from_u8(&b[offset..offset + 4]) as usize;
+                let data = b[offset + 4..offset + 4 + length].to_vec();
+                // names are pascal strings, and an offset of -1 means there isn't one.
+                let name = if name_offset == 0xFFFF {
+                    String::new()
+                } else {
+                    let start = name_list_offset + name_offset as usize;
+                    let len = map[start] as usize;
+                    map[start + 1..start + 1 + len]
+                        .iter()
+                        .map(|c| macroman_to_char(*c))
+                        .collect()
+                };
+                resources.push(Resource {
+                    ty: ty.clone(),
+                    id,
+                    name,
+                    data,
+                });
+            }
+        }
+        Ok(ResourceFork { resources })
+    }
+
+    pub fn get(&self, ty: &str, id: i16) -> Option<&Resource> {
+        self.resources.iter().find(|r| r.ty == ty && r.id == id)
+    }
+
+    pub fn get_named(&self, ty: &str, name: &str) -> Option<&Resource> {
+        self.resources
+            .iter()
+            .find(|r| r.ty == ty && r.name.eq_ignore_ascii_case(name))
+    }
+
+    pub fn of_type<'a>(&'a self, ty: &'a str) -> impl Iterator<Item = &'a Resource> + 'a {
+        self.resources.iter().filter(move |r| r.ty == ty)
+    }
+
+    /// A 32x32 black and white `ICON`; black pixels are 0, white pixels are 255.
+    pub fn icon(&self, id: i16) -> Option<GrayImage> {
+        let res = self.get("ICON", id)?;
+        if res.data.len() < 128 {
+            return None;
+        }
+        Some(GrayImage::from_fn(32, 32, |x, y| {
+            let byte = res.data[(y * 4 + x / 8) as usize];
+            if byte & (0x80 >> (x % 8)) != 0 {
+                Luma([0])
+            } else {
+                Luma([255])
+            }
+        }))
+    }
+}
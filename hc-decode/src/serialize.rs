@@ -0,0 +1,101 @@
+//
+// Helpers for the `serde` feature, for the parts of the model that don't serialize well as they
+// are. Pictures become PNGs, either inline as data URIs or, inside `with_bitmap_dir`, as files
+// next to the output. Raw bytes become base64 rather than a list of numbers, and maps are written
+// sorted by key so that dumps of the same stack come out the same and can be diffed.
+//
+
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use image::{GrayImage, ImageOutputFormat};
+use serde::de::Error as _;
+use serde::ser::Error as _;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+const PNG_DATA_URI: &str = "data:image/png;base64,";
+
+thread_local! {
+    // the directory pictures are written to and read from, and how many have been written.
+    static BITMAP_DIR: RefCell<Option<(PathBuf, usize)>> = const { RefCell::new(None) };
+}
+
+/// Run `f` with pictures serialized as PNG files in `dir` instead of inline, each referred to by
+/// its file name. Deserializing inside this reads relative file names from `dir` too.
+pub fn with_bitmap_dir<R>(dir: &Path, f: impl FnOnce() -> R) -> R {
+    let previous = BITMAP_DIR.with(|d| d.replace(Some((dir.to_path_buf(), 0))));
+    let result = f();
+    BITMAP_DIR.with(|d| *d.borrow_mut() = previous);
+    result
+}
+
+pub(crate) mod image_png {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(image: &GrayImage, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut png = Vec::new();
+        image
+            .write_to(&mut Cursor::new(&mut png), ImageOutputFormat::Png)
+            .map_err(S::Error::custom)?;
+
+        let file = BITMAP_DIR.with(|d| {
+            d.borrow_mut().as_mut().map(|(dir, count)| {
+                *count += 1;
+                let name = format!("bitmap-{}.png", count);
+                fs::write(dir.join(&name), &png).map(|_| name)
+            })
+        });
+        match file {
+            Some(Ok(name)) => serializer.serialize_str(&name),
+            Some(Err(err)) => Err(S::Error::custom(err)),
+            None => serializer.serialize_str(&format!("{}{}", PNG_DATA_URI, STANDARD.encode(png))),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<GrayImage, D::Error> {
+        let value = String::deserialize(deserializer)?;
+        let png = match value.strip_prefix(PNG_DATA_URI) {
+            Some(data) => STANDARD.decode(data).map_err(D::Error::custom)?,
+            None => {
+                let path = BITMAP_DIR.with(|d| match d.borrow().as_ref() {
+                    Some((dir, _)) => dir.join(&value),
+                    None => PathBuf::from(&value),
+                });
+                fs::read(&path).map_err(|err| {
+                    D::Error::custom(format!("couldn't read {}: {}", path.display(), err))
+                })?
+            }
+        };
+        Ok(image::load_from_memory(&png)
+            .map_err(D::Error::custom)?
+            .to_luma8())
+    }
+}
+
+pub(crate) mod base64_bytes {
+    use super::*;
+
+    pub fn serialize<S: Serializer>(bytes: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&STANDARD.encode(bytes))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        STANDARD
+            .decode(String::deserialize(deserializer)?)
+            .map_err(D::Error::custom)
+    }
+}
+
+pub(crate) fn sorted_map<K, V, S>(map: &HashMap<K, V>, serializer: S) -> Result<S::Ok, S::Error>
+where
+    K: Ord + Serialize,
+    V: Serialize,
+    S: Serializer,
+{
+    map.iter().collect::<BTreeMap<_, _>>().serialize(serializer)
+}
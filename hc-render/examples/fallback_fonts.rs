@@ -0,0 +1,182 @@
+//
+// Rasterizes the DejaVu fonts into the QuickDraw bitmap fonts in `fonts/`, which text falls back
+// on when a stack uses a font it doesn't have. Each size is written as a bare `NFNT` resource
+// covering the whole Mac OS Roman character set, with a box for the missing character.
+//
+//     cargo run --example fallback_fonts -- /usr/share/fonts/truetype/dejavu fonts
+//
+
+use std::error::Error;
+use std::path::Path;
+
+use fontdue::{Font, FontSettings};
+use hc_decode::macroman::macroman_to_char;
+
+// the fallback families, and the DejaVu face each is rasterized from.
+const FAMILIES: [(&str, &str); 4] = [
+    ("system", "DejaVuSans-Bold.ttf"),
+    ("application", "DejaVuSans.ttf"),
+    ("monospace", "DejaVuSansMono.ttf"),
+    ("serif", "DejaVuSerif.ttf"),
+];
+
+const SIZES: [u16; 6] = [9, 10, 12, 14, 18, 24];
+
+// how dark an antialiased pixel has to be to be set.
+const THRESHOLD: u8 = 100;
+
+// a character's image, `ascent + descent` rows of `width` pixels.
+struct Glyph {
+    left: i32,
+    advance: i32,
+    width: usize,
+    rows: Vec<Vec<bool>>,
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = std::env::args().collect();
+    let (dejavu, out) = match &args[..] {
+        [_, dejavu, out] => (Path::new(dejavu), Path::new(out)),
+        _ => return Err("usage: fallback_fonts <DejaVu font directory> <output directory>".into()),
+    };
+    std::fs::create_dir_all(out)?;
+    for (family, file) in FAMILIES {
+        let font = Font::from_bytes(std::fs::read(dejavu.join(file))?, FontSettings::default())?;
+        for size in SIZES {
+            let nfnt = nfnt(&font, size)?;
+            std::fs::write(out.join(format!("{}-{}.nfnt", family, size)), nfnt)?;
+        }
+    }
+    Ok(())
+}
+
+// one size of a font as an `NFNT`.
+fn nfnt(font: &Font, size: u16) -> Result<Vec<u8>, Box<dyn Error>> {
+    let px = size as f32;
+    let metrics = font
+        .horizontal_line_metrics(px)
+        .ok_or("the font has no horizontal metrics")?;
+    let ascent = metrics.ascent.round() as i32;
+    let descent = (-metrics.descent).round() as i32;
+    let leading = (size as i32 / 12).max(1);
+    let height = (ascent + descent) as usize;
+
+    let mut glyphs: Vec<Option<Glyph>> = (0..=255u8)
+        .map(|code| glyph(font, px, code, ascent, height))
+        .collect();
+    glyphs.push(Some(missing_glyph(px, ascent, height)));
+
+    let kern_max = glyphs
+        .iter()
+        .flatten()
+        .map(|g| g.left)
+        .min()
+        .unwrap_or(0)
+        .min(0);
+    let total: usize = glyphs.iter().flatten().map(|g| g.width).sum();
+    let row_words = total.div_ceil(16);
+    let mut strike = vec![0u8; row_words * 2 * height];
+    let mut locations = Vec::new();
+    let mut offset_widths = Vec::new();
+    let mut x = 0;
+    for glyph in &glyphs {
+        locations.push(x as u16);
+        let Some(glyph) = glyph else {
+            offset_widths.push(0xffff);
+            continue;
+        };
+        for (y, row) in glyph.rows.iter().enumerate() {
+            for (i, _) in row.iter().enumerate().filter(|(_, set)| **set) {
+                let bit = x + i;
+                strike[y * row_words * 2 + bit / 8] |= 0x80 >> (bit % 8);
+            }
+        }
+        x += glyph.width;
+        offset_widths.push((((glyph.left - kern_max) as u16) << 8) | (glyph.advance as u16 & 0xff));
+    }
+    locations.push(x as u16);
+    offset_widths.push(0xffff);
+
+    let max_width = glyphs
+        .iter()
+        .flatten()
+        .map(|g| g.advance)
+        .max()
+        .unwrap_or(0);
+    let rect_width = glyphs
+        .iter()
+        .flatten()
+        .map(|g| g.width as i32)
+        .max()
+        .unwrap_or(0);
+    // the offset/width table's offset is counted in words from the field that holds it.
+    let offset_widths_start = 26 + strike.len() + locations.len() * 2;
+    let header = [
+        0x9000,
+        0,
+        255,
+        max_width,
+        kern_max,
+        -descent,
+        rect_width,
+        height as i32,
+        ((offset_widths_start - 16) / 2) as i32,
+        ascent,
+        descent,
+        leading,
+        row_words as i32,
+    ];
+
+    let mut b = Vec::new();
+    for field in header {
+        b.extend_from_slice(&(field as i16).to_be_bytes());
+    }
+    b.extend_from_slice(&strike);
+    for n in locations.into_iter().chain(offset_widths) {
+        b.extend_from_slice(&n.to_be_bytes());
+    }
+    Ok(b)
+}
+
+// a character of the font, or `None` for control characters and ones the font hasn't got.
+fn glyph(font: &Font, px: f32, code: u8, ascent: i32, height: usize) -> Option<Glyph> {
+    let ch = macroman_to_char(code);
+    if code < 0x20 || code == 0x7f || (font.lookup_glyph_index(ch) == 0 && ch != ' ') {
+        return None;
+    }
+    let (metrics, coverage) = font.rasterize(ch, px);
+    let mut rows = vec![vec![false; metrics.width]; height];
+    for y in 0..metrics.height {
+        let row = ascent - (metrics.ymin + metrics.height as i32) + y as i32;
+        if row < 0 || row >= height as i32 {
+            continue;
+        }
+        for x in 0..metrics.width {
+            rows[row as usize][x] = coverage[y * metrics.width + x] >= THRESHOLD;
+        }
+    }
+    Some(Glyph {
+        left: metrics.xmin,
+        advance: metrics.advance_width.round() as i32,
+        width: metrics.width,
+        rows,
+    })
+}
+
+// what's drawn for characters the font hasn't got: a box from just below the top to the baseline.
+fn missing_glyph(px: f32, ascent: i32, height: usize) -> Glyph {
+    let width = (px * 0.5) as usize;
+    let bottom = ascent as usize - 1;
+    let mut rows = vec![vec![false; width]; height];
+    for (y, row) in rows.iter_mut().enumerate().take(ascent as usize).skip(2) {
+        for (x, set) in row.iter_mut().enumerate() {
+            *set = y == 2 || y == bottom || x == 0 || x == width - 1;
+        }
+    }
+    Glyph {
+        left: 1,
+        advance: width as i32 + 2,
+        width,
+        rows,
+    }
+}
@@ -0,0 +1,173 @@
+// Animated previews of a stack: its cards one after another, optionally with a visual effect
+// between them, written out as an animated GIF or APNG.
+
+use std::borrow::Cow;
+use std::error::Error;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::Duration;
+
+use hc_decode::stack::Stack;
+use image::RgbaImage;
+
+use crate::compositor::render_card_with_fonts;
+use crate::draw;
+use crate::text::Fonts;
+use crate::transition::{Destination, VisualEffect};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnimationFormat {
+    Gif,
+    Apng,
+}
+
+#[derive(Debug, Clone)]
+pub struct WalkthroughOptions {
+    /// IDs of the cards to show, in order. Empty means every card, in stack order.
+    pub cards: Vec<u32>,
+    /// How long each card stays up.
+    pub card_delay: Duration,
+    /// The effect to play going from one card to the next.
+    pub effect: Option<VisualEffect>,
+    /// How long each frame of the effect stays up.
+    pub frame_delay: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct AnimationFrame {
+    pub image: RgbaImage,
+    pub delay: Duration,
+}
+
+impl Default for WalkthroughOptions {
+    fn default() -> Self {
+        WalkthroughOptions {
+            cards: Vec::new(),
+            card_delay: Duration::from_secs(2),
+            effect: None,
+            frame_delay: Duration::from_millis(30),
+        }
+    }
+}
+
+impl AnimationFormat {
+    /// The format a file name asks for: `.png` and `.apng` are APNG, anything else is GIF.
+    pub fn for_path(path: &Path) -> Self {
+        match path.extension().and_then(|e| e.to_str()) {
+            Some(e) if e.eq_ignore_ascii_case("png") || e.eq_ignore_ascii_case("apng") => {
+                AnimationFormat::Apng
+            }
+            _ => AnimationFormat::Gif,
+        }
+    }
+}
+
+/// Render the frames of a walk through a stack's cards.
+pub fn walkthrough(
+    stack: &Stack,
+    options: &WalkthroughOptions,
+) -> Result<Vec<AnimationFrame>, Box<dyn Error>> {
+    let fonts = Fonts::for_stack(stack);
+    let ids: Vec<u32> = if options.cards.is_empty() {
+        stack.cards.iter().map(|c| c.id).collect()
+    } else {
+        options.cards.clone()
+    };
+
+    let mut frames: Vec<AnimationFrame> = Vec::new();
+    for id in ids {
+        let card = stack
+            .card(id)
+            .ok_or_else(|| format!("No card with ID {}", id))?;
+        let image = render_card_with_fonts(stack, &fonts, card);
+        if let (Some(effect), Some(previous)) = (&options.effect, frames.last()) {
+            let transition = effect.frames(&previous.image, &image);
+            frames.extend(transition.into_iter().map(|image| AnimationFrame {
+                image,
+                delay: options.frame_delay,
+            }));
+            // an effect that goes to the card leaves it up; one that goes to black (or white,
+            // and so on) is followed by the card just appearing.
+            if effect.destination == Destination::Card {
+                if let Some(last) = frames.last_mut() {
+                    last.delay = options.card_delay;
+                }
+                continue;
+            }
+        }
+        frames.push(AnimationFrame {
+            image,
+            delay: options.card_delay,
+        });
+    }
+    Ok(frames)
+}
+
+/// Render a walk through a stack and write it to `path`.
+pub fn export_walkthrough(
+    stack: &Stack,
+    options: &WalkthroughOptions,
+    format: AnimationFormat,
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    let frames = walkthrough(stack, options)?;
+    let mut file = BufWriter::new(File::create(path)?);
+    match format {
+        AnimationFormat::Gif => write_gif(&mut file, &frames)?,
+        AnimationFormat::Apng => write_apng(&mut file, &frames)?,
+    }
+    file.flush()?;
+    Ok(())
+}
+
+/// Write frames as a looping GIF. Cards are black and white, so every frame shares a two color
+/// palette.
+pub fn write_gif<W: Write>(w: W, frames: &[AnimationFrame]) -> Result<(), Box<dyn Error>> {
+    let (width, height) = match frames.first() {
+        Some(f) => (f.image.width() as u16, f.image.height() as u16),
+        None => return Err("No frames to write".into()),
+    };
+    let mut encoder = gif::Encoder::new(w, width, height, &[0, 0, 0, 255, 255, 255])?;
+    encoder.set_repeat(gif::Repeat::Infinite)?;
+    for frame in frames {
+        let pixels: Vec<u8> = frame
+            .image
+            .pixels()
+            .map(|p| if draw::is_black(p) { 0 } else { 1 })
+            .collect();
+        encoder.write_frame(&gif::Frame {
+            width,
+            height,
+            // GIF delays are in hundredths of a second.
+            delay: (frame.delay.as_millis() / 10).min(u16::MAX as u128) as u16,
+            buffer: Cow::Owned(pixels),
+            ..gif::Frame::default()
+        })?;
+    }
+    Ok(())
+}
+
+/// Write frames as a looping APNG.
+pub fn write_apng<W: Write>(w: W, frames: &[AnimationFrame]) -> Result<(), Box<dyn Error>> {
+    let (width, height) = match frames.first() {
+        Some(f) => f.image.dimensions(),
+        None => return Err("No frames to write".into()),
+    };
+    let mut encoder = png::Encoder::new(w, width, height);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_animated(frames.len() as u32, 0)?;
+    let mut writer = encoder.write_header()?;
+    for frame in frames {
+        writer.set_frame_delay(frame.delay.as_millis().min(u16::MAX as u128) as u16, 1000)?;
+        let pixels: Vec<u8> = frame
+            .image
+            .pixels()
+            .map(|p| if draw::is_black(p) { 0 } else { 255 })
+            .collect();
+        writer.write_image_data(&pixels)?;
+    }
+    writer.finish()?;
+    Ok(())
+}
@@ -0,0 +1,377 @@
+// Buttons, drawn the way HyperCard 2.x draws them under System 6 and 7.
+
+use hc_decode::part::{Part, PartStyle, TextAlignment};
+use hc_decode::resource::ResourceFork;
+use image::RgbaImage;
+
+use crate::draw::{self, Rect, BLACK, WHITE};
+use crate::text::{FontMetrics, Fonts, TextStyle};
+
+// diameter of the corner ovals on roundRect and standard buttons.
+pub(crate) const CORNER: i32 = 16;
+// checkboxes and radio buttons are a 12 pixel square/circle at the left of the button.
+pub(crate) const MARK_SIZE: i32 = 12;
+pub(crate) const ICON_SIZE: i32 = 32;
+
+pub fn paint_button(
+    canvas: &mut RgbaImage,
+    part: &Part,
+    resources: Option<&ResourceFork>,
+    fonts: &Fonts,
+) {
+    let rect = draw::rect_from(part.position);
+    match part.style {
+        PartStyle::Checkbox | PartStyle::Radio => paint_mark_button(canvas, part, rect, fonts),
+        PartStyle::Popup => paint_popup(canvas, part, rect, fonts),
+        _ => {
+            // the area inside the frame, which is what gets inverted when the button is hilited.
+            let content = paint_frame(canvas, part, rect);
+            paint_label(canvas, part, content, resources, fonts);
+            if part.hilite {
+                paint_hilite(canvas, part, content);
+            }
+        }
+    }
+}
+
+/// The button's body, which is framed, and the rectangle inside the frame.
+pub(crate) fn frame_of(style: &PartStyle, rect: Rect) -> (Rect, Rect) {
+    match style {
+        PartStyle::Rectangle | PartStyle::Standard => (rect, draw::inset(rect, 1, 1)),
+        // the shadow takes up a pixel on the bottom and right.
+        PartStyle::Shadow | PartStyle::RoundRectangle => {
+            let body = (rect.0, rect.1, rect.2 - 1, rect.3 - 1);
+            (body, draw::inset(body, 1, 1))
+        }
+        // a three pixel thick outline, a pixel of space, and then a standard button.
+        PartStyle::Default => {
+            let body = draw::inset(rect, 4, 4);
+            (body, draw::inset(body, 1, 1))
+        }
+        // opaque, transparent and oval buttons have no frame; an oval button is only an oval when
+        // it's hilited.
+        _ => (rect, rect),
+    }
+}
+
+// Draw the button's body and frame, returning the rectangle inside the frame.
+fn paint_frame(canvas: &mut RgbaImage, part: &Part, rect: Rect) -> Rect {
+    let (body, content) = frame_of(&part.style, rect);
+    match part.style {
+        PartStyle::Opaque => draw::fill_rect(canvas, body, WHITE),
+        PartStyle::Rectangle => {
+            draw::fill_rect(canvas, body, WHITE);
+            draw::frame_rect(canvas, body, BLACK);
+        }
+        PartStyle::Shadow => {
+            // the shadow is one pixel wide, and starts a couple of pixels in from the corners.
+            draw::fill_rect(canvas, body, WHITE);
+            draw::frame_rect(canvas, body, BLACK);
+            draw::hline(canvas, rect.1 + 2, rect.3, rect.2 - 1, BLACK);
+            draw::vline(canvas, rect.3 - 1, rect.0 + 2, rect.2, BLACK);
+        }
+        PartStyle::RoundRectangle => {
+            draw::fill_round_rect(canvas, draw::offset(body, 1, 1), CORNER, CORNER, BLACK);
+            draw::fill_round_rect(canvas, body, CORNER, CORNER, WHITE);
+            draw::frame_round_rect(canvas, body, CORNER, CORNER, BLACK);
+        }
+        PartStyle::Standard => {
+            draw::fill_round_rect(canvas, body, CORNER, CORNER, WHITE);
+            draw::frame_round_rect(canvas, body, CORNER, CORNER, BLACK);
+        }
+        PartStyle::Default => {
+            let outer_corner = CORNER + 6;
+            draw::fill_round_rect(canvas, rect, outer_corner, outer_corner, BLACK);
+            draw::fill_round_rect(
+                canvas,
+                draw::inset(rect, 3, 3),
+                CORNER + 2,
+                CORNER + 2,
+                WHITE,
+            );
+            draw::frame_round_rect(canvas, body, CORNER, CORNER, BLACK);
+        }
+        _ => {}
+    }
+    content
+}
+
+fn paint_hilite(canvas: &mut RgbaImage, part: &Part, content: Rect) {
+    match part.style {
+        PartStyle::RoundRectangle | PartStyle::Standard | PartStyle::Default => {
+            draw::invert_round_rect(canvas, content, CORNER - 2, CORNER - 2)
+        }
+        PartStyle::Oval => draw::invert_oval(canvas, content),
+        _ => draw::invert_rect(canvas, content),
+    }
+}
+
+// Draw the icon and/or name, centered in the button.
+fn paint_label(
+    canvas: &mut RgbaImage,
+    part: &Part,
+    content: Rect,
+    resources: Option<&ResourceFork>,
+    fonts: &Fonts,
+) {
+    let gray = !part.enabled;
+    let style = TextStyle::of_part(part);
+    let metrics = fonts.metrics(&style);
+    let icon = match part.icon_id {
+        0 => None,
+        id => resources.and_then(|r| r.icon(id)),
+    };
+
+    let name_width = fonts.string_width(&part.name, &style);
+    let label = Label::place(part, content, icon.is_some(), name_width, &metrics);
+    if let (Some(icon), Some((x, y))) = (icon, label.icon) {
+        for (ix, iy, pixel) in icon.enumerate_pixels() {
+            let (px, py) = (x + ix as i32, y + iy as i32);
+            if pixel.0[0] < 128 && (!gray || (px + py).rem_euclid(2) == 0) {
+                draw::set_pixel(canvas, px, py, BLACK);
+            }
+        }
+    }
+    if let Some((x, baseline)) = label.name {
+        fonts.draw_string(canvas, x, baseline, &part.name, &style, gray);
+    }
+}
+
+/// Where a button's icon and name go inside its frame.
+pub(crate) struct Label {
+    /// The top left of the icon.
+    pub icon: Option<(i32, i32)>,
+    /// The pen position for the name, on its baseline.
+    pub name: Option<(i32, i32)>,
+}
+
+impl Label {
+    pub(crate) fn place(
+        part: &Part,
+        content: Rect,
+        has_icon: bool,
+        name_width: i32,
+        metrics: &FontMetrics,
+    ) -> Label {
+        if has_icon {
+            // the icon sits above the name, and the pair of them is centered in the button.
+            let label_height = if part.show_name {
+                ICON_SIZE + metrics.line_height()
+            } else {
+                ICON_SIZE
+            };
+            let x = content.1 + (draw::width(content) - ICON_SIZE) / 2;
+            let y = content.0 + (draw::height(content) - label_height) / 2;
+            let name_x = content.1 + (draw::width(content) - name_width) / 2;
+            Label {
+                icon: Some((x, y)),
+                name: part
+                    .show_name
+                    .then_some((name_x, y + ICON_SIZE + metrics.ascent + 1)),
+            }
+        } else {
+            let x = match part.text_alignment {
+                TextAlignment::Left | TextAlignment::ForceLeftAlign => content.1 + 4,
+                TextAlignment::Right | TextAlignment::ForceRightAlign => content.3 - 4 - name_width,
+                _ => content.1 + (draw::width(content) - name_width) / 2,
+            };
+            Label {
+                icon: None,
+                name: part
+                    .show_name
+                    .then_some((x, centered_baseline(content, metrics))),
+            }
+        }
+    }
+}
+
+pub(crate) fn centered_baseline(rect: Rect, metrics: &FontMetrics) -> i32 {
+    rect.0 + (draw::height(rect) - (metrics.ascent + metrics.descent)) / 2 + metrics.ascent
+}
+
+/// The box or circle of a checkbox or radio button, and where its name starts.
+pub(crate) fn mark_of(rect: Rect) -> (Rect, i32) {
+    let top = rect.0 + (draw::height(rect) - MARK_SIZE) / 2;
+    let mark = (top, rect.1 + 3, top + MARK_SIZE, rect.1 + 3 + MARK_SIZE);
+    (mark, mark.3 + 5)
+}
+
+/// The box of a popup menu, to the right of its title and leaving room for the shadow.
+pub(crate) fn popup_body(part: &Part, rect: Rect) -> Rect {
+    (
+        rect.0,
+        rect.1 + part.title_width as i32,
+        rect.2 - 1,
+        rect.3 - 1,
+    )
+}
+
+// Checkboxes and radio buttons: a box or circle on the left that's marked when hilited, then the name.
+fn paint_mark_button(canvas: &mut RgbaImage, part: &Part, rect: Rect, fonts: &Fonts) {
+    let gray = !part.enabled;
+    let style = TextStyle::of_part(part);
+    let (mark, name_x) = mark_of(rect);
+
+    if matches!(part.style, PartStyle::Checkbox) {
+        draw::fill_rect(canvas, mark, WHITE);
+        draw::frame_rect(canvas, mark, BLACK);
+        if part.hilite {
+            // an X from corner to corner
+            for i in 1..MARK_SIZE - 1 {
+                draw::set_pixel(canvas, mark.1 + i, mark.0 + i, BLACK);
+                draw::set_pixel(canvas, mark.3 - 1 - i, mark.0 + i, BLACK);
+            }
+        }
+    } else {
+        draw::fill_oval(canvas, mark, WHITE);
+        draw::frame_oval(canvas, mark, BLACK);
+        if part.hilite {
+            draw::fill_oval(canvas, draw::inset(mark, 3, 3), BLACK);
+        }
+    }
+    if gray {
+        gray_out(canvas, mark);
+    }
+
+    if part.show_name {
+        fonts.draw_string(
+            canvas,
+            name_x,
+            centered_baseline(rect, &fonts.metrics(&style)),
+            &part.name,
+            &style,
+            gray,
+        );
+    }
+}
+
+// Popup menus: the title on the left, then a box with a drop shadow showing the selected item and
+// a downward-pointing triangle.
+fn paint_popup(canvas: &mut RgbaImage, part: &Part, rect: Rect, fonts: &Fonts) {
+    let gray = !part.enabled;
+    let style = TextStyle::of_part(part);
+    let baseline = centered_baseline(rect, &fonts.metrics(&style));
+    let title_width = part.title_width as i32;
+    if title_width > 0 && part.show_name {
+        fonts.draw_string(canvas, rect.1 + 2, baseline, &part.name, &style, gray);
+    }
+
+    let body = popup_body(part, rect);
+    draw::fill_rect(canvas, body, WHITE);
+    draw::frame_rect(canvas, body, BLACK);
+    draw::hline(canvas, body.1 + 3, body.3 + 1, body.2, BLACK);
+    draw::vline(canvas, body.3, body.0 + 3, body.2 + 1, BLACK);
+
+    let items = part.contents.first().map(|c| c.text.as_str()).unwrap_or("");
+    let selected = items
+        .split('\r')
+        .nth((part.selected_line.max(1) - 1) as usize)
+        .unwrap_or("");
+    fonts.draw_string(canvas, body.1 + 13, baseline, selected, &style, gray);
+
+    // 11 pixels wide at the top, narrowing by 2 pixels a row.
+    let arrow_x = body.3 - 22;
+    let arrow_y = body.0 + (draw::height(body) - 6) / 2;
+    for row in 0..6 {
+        draw::hline(
+            canvas,
+            arrow_x + row,
+            arrow_x + 11 - row,
+            arrow_y + row,
+            BLACK,
+        );
+    }
+    if gray {
+        gray_out(canvas, body);
+    }
+}
+
+// Knock out every other pixel, the way the Control Manager draws disabled controls.
+fn gray_out(canvas: &mut RgbaImage, rect: Rect) {
+    for y in rect.0..rect.2 {
+        for x in rect.1..rect.3 {
+            if (x + y).rem_euclid(2) == 1 {
+                draw::set_pixel(canvas, x, y, WHITE);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Chicago 12.
+    const METRICS: FontMetrics = FontMetrics {
+        ascent: 11,
+        descent: 3,
+        leading: 1,
+    };
+
+    fn button(style: PartStyle, text_alignment: TextAlignment) -> Part {
+        Part {
+            style,
+            text_alignment,
+            show_name: true,
+            ..Part::default()
+        }
+    }
+
+    #[test]
+    fn the_frame_leaves_room_for_shadows_and_outlines() {
+        let rect = (10, 20, 40, 120);
+        assert_eq!(frame_of(&PartStyle::Opaque, rect), (rect, rect));
+        assert_eq!(
+            frame_of(&PartStyle::Rectangle, rect),
+            (rect, (11, 21, 39, 119))
+        );
+        assert_eq!(
+            frame_of(&PartStyle::Shadow, rect),
+            ((10, 20, 39, 119), (11, 21, 38, 118))
+        );
+        assert_eq!(
+            frame_of(&PartStyle::Default, rect),
+            ((14, 24, 36, 116), (15, 25, 35, 115))
+        );
+    }
+
+    #[test]
+    fn the_name_follows_the_alignment() {
+        let content = (0, 0, 20, 100);
+        let x = |alignment| {
+            let part = button(PartStyle::Rectangle, alignment);
+            Label::place(&part, content, false, 30, &METRICS)
+                .name
+                .unwrap()
+                .0
+        };
+        assert_eq!(x(TextAlignment::Left), 4);
+        assert_eq!(x(TextAlignment::Center), 35);
+        assert_eq!(x(TextAlignment::Right), 66);
+    }
+
+    #[test]
+    fn an_icon_and_its_name_are_centered_together() {
+        let part = button(PartStyle::Transparent, TextAlignment::Left);
+        let label = Label::place(&part, (0, 0, 80, 100), true, 30, &METRICS);
+        // 32 pixels of icon and a 15 pixel line, so 47 pixels in the middle of 80.
+        assert_eq!(label.icon, Some((34, 16)));
+        assert_eq!(label.name, Some((35, 16 + 32 + 11 + 1)));
+
+        let part = Part {
+            show_name: false,
+            ..part
+        };
+        let label = Label::place(&part, (0, 0, 80, 100), true, 30, &METRICS);
+        assert_eq!(label.icon, Some((34, 24)));
+        assert_eq!(label.name, None);
+    }
+
+    #[test]
+    fn the_mark_is_centered_on_the_left() {
+        let (mark, name_x) = mark_of((10, 20, 30, 120));
+        assert_eq!(mark, (14, 23, 26, 35));
+        assert_eq!(name_x, 40);
+        assert_eq!(centered_baseline((10, 20, 30, 120), &METRICS), 24);
+    }
+}
@@ -0,0 +1,66 @@
+use hc_decode::bitmap::Bitmap;
+use hc_decode::card::Card;
+use hc_decode::stack::Stack;
+use image::RgbaImage;
+
+use crate::draw::{self, BLACK, WHITE};
+use crate::part::paint_part;
+use crate::text::Fonts;
+
+/// Which layer of the card an object lives on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Layer {
+    Background,
+    Card,
+}
+
+/// Render a card the way HyperCard would show it: the background picture, then the background's
+/// buttons and fields, then the card picture, then the card's own buttons and fields.
+/// The result is the size of the stack's cards.
+pub fn render_card(stack: &Stack, card: &Card) -> RgbaImage {
+    render_card_with_fonts(stack, &Fonts::for_stack(stack), card)
+}
+
+/// `render_card`, with fonts that have already been loaded, for rendering a lot of cards.
+pub fn render_card_with_fonts(stack: &Stack, fonts: &Fonts, card: &Card) -> RgbaImage {
+    let (width, height) = (stack.size.0 as u32, stack.size.1 as u32);
+    let mut canvas = RgbaImage::from_pixel(width, height, WHITE);
+
+    if let Some(background) = stack.background(card.background_id) {
+        if background.show_picture() {
+            if let Some(bitmap) = stack.bitmap(background.bitmap_id) {
+                paint_bitmap(&mut canvas, bitmap);
+            }
+        }
+        for part in &background.parts {
+            paint_part(&mut canvas, stack, fonts, card, part, Layer::Background);
+        }
+    }
+
+    if card.show_picture() {
+        if let Some(bitmap) = stack.bitmap(card.bitmap_block_id) {
+            paint_bitmap(&mut canvas, bitmap);
+        }
+    }
+    for part in &card.parts {
+        paint_part(&mut canvas, stack, fonts, card, part, Layer::Card);
+    }
+
+    canvas
+}
+
+/// Paint a layer's picture. Wherever the mask is opaque the picture replaces what's underneath,
+/// and everywhere else only its black pixels are drawn.
+pub fn paint_bitmap(canvas: &mut RgbaImage, bitmap: &Bitmap) {
+    for (x, y, pixel) in bitmap.image.enumerate_pixels() {
+        if x >= canvas.width() || y >= canvas.height() {
+            continue;
+        }
+        let black = pixel.0[0] < 128;
+        if black {
+            draw::set_pixel(canvas, x as i32, y as i32, BLACK);
+        } else if bitmap.mask.get_pixel(x, y).0[0] >= 128 {
+            draw::set_pixel(canvas, x as i32, y as i32, WHITE);
+        }
+    }
+}
@@ -0,0 +1,147 @@
+// Contact sheets: every card of a stack shrunk down and laid out in a labelled grid, for seeing
+// what a stack is at a glance. The thumbnails can be had on their own, too.
+
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use hc_decode::card::Card;
+use hc_decode::stack::Stack;
+use image::{imageops, RgbaImage};
+
+use crate::compositor::render_card_with_fonts;
+use crate::draw::{self, BLACK, WHITE};
+use crate::text::{Fonts, TextStyle};
+
+// space around the sheet and between the cells.
+const MARGIN: u32 = 8;
+const GAP: u32 = 12;
+// labels are in Geneva 9.
+const LABEL_STYLE: TextStyle = TextStyle {
+    font: 3,
+    size: 9,
+    face: 0,
+};
+
+#[derive(Debug, Clone)]
+pub struct ContactSheetOptions {
+    /// How many cards go across each row.
+    pub columns: u32,
+    /// Width of each thumbnail; the height keeps the card's proportions.
+    pub thumbnail_width: u32,
+}
+
+impl Default for ContactSheetOptions {
+    fn default() -> Self {
+        ContactSheetOptions {
+            columns: 4,
+            thumbnail_width: 128,
+        }
+    }
+}
+
+/// A card rendered and scaled down to `width` pixels across.
+pub fn thumbnail(stack: &Stack, fonts: &Fonts, card: &Card, width: u32) -> RgbaImage {
+    let image = render_card_with_fonts(stack, fonts, card);
+    let height = (image.height() * width / image.width().max(1)).max(1);
+    imageops::thumbnail(&image, width, height)
+}
+
+/// Thumbnails of every card, in stack order, along with the card's ID.
+pub fn thumbnails(stack: &Stack, width: u32) -> Vec<(u32, RgbaImage)> {
+    let fonts = Fonts::for_stack(stack);
+    stack
+        .cards
+        .iter()
+        .map(|card| (card.id, thumbnail(stack, &fonts, card, width)))
+        .collect()
+}
+
+/// Write a PNG thumbnail of every card into `dir`, named after the card's number and ID, and
+/// return the paths written.
+pub fn write_thumbnails(
+    stack: &Stack,
+    width: u32,
+    dir: &Path,
+) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+    fs::create_dir_all(dir)?;
+    let mut paths = Vec::new();
+    for (number, (id, image)) in thumbnails(stack, width).into_iter().enumerate() {
+        let path = dir.join(format!("card-{}-{}.png", number + 1, id));
+        image.save(&path)?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+/// Every card of the stack in a grid, each labelled with its number, name and ID.
+pub fn contact_sheet(stack: &Stack, options: &ContactSheetOptions) -> RgbaImage {
+    let fonts = Fonts::for_stack(stack);
+    let metrics = fonts.metrics(&LABEL_STYLE);
+    let columns = options.columns.max(1);
+    let thumb_width = options.thumbnail_width.max(1);
+    let thumb_height = (stack.size.1 as u32 * thumb_width / (stack.size.0 as u32).max(1)).max(1);
+    let label_height = metrics.line_height() as u32 + 2;
+    let cell_height = thumb_height + label_height;
+
+    let count = stack.cards.len() as u32;
+    let rows = count.div_ceil(columns).max(1);
+    let width = MARGIN * 2 + columns * thumb_width + (columns - 1) * GAP;
+    let height = MARGIN * 2 + rows * cell_height + (rows - 1) * GAP;
+    let mut sheet = RgbaImage::from_pixel(width, height, WHITE);
+
+    for (i, card) in stack.cards.iter().enumerate() {
+        let (col, row) = (i as u32 % columns, i as u32 / columns);
+        let x = MARGIN + col * (thumb_width + GAP);
+        let y = MARGIN + row * (cell_height + GAP);
+        let thumb = thumbnail(stack, &fonts, card, thumb_width);
+        imageops::replace(&mut sheet, &thumb, x as i64, y as i64);
+        // a frame around the card, just outside it.
+        draw::frame_rect(
+            &mut sheet,
+            (
+                y as i32 - 1,
+                x as i32 - 1,
+                (y + thumb.height()) as i32 + 1,
+                (x + thumb.width()) as i32 + 1,
+            ),
+            BLACK,
+        );
+
+        let label = card_label(i + 1, card);
+        let label = fit(&fonts, &label, thumb_width as i32);
+        fonts.draw_string(
+            &mut sheet,
+            x as i32,
+            (y + thumb_height + 2) as i32 + metrics.ascent,
+            &label,
+            &LABEL_STYLE,
+            false,
+        );
+    }
+    sheet
+}
+
+fn card_label(number: usize, card: &Card) -> String {
+    if card.name.is_empty() {
+        format!("{}. (ID {})", number, card.id)
+    } else {
+        format!("{}. {} (ID {})", number, card.name, card.id)
+    }
+}
+
+// Shorten text with an ellipsis until it fits in `width`.
+fn fit(fonts: &Fonts, text: &str, width: i32) -> String {
+    if fonts.string_width(text, &LABEL_STYLE) <= width {
+        return text.to_string();
+    }
+    let mut chars: Vec<char> = text.chars().collect();
+    while !chars.is_empty() {
+        chars.pop();
+        let shortened: String = chars.iter().chain(['…'].iter()).collect();
+        if fonts.string_width(&shortened, &LABEL_STYLE) <= width {
+            return shortened;
+        }
+    }
+    String::new()
+}
@@ -0,0 +1,233 @@
+// QuickDraw-style drawing primitives on top of an RgbaImage.
+//
+// Rectangles follow the same (top, left, bottom, right) order as the decoder, and like QuickDraw
+// they describe the lines *between* pixels, so the pixels covered are left..right and top..bottom
+// exclusive. Frames are drawn on the inside of the rectangle.
+
+use image::{Rgba, RgbaImage};
+
+/// (top, left, bottom, right)
+pub type Rect = (i32, i32, i32, i32);
+
+pub const BLACK: Rgba<u8> = Rgba([0, 0, 0, 255]);
+pub const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+
+pub fn rect_from(position: (u16, u16, u16, u16)) -> Rect {
+    (
+        position.0 as i16 as i32,
+        position.1 as i16 as i32,
+        position.2 as i16 as i32,
+        position.3 as i16 as i32,
+    )
+}
+
+pub fn inset(rect: Rect, dx: i32, dy: i32) -> Rect {
+    (rect.0 + dy, rect.1 + dx, rect.2 - dy, rect.3 - dx)
+}
+
+pub fn offset(rect: Rect, dx: i32, dy: i32) -> Rect {
+    (rect.0 + dy, rect.1 + dx, rect.2 + dy, rect.3 + dx)
+}
+
+pub fn intersect(a: Rect, b: Rect) -> Rect {
+    (a.0.max(b.0), a.1.max(b.1), a.2.min(b.2), a.3.min(b.3))
+}
+
+pub fn width(rect: Rect) -> i32 {
+    rect.3 - rect.1
+}
+
+pub fn height(rect: Rect) -> i32 {
+    rect.2 - rect.0
+}
+
+pub fn is_black(c: &Rgba<u8>) -> bool {
+    c.0[0] < 128
+}
+
+pub fn set_pixel(img: &mut RgbaImage, x: i32, y: i32, color: Rgba<u8>) {
+    if x >= 0 && y >= 0 && (x as u32) < img.width() && (y as u32) < img.height() {
+        img.put_pixel(x as u32, y as u32, color);
+    }
+}
+
+pub fn invert_pixel(img: &mut RgbaImage, x: i32, y: i32) {
+    if x >= 0 && y >= 0 && (x as u32) < img.width() && (y as u32) < img.height() {
+        let p = img.get_pixel_mut(x as u32, y as u32);
+        p.0 = [255 - p.0[0], 255 - p.0[1], 255 - p.0[2], p.0[3]];
+    }
+}
+
+pub fn hline(img: &mut RgbaImage, x0: i32, x1: i32, y: i32, color: Rgba<u8>) {
+    for x in x0..x1 {
+        set_pixel(img, x, y, color);
+    }
+}
+
+pub fn vline(img: &mut RgbaImage, x: i32, y0: i32, y1: i32, color: Rgba<u8>) {
+    for y in y0..y1 {
+        set_pixel(img, x, y, color);
+    }
+}
+
+pub fn fill_rect(img: &mut RgbaImage, rect: Rect, color: Rgba<u8>) {
+    for y in rect.0..rect.2 {
+        hline(img, rect.1, rect.3, y, color);
+    }
+}
+
+pub fn frame_rect(img: &mut RgbaImage, rect: Rect, color: Rgba<u8>) {
+    if width(rect) <= 0 || height(rect) <= 0 {
+        return;
+    }
+    hline(img, rect.1, rect.3, rect.0, color);
+    hline(img, rect.1, rect.3, rect.2 - 1, color);
+    vline(img, rect.1, rect.0, rect.2, color);
+    vline(img, rect.3 - 1, rect.0, rect.2, color);
+}
+
+pub fn invert_rect(img: &mut RgbaImage, rect: Rect) {
+    for y in rect.0..rect.2 {
+        for x in rect.1..rect.3 {
+            invert_pixel(img, x, y);
+        }
+    }
+}
+
+/// Whether the pixel at (x, y) falls inside a rounded rectangle whose corners are quarters of an
+/// oval `ow` wide and `oh` high. An oval is just a rounded rectangle with the corners the size of
+/// the whole rectangle.
+pub fn in_round_rect(rect: Rect, ow: i32, oh: i32, x: i32, y: i32) -> bool {
+    if x < rect.1 || x >= rect.3 || y < rect.0 || y >= rect.2 {
+        return false;
+    }
+    let rx = (ow.min(width(rect)) as f32) / 2.0;
+    let ry = (oh.min(height(rect)) as f32) / 2.0;
+    if rx <= 0.0 || ry <= 0.0 {
+        return true;
+    }
+    // sample the centre of the pixel against whichever corner oval it's closest to.
+    let px = x as f32 + 0.5;
+    let py = y as f32 + 0.5;
+    let cx = if px < rect.1 as f32 + rx {
+        rect.1 as f32 + rx
+    } else if px > rect.3 as f32 - rx {
+        rect.3 as f32 - rx
+    } else {
+        return true;
+    };
+    let cy = if py < rect.0 as f32 + ry {
+        rect.0 as f32 + ry
+    } else if py > rect.2 as f32 - ry {
+        rect.2 as f32 - ry
+    } else {
+        return true;
+    };
+    let (dx, dy) = ((px - cx) / rx, (py - cy) / ry);
+    dx * dx + dy * dy <= 1.0
+}
+
+pub fn fill_round_rect(img: &mut RgbaImage, rect: Rect, ow: i32, oh: i32, color: Rgba<u8>) {
+    for y in rect.0..rect.2 {
+        for x in rect.1..rect.3 {
+            if in_round_rect(rect, ow, oh, x, y) {
+                set_pixel(img, x, y, color);
+            }
+        }
+    }
+}
+
+pub fn frame_round_rect(img: &mut RgbaImage, rect: Rect, ow: i32, oh: i32, color: Rgba<u8>) {
+    for y in rect.0..rect.2 {
+        for x in rect.1..rect.3 {
+            if in_round_rect(rect, ow, oh, x, y)
+                && !(in_round_rect(rect, ow, oh, x - 1, y)
+                    && in_round_rect(rect, ow, oh, x + 1, y)
+                    && in_round_rect(rect, ow, oh, x, y - 1)
+                    && in_round_rect(rect, ow, oh, x, y + 1))
+            {
+                set_pixel(img, x, y, color);
+            }
+        }
+    }
+}
+
+pub fn invert_round_rect(img: &mut RgbaImage, rect: Rect, ow: i32, oh: i32) {
+    for y in rect.0..rect.2 {
+        for x in rect.1..rect.3 {
+            if in_round_rect(rect, ow, oh, x, y) {
+                invert_pixel(img, x, y);
+            }
+        }
+    }
+}
+
+pub fn fill_oval(img: &mut RgbaImage, rect: Rect, color: Rgba<u8>) {
+    fill_round_rect(img, rect, width(rect), height(rect), color);
+}
+
+pub fn frame_oval(img: &mut RgbaImage, rect: Rect, color: Rgba<u8>) {
+    frame_round_rect(img, rect, width(rect), height(rect), color);
+}
+
+pub fn invert_oval(img: &mut RgbaImage, rect: Rect) {
+    invert_round_rect(img, rect, width(rect), height(rect));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn black_pixels(img: &RgbaImage) -> Vec<(u32, u32)> {
+        img.enumerate_pixels()
+            .filter(|(_, _, p)| is_black(p))
+            .map(|(x, y, _)| (x, y))
+            .collect()
+    }
+
+    #[test]
+    fn rectangles_cover_the_pixels_between_their_lines() {
+        let mut img = RgbaImage::from_pixel(6, 6, WHITE);
+        fill_rect(&mut img, (1, 2, 3, 4), BLACK);
+        assert_eq!(black_pixels(&img), [(2, 1), (3, 1), (2, 2), (3, 2)]);
+        assert_eq!(width((1, 2, 3, 4)), 2);
+        assert_eq!(height((1, 2, 3, 4)), 2);
+    }
+
+    #[test]
+    fn frames_are_drawn_inside_the_rectangle() {
+        let mut img = RgbaImage::from_pixel(5, 5, WHITE);
+        frame_rect(&mut img, (0, 0, 5, 5), BLACK);
+        assert_eq!(black_pixels(&img).len(), 16);
+        assert!(!is_black(img.get_pixel(2, 2)));
+        // an empty rectangle has no frame.
+        frame_rect(&mut img, (2, 2, 2, 4), WHITE);
+        assert_eq!(black_pixels(&img).len(), 16);
+    }
+
+    #[test]
+    fn drawing_off_the_edge_is_clipped() {
+        let mut img = RgbaImage::from_pixel(4, 4, WHITE);
+        fill_rect(&mut img, (-2, -2, 2, 2), BLACK);
+        invert_rect(&mut img, (1, 1, 10, 10));
+        assert_eq!(black_pixels(&img).len(), 3 + 8);
+    }
+
+    #[test]
+    fn round_rect_corners_are_cut_off() {
+        let rect = (0, 0, 20, 20);
+        assert!(!in_round_rect(rect, 16, 16, 0, 0));
+        assert!(in_round_rect(rect, 16, 16, 10, 0));
+        assert!(in_round_rect(rect, 16, 16, 8, 8));
+        assert!(in_round_rect(rect, 0, 0, 0, 0));
+        assert!(!in_round_rect(rect, 16, 16, 20, 10));
+    }
+
+    #[test]
+    fn positions_are_signed() {
+        assert_eq!(rect_from((0xFFF6, 5, 30, 40)), (-10, 5, 30, 40));
+        assert_eq!(inset((0, 0, 10, 20), 2, 1), (1, 2, 9, 18));
+        assert_eq!(offset((0, 0, 10, 20), 2, 1), (1, 2, 11, 22));
+        assert_eq!(intersect((0, 0, 10, 20), (5, -5, 15, 10)), (5, 0, 10, 10));
+    }
+}
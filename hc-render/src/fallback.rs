@@ -0,0 +1,152 @@
+// The fonts text falls back on when a stack uses a font it doesn't carry, and which of them stands
+// in for each of the classic Mac fonts. See fonts/README.md for where they came from.
+
+use hc_decode::bitmap_font::BitmapFont;
+use hc_decode::stack::Stack;
+
+/// One of the bundled font families.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Family {
+    /// Stands in for Chicago, the system font.
+    System,
+    /// Stands in for Geneva, the application font.
+    Application,
+    /// Stands in for Monaco.
+    Monospace,
+    /// Stands in for New York.
+    Serif,
+}
+
+const SYSTEM: [(u16, &[u8]); 6] = [
+    (9, include_bytes!("../fonts/system-9.nfnt")),
+    (10, include_bytes!("../fonts/system-10.nfnt")),
+    (12, include_bytes!("../fonts/system-12.nfnt")),
+    (14, include_bytes!("../fonts/system-14.nfnt")),
+    (18, include_bytes!("../fonts/system-18.nfnt")),
+    (24, include_bytes!("../fonts/system-24.nfnt")),
+];
+const APPLICATION: [(u16, &[u8]); 6] = [
+    (9, include_bytes!("../fonts/application-9.nfnt")),
+    (10, include_bytes!("../fonts/application-10.nfnt")),
+    (12, include_bytes!("../fonts/application-12.nfnt")),
+    (14, include_bytes!("../fonts/application-14.nfnt")),
+    (18, include_bytes!("../fonts/application-18.nfnt")),
+    (24, include_bytes!("../fonts/application-24.nfnt")),
+];
+const MONOSPACE: [(u16, &[u8]); 6] = [
+    (9, include_bytes!("../fonts/monospace-9.nfnt")),
+    (10, include_bytes!("../fonts/monospace-10.nfnt")),
+    (12, include_bytes!("../fonts/monospace-12.nfnt")),
+    (14, include_bytes!("../fonts/monospace-14.nfnt")),
+    (18, include_bytes!("../fonts/monospace-18.nfnt")),
+    (24, include_bytes!("../fonts/monospace-24.nfnt")),
+];
+const SERIF: [(u16, &[u8]); 6] = [
+    (9, include_bytes!("../fonts/serif-9.nfnt")),
+    (10, include_bytes!("../fonts/serif-10.nfnt")),
+    (12, include_bytes!("../fonts/serif-12.nfnt")),
+    (14, include_bytes!("../fonts/serif-14.nfnt")),
+    (18, include_bytes!("../fonts/serif-18.nfnt")),
+    (24, include_bytes!("../fonts/serif-24.nfnt")),
+];
+
+/// Font IDs from the original Font Manager, and the family that stands in for each. IDs that
+/// aren't listed get `Family::Application`, the same as QuickDraw substituting the application
+/// font for fonts that aren't installed.
+pub const FONT_IDS: [(i16, Family); 25] = [
+    (0, Family::System),       // Chicago (the system font)
+    (1, Family::Application),  // Geneva (the application font)
+    (2, Family::Serif),        // New York
+    (3, Family::Application),  // Geneva
+    (4, Family::Monospace),    // Monaco
+    (5, Family::Serif),        // Venice
+    (6, Family::Serif),        // London
+    (7, Family::Serif),        // Athens
+    (8, Family::System),       // San Francisco
+    (9, Family::Serif),        // Toronto
+    (11, Family::Application), // Cairo
+    (12, Family::Application), // Los Angeles
+    (13, Family::Application), // Zapf Dingbats
+    (14, Family::Serif),       // Bookman
+    (15, Family::Application), // Helvetica Narrow
+    (16, Family::Serif),       // Palatino
+    (18, Family::Serif),       // Zapf Chancery
+    (20, Family::Serif),       // Times
+    (21, Family::Application), // Helvetica
+    (22, Family::Monospace),   // Courier
+    (23, Family::Application), // Symbol
+    (24, Family::Application), // Taliesin
+    (33, Family::Application), // Avant Garde
+    (34, Family::Serif),       // New Century Schoolbook
+    (16383, Family::System),   // Chicago, by its real family ID
+];
+
+// Names from stacks' font tables, which can say more than the ID does.
+const FONT_NAMES: [(&str, Family); 15] = [
+    ("Chicago", Family::System),
+    ("Charcoal", Family::System),
+    ("San Francisco", Family::System),
+    ("Geneva", Family::Application),
+    ("Helvetica", Family::Application),
+    ("Arial", Family::Application),
+    ("Espy Sans", Family::Application),
+    ("Monaco", Family::Monospace),
+    ("Courier", Family::Monospace),
+    ("Courier New", Family::Monospace),
+    ("New York", Family::Serif),
+    ("Times", Family::Serif),
+    ("Times New Roman", Family::Serif),
+    ("Palatino", Family::Serif),
+    ("Bookman", Family::Serif),
+];
+
+pub fn family_for_id(id: i16) -> Family {
+    FONT_IDS
+        .iter()
+        .find(|f| f.0 == id)
+        .map(|f| f.1)
+        .unwrap_or(Family::Application)
+}
+
+pub fn family_for_name(name: &str) -> Option<Family> {
+    FONT_NAMES
+        .iter()
+        .find(|f| f.0.eq_ignore_ascii_case(name))
+        .map(|f| f.1)
+}
+
+/// A CSS `font-family` list for a font: its name from the stack's font table, then the fonts that
+/// look like the bundled family standing in for it.
+pub fn css_font_family(stack: &Stack, id: i16) -> String {
+    let name = stack.font(id as u16).map(|f| f.name.as_str());
+    let family = name
+        .and_then(family_for_name)
+        .unwrap_or_else(|| family_for_id(id));
+    let generic = match family {
+        Family::System => "Chicago, Charcoal, sans-serif",
+        Family::Application => "Geneva, Helvetica, Arial, sans-serif",
+        Family::Monospace => "Monaco, Courier, monospace",
+        Family::Serif => "'New York', Times, serif",
+    };
+    match name {
+        Some(name) => format!("'{}', {}", name.replace('\'', ""), generic),
+        None => generic.to_string(),
+    }
+}
+
+/// The bundled bitmap fonts of a family, by size.
+pub fn bitmap_fonts(family: Family) -> Vec<(u16, BitmapFont)> {
+    let sizes = match family {
+        Family::System => &SYSTEM,
+        Family::Application => &APPLICATION,
+        Family::Monospace => &MONOSPACE,
+        Family::Serif => &SERIF,
+    };
+    sizes
+        .iter()
+        .map(|(size, data)| {
+            let font = BitmapFont::from_bytes(data).expect("bundled font is invalid");
+            (*size, font)
+        })
+        .collect()
+}
@@ -0,0 +1,232 @@
+// Fields: the frame, the text inside it, the dotted lines of a showLines field, and the scroll bar
+// of a scrolling one.
+
+use hc_decode::card::Card;
+use hc_decode::part::{Part, PartStyle};
+use hc_decode::stack::Stack;
+use image::RgbaImage;
+
+use crate::compositor::Layer;
+use crate::draw::{self, Rect, BLACK, WHITE};
+use crate::layout::{self, TextLayout};
+use crate::text::Fonts;
+
+/// Width of a scrolling field's scroll bar, including its frame.
+pub const SCROLL_BAR_WIDTH: i32 = 16;
+
+// the scroll arrows, hollow, in the top 16x16 square of the bar. The leftmost pixel of each row is
+// the highest bit. The down arrow is the same thing upside down.
+const ARROW: [u16; 16] = [
+    0x0000, 0x0000, 0x0000, 0x0000, 0x0100, 0x0280, 0x0440, 0x0820, 0x1010, 0x3C78, 0x0440, 0x0440,
+    0x07C0, 0x0000, 0x0000, 0x0000,
+];
+
+pub fn paint_field(
+    canvas: &mut RgbaImage,
+    stack: &Stack,
+    fonts: &Fonts,
+    card: &Card,
+    part: &Part,
+    layer: Layer,
+) {
+    let rect = draw::rect_from(part.position);
+    let mut interior = paint_frame(canvas, part, rect);
+    let layout = TextLayout::for_field(stack, fonts, card, part, layer == Layer::Background);
+    let (scroll, max_scroll) = scroll_of(part, &layout);
+
+    if matches!(part.style, PartStyle::Scrolling) {
+        let bar = (rect.0, rect.3 - SCROLL_BAR_WIDTH, rect.2, rect.3);
+        paint_scroll_bar(canvas, bar, scroll, max_scroll);
+        interior.3 = bar.1;
+    }
+    if part.show_lines {
+        paint_lines(canvas, &layout, interior, scroll);
+    }
+    // text is clipped to the inside of the frame, so draw it off to the side first.
+    let mut text_layer = RgbaImage::new(canvas.width(), canvas.height());
+    for glyph in &layout.glyphs {
+        let baseline = glyph.baseline - scroll;
+        if baseline < interior.0 || baseline - fonts.metrics(&glyph.style).ascent > interior.2 {
+            continue;
+        }
+        let ch = layout::visible_char(glyph.ch).to_string();
+        fonts.draw_string(&mut text_layer, glyph.x, baseline, &ch, &glyph.style, false);
+    }
+    let clip = draw::intersect(
+        interior,
+        (0, 0, canvas.height() as i32, canvas.width() as i32),
+    );
+    for y in clip.0..clip.2 {
+        for x in clip.1..clip.3 {
+            if text_layer.get_pixel(x as u32, y as u32).0[3] != 0 {
+                draw::set_pixel(canvas, x, y, BLACK);
+            }
+        }
+    }
+}
+
+/// How far a field's text can be scrolled: the height of its text less the height it has to show
+/// it in.
+pub fn max_scroll(stack: &Stack, fonts: &Fonts, card: &Card, part: &Part, layer: Layer) -> u16 {
+    max_scroll_of(&TextLayout::for_field(
+        stack,
+        fonts,
+        card,
+        part,
+        layer == Layer::Background,
+    )) as u16
+}
+
+/// Set the scroll of a field as seen from the card `card_id`, like `set the scroll of field 1 to
+/// 40`. The scroll is clamped to what the field's text allows, and the scroll the field ended up
+/// with is returned; `None` means there's no such card or field.
+pub fn set_scroll(
+    stack: &mut Stack,
+    card_id: u32,
+    layer: Layer,
+    part_id: u16,
+    scroll: i32,
+) -> Option<u16> {
+    let card = stack.card(card_id)?;
+    let part = match layer {
+        Layer::Card => Part::find(&card.parts, part_id)?,
+        Layer::Background => Part::find(&stack.background(card.background_id)?.parts, part_id)?,
+    };
+    let fonts = Fonts::for_stack(stack);
+    let scroll = scroll.clamp(0, max_scroll(stack, &fonts, card, part, layer) as i32) as u16;
+
+    let background_id = card.background_id;
+    let parts = match layer {
+        Layer::Card => &mut stack.card_mut(card_id)?.parts,
+        Layer::Background => &mut stack.background_mut(background_id)?.parts,
+    };
+    Part::find_mut(parts, part_id)?.scroll = scroll;
+    Some(scroll)
+}
+
+fn max_scroll_of(layout: &TextLayout) -> i32 {
+    (layout.height() - draw::height(layout.rect)).max(0)
+}
+
+// The field's scroll and the furthest it can scroll. A scroll past the end of the text, from the
+// stack or a script, shows the end.
+pub(crate) fn scroll_of(part: &Part, layout: &TextLayout) -> (i32, i32) {
+    let max_scroll = max_scroll_of(layout);
+    ((part.scroll as i32).min(max_scroll), max_scroll)
+}
+
+// Draw the field's body and frame, returning the rectangle inside the frame.
+fn paint_frame(canvas: &mut RgbaImage, part: &Part, rect: Rect) -> Rect {
+    match part.style {
+        PartStyle::Transparent => rect,
+        PartStyle::Opaque => {
+            draw::fill_rect(canvas, rect, WHITE);
+            rect
+        }
+        PartStyle::Shadow => {
+            let body = (rect.0, rect.1, rect.2 - 1, rect.3 - 1);
+            draw::fill_rect(canvas, body, WHITE);
+            draw::frame_rect(canvas, body, BLACK);
+            draw::hline(canvas, rect.1 + 2, rect.3, rect.2 - 1, BLACK);
+            draw::vline(canvas, rect.3 - 1, rect.0 + 2, rect.2, BLACK);
+            draw::inset(body, 1, 1)
+        }
+        _ => {
+            draw::fill_rect(canvas, rect, WHITE);
+            draw::frame_rect(canvas, rect, BLACK);
+            draw::inset(rect, 1, 1)
+        }
+    }
+}
+
+// A vertical scroll bar: arrows at each end and a gray track with a white thumb between them. When
+// there's nothing to scroll the track is left white and there's no thumb, like an inactive control.
+fn paint_scroll_bar(canvas: &mut RgbaImage, bar: Rect, scroll: i32, max_scroll: i32) {
+    draw::fill_rect(canvas, bar, WHITE);
+    draw::frame_rect(canvas, bar, BLACK);
+    let size = SCROLL_BAR_WIDTH;
+    let up = (bar.0, bar.1, bar.0 + size, bar.3);
+    let down = (bar.2 - size, bar.1, bar.2, bar.3);
+    draw::frame_rect(canvas, up, BLACK);
+    draw::frame_rect(canvas, down, BLACK);
+    for (row, bits) in ARROW.iter().enumerate() {
+        for bit in 0..16 {
+            if bits & (0x8000 >> bit) != 0 {
+                draw::set_pixel(canvas, bar.1 + bit, up.0 + row as i32, BLACK);
+                draw::set_pixel(canvas, bar.1 + bit, down.2 - 1 - row as i32, BLACK);
+            }
+        }
+    }
+
+    // the track runs between the arrows, sharing their edges.
+    let track = (up.2 - 1, bar.1, down.0 + 1, bar.3);
+    if max_scroll == 0 || draw::height(track) < size {
+        return;
+    }
+    let inside = draw::inset(track, 1, 1);
+    for y in inside.0..inside.2 {
+        for x in inside.1..inside.3 {
+            if (x + y).rem_euclid(2) == 0 {
+                draw::set_pixel(canvas, x, y, BLACK);
+            }
+        }
+    }
+    let thumb_top =
+        track.0 + ((draw::height(track) - size) as i64 * scroll as i64 / max_scroll as i64) as i32;
+    let thumb = (thumb_top, bar.1, thumb_top + size, bar.3);
+    draw::fill_rect(canvas, thumb, WHITE);
+    draw::frame_rect(canvas, thumb, BLACK);
+}
+
+// A dotted line under every line of text, carrying on at the same spacing to the bottom of the
+// field.
+fn paint_lines(canvas: &mut RgbaImage, layout: &TextLayout, interior: Rect, scroll: i32) {
+    let mut baselines: Vec<i32> = layout.lines.iter().map(|l| l.baseline).collect();
+    let (mut top, descent) = match layout.lines.last() {
+        Some(last) => (last.bottom, last.bottom - last.baseline),
+        None => (layout.rect.0, layout.empty_line_height / 4),
+    };
+    while top + layout.empty_line_height <= interior.2 + scroll {
+        top += layout.empty_line_height;
+        baselines.push(top - descent);
+    }
+    for y in baselines.into_iter().map(|y| y - scroll) {
+        if y < interior.0 || y >= interior.2 {
+            continue;
+        }
+        for x in (interior.1..interior.3).filter(|x| x % 2 == 0) {
+            draw::set_pixel(canvas, x, y, BLACK);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::layout::LayoutOptions;
+    use crate::text::TextStyle;
+    use hc_decode::part::TextAlignment;
+
+    #[test]
+    fn a_scroll_past_the_end_shows_the_end() {
+        // three lines of Chicago 12, 15 pixels each, in a field 20 pixels high.
+        let options = LayoutOptions {
+            rect: (0, 0, 20, 100),
+            wrap: true,
+            fixed_line_height: None,
+            alignment: TextAlignment::Left,
+            style: TextStyle {
+                font: 0,
+                size: 12,
+                face: 0,
+            },
+        };
+        let layout = TextLayout::new("one\rtwo\rthree", &[], &options, &Fonts::default());
+        let part = |scroll| Part {
+            scroll,
+            ..Part::default()
+        };
+        assert_eq!(scroll_of(&part(10), &layout), (10, 25));
+        assert_eq!(scroll_of(&part(400), &layout), (25, 25));
+    }
+}
@@ -0,0 +1,486 @@
+// Field text layout: breaking a field's (possibly styled) text into lines and working out where
+// every character goes. The renderer draws from this, and anything that needs to know which
+// character is under the mouse can hit-test against it.
+
+use hc_decode::card::Card;
+use hc_decode::part::{Part, PartStyle, TextAlignment};
+use hc_decode::stack::Stack;
+
+use crate::draw::{self, Rect};
+use crate::field::SCROLL_BAR_WIDTH;
+use crate::text::{FontMetrics, Fonts, TextStyle};
+
+// horizontal space between a field's frame and its text, and the extra added by wideMargins.
+const MARGIN: i32 = 3;
+const WIDE_MARGIN: i32 = 5;
+
+#[derive(Debug, Clone)]
+pub struct LayoutOptions {
+    /// The area the text is laid out in.
+    pub rect: Rect,
+    /// Break lines that are too long for the rectangle (that is, not dontWrap).
+    pub wrap: bool,
+    /// Every line is this high, rather than as high as its tallest run.
+    pub fixed_line_height: Option<i32>,
+    pub alignment: TextAlignment,
+    /// The style of any text not covered by a run.
+    pub style: TextStyle,
+}
+
+/// A change of style, starting at the character `start`.
+#[derive(Debug, Clone, Copy)]
+pub struct StyleRun {
+    pub start: usize,
+    pub style: TextStyle,
+}
+
+/// Where a single character was placed.
+#[derive(Debug, Clone, Copy)]
+pub struct GlyphPosition {
+    /// Index of the character in the text.
+    pub index: usize,
+    pub ch: char,
+    pub x: i32,
+    pub baseline: i32,
+    pub width: i32,
+    pub line: usize,
+    pub style: TextStyle,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct LineBox {
+    /// First character of the line.
+    pub start: usize,
+    /// One past the last character of the line, not counting the return that ended it.
+    pub end: usize,
+    pub top: i32,
+    pub baseline: i32,
+    pub bottom: i32,
+    pub left: i32,
+    pub width: i32,
+}
+
+#[derive(Debug, Clone)]
+pub struct TextLayout {
+    pub rect: Rect,
+    pub lines: Vec<LineBox>,
+    pub glyphs: Vec<GlyphPosition>,
+    /// Height of a line when there's no text to measure, used to continue the lines of a
+    /// showLines field past the end of its text.
+    pub empty_line_height: i32,
+}
+
+impl TextLayout {
+    /// Lay out the contents of a field as they appear on `card`. `background` says whether the
+    /// field belongs to the card's background.
+    pub fn for_field(
+        stack: &Stack,
+        fonts: &Fonts,
+        card: &Card,
+        part: &Part,
+        background: bool,
+    ) -> Self {
+        let (text, runs) = field_text(stack, card, part, background);
+        let options = LayoutOptions {
+            rect: text_rect(part),
+            wrap: !part.dont_wrap,
+            fixed_line_height: if part.fixed_line_height {
+                Some(part.line_height as i32)
+            } else {
+                None
+            },
+            alignment: part.text_alignment.clone(),
+            style: TextStyle::of_part(part),
+        };
+        Self::new(text, &runs, &options, fonts)
+    }
+
+    pub fn new(text: &str, runs: &[StyleRun], options: &LayoutOptions, fonts: &Fonts) -> Self {
+        let chars: Vec<char> = text.chars().collect();
+        let styles: Vec<TextStyle> = (0..chars.len())
+            .map(|i| {
+                runs.iter()
+                    .filter(|r| r.start <= i)
+                    .max_by_key(|r| r.start)
+                    .map(|r| r.style)
+                    .unwrap_or(options.style)
+            })
+            .collect();
+        let widths: Vec<i32> = chars
+            .iter()
+            .zip(&styles)
+            .map(|(ch, style)| fonts.char_width(visible_char(*ch), style))
+            .collect();
+        let default_metrics = fonts.metrics(&options.style);
+        let empty_line_height = options
+            .fixed_line_height
+            .unwrap_or(default_metrics.line_height());
+
+        let mut layout = TextLayout {
+            rect: options.rect,
+            lines: Vec::new(),
+            glyphs: Vec::new(),
+            empty_line_height,
+        };
+
+        // break into lines first, then place each one.
+        let mut breaks: Vec<(usize, usize)> = Vec::new();
+        let max_width = draw::width(options.rect);
+        let mut paragraph_start = 0;
+        for i in 0..=chars.len() {
+            if i < chars.len() && !is_return(chars[i]) {
+                continue;
+            }
+            if options.wrap {
+                wrap_paragraph(&chars, &widths, paragraph_start, i, max_width, &mut breaks);
+            } else {
+                breaks.push((paragraph_start, i));
+            }
+            paragraph_start = i + 1;
+        }
+
+        let mut top = options.rect.0;
+        for (start, end) in breaks {
+            let metrics = line_metrics(fonts, &styles[start..end], &default_metrics);
+            let (height, baseline) = match options.fixed_line_height {
+                Some(h) => (
+                    h,
+                    top + h - default_metrics.descent - default_metrics.leading,
+                ),
+                None => (metrics.line_height(), top + metrics.ascent),
+            };
+            // trailing spaces hang off the end of the line and don't count towards alignment.
+            let mut visible_end = end;
+            while visible_end > start && chars[visible_end - 1] == ' ' {
+                visible_end -= 1;
+            }
+            let width: i32 = widths[start..visible_end].iter().sum();
+            let left = match options.alignment {
+                TextAlignment::Center | TextAlignment::ForceCenterAlign => {
+                    options.rect.1 + (max_width - width) / 2
+                }
+                TextAlignment::Right | TextAlignment::ForceRightAlign => options.rect.3 - width,
+                _ => options.rect.1,
+            };
+
+            let line = layout.lines.len();
+            let mut x = left;
+            for i in start..end {
+                layout.glyphs.push(GlyphPosition {
+                    index: i,
+                    ch: chars[i],
+                    x,
+                    baseline,
+                    width: widths[i],
+                    line,
+                    style: styles[i],
+                });
+                x += widths[i];
+            }
+            layout.lines.push(LineBox {
+                start,
+                end,
+                top,
+                baseline,
+                bottom: top + height,
+                left,
+                width,
+            });
+            top += height;
+        }
+        layout
+    }
+
+    /// Total height of the laid out text.
+    pub fn height(&self) -> i32 {
+        match (self.lines.first(), self.lines.last()) {
+            (Some(first), Some(last)) => last.bottom - first.top,
+            _ => 0,
+        }
+    }
+
+    /// The character index nearest to the point, the way a click would place the insertion point.
+    /// The point is in unscrolled coordinates, so add the field's scroll to `y` first.
+    pub fn hit_test(&self, x: i32, y: i32) -> usize {
+        let line = match self
+            .lines
+            .iter()
+            .position(|l| y < l.bottom)
+            .or_else(|| self.lines.len().checked_sub(1))
+        {
+            Some(a) => &self.lines[a],
+            None => return 0,
+        };
+        for glyph in &self.glyphs[self.glyph_index(line.start)..self.glyph_index(line.end)] {
+            if x < glyph.x + glyph.width / 2 {
+                return glyph.index;
+            }
+        }
+        line.end
+    }
+
+    fn glyph_index(&self, char_index: usize) -> usize {
+        self.glyphs
+            .iter()
+            .position(|g| g.index >= char_index)
+            .unwrap_or(self.glyphs.len())
+    }
+}
+
+/// The text of a field as it appears on `card`, and the style runs it's drawn in.
+pub fn field_text<'a>(
+    stack: &Stack,
+    card: &'a Card,
+    part: &'a Part,
+    background: bool,
+) -> (&'a str, Vec<StyleRun>) {
+    let style = TextStyle::of_part(part);
+    match card.contents_of(part, background) {
+        Some(entry) => {
+            let runs = entry
+                .styles
+                .iter()
+                .flatten()
+                .map(|run| StyleRun {
+                    start: run.text_position as usize,
+                    style: match stack.styles.get(&(run.id as u32)) {
+                        Some(s) => TextStyle {
+                            font: if s.font == -1 { style.font } else { s.font },
+                            size: if s.font_size == -1 {
+                                style.size
+                            } else {
+                                s.font_size as u16
+                            },
+                            face: s.face().unwrap_or(style.face),
+                        },
+                        None => style,
+                    },
+                })
+                .collect();
+            (entry.text.as_str(), runs)
+        }
+        None => ("", Vec::new()),
+    }
+}
+
+/// The area a field's text goes in: inside the frame and margins.
+pub fn text_rect(part: &Part) -> Rect {
+    let rect = draw::rect_from(part.position);
+    let frame = match part.style {
+        PartStyle::Transparent | PartStyle::Opaque => 0,
+        _ => 1,
+    };
+    let mut rect = draw::inset(rect, frame + MARGIN, frame);
+    if matches!(part.style, PartStyle::Shadow) {
+        rect = (rect.0, rect.1, rect.2 - 1, rect.3 - 1);
+    }
+    if matches!(part.style, PartStyle::Scrolling) {
+        // the scroll bar shares its left edge with the text's right margin.
+        rect.3 -= SCROLL_BAR_WIDTH - 1;
+    }
+    if part.wide_margins {
+        rect = (
+            rect.0 + WIDE_MARGIN,
+            rect.1 + WIDE_MARGIN,
+            rect.2,
+            rect.3 - WIDE_MARGIN,
+        );
+    }
+    rect
+}
+
+fn is_return(ch: char) -> bool {
+    ch == '\r' || ch == '\n'
+}
+
+// tabs are drawn (and measured) as spaces.
+pub(crate) fn visible_char(ch: char) -> char {
+    if ch == '\t' {
+        ' '
+    } else {
+        ch
+    }
+}
+
+fn line_metrics(fonts: &Fonts, styles: &[TextStyle], default: &FontMetrics) -> FontMetrics {
+    if styles.is_empty() {
+        return *default;
+    }
+    let mut metrics = FontMetrics {
+        ascent: 0,
+        descent: 0,
+        leading: 0,
+    };
+    for style in styles {
+        let m = fonts.metrics(style);
+        metrics.ascent = metrics.ascent.max(m.ascent);
+        metrics.descent = metrics.descent.max(m.descent);
+        metrics.leading = metrics.leading.max(m.leading);
+    }
+    metrics
+}
+
+// Greedy word wrap of chars[start..end], pushing the (start, end) of each line.
+fn wrap_paragraph(
+    chars: &[char],
+    widths: &[i32],
+    start: usize,
+    end: usize,
+    max_width: i32,
+    breaks: &mut Vec<(usize, usize)>,
+) {
+    let mut line_start = start;
+    let mut line_width = 0;
+    let mut i = start;
+    while i < end {
+        // a word is a run of non-spaces plus the spaces after it.
+        let word_start = i;
+        while i < end && chars[i] != ' ' {
+            i += 1;
+        }
+        let word_width: i32 = widths[word_start..i].iter().sum();
+        while i < end && chars[i] == ' ' {
+            i += 1;
+        }
+        let spaces_width: i32 = widths[word_start..i].iter().sum::<i32>() - word_width;
+
+        if line_width + word_width > max_width && line_start < word_start {
+            breaks.push((line_start, word_start));
+            line_start = word_start;
+            line_width = 0;
+        }
+        if word_width > max_width && line_start == word_start {
+            // the word doesn't fit on a line of its own, so it has to be split up.
+            let mut w = 0;
+            for (j, width) in widths.iter().enumerate().take(i).skip(word_start) {
+                if w + width > max_width && j > line_start {
+                    breaks.push((line_start, j));
+                    line_start = j;
+                    w = 0;
+                }
+                w += width;
+            }
+            line_width = w;
+        } else {
+            line_width += word_width + spaces_width;
+        }
+    }
+    breaks.push((line_start, end));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Chicago 12: lines 15 pixels high, with an ascent of 11.
+    const SYSTEM_12: TextStyle = TextStyle {
+        font: 0,
+        size: 12,
+        face: 0,
+    };
+
+    fn options(width: i32, alignment: TextAlignment) -> LayoutOptions {
+        LayoutOptions {
+            rect: (10, 20, 200, 20 + width),
+            wrap: true,
+            fixed_line_height: None,
+            alignment,
+            style: SYSTEM_12,
+        }
+    }
+
+    fn lines(layout: &TextLayout) -> Vec<(usize, usize)> {
+        layout.lines.iter().map(|l| (l.start, l.end)).collect()
+    }
+
+    fn width(text: &str) -> i32 {
+        Fonts::default().string_width(text, &SYSTEM_12)
+    }
+
+    #[test]
+    fn lines_wrap_between_words() {
+        let options = options(width("one two") + 2, TextAlignment::Left);
+        let layout = TextLayout::new("one two three", &[], &options, &Fonts::default());
+        assert_eq!(lines(&layout), [(0, 8), (8, 13)]);
+        assert_eq!(layout.lines[1].top, 25);
+        assert_eq!(layout.lines[1].baseline, 25 + 11);
+        assert_eq!(layout.height(), 30);
+
+        let no_wrap = LayoutOptions {
+            wrap: false,
+            ..options
+        };
+        let layout = TextLayout::new("one two three\rfour", &[], &no_wrap, &Fonts::default());
+        assert_eq!(lines(&layout), [(0, 13), (14, 18)]);
+    }
+
+    #[test]
+    fn a_word_too_long_for_the_line_is_split() {
+        let options = options(width("mmm"), TextAlignment::Left);
+        let layout = TextLayout::new("mmmmmmm ok", &[], &options, &Fonts::default());
+        assert_eq!(lines(&layout), [(0, 3), (3, 6), (6, 10)]);
+    }
+
+    #[test]
+    fn trailing_spaces_dont_count_towards_alignment() {
+        let fonts = Fonts::default();
+        let text_width = width("ab");
+        let left = |alignment| {
+            let layout = TextLayout::new("ab   ", &[], &options(100, alignment), &fonts);
+            assert_eq!(layout.lines[0].width, text_width);
+            layout.lines[0].left
+        };
+        assert_eq!(left(TextAlignment::Left), 20);
+        assert_eq!(left(TextAlignment::Center), 20 + (100 - text_width) / 2);
+        assert_eq!(
+            left(TextAlignment::ForceCenterAlign),
+            20 + (100 - text_width) / 2
+        );
+        assert_eq!(left(TextAlignment::Right), 120 - text_width);
+    }
+
+    #[test]
+    fn a_fixed_line_height_puts_the_baseline_above_the_descent() {
+        let options = LayoutOptions {
+            fixed_line_height: Some(20),
+            ..options(100, TextAlignment::Left)
+        };
+        let layout = TextLayout::new("a\rb", &[], &options, &Fonts::default());
+        // 20 high, less a descent of 3 and leading of 1.
+        let baselines: Vec<(i32, i32)> = layout
+            .lines
+            .iter()
+            .map(|l| (l.baseline, l.bottom))
+            .collect();
+        assert_eq!(baselines, [(26, 30), (46, 50)]);
+        assert_eq!(layout.empty_line_height, 20);
+    }
+
+    #[test]
+    fn a_click_finds_the_nearest_character() {
+        let layout = TextLayout::new(
+            "ab\rcd",
+            &[],
+            &options(100, TextAlignment::Left),
+            &Fonts::default(),
+        );
+        let a = width("a");
+        assert_eq!(layout.hit_test(20, 10), 0);
+        assert_eq!(layout.hit_test(20 + a / 2 + 1, 10), 1);
+        // past the end of a line is the end of it.
+        assert_eq!(layout.hit_test(110, 12), 2);
+        assert_eq!(layout.hit_test(20, 25), 3);
+        // and below the last line is the last line.
+        assert_eq!(layout.hit_test(110, 190), 5);
+        assert_eq!(
+            TextLayout::new(
+                "",
+                &[],
+                &options(100, TextAlignment::Left),
+                &Fonts::default()
+            )
+            .hit_test(0, 0),
+            0
+        );
+    }
+}
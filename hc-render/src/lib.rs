@@ -0,0 +1,16 @@
+pub mod animation;
+pub mod button;
+pub mod compositor;
+pub mod contact_sheet;
+pub mod draw;
+pub mod fallback;
+pub mod field;
+pub mod layout;
+pub(crate) mod part;
+pub mod pdf;
+pub mod site;
+pub mod svg;
+pub mod table;
+pub mod text;
+pub mod transition;
+pub use compositor::*;
@@ -0,0 +1,28 @@
+use hc_decode::card::Card;
+use hc_decode::part::{Part, PartType};
+use hc_decode::stack::Stack;
+use image::RgbaImage;
+
+use crate::button::paint_button;
+use crate::compositor::Layer;
+use crate::field::paint_field;
+use crate::text::Fonts;
+
+/// Paint a single button or field onto `card`.
+pub fn paint_part(
+    canvas: &mut RgbaImage,
+    stack: &Stack,
+    fonts: &Fonts,
+    card: &Card,
+    part: &Part,
+    layer: Layer,
+) {
+    if !part.visible {
+        return;
+    }
+    match part.ty {
+        PartType::Button => paint_button(canvas, part, stack.resources.as_ref(), fonts),
+        PartType::Field => paint_field(canvas, stack, fonts, card, part, layer),
+        PartType::Unknown => {}
+    }
+}
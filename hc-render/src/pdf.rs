@@ -0,0 +1,557 @@
+// PDF export, after HyperCard's printing: every card full size on its own page, several cards to
+// a page, or a report that prints the items of one of the stack's report templates in a cell for
+// each card. Cards go in as 1-bit images at 72 dpi, so a card pixel is a point on paper, the same
+// as it was on the Mac. Report text is set in the standard PDF fonts closest to the Mac ones.
+
+use std::collections::BTreeMap;
+use std::error::Error;
+use std::fs;
+use std::path::Path;
+
+use hc_decode::card::Card;
+use hc_decode::part::{Part, PartType};
+use hc_decode::print::{ReportItem, ReportTemplate};
+use hc_decode::stack::Stack;
+use image::RgbaImage;
+use pdf_writer::{Content, Filter, Finish, Name, Pdf, Rect, Ref, Str};
+
+use crate::compositor::render_card_with_fonts;
+use crate::draw;
+use crate::fallback::{self, Family};
+use crate::text::{Fonts, TextStyle, BOLD, ITALIC};
+
+/// US Letter, in points.
+pub const LETTER: (f32, f32) = (612.0, 792.0);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PdfLayout {
+    /// One card to a page at full size, or shrunk to fit if it's bigger than the page.
+    FullSize,
+    /// A grid of cards on each page, shrunk to fit.
+    CardsPerPage { columns: u32, rows: u32 },
+    /// The report template with the given block ID. Its own margins and spacing are used rather
+    /// than the options'.
+    Report(u32),
+}
+
+#[derive(Debug, Clone)]
+pub struct PdfOptions {
+    /// (width, height), in points.
+    pub page_size: (f32, f32),
+    /// (top, left, bottom, right), in points.
+    pub margins: (f32, f32, f32, f32),
+    /// Space between cards: (height, width), in points.
+    pub spacing: (f32, f32),
+    pub layout: PdfLayout,
+    /// IDs of the cards to print, in order. Empty means every card, in stack order.
+    pub cards: Vec<u32>,
+}
+
+impl Default for PdfOptions {
+    fn default() -> Self {
+        PdfOptions {
+            page_size: LETTER,
+            margins: (36.0, 36.0, 36.0, 36.0),
+            spacing: (8.0, 8.0),
+            layout: PdfLayout::FullSize,
+            cards: Vec::new(),
+        }
+    }
+}
+
+impl PdfOptions {
+    /// The defaults, with the margins and spacing from the stack's print settings if it has them.
+    pub fn for_stack(stack: &Stack) -> Self {
+        let mut options = PdfOptions::default();
+        if let Some(settings) = &stack.print_settings {
+            let m = settings.margins;
+            options.margins = (m.0 as f32, m.1 as f32, m.2 as f32, m.3 as f32);
+            options.spacing = (settings.spacing.0 as f32, settings.spacing.1 as f32);
+        }
+        options
+    }
+}
+
+/// Print a stack to a PDF document.
+pub fn stack_to_pdf(stack: &Stack, options: &PdfOptions) -> Result<Vec<u8>, Box<dyn Error>> {
+    let ids: Vec<u32> = if options.cards.is_empty() {
+        stack.cards.iter().map(|c| c.id).collect()
+    } else {
+        options.cards.clone()
+    };
+    let mut cards = Vec::new();
+    for id in ids {
+        let index = stack
+            .cards
+            .iter()
+            .position(|c| c.id == id)
+            .ok_or_else(|| format!("No card with ID {}", id))?;
+        cards.push(index);
+    }
+
+    let fonts = Fonts::for_stack(stack);
+    let mut doc = Document::new(options.page_size);
+    match &options.layout {
+        PdfLayout::FullSize => print_cards(&mut doc, stack, &fonts, &cards, options, 1, 1),
+        PdfLayout::CardsPerPage { columns, rows } => print_cards(
+            &mut doc,
+            stack,
+            &fonts,
+            &cards,
+            options,
+            (*columns).max(1),
+            (*rows).max(1),
+        ),
+        PdfLayout::Report(id) => {
+            let template = stack
+                .report_templates
+                .iter()
+                .find(|t| t.id == *id)
+                .ok_or_else(|| format!("No report template with ID {}", id))?;
+            print_report(&mut doc, stack, &fonts, &cards, template);
+        }
+    }
+    Ok(doc.finish())
+}
+
+/// Print a stack to a PDF file.
+pub fn export_pdf(stack: &Stack, options: &PdfOptions, path: &Path) -> Result<(), Box<dyn Error>> {
+    fs::write(path, stack_to_pdf(stack, options)?)?;
+    Ok(())
+}
+
+fn print_cards(
+    doc: &mut Document,
+    stack: &Stack,
+    fonts: &Fonts,
+    cards: &[usize],
+    options: &PdfOptions,
+    columns: u32,
+    rows: u32,
+) {
+    let (page_width, page_height) = options.page_size;
+    let (top, left, bottom, right) = options.margins;
+    let (spacing_height, spacing_width) = options.spacing;
+    let area_width = page_width - left - right;
+    let area_height = page_height - top - bottom;
+    let cell_width = (area_width - spacing_width * (columns - 1) as f32) / columns as f32;
+    let cell_height = (area_height - spacing_height * (rows - 1) as f32) / rows as f32;
+    let (card_width, card_height) = (stack.size.0 as f32, stack.size.1 as f32);
+    // never bigger than the card really is.
+    let scale = (cell_width / card_width)
+        .min(cell_height / card_height)
+        .min(1.0);
+    let (width, height) = (card_width * scale, card_height * scale);
+
+    for page_cards in cards.chunks((columns * rows) as usize) {
+        let mut page = doc.page();
+        for (i, index) in page_cards.iter().enumerate() {
+            let (column, row) = (i as u32 % columns, i as u32 / columns);
+            // centered in its cell, and a lone card centered on the page.
+            let x =
+                left + column as f32 * (cell_width + spacing_width) + (cell_width - width) / 2.0;
+            let y =
+                top + row as f32 * (cell_height + spacing_height) + (cell_height - height) / 2.0;
+            let image = render_card_with_fonts(stack, fonts, &stack.cards[*index]);
+            page.image(doc, &image, x, y, width, height);
+        }
+        doc.add(page);
+    }
+}
+
+fn print_report(
+    doc: &mut Document,
+    stack: &Stack,
+    fonts: &Fonts,
+    cards: &[usize],
+    template: &ReportTemplate,
+) {
+    let (page_width, page_height) = doc.page_size;
+    let m = template.margins;
+    let (top, left, bottom, right) = (m.0 as f32, m.1 as f32, m.2 as f32, m.3 as f32);
+    let (spacing_height, spacing_width) = (template.spacing.0 as f32, template.spacing.1 as f32);
+    let cell_height = (template.cell_size.0 as f32).max(1.0);
+    let cell_width = (template.cell_size.1 as f32).max(1.0);
+    let header_style = TextStyle {
+        font: 0,
+        size: 12,
+        face: 0,
+    };
+    let header_height = if template.header.is_empty() {
+        0.0
+    } else {
+        fonts.metrics(&header_style).line_height() as f32 + spacing_height
+    };
+
+    let area_width = page_width - left - right;
+    let area_height = page_height - top - bottom - header_height;
+    let columns = ((area_width + spacing_width) / (cell_width + spacing_width)).max(1.0) as usize;
+    let rows = ((area_height + spacing_height) / (cell_height + spacing_height)).max(1.0) as usize;
+
+    for page_cards in cards.chunks(columns * rows) {
+        let mut page = doc.page();
+        if !template.header.is_empty() {
+            let baseline = top + fonts.metrics(&header_style).ascent as f32;
+            page.text(doc, &template.header, left, baseline, &header_style, stack);
+        }
+        for (i, index) in page_cards.iter().enumerate() {
+            let (column, row) = (i % columns, i / columns);
+            let x = left + column as f32 * (cell_width + spacing_width);
+            let y = top + header_height + row as f32 * (cell_height + spacing_height);
+            for item in &template.items {
+                print_item(&mut page, doc, stack, fonts, *index, item, x, y);
+            }
+        }
+        doc.add(page);
+    }
+}
+
+// One report item for one card: its contents, wrapped to the item's width and cut off at its
+// bottom.
+#[allow(clippy::too_many_arguments)]
+fn print_item(
+    page: &mut Page,
+    doc: &mut Document,
+    stack: &Stack,
+    fonts: &Fonts,
+    index: usize,
+    item: &ReportItem,
+    x: f32,
+    y: f32,
+) {
+    let style = TextStyle {
+        font: item.font as i16,
+        size: item.size.max(1),
+        face: item.style as u8,
+    };
+    let rect = draw::rect_from(item.rect);
+    let metrics = fonts.metrics(&style);
+    let text = report_value(stack, index, &item.contents);
+    let mut baseline = rect.0 + metrics.ascent;
+    for line in wrap(fonts, &text, &style, draw::width(rect)) {
+        if baseline + metrics.descent > rect.2 {
+            break;
+        }
+        let width = fonts.string_width(&line, &style);
+        let left = match item.align {
+            1 => rect.1 + (draw::width(rect) - width) / 2,
+            -1 => rect.3 - width,
+            _ => rect.1,
+        };
+        page.text(
+            doc,
+            &line,
+            x + left as f32,
+            y + baseline as f32,
+            &style,
+            stack,
+        );
+        baseline += metrics.line_height();
+    }
+}
+
+// Break text into lines no wider than `width`, at spaces where possible.
+fn wrap(fonts: &Fonts, text: &str, style: &TextStyle, width: i32) -> Vec<String> {
+    let mut lines = Vec::new();
+    for paragraph in text.split(['\r', '\n']) {
+        let mut line = String::new();
+        for word in paragraph.split(' ') {
+            let candidate = if line.is_empty() {
+                word.to_string()
+            } else {
+                format!("{} {}", line, word)
+            };
+            if fonts.string_width(&candidate, style) <= width || line.is_empty() {
+                line = candidate;
+            } else {
+                lines.push(line);
+                line = word.to_string();
+            }
+        }
+        lines.push(line);
+    }
+    lines
+}
+
+/// What a report item's contents come to for the card at `index` of `stack.cards`. Report items
+/// are HyperTalk expressions, but almost always just a field; this handles fields, quoted text,
+/// and the name, number and ID of the card, and anything else comes out empty.
+pub fn report_value(stack: &Stack, index: usize, expression: &str) -> String {
+    let card = match stack.cards.get(index) {
+        Some(a) => a,
+        None => return String::new(),
+    };
+    let expression = expression.trim();
+    if expression.len() >= 2 && expression.starts_with('"') && expression.ends_with('"') {
+        return expression[1..expression.len() - 1].to_string();
+    }
+    let lower = expression.to_lowercase();
+    let mut words: Vec<&str> = lower.split_whitespace().collect();
+    if words.first() == Some(&"the") {
+        words.remove(0);
+    }
+    match words.as_slice() {
+        ["name" | "short" | "long", ..] if lower.ends_with("card") => card.name.clone(),
+        ["number", "of", ..] if lower.ends_with("card") => (index + 1).to_string(),
+        ["id", "of", ..] if lower.ends_with("card") => card.id.to_string(),
+        _ => field_value(stack, card, expression).unwrap_or_default(),
+    }
+}
+
+// `[card|bg] field <number|id n|name>`. A field on its own means a background field, as in
+// HyperTalk.
+fn field_value(stack: &Stack, card: &Card, expression: &str) -> Option<String> {
+    let mut words = expression.split_whitespace().peekable();
+    let background = match words.peek()?.to_lowercase().as_str() {
+        "card" | "cd" => {
+            words.next();
+            false
+        }
+        "bg" | "bkgnd" | "background" => {
+            words.next();
+            true
+        }
+        _ => true,
+    };
+    if !matches!(words.next()?.to_lowercase().as_str(), "field" | "fld") {
+        return None;
+    }
+    let rest: Vec<&str> = words.collect();
+    let parts = if background {
+        &stack.background(card.background_id)?.parts
+    } else {
+        &card.parts
+    };
+    let mut fields = parts.iter().filter(|p| matches!(p.ty, PartType::Field));
+    let part: &Part = match rest.as_slice() {
+        [id, n] if id.eq_ignore_ascii_case("id") => {
+            let n: u16 = n.parse().ok()?;
+            fields.find(|p| p.id == n)?
+        }
+        [n] if n.parse::<usize>().is_ok() => {
+            fields.nth(n.parse::<usize>().ok()?.checked_sub(1)?)?
+        }
+        _ => {
+            let name = rest.join(" ");
+            let name = name.trim_matches('"');
+            fields.find(|p| p.name.eq_ignore_ascii_case(name))?
+        }
+    };
+    Some(
+        card.contents_of(part, background)
+            .map(|c| c.text.clone())
+            .unwrap_or_default(),
+    )
+}
+
+// a page's object, its content stream, and the images it draws by resource name.
+type PageRefs = (Ref, Ref, Vec<(String, Ref)>);
+
+// The PDF being put together: pages are built one at a time and everything is written out at the
+// end, once the fonts they use are known.
+struct Document {
+    pdf: Pdf,
+    page_size: (f32, f32),
+    next_ref: i32,
+    pages: Vec<PageRefs>,
+    // the standard fonts used so far, by base font name.
+    fonts: BTreeMap<&'static str, (String, Ref)>,
+}
+
+struct Page {
+    content: Content,
+    images: Vec<(String, Ref)>,
+}
+
+impl Document {
+    fn new(page_size: (f32, f32)) -> Self {
+        Document {
+            pdf: Pdf::new(),
+            page_size,
+            // 1 and 2 are the catalog and page tree.
+            next_ref: 3,
+            pages: Vec::new(),
+            fonts: BTreeMap::new(),
+        }
+    }
+
+    fn alloc(&mut self) -> Ref {
+        let r = Ref::new(self.next_ref);
+        self.next_ref += 1;
+        r
+    }
+
+    fn page(&self) -> Page {
+        Page {
+            content: Content::new(),
+            images: Vec::new(),
+        }
+    }
+
+    fn add(&mut self, page: Page) {
+        let (page_id, content_id) = (self.alloc(), self.alloc());
+        self.pdf.stream(content_id, &page.content.finish());
+        self.pages.push((page_id, content_id, page.images));
+    }
+
+    // The resource name of a standard font, adding it if it hasn't been used yet.
+    fn font(&mut self, base: &'static str) -> String {
+        if let Some((name, _)) = self.fonts.get(base) {
+            return name.clone();
+        }
+        let name = format!("F{}", self.fonts.len() + 1);
+        let id = self.alloc();
+        self.fonts.insert(base, (name.clone(), id));
+        name
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        let catalog_id = Ref::new(1);
+        let tree_id = Ref::new(2);
+        self.pdf.catalog(catalog_id).pages(tree_id);
+        self.pdf
+            .pages(tree_id)
+            .kids(self.pages.iter().map(|p| p.0))
+            .count(self.pages.len() as i32);
+        for (base, (_, id)) in &self.fonts {
+            self.pdf
+                .type1_font(*id)
+                .base_font(Name(base.as_bytes()))
+                .encoding_predefined(Name(b"WinAnsiEncoding"));
+        }
+        let (width, height) = self.page_size;
+        for (page_id, content_id, images) in &self.pages {
+            let mut page = self.pdf.page(*page_id);
+            page.media_box(Rect::new(0.0, 0.0, width, height));
+            page.parent(tree_id);
+            page.contents(*content_id);
+            let mut resources = page.resources();
+            let mut fonts = resources.fonts();
+            for (name, id) in self.fonts.values() {
+                fonts.pair(Name(name.as_bytes()), *id);
+            }
+            fonts.finish();
+            let mut x_objects = resources.x_objects();
+            for (name, id) in images {
+                x_objects.pair(Name(name.as_bytes()), *id);
+            }
+            x_objects.finish();
+            resources.finish();
+            page.finish();
+        }
+        self.pdf.finish()
+    }
+}
+
+impl Page {
+    // Place an image with its top left corner at (x, y), measured from the top of the page.
+    fn image(&mut self, doc: &mut Document, image: &RgbaImage, x: f32, y: f32, w: f32, h: f32) {
+        let id = doc.alloc();
+        let (width, height) = image.dimensions();
+        let row_bytes = (width as usize).div_ceil(8);
+        let mut bits = vec![0u8; row_bytes * height as usize];
+        for (px, py, pixel) in image.enumerate_pixels() {
+            // white is 1 in DeviceGray.
+            if !draw::is_black(pixel) {
+                bits[py as usize * row_bytes + px as usize / 8] |= 0x80 >> (px % 8);
+            }
+        }
+        let data = miniz_oxide::deflate::compress_to_vec_zlib(&bits, 6);
+        let mut xobject = doc.pdf.image_xobject(id, &data);
+        xobject.filter(Filter::FlateDecode);
+        xobject.width(width as i32);
+        xobject.height(height as i32);
+        xobject.color_space().device_gray();
+        xobject.bits_per_component(1);
+        xobject.finish();
+
+        let name = format!("Im{}", self.images.len() + 1);
+        let page_height = doc.page_size.1;
+        self.content.save_state();
+        self.content
+            .transform([w, 0.0, 0.0, h, x, page_height - y - h]);
+        self.content.x_object(Name(name.as_bytes()));
+        self.content.restore_state();
+        self.images.push((name, id));
+    }
+
+    // Set a line of text with its baseline at `y`, measured from the top of the page.
+    fn text(
+        &mut self,
+        doc: &mut Document,
+        text: &str,
+        x: f32,
+        y: f32,
+        style: &TextStyle,
+        stack: &Stack,
+    ) {
+        if text.is_empty() {
+            return;
+        }
+        let font = doc.font(standard_font(stack, style));
+        let bytes: Vec<u8> = text.chars().map(win_ansi).collect();
+        self.content.begin_text();
+        self.content
+            .set_font(Name(font.as_bytes()), style.size as f32);
+        self.content.next_line(x, doc.page_size.1 - y);
+        self.content.show(Str(&bytes));
+        self.content.end_text();
+    }
+}
+
+// The standard PDF font that stands in for a Mac font.
+fn standard_font(stack: &Stack, style: &TextStyle) -> &'static str {
+    let family = stack
+        .font(style.font as u16)
+        .and_then(|f| fallback::family_for_name(&f.name))
+        .unwrap_or_else(|| fallback::family_for_id(style.font));
+    let bold = style.face & BOLD != 0;
+    let italic = style.face & ITALIC != 0;
+    match (family, bold, italic) {
+        (Family::Serif, false, false) => "Times-Roman",
+        (Family::Serif, true, false) => "Times-Bold",
+        (Family::Serif, false, true) => "Times-Italic",
+        (Family::Serif, true, true) => "Times-BoldItalic",
+        (Family::Monospace, false, false) => "Courier",
+        (Family::Monospace, true, false) => "Courier-Bold",
+        (Family::Monospace, false, true) => "Courier-Oblique",
+        (Family::Monospace, true, true) => "Courier-BoldOblique",
+        (_, false, false) => "Helvetica",
+        (_, true, false) => "Helvetica-Bold",
+        (_, false, true) => "Helvetica-Oblique",
+        (_, true, true) => "Helvetica-BoldOblique",
+    }
+}
+
+// A character in WinAnsiEncoding, which is Latin-1 with typographic punctuation in 0x80-0x9F.
+fn win_ansi(ch: char) -> u8 {
+    match ch {
+        '€' => 0x80,
+        '‚' => 0x82,
+        'ƒ' => 0x83,
+        '„' => 0x84,
+        '…' => 0x85,
+        '†' => 0x86,
+        '‡' => 0x87,
+        'ˆ' => 0x88,
+        '‰' => 0x89,
+        '‹' => 0x8B,
+        'Œ' => 0x8C,
+        '‘' => 0x91,
+        '’' => 0x92,
+        '“' => 0x93,
+        '”' => 0x94,
+        '•' => 0x95,
+        '–' => 0x96,
+        '—' => 0x97,
+        '˜' => 0x98,
+        '™' => 0x99,
+        '›' => 0x9B,
+        'œ' => 0x9C,
+        'Ÿ' => 0x9F,
+        '\t' => b' ',
+        c if (c as u32) >= 0x20 && (c as u32) < 0x7F => c as u8,
+        c if (c as u32) >= 0xA0 && (c as u32) <= 0xFF => c as u8,
+        _ => b'?',
+    }
+}
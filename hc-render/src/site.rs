@@ -0,0 +1,628 @@
+// Static HTML export: a folder with a page for every card that opens in any browser. The card and
+// background pictures are PNGs, buttons and fields are absolutely positioned elements on top of
+// them, and field text is ordinary HTML text. Buttons that do nothing but go to another card
+// become links to that card's page.
+
+use std::error::Error;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use hc_decode::bitmap::Bitmap;
+use hc_decode::card::Card;
+use hc_decode::part::{Part, PartStyle, PartType, TextAlignment};
+use hc_decode::stack::Stack;
+use image::{Rgba, RgbaImage};
+
+use crate::compositor::{paint_bitmap, Layer};
+use crate::contact_sheet::thumbnail;
+use crate::draw::{self, WHITE};
+use crate::fallback;
+use crate::field::SCROLL_BAR_WIDTH;
+use crate::layout::{self, StyleRun};
+use crate::svg::escape;
+use crate::text::{Fonts, TextStyle, BOLD, ITALIC, OUTLINE, SHADOW, UNDERLINE};
+
+const THUMBNAIL_WIDTH: u32 = 128;
+
+const STYLESHEET: &str = "\
+body { font-family: Geneva, Helvetica, Arial, sans-serif; font-size: 12px; margin: 16px; }
+.card { position: relative; overflow: hidden; background: white; border: 1px solid black; }
+.card > * { position: absolute; box-sizing: border-box; margin: 0; }
+.picture { left: 0; top: 0; image-rendering: pixelated; }
+.button { display: flex; flex-direction: column; align-items: center; justify-content: center; \
+color: black; text-decoration: none; white-space: nowrap; overflow: hidden; }
+.button.left { align-items: flex-start; padding-left: 4px; }
+.button.right { align-items: flex-end; padding-right: 4px; }
+.button img { image-rendering: pixelated; }
+.opaque, .rectangle, .shadow, .round-rect, .standard, .default, .scrolling { background: white; }
+.rectangle, .scrolling { border: 1px solid black; }
+.shadow { border: 1px solid black; border-width: 1px 2px 2px 1px; }
+.round-rect { border: 1px solid black; border-width: 1px 2px 2px 1px; border-radius: 8px; }
+.standard { border: 1px solid black; border-radius: 8px; }
+.default { border: 1px solid black; border-radius: 8px; outline: 3px solid black; \
+outline-offset: -5px; box-shadow: inset 0 0 0 4px white; }
+.oval { border-radius: 50%; }
+.button.hilite { background: black; color: white; }
+.button.hilite img { filter: invert(1); }
+.checkbox, .radio, .popup { flex-direction: row; justify-content: flex-start; gap: 5px; padding-left: 3px; }
+.popup .menu { flex: 1; border: 1px solid black; border-width: 1px 2px 2px 1px; padding: 0 4px 0 12px; \
+display: flex; justify-content: space-between; align-items: center; height: 100%; box-sizing: border-box; }
+.disabled { opacity: 0.5; }
+a.button { cursor: pointer; }
+.field { overflow: hidden; white-space: pre-wrap; overflow-wrap: break-word; }
+.field.nowrap { white-space: pre; }
+.field.scrolling { overflow-y: scroll; }
+nav { margin-top: 8px; display: flex; gap: 16px; }
+.index { list-style: none; padding: 0; display: flex; flex-wrap: wrap; gap: 16px; }
+.index a { color: black; text-decoration: none; display: block; width: 128px; }
+.index img { border: 1px solid black; display: block; }
+";
+
+/// Where a button's `go` command goes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GoDestination {
+    Next,
+    Previous,
+    First,
+    Last,
+    Back,
+    /// A card by its number, starting at 1.
+    Number(usize),
+    Id(u32),
+    Name(String),
+}
+
+/// Write a page for every card of the stack into `dir`, along with an index of all the cards and
+/// the pictures and stylesheet the pages use.
+pub fn export_site(stack: &Stack, dir: &Path) -> Result<(), Box<dyn Error>> {
+    let images = dir.join("images");
+    fs::create_dir_all(&images)?;
+    fs::write(dir.join("stack.css"), STYLESHEET)?;
+    let fonts = Fonts::for_stack(stack);
+
+    for background in &stack.backgrounds {
+        if let Some(bitmap) = stack.bitmap(background.bitmap_id) {
+            let mut image =
+                RgbaImage::from_pixel(bitmap.image.width(), bitmap.image.height(), WHITE);
+            paint_bitmap(&mut image, bitmap);
+            image.save(images.join(format!("background-{}.png", background.id)))?;
+        }
+    }
+    for (index, card) in stack.cards.iter().enumerate() {
+        if let Some(bitmap) = stack.bitmap(card.bitmap_block_id) {
+            transparent_picture(bitmap).save(images.join(format!("card-{}.png", card.id)))?;
+        }
+        let mut icons = Vec::new();
+        fs::write(
+            dir.join(page_name(card.id)),
+            card_page(stack, index, &mut icons),
+        )?;
+        if let Some(resources) = &stack.resources {
+            for id in icons {
+                let path = images.join(format!("icon-{}.png", id));
+                if path.exists() {
+                    continue;
+                }
+                if let Some(icon) = resources.icon(id) {
+                    let image = RgbaImage::from_fn(icon.width(), icon.height(), |x, y| {
+                        if icon.get_pixel(x, y).0[0] < 128 {
+                            Rgba([0, 0, 0, 255])
+                        } else {
+                            Rgba([0, 0, 0, 0])
+                        }
+                    });
+                    image.save(path)?;
+                }
+            }
+        }
+        thumbnail(stack, &fonts, card, THUMBNAIL_WIDTH)
+            .save(images.join(format!("thumb-{}.png", card.id)))?;
+    }
+    fs::write(dir.join("index.html"), index_page(stack))?;
+    Ok(())
+}
+
+/// Where a button's script goes when clicked, if all its `mouseUp` (or `mouseDown`) handler does
+/// is go to another card. Visual effects and locking the screen don't count.
+pub fn link_target(script: &str) -> Option<GoDestination> {
+    let script = script.replace("¬\r", " ").replace("¬\n", " ");
+    let mut handler: Option<String> = None;
+    let mut mouse_up = Vec::new();
+    let mut mouse_down = Vec::new();
+    for line in script.split(['\r', '\n']) {
+        let line = strip_comment(line).trim();
+        let lower = line.to_lowercase();
+        let words: Vec<&str> = lower.split_whitespace().collect();
+        match words.as_slice() {
+            ["on", name, ..] => handler = Some(name.to_string()),
+            ["end", ..] => handler = None,
+            [] | ["visual", ..] | ["lock", "screen"] | ["unlock", "screen", ..] => {}
+            _ => match handler.as_deref() {
+                Some("mouseup") => mouse_up.push(line.to_string()),
+                Some("mousedown") => mouse_down.push(line.to_string()),
+                _ => {}
+            },
+        }
+    }
+    let statements = if mouse_up.is_empty() {
+        mouse_down
+    } else {
+        mouse_up
+    };
+    match statements.as_slice() {
+        [statement] => parse_go(statement),
+        _ => None,
+    }
+}
+
+// The line up to a `--` comment. A `--` in a quoted string isn't a comment.
+fn strip_comment(line: &str) -> &str {
+    let mut quoted = false;
+    for (i, ch) in line.char_indices() {
+        match ch {
+            '"' => quoted = !quoted,
+            '-' if !quoted && line[i + 1..].starts_with('-') => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+// `go [to] <destination>`, where the destination is a card of this stack.
+fn parse_go(statement: &str) -> Option<GoDestination> {
+    let mut words: Vec<&str> = statement.split_whitespace().collect();
+    if !words.first()?.eq_ignore_ascii_case("go") {
+        return None;
+    }
+    words.remove(0);
+    if words.first()?.eq_ignore_ascii_case("to") {
+        words.remove(0);
+    }
+    let rest = words.join(" ");
+    let lower = rest.to_lowercase();
+    let lower_words: Vec<&str> = lower.split_whitespace().collect();
+    let is_card = |w: &str| w == "card" || w == "cd";
+    // "next", "next card", "third cd" and so on.
+    let relative = lower_words.len() == 1 || (lower_words.len() == 2 && is_card(lower_words[1]));
+    match lower_words.as_slice() {
+        ["back"] => Some(GoDestination::Back),
+        [first, ..] if relative && !is_card(first) => match *first {
+            "next" => Some(GoDestination::Next),
+            "prev" | "previous" => Some(GoDestination::Previous),
+            "first" => Some(GoDestination::First),
+            "last" => Some(GoDestination::Last),
+            o => ordinal_number(o).map(GoDestination::Number),
+        },
+        [c, "id", id] if is_card(c) => id.parse().ok().map(GoDestination::Id),
+        [c, ..] if is_card(c) => {
+            let name = rest[c.len()..].trim();
+            if let Ok(number) = name.parse() {
+                Some(GoDestination::Number(number))
+            } else if name.len() >= 2 && name.starts_with('"') && name.ends_with('"') {
+                Some(GoDestination::Name(name[1..name.len() - 1].to_string()))
+            } else if !name.is_empty() && !name.contains(' ') {
+                Some(GoDestination::Name(name.to_string()))
+            } else {
+                None
+            }
+        }
+        _ => None,
+    }
+}
+
+fn ordinal_number(word: &str) -> Option<usize> {
+    let ordinals = [
+        "first", "second", "third", "fourth", "fifth", "sixth", "seventh", "eighth", "ninth",
+        "tenth",
+    ];
+    ordinals.iter().position(|o| *o == word).map(|i| i + 1)
+}
+
+// The index in `stack.cards` a destination refers to, from the card at `from`. Next and previous
+// wrap around, the same as in HyperCard.
+fn resolve(stack: &Stack, from: usize, destination: &GoDestination) -> Option<usize> {
+    let count = stack.cards.len();
+    if count == 0 {
+        return None;
+    }
+    match destination {
+        GoDestination::Next => Some((from + 1) % count),
+        GoDestination::Previous => Some((from + count - 1) % count),
+        GoDestination::First => Some(0),
+        GoDestination::Last => Some(count - 1),
+        GoDestination::Back => None,
+        GoDestination::Number(n) => (1..=count).contains(n).then(|| n - 1),
+        GoDestination::Id(id) => stack.cards.iter().position(|c| c.id == *id),
+        GoDestination::Name(name) => stack
+            .cards
+            .iter()
+            .position(|c| c.name.eq_ignore_ascii_case(name)),
+    }
+}
+
+fn page_name(card_id: u32) -> String {
+    format!("card-{}.html", card_id)
+}
+
+// A layer's picture with its see-through parts left transparent.
+fn transparent_picture(bitmap: &Bitmap) -> RgbaImage {
+    RgbaImage::from_fn(bitmap.image.width(), bitmap.image.height(), |x, y| {
+        if bitmap.image.get_pixel(x, y).0[0] < 128 {
+            Rgba([0, 0, 0, 255])
+        } else if x < bitmap.mask.width()
+            && y < bitmap.mask.height()
+            && bitmap.mask.get_pixel(x, y).0[0] >= 128
+        {
+            Rgba([255, 255, 255, 255])
+        } else {
+            Rgba([0, 0, 0, 0])
+        }
+    })
+}
+
+fn card_title(index: usize, card: &Card) -> String {
+    if card.name.is_empty() {
+        format!("Card {}", index + 1)
+    } else {
+        card.name.clone()
+    }
+}
+
+// The page for the card at `index`, noting the icons it uses in `icons`.
+fn card_page(stack: &Stack, index: usize, icons: &mut Vec<i16>) -> String {
+    let card = &stack.cards[index];
+    let count = stack.cards.len();
+    let mut page = String::new();
+    let _ = write!(
+        page,
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n\
+         <link rel=\"stylesheet\" href=\"stack.css\">\n</head>\n<body>\n\
+         <div class=\"card\" style=\"width:{}px;height:{}px\">\n",
+        escape(&card_title(index, card)),
+        stack.size.0,
+        stack.size.1
+    );
+
+    if let Some(background) = stack.background(card.background_id) {
+        if background.show_picture() && stack.bitmap(background.bitmap_id).is_some() {
+            let _ = writeln!(
+                page,
+                r#"<img class="picture" src="images/background-{}.png" alt="">"#,
+                background.id
+            );
+        }
+        for part in &background.parts {
+            write_part(&mut page, stack, index, part, Layer::Background, icons);
+        }
+    }
+    if card.show_picture() && stack.bitmap(card.bitmap_block_id).is_some() {
+        let _ = writeln!(
+            page,
+            r#"<img class="picture" src="images/card-{}.png" alt="">"#,
+            card.id
+        );
+    }
+    for part in &card.parts {
+        write_part(&mut page, stack, index, part, Layer::Card, icons);
+    }
+    page.push_str("</div>\n");
+
+    let previous = &stack.cards[(index + count - 1) % count];
+    let next = &stack.cards[(index + 1) % count];
+    let _ = writeln!(
+        page,
+        "<nav><a href=\"{}\">Previous</a><a href=\"index.html\">All cards</a>\
+         <a href=\"{}\">Next</a><span>Card {} of {}</span></nav>",
+        page_name(previous.id),
+        page_name(next.id),
+        index + 1,
+        count
+    );
+    // browsers can't be told where a scrolling field starts scrolled to in HTML alone.
+    page.push_str(
+        "<script>for (const f of document.querySelectorAll('[data-scroll]')) \
+         f.scrollTop = f.dataset.scroll;</script>\n</body>\n</html>\n",
+    );
+    page
+}
+
+fn index_page(stack: &Stack) -> String {
+    let mut page = String::from(
+        "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>All cards</title>\n\
+         <link rel=\"stylesheet\" href=\"stack.css\">\n</head>\n<body>\n<ul class=\"index\">\n",
+    );
+    for (index, card) in stack.cards.iter().enumerate() {
+        let _ = writeln!(
+            page,
+            r#"<li><a href="{}"><img src="images/thumb-{}.png" alt="">{}. {}</a></li>"#,
+            page_name(card.id),
+            card.id,
+            index + 1,
+            escape(&card_title(index, card))
+        );
+    }
+    page.push_str("</ul>\n</body>\n</html>\n");
+    page
+}
+
+fn write_part(
+    page: &mut String,
+    stack: &Stack,
+    index: usize,
+    part: &Part,
+    layer: Layer,
+    icons: &mut Vec<i16>,
+) {
+    if !part.visible {
+        return;
+    }
+    match part.ty {
+        PartType::Button => write_button(page, stack, index, part, icons),
+        PartType::Field => write_field(page, stack, &stack.cards[index], part, layer),
+        PartType::Unknown => {}
+    }
+}
+
+fn write_button(page: &mut String, stack: &Stack, index: usize, part: &Part, icons: &mut Vec<i16>) {
+    let mut classes = vec!["button", style_class(&part.style)];
+    if part.hilite && !matches!(part.style, PartStyle::Checkbox | PartStyle::Radio) {
+        classes.push("hilite");
+    }
+    if !part.enabled {
+        classes.push("disabled");
+    }
+    match part.text_alignment {
+        TextAlignment::Left | TextAlignment::ForceLeftAlign => classes.push("left"),
+        TextAlignment::Right | TextAlignment::ForceRightAlign => classes.push("right"),
+        _ => {}
+    }
+    let link = link_target(&part.script).and_then(|d| match d {
+        GoDestination::Back => Some("javascript:history.back()".to_string()),
+        d => resolve(stack, index, &d).map(|i| page_name(stack.cards[i].id)),
+    });
+    let style = format!(
+        "{}{}",
+        position(part),
+        font_css(stack, &TextStyle::of_part(part))
+    );
+    match &link {
+        Some(href) => {
+            let _ = write!(
+                page,
+                r#"<a class="{}" href="{}" style="{}" title="{}">"#,
+                classes.join(" "),
+                href,
+                style,
+                escape(&part.name)
+            );
+        }
+        None => {
+            let _ = write!(
+                page,
+                r#"<div class="{}" style="{}" title="{}">"#,
+                classes.join(" "),
+                style,
+                escape(&part.name)
+            );
+        }
+    }
+
+    let name = if part.show_name {
+        escape(&part.name)
+    } else {
+        String::new()
+    };
+    match part.style {
+        PartStyle::Checkbox => {
+            let mark = if part.hilite { "☒" } else { "☐" };
+            let _ = write!(page, "<span>{}</span><span>{}</span>", mark, name);
+        }
+        PartStyle::Radio => {
+            let mark = if part.hilite { "◉" } else { "○" };
+            let _ = write!(page, "<span>{}</span><span>{}</span>", mark, name);
+        }
+        PartStyle::Popup => {
+            let items = part.contents.first().map(|c| c.text.as_str()).unwrap_or("");
+            let selected = items
+                .split('\r')
+                .nth((part.selected_line.max(1) - 1) as usize)
+                .unwrap_or("");
+            if part.title_width > 0 && part.show_name {
+                let _ = write!(
+                    page,
+                    r#"<span style="width:{}px">{}</span>"#,
+                    part.title_width, name
+                );
+            }
+            let _ = write!(
+                page,
+                r#"<span class="menu"><span>{}</span><span>▼</span></span>"#,
+                escape(selected)
+            );
+        }
+        _ => {
+            if part.icon_id != 0 {
+                icons.push(part.icon_id);
+                let _ = write!(
+                    page,
+                    r#"<img src="images/icon-{}.png" alt="">"#,
+                    part.icon_id
+                );
+            }
+            if !name.is_empty() {
+                let _ = write!(page, "<span>{}</span>", name);
+            }
+        }
+    }
+    page.push_str(if link.is_some() { "</a>\n" } else { "</div>\n" });
+}
+
+fn write_field(page: &mut String, stack: &Stack, card: &Card, part: &Part, layer: Layer) {
+    let base = TextStyle::of_part(part);
+    let mut classes = vec!["field", style_class(&part.style)];
+    if part.dont_wrap {
+        classes.push("nowrap");
+    }
+    // the text goes where the renderer would put it: inside the frame and margins.
+    let rect = draw::rect_from(part.position);
+    let text = layout::text_rect(part);
+    let mut right = rect.3 - text.3;
+    if matches!(part.style, PartStyle::Scrolling) {
+        right -= SCROLL_BAR_WIDTH - 1;
+    }
+    let border = match part.style {
+        PartStyle::Transparent | PartStyle::Opaque => 0,
+        _ => 1,
+    };
+    let mut style = format!(
+        "{}{}padding:{}px {}px 0 {}px;text-align:{};",
+        position(part),
+        font_css(stack, &base),
+        text.0 - rect.0 - border,
+        (right - border).max(0),
+        text.1 - rect.1 - border,
+        match part.text_alignment {
+            TextAlignment::Center | TextAlignment::ForceCenterAlign => "center",
+            TextAlignment::Right | TextAlignment::ForceRightAlign => "right",
+            _ => "left",
+        }
+    );
+    if part.fixed_line_height {
+        let _ = write!(style, "line-height:{}px;", part.line_height);
+    }
+    let _ = write!(
+        page,
+        r#"<div class="{}" style="{}""#,
+        classes.join(" "),
+        style
+    );
+    if part.scroll > 0 {
+        let _ = write!(page, r#" data-scroll="{}""#, part.scroll);
+    }
+    page.push('>');
+
+    let (text, runs) = layout::field_text(stack, card, part, layer == Layer::Background);
+    let chars: Vec<char> = text.chars().collect();
+    let mut runs: Vec<StyleRun> = runs.into_iter().filter(|r| r.start < chars.len()).collect();
+    runs.sort_by_key(|r| r.start);
+    // text before the first run is in the field's own style.
+    let mut pos = 0;
+    let mut current = base;
+    for run in runs {
+        if run.start > pos {
+            let piece: String = chars[pos..run.start].iter().collect();
+            write_text_run(page, stack, &piece, &current, &base);
+            pos = run.start;
+        }
+        current = run.style;
+    }
+    let piece: String = chars[pos..].iter().collect();
+    write_text_run(page, stack, &piece, &current, &base);
+    page.push_str("</div>\n");
+}
+
+fn write_text_run(
+    page: &mut String,
+    stack: &Stack,
+    text: &str,
+    style: &TextStyle,
+    base: &TextStyle,
+) {
+    let text = escape(&text.replace('\r', "\n"));
+    if style == base {
+        page.push_str(&text);
+    } else {
+        let _ = write!(
+            page,
+            r#"<span style="{}">{}</span>"#,
+            font_css(stack, style),
+            text
+        );
+    }
+}
+
+fn position(part: &Part) -> String {
+    let rect = draw::rect_from(part.position);
+    format!(
+        "left:{}px;top:{}px;width:{}px;height:{}px;",
+        rect.1,
+        rect.0,
+        draw::width(rect),
+        draw::height(rect)
+    )
+}
+
+fn font_css(stack: &Stack, style: &TextStyle) -> String {
+    let mut css = format!(
+        "font-family:{};font-size:{}px;",
+        escape(&fallback::css_font_family(stack, style.font)),
+        style.size
+    );
+    if style.face & BOLD != 0 {
+        css.push_str("font-weight:bold;");
+    }
+    if style.face & ITALIC != 0 {
+        css.push_str("font-style:italic;");
+    }
+    if style.face & UNDERLINE != 0 {
+        css.push_str("text-decoration:underline;");
+    }
+    if style.face & SHADOW != 0 {
+        css.push_str(
+            "color:white;text-shadow:-1px 0 #000,1px 0 #000,0 -1px #000,0 1px #000,2px 2px #000;",
+        );
+    } else if style.face & OUTLINE != 0 {
+        css.push_str("color:white;text-shadow:-1px 0 #000,1px 0 #000,0 -1px #000,0 1px #000;");
+    }
+    css
+}
+
+fn style_class(style: &PartStyle) -> &'static str {
+    match style {
+        PartStyle::Transparent => "transparent",
+        PartStyle::Opaque => "opaque",
+        PartStyle::Rectangle => "rectangle",
+        PartStyle::RoundRectangle => "round-rect",
+        PartStyle::Shadow => "shadow",
+        PartStyle::Checkbox => "checkbox",
+        PartStyle::Radio => "radio",
+        PartStyle::Scrolling => "scrolling",
+        PartStyle::Standard => "standard",
+        PartStyle::Default => "default",
+        PartStyle::Oval => "oval",
+        PartStyle::Popup => "popup",
+        PartStyle::Unknown => "unknown",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_button_that_only_goes_somewhere_is_a_link() {
+        let script = "on mouseUp\r  visual effect dissolve\r  go to next card\rend mouseUp";
+        assert_eq!(link_target(script), Some(GoDestination::Next));
+        assert_eq!(
+            link_target("on mouseUp\r  go cd id 3021 -- the index\rend mouseUp"),
+            Some(GoDestination::Id(3021))
+        );
+        assert_eq!(
+            link_target("on mouseUp\r  go third card\r  beep\rend mouseUp"),
+            None
+        );
+    }
+
+    #[test]
+    fn dashes_in_quotes_are_not_a_comment() {
+        assert_eq!(
+            link_target("on mouseUp\r  go to card \"A -- B\" -- the end\rend mouseUp"),
+            Some(GoDestination::Name("A -- B".to_string()))
+        );
+        assert_eq!(
+            strip_comment("put \"--\" into x -- two dashes"),
+            "put \"--\" into x "
+        );
+    }
+}
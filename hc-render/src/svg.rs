@@ -0,0 +1,599 @@
+// SVG export. Unlike `render_card` this keeps the structure of the card: each layer's picture is
+// an embedded 1-bit PNG, buttons and field frames are vector shapes, and field text is real text
+// that can be selected and searched. Every character is still placed where HyperCard would put it,
+// so the layout holds up even when the viewer substitutes a font.
+
+use std::error::Error;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::Path;
+
+use base64::Engine;
+use hc_decode::bitmap::Bitmap;
+use hc_decode::card::Card;
+use hc_decode::part::{Part, PartStyle, PartType};
+use hc_decode::stack::Stack;
+use image::GrayImage;
+
+use crate::button::{self, Label, CORNER};
+use crate::compositor::Layer;
+use crate::draw::{self, Rect};
+use crate::fallback;
+use crate::field::{self, SCROLL_BAR_WIDTH};
+use crate::layout::{self, TextLayout};
+use crate::text::{Fonts, TextStyle, BOLD, ITALIC, OUTLINE, SHADOW, UNDERLINE};
+
+/// A card as an SVG document, the size of the stack's cards.
+pub fn card_to_svg(stack: &Stack, card: &Card) -> String {
+    card_to_svg_with_fonts(stack, &Fonts::for_stack(stack), card)
+}
+
+/// `card_to_svg`, with fonts that have already been loaded, for exporting a lot of cards.
+pub fn card_to_svg_with_fonts(stack: &Stack, fonts: &Fonts, card: &Card) -> String {
+    let (width, height) = (stack.size.0, stack.size.1);
+    let mut svg = Svg {
+        stack,
+        fonts,
+        out: String::new(),
+        clips: 0,
+    };
+    let _ = write!(
+        svg.out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" xmlns:xlink="http://www.w3.org/1999/xlink" width="{w}" height="{h}" viewBox="0 0 {w} {h}" shape-rendering="crispEdges">"#,
+        w = width,
+        h = height
+    );
+    svg.out.push('\n');
+    // the 50% gray pattern, for scroll bar tracks.
+    svg.out.push_str(
+        r#"<defs><pattern id="gray" width="2" height="2" patternUnits="userSpaceOnUse"><rect width="2" height="2" fill="white"/><rect width="1" height="1" fill="black"/><rect x="1" y="1" width="1" height="1" fill="black"/></pattern></defs>"#,
+    );
+    svg.out.push('\n');
+    let _ = writeln!(
+        svg.out,
+        r#"<rect width="{}" height="{}" fill="white"/>"#,
+        width, height
+    );
+
+    if let Some(background) = stack.background(card.background_id) {
+        svg.out.push_str("<g id=\"background\">\n");
+        if background.show_picture() {
+            if let Some(bitmap) = stack.bitmap(background.bitmap_id) {
+                svg.picture("background-picture", bitmap);
+            }
+        }
+        for part in &background.parts {
+            svg.part(card, part, Layer::Background);
+        }
+        svg.out.push_str("</g>\n");
+    }
+
+    svg.out.push_str("<g id=\"card\">\n");
+    if card.show_picture() {
+        if let Some(bitmap) = stack.bitmap(card.bitmap_block_id) {
+            svg.picture("card-picture", bitmap);
+        }
+    }
+    for part in &card.parts {
+        svg.part(card, part, Layer::Card);
+    }
+    svg.out.push_str("</g>\n</svg>\n");
+    svg.out
+}
+
+/// Export a card as SVG to `path`.
+pub fn export_svg(stack: &Stack, card: &Card, path: &Path) -> Result<(), Box<dyn Error>> {
+    fs::write(path, card_to_svg(stack, card))?;
+    Ok(())
+}
+
+/// Encode a black and white image as a 1-bit grayscale PNG.
+pub fn encode_1bit_png(image: &GrayImage) -> Result<Vec<u8>, Box<dyn Error>> {
+    let (width, height) = image.dimensions();
+    let row_bytes = (width as usize).div_ceil(8);
+    let mut data = vec![0u8; row_bytes * height as usize];
+    for (x, y, pixel) in image.enumerate_pixels() {
+        // white is 1 in a grayscale PNG.
+        if pixel.0[0] >= 128 {
+            data[y as usize * row_bytes + x as usize / 8] |= 0x80 >> (x % 8);
+        }
+    }
+    let mut out = Vec::new();
+    let mut encoder = png::Encoder::new(&mut out, width, height);
+    encoder.set_color(png::ColorType::Grayscale);
+    encoder.set_depth(png::BitDepth::One);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)?;
+    writer.finish()?;
+    Ok(out)
+}
+
+struct Svg<'a> {
+    stack: &'a Stack,
+    fonts: &'a Fonts,
+    out: String,
+    // clip paths made so far, for naming the next one.
+    clips: u32,
+}
+
+impl Svg<'_> {
+    // A layer's picture, masked so that only its opaque parts and black pixels cover what's below,
+    // the same as `paint_bitmap`.
+    fn picture(&mut self, id: &str, bitmap: &Bitmap) {
+        let mask = GrayImage::from_fn(bitmap.image.width(), bitmap.image.height(), |x, y| {
+            let opaque = bitmap.image.get_pixel(x, y).0[0] < 128
+                || (x < bitmap.mask.width()
+                    && y < bitmap.mask.height()
+                    && bitmap.mask.get_pixel(x, y).0[0] >= 128);
+            image::Luma([if opaque { 255 } else { 0 }])
+        });
+        let (Some(image), Some(mask_image)) = (png_uri(&bitmap.image), png_uri(&mask)) else {
+            return;
+        };
+        let (w, h) = bitmap.image.dimensions();
+        let _ = writeln!(
+            self.out,
+            r#"<mask id="{id}-mask" maskUnits="userSpaceOnUse" x="0" y="0" width="{w}" height="{h}"><image width="{w}" height="{h}" xlink:href="{mask_image}" style="image-rendering:pixelated"/></mask>"#
+        );
+        let _ = writeln!(
+            self.out,
+            r#"<image id="{id}" width="{w}" height="{h}" xlink:href="{image}" mask="url(#{id}-mask)" style="image-rendering:pixelated"/>"#
+        );
+    }
+
+    fn part(&mut self, card: &Card, part: &Part, layer: Layer) {
+        if !part.visible {
+            return;
+        }
+        let prefix = match layer {
+            Layer::Background => "bg",
+            Layer::Card => "card",
+        };
+        match part.ty {
+            PartType::Button => {
+                let _ = writeln!(
+                    self.out,
+                    r#"<g id="{}-button-{}" data-name="{}"{}>"#,
+                    prefix,
+                    part.id,
+                    escape(&part.name),
+                    if part.enabled {
+                        ""
+                    } else {
+                        r#" opacity="0.5""#
+                    }
+                );
+                self.button(part);
+            }
+            PartType::Field => {
+                let _ = writeln!(
+                    self.out,
+                    r#"<g id="{}-field-{}" data-name="{}">"#,
+                    prefix,
+                    part.id,
+                    escape(&part.name)
+                );
+                self.field(card, part, layer);
+            }
+            PartType::Unknown => return,
+        }
+        self.out.push_str("</g>\n");
+    }
+
+    fn button(&mut self, part: &Part) {
+        let rect = draw::rect_from(part.position);
+        match part.style {
+            PartStyle::Checkbox | PartStyle::Radio => self.mark_button(part, rect),
+            PartStyle::Popup => self.popup(part, rect),
+            _ => {
+                let content = self.button_frame(part, rect);
+                if part.hilite {
+                    match part.style {
+                        PartStyle::RoundRectangle | PartStyle::Standard | PartStyle::Default => {
+                            self.round_rect(content, CORNER - 2, "black", None)
+                        }
+                        PartStyle::Oval => self.oval(content, "black", None),
+                        _ => self.rect(content, "black", None),
+                    }
+                }
+                self.button_label(part, content);
+            }
+        }
+    }
+
+    // The button's body and frame, returning the rectangle inside the frame.
+    fn button_frame(&mut self, part: &Part, rect: Rect) -> Rect {
+        let (body, content) = button::frame_of(&part.style, rect);
+        match part.style {
+            PartStyle::Opaque => self.rect(body, "white", None),
+            PartStyle::Rectangle => self.rect(body, "white", Some("black")),
+            PartStyle::Shadow => {
+                self.shadow(rect);
+                self.rect(body, "white", Some("black"));
+            }
+            PartStyle::RoundRectangle => {
+                self.round_rect(draw::offset(body, 1, 1), CORNER, "black", None);
+                self.round_rect(body, CORNER, "white", Some("black"));
+            }
+            PartStyle::Standard => self.round_rect(body, CORNER, "white", Some("black")),
+            PartStyle::Default => {
+                self.round_rect(rect, CORNER + 6, "black", None);
+                self.round_rect(draw::inset(rect, 3, 3), CORNER + 2, "white", None);
+                self.round_rect(body, CORNER, "none", Some("black"));
+            }
+            _ => {}
+        }
+        content
+    }
+
+    // The icon and/or name, centered in the button.
+    fn button_label(&mut self, part: &Part, content: Rect) {
+        let style = TextStyle::of_part(part);
+        let metrics = self.fonts.metrics(&style);
+        let color = if part.hilite { "white" } else { "black" };
+        let icon = match part.icon_id {
+            0 => None,
+            id => self.stack.resources.as_ref().and_then(|r| r.icon(id)),
+        };
+
+        let name_width = self.fonts.string_width(&part.name, &style);
+        let label = Label::place(part, content, icon.is_some(), name_width, &metrics);
+        if let (Some(icon), Some((x, y))) = (icon, label.icon) {
+            self.icon(&icon, x, y, part.hilite);
+        }
+        if let Some((x, baseline)) = label.name {
+            self.string(&part.name, x, baseline, &style, color);
+        }
+    }
+
+    fn mark_button(&mut self, part: &Part, rect: Rect) {
+        let style = TextStyle::of_part(part);
+        let (mark, name_x) = button::mark_of(rect);
+        if matches!(part.style, PartStyle::Checkbox) {
+            self.rect(mark, "white", Some("black"));
+            if part.hilite {
+                let _ = writeln!(
+                    self.out,
+                    r#"<path d="M{l} {t}L{r} {b}M{r} {t}L{l} {b}" stroke="black" fill="none"/>"#,
+                    l = mark.1 + 1,
+                    t = mark.0 + 1,
+                    r = mark.3 - 1,
+                    b = mark.2 - 1
+                );
+            }
+        } else {
+            self.oval(mark, "white", Some("black"));
+            if part.hilite {
+                self.oval(draw::inset(mark, 3, 3), "black", None);
+            }
+        }
+        if part.show_name {
+            let baseline = self.centered_baseline(rect, &style);
+            self.string(&part.name, name_x, baseline, &style, "black");
+        }
+    }
+
+    fn popup(&mut self, part: &Part, rect: Rect) {
+        let style = TextStyle::of_part(part);
+        let baseline = self.centered_baseline(rect, &style);
+        let title_width = part.title_width as i32;
+        if title_width > 0 && part.show_name {
+            self.string(&part.name, rect.1 + 2, baseline, &style, "black");
+        }
+
+        let body = button::popup_body(part, rect);
+        self.shadow((body.0, body.1, body.2 + 1, body.3 + 1));
+        self.rect(body, "white", Some("black"));
+
+        let items = part.contents.first().map(|c| c.text.as_str()).unwrap_or("");
+        let selected = items
+            .split('\r')
+            .nth((part.selected_line.max(1) - 1) as usize)
+            .unwrap_or("");
+        self.string(selected, body.1 + 13, baseline, &style, "black");
+
+        let x = body.3 - 22;
+        let y = body.0 + (draw::height(body) - 6) / 2;
+        let _ = writeln!(
+            self.out,
+            r#"<path d="M{} {}h11l-5.5 6z" fill="black"/>"#,
+            x, y
+        );
+    }
+
+    fn field(&mut self, card: &Card, part: &Part, layer: Layer) {
+        let rect = draw::rect_from(part.position);
+        let mut interior = match part.style {
+            PartStyle::Transparent => rect,
+            PartStyle::Opaque => {
+                self.rect(rect, "white", None);
+                rect
+            }
+            PartStyle::Shadow => {
+                let body = (rect.0, rect.1, rect.2 - 1, rect.3 - 1);
+                self.shadow(rect);
+                self.rect(body, "white", Some("black"));
+                draw::inset(body, 1, 1)
+            }
+            _ => {
+                self.rect(rect, "white", Some("black"));
+                draw::inset(rect, 1, 1)
+            }
+        };
+        let layout = TextLayout::for_field(
+            self.stack,
+            self.fonts,
+            card,
+            part,
+            layer == Layer::Background,
+        );
+        let (scroll, max_scroll) = field::scroll_of(part, &layout);
+        if matches!(part.style, PartStyle::Scrolling) {
+            let bar = (rect.0, rect.3 - SCROLL_BAR_WIDTH, rect.2, rect.3);
+            self.scroll_bar(bar, scroll, max_scroll);
+            interior.3 = bar.1;
+        }
+
+        // everything inside the frame is clipped to it and moved up by the scroll.
+        self.clips += 1;
+        let clip = format!("clip-{}", self.clips);
+        let _ = writeln!(
+            self.out,
+            r#"<clipPath id="{}"><rect x="{}" y="{}" width="{}" height="{}"/></clipPath>"#,
+            clip,
+            interior.1,
+            interior.0,
+            draw::width(interior).max(0),
+            draw::height(interior).max(0)
+        );
+        let _ = writeln!(self.out, r#"<g clip-path="url(#{})">"#, clip);
+        let _ = writeln!(self.out, r#"<g transform="translate(0 {})">"#, -scroll);
+        if part.show_lines {
+            self.field_lines(&layout, interior, scroll);
+        }
+        self.field_text(&layout);
+        self.out.push_str("</g>\n</g>\n");
+    }
+
+    // Each line of text is a `<text>` with a `<tspan>` for each style run, every character placed
+    // at the position the layout gave it.
+    fn field_text(&mut self, layout: &TextLayout) {
+        for (index, line) in layout.lines.iter().enumerate() {
+            let glyphs: Vec<_> = layout
+                .glyphs
+                .iter()
+                .filter(|g| g.line == index && !matches!(g.ch, '\r' | '\n'))
+                .collect();
+            if glyphs.is_empty() {
+                continue;
+            }
+            let _ = write!(
+                self.out,
+                r#"<text y="{}" xml:space="preserve">"#,
+                line.baseline
+            );
+            for run in glyphs.chunk_by(|a, b| a.style == b.style) {
+                let xs: Vec<String> = run.iter().map(|g| g.x.to_string()).collect();
+                let text: String = run.iter().map(|g| layout::visible_char(g.ch)).collect();
+                let _ = write!(
+                    self.out,
+                    r#"<tspan x="{}" {}>{}</tspan>"#,
+                    xs.join(" "),
+                    self.font_attributes(&run[0].style, "black"),
+                    escape(&text)
+                );
+            }
+            self.out.push_str("</text>\n");
+        }
+    }
+
+    // The dotted lines of a showLines field, carrying on to the bottom of the field.
+    fn field_lines(&mut self, layout: &TextLayout, interior: Rect, scroll: i32) {
+        let mut baselines: Vec<i32> = layout.lines.iter().map(|l| l.baseline).collect();
+        let (mut top, descent) = match layout.lines.last() {
+            Some(last) => (last.bottom, last.bottom - last.baseline),
+            None => (layout.rect.0, layout.empty_line_height / 4),
+        };
+        while top + layout.empty_line_height <= interior.2 + scroll {
+            top += layout.empty_line_height;
+            baselines.push(top - descent);
+        }
+        for y in baselines {
+            let _ = writeln!(
+                self.out,
+                r#"<line x1="{}" y1="{y}.5" x2="{}" y2="{y}.5" stroke="black" stroke-dasharray="1 1"/>"#,
+                interior.1, interior.3,
+            );
+        }
+    }
+
+    fn scroll_bar(&mut self, bar: Rect, scroll: i32, max_scroll: i32) {
+        let size = SCROLL_BAR_WIDTH;
+        self.rect(bar, "white", Some("black"));
+        let up = (bar.0, bar.1, bar.0 + size, bar.3);
+        let down = (bar.2 - size, bar.1, bar.2, bar.3);
+        self.rect(up, "none", Some("black"));
+        self.rect(down, "none", Some("black"));
+        let mid = bar.1 as f32 + size as f32 / 2.0;
+        // the same hollow arrows as the raster scroll bar.
+        let _ = writeln!(
+            self.out,
+            r#"<path d="M{m} {t}l-7 6h4v3h6v-3h4z M{m} {b}l-7 -6h4v-3h6v3h4z" fill="white" stroke="black"/>"#,
+            m = mid,
+            t = up.0 as f32 + 4.5,
+            b = down.2 as f32 - 4.5
+        );
+
+        let track = (up.2 - 1, bar.1, down.0 + 1, bar.3);
+        if max_scroll == 0 || draw::height(track) < size {
+            return;
+        }
+        self.rect(draw::inset(track, 1, 1), "url(#gray)", None);
+        let thumb_top = track.0
+            + ((draw::height(track) - size) as i64 * scroll as i64 / max_scroll as i64) as i32;
+        self.rect(
+            (thumb_top, bar.1, thumb_top + size, bar.3),
+            "white",
+            Some("black"),
+        );
+    }
+
+    fn icon(&mut self, icon: &GrayImage, x: i32, y: i32, inverted: bool) {
+        let mask = GrayImage::from_fn(icon.width(), icon.height(), |x, y| {
+            image::Luma([if icon.get_pixel(x, y).0[0] < 128 {
+                0
+            } else {
+                255
+            }])
+        });
+        let Some(uri) = png_uri(&mask) else {
+            return;
+        };
+        // the icon's black pixels only, so that it can sit on anything.
+        let _ = writeln!(
+            self.out,
+            r#"<image x="{x}" y="{y}" width="{w}" height="{h}" xlink:href="{uri}" style="image-rendering:pixelated;mix-blend-mode:{mode}"/>"#,
+            w = icon.width(),
+            h = icon.height(),
+            mode = if inverted { "screen" } else { "multiply" }
+        );
+    }
+
+    fn string(&mut self, text: &str, x: i32, baseline: i32, style: &TextStyle, color: &str) {
+        if text.is_empty() {
+            return;
+        }
+        let _ = writeln!(
+            self.out,
+            r#"<text x="{}" y="{}" {} xml:space="preserve">{}</text>"#,
+            x,
+            baseline,
+            self.font_attributes(style, color),
+            escape(text)
+        );
+    }
+
+    // font-family and friends for a text style. Outlined and shadowed text are drawn hollow; SVG
+    // can't do QuickDraw's shadow, so the shadow is left off.
+    fn font_attributes(&self, style: &TextStyle, color: &str) -> String {
+        let mut attributes = format!(
+            r#"font-family="{}" font-size="{}""#,
+            escape(&fallback::css_font_family(self.stack, style.font)),
+            style.size
+        );
+        if style.face & BOLD != 0 {
+            attributes.push_str(r#" font-weight="bold""#);
+        }
+        if style.face & ITALIC != 0 {
+            attributes.push_str(r#" font-style="italic""#);
+        }
+        if style.face & UNDERLINE != 0 {
+            attributes.push_str(r#" text-decoration="underline""#);
+        }
+        if style.face & (OUTLINE | SHADOW) != 0 {
+            let fill = if color == "black" { "white" } else { "black" };
+            let _ = write!(
+                attributes,
+                r#" fill="{}" stroke="{}" stroke-width="0.75""#,
+                fill, color
+            );
+        } else {
+            let _ = write!(attributes, r#" fill="{}""#, color);
+        }
+        attributes
+    }
+
+    fn centered_baseline(&self, rect: Rect, style: &TextStyle) -> i32 {
+        button::centered_baseline(rect, &self.fonts.metrics(style))
+    }
+
+    // A drop shadow one pixel below and right of `rect`, starting a couple of pixels in from the
+    // corners.
+    fn shadow(&mut self, rect: Rect) {
+        let _ = writeln!(
+            self.out,
+            r#"<path d="M{} {b}.5H{r}M{r}.5 {}V{}" stroke="black" fill="none"/>"#,
+            rect.1 + 2,
+            rect.0 + 2,
+            rect.2,
+            b = rect.2 - 1,
+            r = rect.3 - 1,
+        );
+    }
+
+    // Shapes take QuickDraw rectangles; a stroke is drawn on the inside, like a QuickDraw frame.
+    fn rect(&mut self, rect: Rect, fill: &str, stroke: Option<&str>) {
+        let _ = writeln!(self.out, "<rect {}/>", shape(rect, fill, stroke));
+    }
+
+    fn round_rect(&mut self, rect: Rect, corner: i32, fill: &str, stroke: Option<&str>) {
+        let _ = writeln!(
+            self.out,
+            r#"<rect rx="{}" {}/>"#,
+            corner as f32 / 2.0,
+            shape(rect, fill, stroke)
+        );
+    }
+
+    fn oval(&mut self, rect: Rect, fill: &str, stroke: Option<&str>) {
+        let inset = if stroke.is_some() { 0.5 } else { 0.0 };
+        let _ = writeln!(
+            self.out,
+            r#"<ellipse cx="{}" cy="{}" rx="{}" ry="{}" fill="{}"{}/>"#,
+            (rect.1 + rect.3) as f32 / 2.0,
+            (rect.0 + rect.2) as f32 / 2.0,
+            draw::width(rect) as f32 / 2.0 - inset,
+            draw::height(rect) as f32 / 2.0 - inset,
+            fill,
+            stroke
+                .map(|s| format!(r#" stroke="{}""#, s))
+                .unwrap_or_default()
+        );
+    }
+}
+
+fn shape(rect: Rect, fill: &str, stroke: Option<&str>) -> String {
+    match stroke {
+        Some(stroke) => format!(
+            r#"x="{}" y="{}" width="{}" height="{}" fill="{}" stroke="{}""#,
+            rect.1 as f32 + 0.5,
+            rect.0 as f32 + 0.5,
+            (draw::width(rect) - 1).max(0),
+            (draw::height(rect) - 1).max(0),
+            fill,
+            stroke
+        ),
+        None => format!(
+            r#"x="{}" y="{}" width="{}" height="{}" fill="{}""#,
+            rect.1,
+            rect.0,
+            draw::width(rect).max(0),
+            draw::height(rect).max(0),
+            fill
+        ),
+    }
+}
+
+fn png_uri(image: &GrayImage) -> Option<String> {
+    let png = encode_1bit_png(image).ok()?;
+    Some(format!(
+        "data:image/png;base64,{}",
+        base64::engine::general_purpose::STANDARD.encode(png)
+    ))
+}
+
+pub(crate) fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for ch in text.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            // XML has no way to write most control characters at all.
+            c if (c as u32) < 0x20 && c != '\t' && c != '\n' => escaped.push(' '),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
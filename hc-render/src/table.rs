@@ -0,0 +1,328 @@
+// Background field data as a table. A lot of stacks are databases really: one background with
+// its fields, and a card for every record. This pulls the text of a background's fields out of
+// every card using it, a column per field and a row per card in stack order, and writes it as
+// CSV, JSON Lines or a SQLite database.
+
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use hc_decode::part::{Part, PartType};
+use hc_decode::stack::Stack;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TableFormat {
+    Csv,
+    JsonLines,
+    Sqlite,
+}
+
+impl TableFormat {
+    /// The format going by a file's extension, if it's one we know.
+    pub fn from_extension(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "csv" => Some(TableFormat::Csv),
+            "jsonl" | "ndjson" | "json" => Some(TableFormat::JsonLines),
+            "sqlite" | "sqlite3" | "db" => Some(TableFormat::Sqlite),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FieldTable {
+    pub background_id: u32,
+    /// The background's name, or `background <id>` if it hasn't got one.
+    pub name: String,
+    /// A column per background field, in the background's part order.
+    pub columns: Vec<String>,
+    pub rows: Vec<FieldRow>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FieldRow {
+    pub card_id: u32,
+    pub card_name: String,
+    /// The card's text for each of the table's columns.
+    pub values: Vec<String>,
+}
+
+impl FieldTable {
+    /// The field data of every card using the background with the given ID, or `None` if the
+    /// stack hasn't got such a background.
+    pub fn for_background(stack: &Stack, background_id: u32) -> Option<Self> {
+        let background = stack.backgrounds.iter().find(|b| b.id == background_id)?;
+        let fields: Vec<&Part> = background
+            .parts
+            .iter()
+            .filter(|part| matches!(part.ty, PartType::Field))
+            .collect();
+
+        let rows = stack
+            .cards
+            .iter()
+            .filter(|card| card.background_id == background_id)
+            .map(|card| FieldRow {
+                card_id: card.id,
+                card_name: card.name.clone(),
+                values: fields
+                    .iter()
+                    .map(|part| {
+                        card.contents_of(part, true)
+                            // lines end in returns in HyperCard; nothing reads those any more.
+                            .map(|entry| entry.text.replace('\r', "\n"))
+                            .unwrap_or_default()
+                    })
+                    .collect(),
+            })
+            .collect();
+
+        Some(FieldTable {
+            background_id,
+            name: if background.name.is_empty() {
+                format!("background {}", background.id)
+            } else {
+                background.name.clone()
+            },
+            columns: column_names(&fields),
+            rows,
+        })
+    }
+
+    /// A table for every background of the stack. Backgrounds can share a name, which tables in
+    /// the same database can't, so repeats get their ID added.
+    pub fn all(stack: &Stack) -> Vec<Self> {
+        let mut tables: Vec<Self> = stack
+            .backgrounds
+            .iter()
+            .filter_map(|background| Self::for_background(stack, background.id))
+            .collect();
+        let names = unique_names(
+            tables
+                .iter()
+                .map(|t| (t.name.clone(), t.background_id))
+                .collect(),
+            &[],
+        );
+        for (table, name) in tables.iter_mut().zip(names) {
+            table.name = name;
+        }
+        tables
+    }
+
+    /// Write the table out, with the card's ID and name leading each row.
+    pub fn write(&self, format: TableFormat, path: &Path) -> Result<(), Box<dyn Error>> {
+        match format {
+            TableFormat::Csv => self.write_csv(path),
+            TableFormat::JsonLines => self.write_json_lines(path),
+            TableFormat::Sqlite => self.write_sqlite(path),
+        }
+    }
+
+    pub fn write_csv(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let mut writer = csv::Writer::from_path(path)?;
+        writer.write_record(self.header())?;
+        for row in &self.rows {
+            writer.write_record(
+                [row.card_id.to_string(), row.card_name.clone()]
+                    .iter()
+                    .chain(row.values.iter()),
+            )?;
+        }
+        writer.flush()?;
+        Ok(())
+    }
+
+    pub fn write_json_lines(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let mut out = BufWriter::new(File::create(path)?);
+        for row in &self.rows {
+            // written by hand rather than through a map so the keys keep the column order.
+            write!(
+                out,
+                "{{\"card id\":{},\"card name\":{}",
+                row.card_id,
+                serde_json::to_string(&row.card_name)?
+            )?;
+            for (column, value) in self.columns.iter().zip(&row.values) {
+                write!(
+                    out,
+                    ",{}:{}",
+                    serde_json::to_string(column)?,
+                    serde_json::to_string(value)?
+                )?;
+            }
+            writeln!(out, "}}")?;
+        }
+        out.flush()?;
+        Ok(())
+    }
+
+    /// Write the table into a SQLite database, replacing a table of the same name if the file
+    /// already has one. The table is named after the background.
+    pub fn write_sqlite(&self, path: &Path) -> Result<(), Box<dyn Error>> {
+        let mut connection = rusqlite::Connection::open(path)?;
+        let transaction = connection.transaction()?;
+        let table = quote_identifier(&self.name);
+        let columns: Vec<String> = self.columns.iter().map(|c| quote_identifier(c)).collect();
+
+        transaction.execute(&format!("DROP TABLE IF EXISTS {}", table), [])?;
+        let mut create = format!(
+            "CREATE TABLE {} (\"card id\" INTEGER PRIMARY KEY, \"card name\" TEXT",
+            table
+        );
+        for column in &columns {
+            create.push_str(&format!(", {} TEXT", column));
+        }
+        create.push(')');
+        transaction.execute(&create, [])?;
+
+        let placeholders: Vec<String> =
+            (1..=columns.len() + 2).map(|i| format!("?{}", i)).collect();
+        let insert = format!(
+            "INSERT INTO {} (\"card id\", \"card name\"{}) VALUES ({})",
+            table,
+            columns
+                .iter()
+                .map(|c| format!(", {}", c))
+                .collect::<String>(),
+            placeholders.join(", ")
+        );
+        {
+            let mut statement = transaction.prepare(&insert)?;
+            for row in &self.rows {
+                let mut values: Vec<&dyn rusqlite::ToSql> = vec![&row.card_id, &row.card_name];
+                values.extend(row.values.iter().map(|v| v as &dyn rusqlite::ToSql));
+                statement.execute(values.as_slice())?;
+            }
+        }
+        transaction.commit()?;
+        Ok(())
+    }
+
+    fn header(&self) -> Vec<String> {
+        ["card id", "card name"]
+            .iter()
+            .map(|s| s.to_string())
+            .chain(self.columns.iter().cloned())
+            .collect()
+    }
+}
+
+/// Write the field data of every background into `dir`, a file per background named after its ID,
+/// or for SQLite a single `stack.sqlite` with a table per background.
+pub fn export_tables(stack: &Stack, format: TableFormat, dir: &Path) -> Result<(), Box<dyn Error>> {
+    fs::create_dir_all(dir)?;
+    for table in FieldTable::all(stack) {
+        let path = match format {
+            TableFormat::Csv => dir.join(format!("background-{}.csv", table.background_id)),
+            TableFormat::JsonLines => dir.join(format!("background-{}.jsonl", table.background_id)),
+            TableFormat::Sqlite => dir.join("stack.sqlite"),
+        };
+        table.write(format, &path)?;
+    }
+    Ok(())
+}
+
+// Fields are named after the part, falling back on `field <id>` like HyperCard does for unnamed
+// parts. Two fields can have the same name, which a column can't, so repeats get their ID added.
+fn column_names(fields: &[&Part]) -> Vec<String> {
+    let names = fields
+        .iter()
+        .map(|part| {
+            let name = if part.name.is_empty() {
+                format!("field id {}", part.id)
+            } else {
+                part.name.clone()
+            };
+            (name, part.id as u32)
+        })
+        .collect();
+    unique_names(names, &["card id", "card name"])
+}
+
+// Add the ID to any name that's used more than once or is `reserved`. SQLite doesn't care about
+// case in names, so neither does this.
+fn unique_names(names: Vec<(String, u32)>, reserved: &[&str]) -> Vec<String> {
+    names
+        .iter()
+        .map(|(name, id)| {
+            let taken = names
+                .iter()
+                .filter(|(n, _)| n.eq_ignore_ascii_case(name))
+                .count()
+                > 1
+                || reserved.iter().any(|r| name.eq_ignore_ascii_case(r));
+            if taken {
+                format!("{} (id {})", name, id)
+            } else {
+                name.clone()
+            }
+        })
+        .collect()
+}
+
+fn quote_identifier(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[(&str, u32)], reserved: &[&str]) -> Vec<String> {
+        unique_names(
+            names.iter().map(|(n, id)| (n.to_string(), *id)).collect(),
+            reserved,
+        )
+    }
+
+    #[test]
+    fn repeated_names_get_their_id() {
+        assert_eq!(
+            names(&[("Records", 2), ("records", 7), ("Index", 9)], &[]),
+            ["Records (id 2)", "records (id 7)", "Index"]
+        );
+    }
+
+    #[test]
+    fn reserved_names_get_their_id() {
+        assert_eq!(
+            names(&[("Card ID", 4), ("Notes", 5)], &["card id", "card name"]),
+            ["Card ID (id 4)", "Notes"]
+        );
+    }
+
+    #[test]
+    fn backgrounds_with_the_same_name_are_separate_tables() {
+        let path = std::env::temp_dir().join(format!("tables-{}.sqlite", std::process::id()));
+        let table = |background_id, name: &str, text: &str| FieldTable {
+            background_id,
+            name: name.to_string(),
+            columns: vec!["Name".to_string()],
+            rows: vec![FieldRow {
+                card_id: background_id + 100,
+                card_name: String::new(),
+                values: vec![text.to_string()],
+            }],
+        };
+        let tables = [
+            table(1, "Records (id 1)", "a"),
+            table(2, "records (id 2)", "b"),
+        ];
+        for table in &tables {
+            table.write_sqlite(&path).unwrap();
+        }
+        let connection = rusqlite::Connection::open(&path).unwrap();
+        let count: i64 = connection
+            .query_row(
+                "SELECT count(*) FROM sqlite_master WHERE type = 'table'",
+                [],
+                |r| r.get(0),
+            )
+            .unwrap();
+        drop(connection);
+        fs::remove_file(&path).unwrap();
+        assert_eq!(count, 2);
+    }
+}
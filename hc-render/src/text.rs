@@ -0,0 +1,475 @@
+// Text drawing.
+//
+// Text is drawn from the QuickDraw bitmap fonts (`NFNT`s and `FONT`s) in the stack's resource
+// fork where it has them, and otherwise from the bundled fonts in `fallback`, scaled from the
+// nearest size if there isn't the exact one. The QuickDraw style variants are applied to each
+// character on the way to the canvas.
+
+use std::collections::HashMap;
+
+use hc_decode::bitmap_font::BitmapFont;
+use hc_decode::macroman::char_to_macroman;
+use hc_decode::part::Part;
+use hc_decode::stack::Stack;
+use image::RgbaImage;
+
+use crate::draw::{self, BLACK};
+use crate::fallback::{self, Family};
+
+// QuickDraw text faces.
+pub const BOLD: u8 = 0x01;
+pub const ITALIC: u8 = 0x02;
+pub const UNDERLINE: u8 = 0x04;
+pub const OUTLINE: u8 = 0x08;
+pub const SHADOW: u8 = 0x10;
+pub const CONDENSE: u8 = 0x20;
+pub const EXTEND: u8 = 0x40;
+
+/// The font, size and QuickDraw face of a run of text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TextStyle {
+    pub font: i16,
+    pub size: u16,
+    pub face: u8,
+}
+
+impl TextStyle {
+    /// The text style a part's name or contents are drawn in by default.
+    pub fn of_part(part: &Part) -> Self {
+        TextStyle {
+            font: part.font_id as i16,
+            size: part.font_size,
+            face: part.text_style,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FontMetrics {
+    pub ascent: i32,
+    pub descent: i32,
+    pub leading: i32,
+}
+
+impl FontMetrics {
+    /// The height of a line of text, baseline to baseline.
+    pub fn line_height(&self) -> i32 {
+        self.ascent + self.descent + self.leading
+    }
+}
+
+/// The bitmap fonts text can be drawn in, by font ID and then size.
+#[derive(Debug, Clone)]
+pub struct Fonts {
+    pub families: HashMap<i16, Vec<(u16, BitmapFont)>>,
+    /// The bundled fonts, for font IDs that aren't in `families`.
+    pub fallbacks: HashMap<Family, Vec<(u16, BitmapFont)>>,
+    /// Which bundled family stands in for a font ID, where the stack's font table says better
+    /// than the ID alone.
+    pub substitutes: HashMap<i16, Family>,
+}
+
+impl Default for Fonts {
+    /// Just the bundled fonts.
+    fn default() -> Self {
+        let fallbacks = [
+            Family::System,
+            Family::Application,
+            Family::Monospace,
+            Family::Serif,
+        ]
+        .into_iter()
+        .map(|family| (family, fallback::bitmap_fonts(family)))
+        .collect();
+        Fonts {
+            families: HashMap::new(),
+            fallbacks,
+            substitutes: HashMap::new(),
+        }
+    }
+}
+
+// A character ready to be drawn: its image, `top` rows of which are above the baseline.
+#[derive(Debug, Clone)]
+struct Glyph {
+    left: i32,
+    top: i32,
+    width: i32,
+    height: i32,
+    advance: i32,
+    bits: Vec<bool>,
+}
+
+impl Glyph {
+    fn blank(left: i32, top: i32, width: i32, height: i32, advance: i32) -> Self {
+        Glyph {
+            left,
+            top,
+            width,
+            height,
+            advance,
+            bits: vec![false; (width.max(0) * height.max(0)) as usize],
+        }
+    }
+
+    fn get(&self, x: i32, y: i32) -> bool {
+        x >= 0
+            && y >= 0
+            && x < self.width
+            && y < self.height
+            && self.bits[(y * self.width + x) as usize]
+    }
+
+    fn set(&mut self, x: i32, y: i32) {
+        if x >= 0 && y >= 0 && x < self.width && y < self.height {
+            self.bits[(y * self.width + x) as usize] = true;
+        }
+    }
+}
+
+impl Fonts {
+    /// The bitmap fonts in a stack's resource fork, under the IDs the stack's font table gives
+    /// them, with the bundled fonts for everything else.
+    pub fn for_stack(stack: &Stack) -> Self {
+        let mut fonts = Fonts::default();
+        // the font table says which name each of the stack's font IDs had when it was saved,
+        // which may not be the ID the family has in the fork, or the one it has in the table of
+        // classic font IDs.
+        for font in &stack.fonts {
+            if let Some(family) = fallback::family_for_name(&font.name) {
+                fonts.substitutes.insert(font.id as i16, family);
+            }
+        }
+        let resources = match &stack.resources {
+            Some(a) => a,
+            None => return fonts,
+        };
+        for family in resources.font_families() {
+            let sizes = resources.bitmap_fonts(&family);
+            if !sizes.is_empty() {
+                fonts.families.insert(family.id, sizes);
+            }
+        }
+        for font in &stack.fonts {
+            if let Some(family) = resources.font_family(&font.name) {
+                let sizes = resources.bitmap_fonts(&family);
+                if !sizes.is_empty() {
+                    fonts.families.insert(font.id as i16, sizes);
+                }
+            }
+        }
+        fonts
+    }
+
+    pub fn insert(&mut self, font: i16, size: u16, bitmap_font: BitmapFont) {
+        let sizes = self.families.entry(font).or_default();
+        sizes.retain(|s| s.0 != size);
+        sizes.push((size, bitmap_font));
+        sizes.sort_by_key(|s| s.0);
+    }
+
+    // The font to draw a style in, and how much it has to be scaled by: the exact size if there
+    // is one, otherwise whichever is closest (preferring the larger).
+    fn font_for(&self, style: &TextStyle) -> Option<(&BitmapFont, f32)> {
+        let sizes = match self.families.get(&style.font) {
+            Some(a) => a,
+            None => {
+                let family = match self.substitutes.get(&style.font) {
+                    Some(a) => *a,
+                    None => fallback::family_for_id(style.font),
+                };
+                self.fallbacks.get(&family)?
+            }
+        };
+        let wanted = if style.size == 0 { 12 } else { style.size };
+        let (size, font) = sizes
+            .iter()
+            .min_by_key(|(size, _)| ((*size as i32 - wanted as i32).abs(), -(*size as i32)))?;
+        Some((font, wanted as f32 / *size as f32))
+    }
+
+    pub fn metrics(&self, style: &TextStyle) -> FontMetrics {
+        match self.font_for(style) {
+            Some((font, scale)) => FontMetrics {
+                ascent: scaled(font.ascent as i32, scale),
+                descent: scaled(font.descent as i32, scale),
+                leading: scaled(font.leading as i32, scale),
+            },
+            None => FontMetrics {
+                ascent: 0,
+                descent: 0,
+                leading: 0,
+            },
+        }
+    }
+
+    pub fn char_width(&self, ch: char, style: &TextStyle) -> i32 {
+        self.glyph(ch, style).advance
+    }
+
+    pub fn string_width(&self, text: &str, style: &TextStyle) -> i32 {
+        text.chars().map(|ch| self.char_width(ch, style)).sum()
+    }
+
+    /// Draw a string with its baseline at `y`, returning the pen position after the last
+    /// character. Gray text (for disabled buttons) only sets every other pixel, like drawing
+    /// through the gray pattern.
+    pub fn draw_string(
+        &self,
+        canvas: &mut RgbaImage,
+        x: i32,
+        y: i32,
+        text: &str,
+        style: &TextStyle,
+        gray: bool,
+    ) -> i32 {
+        let mut pen = x;
+        let plot = |canvas: &mut RgbaImage, px: i32, py: i32| {
+            if !gray || (px + py).rem_euclid(2) == 0 {
+                draw::set_pixel(canvas, px, py, BLACK);
+            }
+        };
+        for ch in text.chars() {
+            let glyph = self.glyph(ch, style);
+            let (gx, gy) = (pen + glyph.left, y - glyph.top);
+            for row in 0..glyph.height {
+                for col in 0..glyph.width {
+                    if glyph.get(col, row) {
+                        plot(canvas, gx + col, gy + row);
+                    }
+                }
+            }
+            if style.face & UNDERLINE != 0 {
+                // the underline is a pixel below the baseline, with a gap around descenders.
+                let row = glyph.top + 1;
+                for dx in 0..glyph.advance {
+                    let col = dx - glyph.left;
+                    if !(col - 1..=col + 1).any(|c| glyph.get(c, row)) {
+                        plot(canvas, pen + dx, y + 1);
+                    }
+                }
+            }
+            pen += glyph.advance;
+        }
+        pen
+    }
+
+    fn glyph(&self, ch: char, style: &TextStyle) -> Glyph {
+        let glyph = match self.font_for(style) {
+            Some((font, scale)) => font_glyph(font, ch, scale),
+            None => Glyph::blank(0, 0, 0, 0, 0),
+        };
+        apply_face(glyph, style.face)
+    }
+}
+
+fn scaled(n: i32, scale: f32) -> i32 {
+    (n as f32 * scale).round() as i32
+}
+
+fn font_glyph(font: &BitmapFont, ch: char, scale: f32) -> Glyph {
+    let bitmap = match font.glyph(char_to_macroman(ch).unwrap_or(0)) {
+        Some(a) => a,
+        None => return Glyph::blank(0, 0, 0, 0, 0),
+    };
+    let ascent = font.ascent as i32;
+    let (width, height) = (bitmap.image.width() as i32, bitmap.image.height() as i32);
+    let top = scaled(ascent, scale);
+    let mut glyph = Glyph::blank(
+        scaled(bitmap.left, scale),
+        top,
+        scaled(width, scale),
+        top + scaled(height - ascent, scale),
+        scaled(bitmap.advance, scale),
+    );
+    for y in 0..glyph.height {
+        for x in 0..glyph.width {
+            let sx = (x as f32 / scale) as u32;
+            let sy = (y as f32 / scale) as u32;
+            if sx < width as u32 && sy < height as u32 && bitmap.image.get_pixel(sx, sy).0[0] < 128
+            {
+                glyph.set(x, y);
+            }
+        }
+    }
+    glyph
+}
+
+// Apply the QuickDraw style variants to a plain character, in the order QuickDraw does: bold,
+// italic, then outline or shadow, with condense and extend only changing the advance.
+fn apply_face(mut glyph: Glyph, face: u8) -> Glyph {
+    if face & BOLD != 0 {
+        // smeared a pixel to the right.
+        let mut bold = Glyph::blank(
+            glyph.left,
+            glyph.top,
+            glyph.width + 1,
+            glyph.height,
+            glyph.advance + 1,
+        );
+        for y in 0..glyph.height {
+            for x in 0..glyph.width {
+                if glyph.get(x, y) {
+                    bold.set(x, y);
+                    bold.set(x + 1, y);
+                }
+            }
+        }
+        glyph = bold;
+    }
+    if face & ITALIC != 0 {
+        // slanted a pixel for every two rows away from the baseline.
+        let below = glyph.height - glyph.top;
+        let (rise, fall) = (glyph.top / 2, (below + 1) / 2);
+        let mut italic = Glyph::blank(
+            glyph.left - fall,
+            glyph.top,
+            glyph.width + rise + fall,
+            glyph.height,
+            glyph.advance,
+        );
+        for y in 0..glyph.height {
+            let shift = (glyph.top - 1 - y).div_euclid(2) + fall;
+            for x in 0..glyph.width {
+                if glyph.get(x, y) {
+                    italic.set(x + shift, y);
+                }
+            }
+        }
+        glyph = italic;
+    }
+    if face & (OUTLINE | SHADOW) != 0 {
+        // the character's own pixels are knocked out of a copy grown by a pixel all round, and a
+        // shadow grows it another pixel down and to the right.
+        let shadow = if face & SHADOW != 0 { 1 } else { 0 };
+        let mut hollow = Glyph::blank(
+            glyph.left - 1,
+            glyph.top + 1,
+            glyph.width + 2 + shadow,
+            glyph.height + 2 + shadow,
+            glyph.advance + 1 + shadow,
+        );
+        for y in 0..glyph.height {
+            for x in 0..glyph.width {
+                if !glyph.get(x, y) {
+                    continue;
+                }
+                for dy in 0..=2 + shadow {
+                    for dx in 0..=2 + shadow {
+                        hollow.set(x + dx, y + dy);
+                    }
+                }
+            }
+        }
+        for y in 0..glyph.height {
+            for x in 0..glyph.width {
+                if glyph.get(x, y) {
+                    hollow.bits[((y + 1) * hollow.width + x + 1) as usize] = false;
+                }
+            }
+        }
+        glyph = hollow;
+    }
+    if face & CONDENSE != 0 {
+        glyph.advance -= 1;
+    }
+    if face & EXTEND != 0 {
+        glyph.advance += 1;
+    }
+    glyph
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Chicago 12, from the bundled fonts.
+    const SYSTEM_12: TextStyle = TextStyle {
+        font: 0,
+        size: 12,
+        face: 0,
+    };
+
+    fn with_face(face: u8) -> TextStyle {
+        TextStyle { face, ..SYSTEM_12 }
+    }
+
+    #[test]
+    fn widths_come_from_the_font() {
+        let fonts = Fonts::default();
+        assert_eq!(fonts.char_width('A', &SYSTEM_12), 9);
+        assert_eq!(fonts.string_width("Ai", &SYSTEM_12), 13);
+        let metrics = fonts.metrics(&SYSTEM_12);
+        assert_eq!(metrics.line_height(), 15);
+    }
+
+    #[test]
+    fn a_glyph_is_placed_by_its_offset() {
+        let fonts = Fonts::default();
+        let j = fonts.glyph('j', &SYSTEM_12);
+        assert_eq!((j.left, j.top, j.advance), (-1, 11, 4));
+    }
+
+    #[test]
+    fn characters_the_font_lacks_are_drawn_as_the_missing_symbol() {
+        let fonts = Fonts::default();
+        assert_eq!(fonts.char_width('\u{1}', &SYSTEM_12), 8);
+        // and characters that aren't in Mac OS Roman at all.
+        assert_eq!(fonts.char_width('中', &SYSTEM_12), 8);
+    }
+
+    #[test]
+    fn bold_is_a_pixel_wider() {
+        let fonts = Fonts::default();
+        let (plain, bold) = (
+            fonts.glyph('A', &SYSTEM_12),
+            fonts.glyph('A', &with_face(BOLD)),
+        );
+        assert_eq!(
+            (bold.width, bold.advance),
+            (plain.width + 1, plain.advance + 1)
+        );
+    }
+
+    #[test]
+    fn italic_leans_without_changing_the_advance() {
+        let fonts = Fonts::default();
+        let italic = fonts.glyph('A', &with_face(ITALIC));
+        // 11 rows above the baseline lean 5 pixels right, and 3 below lean 2 left.
+        assert_eq!((italic.left, italic.width, italic.advance), (-2, 17, 9));
+    }
+
+    #[test]
+    fn outline_grows_a_pixel_all_round() {
+        let fonts = Fonts::default();
+        let outline = fonts.glyph('A', &with_face(OUTLINE));
+        assert_eq!(
+            (outline.left, outline.top, outline.width, outline.height),
+            (-1, 12, 12, 16)
+        );
+        assert_eq!(outline.advance, 10);
+        let shadow = fonts.glyph('A', &with_face(SHADOW));
+        assert_eq!((shadow.width, shadow.advance), (13, 11));
+    }
+
+    #[test]
+    fn sizes_without_a_font_are_scaled_from_the_nearest() {
+        let fonts = Fonts::default();
+        let sized = |size| TextStyle { size, ..SYSTEM_12 };
+        let (fourteen, _) = fonts.font_for(&sized(14)).unwrap();
+        // 12 and 14 are as near as each other, and the larger is taken.
+        let (font, scale) = fonts.font_for(&sized(13)).unwrap();
+        assert!(std::ptr::eq(font, fourteen));
+        assert_eq!(scale, 13.0 / 14.0);
+    }
+
+    #[test]
+    fn drawing_moves_the_pen_by_the_widths() {
+        let fonts = Fonts::default();
+        let mut canvas = RgbaImage::from_pixel(40, 20, image::Rgba([255, 255, 255, 255]));
+        let pen = fonts.draw_string(&mut canvas, 2, 12, "Ai", &SYSTEM_12, false);
+        assert_eq!(pen, 15);
+        // the stem of the `i` reaches the baseline.
+        assert_eq!(canvas.get_pixel(2 + 9 + 1, 11).0, [0, 0, 0, 255]);
+    }
+}
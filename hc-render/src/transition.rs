@@ -0,0 +1,501 @@
+// Visual effects: the transitions HyperCard plays between cards, as a sequence of frames.
+//
+// An effect takes the card that's on screen now and the image it's heading for (the next card,
+// or black, white, gray or the inverse of the next card) and produces the frames in between. The
+// speed only changes how many frames there are; how long each one stays up is the caller's
+// business.
+
+use std::borrow::Cow;
+
+use image::{Rgba, RgbaImage};
+
+use crate::draw::{BLACK, WHITE};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Effect {
+    /// No effect; the next card just appears. Also called `cut`.
+    Plain,
+    Dissolve,
+    WipeLeft,
+    WipeRight,
+    WipeUp,
+    WipeDown,
+    IrisOpen,
+    IrisClose,
+    BarnDoorOpen,
+    BarnDoorClose,
+    Checkerboard,
+    VenetianBlinds,
+    ScrollLeft,
+    ScrollRight,
+    ScrollUp,
+    ScrollDown,
+    /// Also called `zoom out`.
+    ZoomOpen,
+    /// Also called `zoom in`.
+    ZoomClose,
+    ShrinkToTop,
+    ShrinkToBottom,
+    ShrinkToCenter,
+    StretchFromTop,
+    StretchFromBottom,
+    StretchFromCenter,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Speed {
+    VerySlow,
+    Slow,
+    Normal,
+    Fast,
+    VeryFast,
+}
+
+/// What the effect ends up showing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Destination {
+    Card,
+    Black,
+    White,
+    Gray,
+    Inverse,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct VisualEffect {
+    pub effect: Effect,
+    pub speed: Speed,
+    pub destination: Destination,
+}
+
+// effects are made of cells this big: the squares of a checkerboard and the slats of the blinds.
+const CHECKER_CELLS: u32 = 8;
+const BLIND_HEIGHT: u32 = 16;
+
+impl Speed {
+    /// How many frames an effect takes at this speed, not counting the one it starts from.
+    pub fn frame_count(&self) -> u32 {
+        match self {
+            Speed::VerySlow => 32,
+            Speed::Slow => 24,
+            Speed::Normal => 16,
+            Speed::Fast => 8,
+            Speed::VeryFast => 4,
+        }
+    }
+}
+
+impl VisualEffect {
+    pub fn new(effect: Effect) -> Self {
+        VisualEffect {
+            effect,
+            speed: Speed::Normal,
+            destination: Destination::Card,
+        }
+    }
+
+    /// Parse an effect the way HyperTalk's `visual effect` command spells it, like
+    /// `iris open slowly to black` or `visual effect wipe left very fast`.
+    pub fn parse(text: &str) -> Option<Self> {
+        let lower = text.to_lowercase();
+        let mut words: Vec<&str> = lower.split_whitespace().collect();
+        if words.first() == Some(&"visual") {
+            words.remove(0);
+        }
+        if words.first() == Some(&"effect") {
+            words.remove(0);
+        }
+
+        // the destination comes last, after "to".
+        let mut destination = Destination::Card;
+        if let Some(to) = words.iter().rposition(|w| *w == "to") {
+            if to + 2 == words.len() {
+                let dest = match words[to + 1..].join(" ").as_str() {
+                    "card" => Some(Destination::Card),
+                    "black" => Some(Destination::Black),
+                    "white" => Some(Destination::White),
+                    "gray" | "grey" => Some(Destination::Gray),
+                    "inverse" => Some(Destination::Inverse),
+                    _ => None,
+                };
+                // "shrink to top" has a "to" that isn't the destination.
+                if let Some(dest) = dest {
+                    destination = dest;
+                    words.truncate(to);
+                }
+            }
+        }
+
+        let mut speed = Speed::Normal;
+        let speeds: [(&[&str], Speed); 8] = [
+            (&["very", "slowly"], Speed::VerySlow),
+            (&["very", "slow"], Speed::VerySlow),
+            (&["very", "fast"], Speed::VeryFast),
+            (&["slowly"], Speed::Slow),
+            (&["slow"], Speed::Slow),
+            (&["fast"], Speed::Fast),
+            (&["normal"], Speed::Normal),
+            (&["normally"], Speed::Normal),
+        ];
+        for (spelling, s) in speeds {
+            if words.ends_with(spelling) {
+                speed = s;
+                words.truncate(words.len() - spelling.len());
+                break;
+            }
+        }
+
+        let effect = match words.join(" ").as_str() {
+            "plain" | "cut" => Effect::Plain,
+            "dissolve" => Effect::Dissolve,
+            "wipe left" => Effect::WipeLeft,
+            "wipe right" => Effect::WipeRight,
+            "wipe up" => Effect::WipeUp,
+            "wipe down" => Effect::WipeDown,
+            "iris open" => Effect::IrisOpen,
+            "iris close" => Effect::IrisClose,
+            "barn door open" => Effect::BarnDoorOpen,
+            "barn door close" => Effect::BarnDoorClose,
+            "checkerboard" => Effect::Checkerboard,
+            "venetian blinds" => Effect::VenetianBlinds,
+            "scroll left" => Effect::ScrollLeft,
+            "scroll right" => Effect::ScrollRight,
+            "scroll up" => Effect::ScrollUp,
+            "scroll down" => Effect::ScrollDown,
+            "zoom open" | "zoom out" => Effect::ZoomOpen,
+            "zoom close" | "zoom in" => Effect::ZoomClose,
+            "shrink to top" => Effect::ShrinkToTop,
+            "shrink to bottom" => Effect::ShrinkToBottom,
+            "shrink to center" => Effect::ShrinkToCenter,
+            "stretch from top" => Effect::StretchFromTop,
+            "stretch from bottom" => Effect::StretchFromBottom,
+            "stretch from center" => Effect::StretchFromCenter,
+            _ => return None,
+        };
+        Some(VisualEffect {
+            effect,
+            speed,
+            destination,
+        })
+    }
+
+    /// The image the effect ends on, given the card it's going to.
+    pub fn target(&self, to: &RgbaImage) -> RgbaImage {
+        let (w, h) = to.dimensions();
+        match self.destination {
+            Destination::Card => to.clone(),
+            Destination::Black => RgbaImage::from_pixel(w, h, BLACK),
+            Destination::White => RgbaImage::from_pixel(w, h, WHITE),
+            Destination::Gray => {
+                RgbaImage::from_fn(w, h, |x, y| if (x + y) % 2 == 0 { BLACK } else { WHITE })
+            }
+            Destination::Inverse => RgbaImage::from_fn(w, h, |x, y| {
+                let p = to.get_pixel(x, y).0;
+                Rgba([255 - p[0], 255 - p[1], 255 - p[2], p[3]])
+            }),
+        }
+    }
+
+    /// The frames of the effect going from `from` to `to`, not including `from` itself. The last
+    /// frame is always the effect's target. Frames are the size of `from`; if `to` isn't, it's
+    /// cropped or padded out with white.
+    pub fn frames(&self, from: &RgbaImage, to: &RgbaImage) -> Vec<RgbaImage> {
+        let target = self.target(&fit(to, from.dimensions()));
+        if self.effect == Effect::Plain {
+            return vec![target];
+        }
+        let count = self.speed.frame_count();
+        (1..=count)
+            .map(|i| self.frame(from, &target, i as f32 / count as f32))
+            .collect()
+    }
+
+    /// A single frame of the effect, `t` of the way through (0 is all `from`, 1 is all `to`). `to`
+    /// is fitted to the size of `from` like in `frames`.
+    pub fn frame(&self, from: &RgbaImage, to: &RgbaImage, t: f32) -> RgbaImage {
+        let (w, h) = from.dimensions();
+        let to = fit(to, (w, h));
+        let to = to.as_ref();
+        let (wf, hf) = (w as f32, h as f32);
+        let (cx, cy) = (wf / 2.0, hf / 2.0);
+        // pick each pixel from one image or the other.
+        let reveal = |show: &dyn Fn(u32, u32) -> bool| {
+            RgbaImage::from_fn(w, h, |x, y| {
+                if show(x, y) {
+                    *to.get_pixel(x, y)
+                } else {
+                    *from.get_pixel(x, y)
+                }
+            })
+        };
+
+        match self.effect {
+            Effect::Plain => to.clone(),
+            Effect::Dissolve => reveal(&|x, y| dissolve_order(x, y) < t),
+            Effect::WipeLeft => reveal(&|x, _| x as f32 >= wf * (1.0 - t)),
+            Effect::WipeRight => reveal(&|x, _| (x as f32) < wf * t),
+            Effect::WipeUp => reveal(&|_, y| y as f32 >= hf * (1.0 - t)),
+            Effect::WipeDown => reveal(&|_, y| (y as f32) < hf * t),
+            Effect::IrisOpen | Effect::IrisClose => {
+                let max = (cx * cx + cy * cy).sqrt();
+                let inside = |x: u32, y: u32, r: f32| {
+                    let (dx, dy) = (x as f32 + 0.5 - cx, y as f32 + 0.5 - cy);
+                    (dx * dx + dy * dy).sqrt() < r
+                };
+                if self.effect == Effect::IrisOpen {
+                    reveal(&|x, y| inside(x, y, max * t))
+                } else {
+                    reveal(&|x, y| !inside(x, y, max * (1.0 - t)))
+                }
+            }
+            Effect::BarnDoorOpen => reveal(&|x, _| (x as f32 + 0.5 - cx).abs() < cx * t),
+            Effect::BarnDoorClose => reveal(&|x, _| (x as f32 + 0.5 - cx).abs() >= cx * (1.0 - t)),
+            Effect::Checkerboard => {
+                let (cell_w, cell_h) = (
+                    (w / CHECKER_CELLS).max(1) as f32,
+                    (h / CHECKER_CELLS).max(1) as f32,
+                );
+                // half the squares fill in from the top, then the other half.
+                reveal(&|x, y| {
+                    let (col, row) = ((x as f32 / cell_w) as u32, (y as f32 / cell_h) as u32);
+                    let within = (y as f32 % cell_h) / cell_h;
+                    if (col + row) % 2 == 0 {
+                        within < t * 2.0
+                    } else {
+                        within < t * 2.0 - 1.0
+                    }
+                })
+            }
+            Effect::VenetianBlinds => {
+                reveal(&|_, y| ((y % BLIND_HEIGHT) as f32) < BLIND_HEIGHT as f32 * t)
+            }
+            Effect::ScrollLeft | Effect::ScrollRight | Effect::ScrollUp | Effect::ScrollDown => {
+                // the new card pushes the old one off the screen.
+                let (dx, dy) = match self.effect {
+                    Effect::ScrollLeft => (-(wf * t) as i64, 0),
+                    Effect::ScrollRight => ((wf * t) as i64, 0),
+                    Effect::ScrollUp => (0, -(hf * t) as i64),
+                    _ => (0, (hf * t) as i64),
+                };
+                RgbaImage::from_fn(w, h, |x, y| {
+                    let (fx, fy) = (x as i64 - dx, y as i64 - dy);
+                    if fx >= 0 && fy >= 0 && fx < w as i64 && fy < h as i64 {
+                        *from.get_pixel(fx as u32, fy as u32)
+                    } else {
+                        let tx = (fx.rem_euclid(w as i64)) as u32;
+                        let ty = (fy.rem_euclid(h as i64)) as u32;
+                        *to.get_pixel(tx, ty)
+                    }
+                })
+            }
+            Effect::ZoomOpen | Effect::ZoomClose => {
+                // a rectangle grows out of (or shrinks into) the middle of the screen, with its
+                // edge drawn in.
+                let s = if self.effect == Effect::ZoomOpen {
+                    t
+                } else {
+                    1.0 - t
+                };
+                let (rw, rh) = (cx * s, cy * s);
+                let inside = |x: u32, y: u32| {
+                    (x as f32 + 0.5 - cx).abs() < rw && (y as f32 + 0.5 - cy).abs() < rh
+                };
+                let edge = |x: u32, y: u32| {
+                    inside(x, y)
+                        && !(inside(x.wrapping_sub(1), y)
+                            && inside(x + 1, y)
+                            && inside(x, y.wrapping_sub(1))
+                            && inside(x, y + 1))
+                };
+                let open = self.effect == Effect::ZoomOpen;
+                RgbaImage::from_fn(w, h, |x, y| {
+                    if t < 1.0 && edge(x, y) {
+                        BLACK
+                    } else if inside(x, y) == open {
+                        *to.get_pixel(x, y)
+                    } else {
+                        *from.get_pixel(x, y)
+                    }
+                })
+            }
+            Effect::ShrinkToTop
+            | Effect::ShrinkToBottom
+            | Effect::ShrinkToCenter
+            | Effect::StretchFromTop
+            | Effect::StretchFromBottom
+            | Effect::StretchFromCenter => {
+                // one card is squashed vertically over the other: the old one shrinking away, or
+                // the new one stretching out.
+                let shrink = matches!(
+                    self.effect,
+                    Effect::ShrinkToTop | Effect::ShrinkToBottom | Effect::ShrinkToCenter
+                );
+                let (moving, still, scale) = if shrink {
+                    (from, to, 1.0 - t)
+                } else {
+                    (to, from, t)
+                };
+                let height = hf * scale;
+                let top = match self.effect {
+                    Effect::ShrinkToTop | Effect::StretchFromTop => 0.0,
+                    Effect::ShrinkToBottom | Effect::StretchFromBottom => hf - height,
+                    _ => (hf - height) / 2.0,
+                };
+                RgbaImage::from_fn(w, h, |x, y| {
+                    let rel = y as f32 + 0.5 - top;
+                    if height > 0.0 && rel >= 0.0 && rel < height {
+                        let sy = ((rel / scale) as u32).min(h - 1);
+                        *moving.get_pixel(x, sy)
+                    } else {
+                        *still.get_pixel(x, y)
+                    }
+                })
+            }
+        }
+    }
+}
+
+// The image cropped or padded out with white to the given size, when it isn't that size already.
+fn fit(image: &RgbaImage, (w, h): (u32, u32)) -> Cow<'_, RgbaImage> {
+    if image.dimensions() == (w, h) {
+        return Cow::Borrowed(image);
+    }
+    Cow::Owned(RgbaImage::from_fn(w, h, |x, y| {
+        image.get_pixel_checked(x, y).copied().unwrap_or(WHITE)
+    }))
+}
+
+// Where in the dissolve a pixel changes over, between 0 and 1. A hash of its position, so the
+// pixels come in scattered but always in the same order.
+fn dissolve_order(x: u32, y: u32) -> f32 {
+    let mut n = x.wrapping_mul(0x9E37_79B1) ^ y.wrapping_mul(0x85EB_CA77);
+    n ^= n >> 15;
+    n = n.wrapping_mul(0x2C1B_3C6D);
+    n ^= n >> 12;
+    n = n.wrapping_mul(0x297A_2D39);
+    n ^= n >> 15;
+    (n >> 8) as f32 / (1 << 24) as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EFFECTS: [Effect; 24] = [
+        Effect::Plain,
+        Effect::Dissolve,
+        Effect::WipeLeft,
+        Effect::WipeRight,
+        Effect::WipeUp,
+        Effect::WipeDown,
+        Effect::IrisOpen,
+        Effect::IrisClose,
+        Effect::BarnDoorOpen,
+        Effect::BarnDoorClose,
+        Effect::Checkerboard,
+        Effect::VenetianBlinds,
+        Effect::ScrollLeft,
+        Effect::ScrollRight,
+        Effect::ScrollUp,
+        Effect::ScrollDown,
+        Effect::ZoomOpen,
+        Effect::ZoomClose,
+        Effect::ShrinkToTop,
+        Effect::ShrinkToBottom,
+        Effect::ShrinkToCenter,
+        Effect::StretchFromTop,
+        Effect::StretchFromBottom,
+        Effect::StretchFromCenter,
+    ];
+
+    // A card with a black square in the top left.
+    fn card(w: u32, h: u32) -> RgbaImage {
+        RgbaImage::from_fn(w, h, |x, y| if x < 8 && y < 8 { BLACK } else { WHITE })
+    }
+
+    #[test]
+    fn the_speed_sets_the_number_of_frames() {
+        let (from, to) = (card(40, 30), RgbaImage::from_pixel(40, 30, WHITE));
+        for (speed, count) in [
+            (Speed::VerySlow, 32),
+            (Speed::Slow, 24),
+            (Speed::Normal, 16),
+            (Speed::Fast, 8),
+            (Speed::VeryFast, 4),
+        ] {
+            let effect = VisualEffect {
+                speed,
+                ..VisualEffect::new(Effect::Dissolve)
+            };
+            assert_eq!(effect.frames(&from, &to).len(), count);
+        }
+        // a plain effect is only ever the one frame.
+        assert_eq!(VisualEffect::new(Effect::Plain).frames(&from, &to).len(), 1);
+    }
+
+    #[test]
+    fn every_effect_ends_on_its_target() {
+        let (from, to) = (card(40, 30), RgbaImage::from_pixel(40, 30, BLACK));
+        for effect in EFFECTS {
+            for destination in [Destination::Card, Destination::Gray, Destination::Inverse] {
+                let effect = VisualEffect {
+                    effect,
+                    speed: Speed::VeryFast,
+                    destination,
+                };
+                let frames = effect.frames(&from, &to);
+                assert!(
+                    frames.last() == Some(&effect.target(&to)),
+                    "{:?} doesn't end on its target",
+                    effect
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn a_card_of_another_size_is_fitted() {
+        let from = card(40, 30);
+        for to in [card(20, 50), card(60, 10)] {
+            for effect in EFFECTS {
+                let frames = VisualEffect::new(effect).frames(&from, &to);
+                assert!(frames.iter().all(|f| f.dimensions() == (40, 30)));
+            }
+        }
+        let last = VisualEffect::new(Effect::Dissolve)
+            .frames(&from, &card(20, 50))
+            .pop()
+            .unwrap();
+        assert_eq!(*last.get_pixel(0, 0), BLACK);
+        assert_eq!(*last.get_pixel(30, 10), WHITE);
+    }
+
+    #[test]
+    fn parses_effects_the_way_hypertalk_spells_them() {
+        assert_eq!(
+            VisualEffect::parse("shrink to top to black"),
+            Some(VisualEffect {
+                effect: Effect::ShrinkToTop,
+                speed: Speed::Normal,
+                destination: Destination::Black,
+            })
+        );
+        assert_eq!(
+            VisualEffect::parse("shrink to top").map(|e| e.destination),
+            Some(Destination::Card)
+        );
+        assert_eq!(
+            VisualEffect::parse("visual effect iris open very slowly"),
+            Some(VisualEffect {
+                effect: Effect::IrisOpen,
+                speed: Speed::VerySlow,
+                destination: Destination::Card,
+            })
+        );
+        assert_eq!(
+            VisualEffect::parse("Zoom In Fast To Grey").map(|e| (e.effect, e.speed, e.destination)),
+            Some((Effect::ZoomClose, Speed::Fast, Destination::Gray))
+        );
+        assert_eq!(VisualEffect::parse("wipe sideways"), None);
+    }
+}
@@ -0,0 +1,497 @@
+use hc_render::transition::VisualEffect;
+
+use crate::lexer::{Span, Token};
+
+/// A parsed script: the message and function handlers in it, in the order they're written.
+#[derive(Debug, Clone, Default)]
+pub struct Script {
+    pub handlers: Vec<Handler>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HandlerKind {
+    /// `on name`, run when the message is sent.
+    Message,
+    /// `function name`, run when the function is called.
+    Function,
+}
+
+#[derive(Debug, Clone)]
+pub struct Handler {
+    pub kind: HandlerKind,
+    pub name: String,
+    pub params: Vec<String>,
+    pub body: Vec<Statement>,
+    /// Where the `on` or `function` line is.
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub struct Statement {
+    pub kind: StatementKind,
+    /// Where the statement starts.
+    pub span: Span,
+}
+
+#[derive(Debug, Clone)]
+pub enum StatementKind {
+    Command(Command),
+    If(If),
+    Repeat(Repeat),
+    NextRepeat,
+    ExitRepeat,
+    /// `exit <handler>`.
+    Exit(String),
+    /// `exit to HyperCard`.
+    ExitToHyperCard,
+    /// `pass <handler>`.
+    Pass(String),
+    Return(Option<Expr>),
+    Global(Vec<String>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// A number as it was written.
+    Number(String),
+    String(String),
+    /// A variable. This also covers constants like `empty` and `return`, and unquoted words that
+    /// are neither, which HyperTalk takes as literals; which one it is is found out when it runs.
+    Variable(String),
+    Unary(UnaryOp, Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    /// `x is a number`, `x is not an integer` and so on.
+    IsA {
+        value: Box<Expr>,
+        ty: String,
+        negated: bool,
+    },
+    /// `f(x, y)`: a function handler, or a built-in function.
+    Call {
+        name: String,
+        args: Vec<Expr>,
+    },
+    /// `the f`, `the f of x` or `f of x`: a built-in function, or a property of an object.
+    The {
+        modifier: Option<Modifier>,
+        name: String,
+        of: Option<Box<Expr>>,
+    },
+    /// `word 2 of x`, `char 1 to 3 of line 2 of x`. Chunks nest through `of`.
+    Chunk {
+        chunk: Box<Chunk>,
+        of: Box<Expr>,
+    },
+    /// `the number of words in x`.
+    NumberOf {
+        kind: ChunkKind,
+        of: Box<Expr>,
+    },
+    /// A button, field, card, background or stack. As a value it's what's in it for a field,
+    /// and its name for anything else.
+    Object(Box<Object>),
+    /// `the number of cd btns`, `the number of cards of bkgnd 2`.
+    NumberOfObjects {
+        objects: Objects,
+        of: Option<Box<Object>>,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnaryOp {
+    Negate,
+    Not,
+}
+
+/// From tightest to loosest: `^`; `* / div mod`; `+ -`; `& &&`; the comparisons, `is in`,
+/// `contains` and `is within`; `= <> ≠`; `and`; `or`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Power,
+    Multiply,
+    Divide,
+    Div,
+    Mod,
+    Add,
+    Subtract,
+    Concat,
+    ConcatSpace,
+    Less,
+    Greater,
+    LessEqual,
+    GreaterEqual,
+    IsIn,
+    IsNotIn,
+    Contains,
+    IsWithin,
+    IsNotWithin,
+    Equal,
+    NotEqual,
+    And,
+    Or,
+}
+
+/// The word that can go before a function or property: `the long date`, `the short name of`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Modifier {
+    Long,
+    Short,
+    Abbreviated,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkKind {
+    Char,
+    Word,
+    Item,
+    Line,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Chunk {
+    pub kind: ChunkKind,
+    pub start: Position,
+    /// The end of a range, as in `char 1 to 3`.
+    pub end: Option<Position>,
+}
+
+/// Which chunk: by number (`first` to `tenth` are numbers too), or `last`, `middle` or `any`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Position {
+    Number(Expr),
+    Last,
+    Middle,
+    Any,
+}
+
+/// An object as a script refers to it: `btn 3`, `card field "Name"`, `bkgnd id 12`,
+/// `card "Index" of stack "Home"`, `me`, `this card` and so on.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Object {
+    Me,
+    /// `the target`: the object the message being handled was first sent to.
+    Target,
+    Part {
+        kind: PartKind,
+        /// Which layer the script said, if it did. Buttons are card buttons and fields are
+        /// background fields unless it says otherwise.
+        layer: Option<Layer>,
+        which: Which,
+        /// The card it's on, if not this one.
+        of: Option<Box<Object>>,
+    },
+    Card {
+        which: Which,
+        /// `next marked card`: only marked cards count.
+        marked: bool,
+        /// The background or stack to look for the card in.
+        of: Option<Box<Object>>,
+    },
+    Background {
+        which: Which,
+        of: Option<Box<Object>>,
+    },
+    Stack(Which),
+    /// `card window`, `msg window` and HyperCard's other windows.
+    Window(Window),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Window {
+    Card,
+    /// The message box.
+    Message,
+    Tool,
+    Pattern,
+    Scroll,
+    MessageWatcher,
+    VariableWatcher,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartKind {
+    Button,
+    Field,
+    /// `part 3`: a button or a field, counting both.
+    Part,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Layer {
+    Card,
+    Background,
+}
+
+/// Which one of the objects of a kind.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Which {
+    /// `card 3` or `card "Index"`: a number or a name, going by the value.
+    Value(Expr),
+    Id(Expr),
+    /// `first card`, `last btn`, `any card`.
+    Position(Position),
+    This,
+    Next,
+    Previous,
+    /// `recent card`: the card that was shown before this one.
+    Recent,
+}
+
+/// What `the number of` can count besides chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Objects {
+    Parts {
+        kind: PartKind,
+        layer: Option<Layer>,
+    },
+    /// `cards`, or `marked cards`.
+    Cards {
+        marked: bool,
+    },
+    Backgrounds,
+}
+
+/// Where `put` puts things.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Preposition {
+    Into,
+    Before,
+    After,
+}
+
+/// `if … then … else … end if`, in any of its forms. `else if` is an `If` as the only statement
+/// of the else branch.
+#[derive(Debug, Clone)]
+pub struct If {
+    pub condition: Expr,
+    pub then: Vec<Statement>,
+    pub otherwise: Option<Vec<Statement>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Repeat {
+    pub kind: RepeatKind,
+    pub body: Vec<Statement>,
+}
+
+#[derive(Debug, Clone)]
+pub enum RepeatKind {
+    Forever,
+    Times(Expr),
+    While(Expr),
+    Until(Expr),
+    With {
+        variable: String,
+        from: Expr,
+        to: Expr,
+        down: bool,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub enum Command {
+    /// `put x into y`. Without a container it goes in the message box.
+    Put {
+        value: Expr,
+        into: Option<(Preposition, Container)>,
+    },
+    Get(Expr),
+    /// `set the name of x to y`. What it's of is an object, or a chunk of a field.
+    Set {
+        name: String,
+        of: Option<Expr>,
+        value: Expr,
+    },
+    /// `send "mouseUp" to card button 1`: the message is a command in a string, sent to the
+    /// object, or to `me` without one.
+    Send {
+        message: Expr,
+        to: Option<Object>,
+    },
+    /// `go to card 3`, `go next`, `go back`, `go home`, with the effect it's shown with.
+    Go {
+        destination: Destination,
+        effect: Option<VisualEffect>,
+    },
+    /// `add 1 to x`, `subtract 1 from x`, `multiply x by 2` and `divide x by 2`: the operator is
+    /// `Add`, `Subtract`, `Multiply` or `Divide`, with the container on its left.
+    Arithmetic {
+        op: BinaryOp,
+        value: Expr,
+        container: Container,
+    },
+    /// `show btn 1 at 10,20` or `hide msg`.
+    Show {
+        visible: bool,
+        what: Showable,
+        at: Option<Expr>,
+    },
+    /// `show 5 cards`, `show all cards`, `show marked cards`: the cards one after another. No
+    /// count is all of them.
+    ShowCards {
+        count: Option<Expr>,
+        marked: bool,
+    },
+    /// `lock screen`, `unlock messages`. `unlock screen` can show what changed with an effect.
+    Lock {
+        what: Lockable,
+        locked: bool,
+        effect: Option<VisualEffect>,
+    },
+    /// `push card`, `push recent card`: remember a card for `pop card` to go back to.
+    Push(Object),
+    /// `pop card`, which goes to the card, or `pop card into x`, which puts its name there.
+    Pop(Option<(Preposition, Container)>),
+    /// `beep` or `beep 3`.
+    Beep(Option<Expr>),
+    Wait(Wait),
+    /// `do "put 1 into x"`: the statements in a value, run as part of the handler.
+    Do(Expr),
+    /// `delete line 2 of x` takes a chunk out of its container; `delete cd btn 1` or
+    /// `delete this card` takes a button, field or card out of the stack.
+    Delete(Container),
+    Select(Selectable),
+    /// `type "abc" with shiftKey`, which types into whatever is selected.
+    Type {
+        text: Expr,
+        keys: Keys,
+    },
+    /// `click at 100,200`.
+    Click {
+        at: Expr,
+        keys: Keys,
+    },
+    /// `drag from 10,10 to 100,100`.
+    Drag {
+        from: Expr,
+        to: Expr,
+        keys: Keys,
+    },
+    /// `choose browse tool` or `choose tool 3`: the tool's name or number.
+    Choose(Expr),
+    /// `convert x to long date and time`: a date or time in a container, written another way.
+    Convert {
+        container: Container,
+        to: Vec<DateFormat>,
+    },
+    /// `visual effect dissolve slowly`: an effect for the next `go` to show the card with.
+    Visual(VisualEffect),
+    /// `mark card 3` and `unmark all cards`.
+    Mark {
+        marked: bool,
+        cards: Markable,
+    },
+    /// Any other command, which is sent as a message: `myHandler a, b`.
+    Message {
+        name: String,
+        args: Vec<Expr>,
+    },
+    /// One of HyperCard's own commands that isn't run yet, with the rest of its line. It's
+    /// handed to the host, and does nothing if the host doesn't know it either.
+    Unparsed {
+        name: String,
+        args: Vec<Token>,
+    },
+}
+
+/// Where `go` goes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Destination {
+    /// A card, the first card of a background, or a stack.
+    Object(Object),
+    /// The card before this one, going back through the cards that have been shown.
+    Back,
+    /// The Home stack.
+    Home,
+}
+
+/// What `show` and `hide` show and hide.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Showable {
+    /// A button or field, or a window.
+    Object(Object),
+    /// `card picture`, `picture of bkgnd 2`.
+    Picture(Object),
+    MenuBar,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Lockable {
+    Screen,
+    Messages,
+    Recent,
+}
+
+/// The cards `mark` and `unmark` mark.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Markable {
+    Card(Object),
+    All,
+    /// `mark cards where the hilite of bkgnd btn 1`: the cards it's true of.
+    Where(Expr),
+}
+
+/// A way `convert` writes a date or time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DateFormat {
+    Seconds,
+    DateItems,
+    Date(Option<Modifier>),
+    Time(Option<Modifier>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Wait {
+    /// `wait 10` or `wait 2 seconds`: a number of ticks, or of seconds.
+    For {
+        time: Expr,
+        seconds: bool,
+    },
+    Until(Expr),
+    While(Expr),
+}
+
+/// What `select` selects.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Selectable {
+    /// `select empty`: nothing.
+    Empty,
+    /// `select btn 1`: the button or field itself.
+    Object(Object),
+    /// `select line 2 of fld 1`, `select text of fld 1`, `select after text of fld 1`: a chunk
+    /// of a field or all of its text, or the insertion point before or after it.
+    Text {
+        preposition: Preposition,
+        of: Container,
+    },
+}
+
+/// `with shiftKey, optionKey`: the keys `type`, `click` and `drag` hold down.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Keys {
+    pub shift: bool,
+    pub option: bool,
+    pub command: bool,
+}
+
+/// Somewhere `put` can put things.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Container {
+    Variable(String),
+    /// A field or button, which holds text.
+    Object(Object),
+    MessageBox,
+    Chunk {
+        chunk: Chunk,
+        of: Box<Container>,
+    },
+}
+
+impl Script {
+    /// The handler for a message or function, going by name and ignoring case.
+    pub fn handler(&self, kind: HandlerKind, name: &str) -> Option<&Handler> {
+        self.handlers
+            .iter()
+            .find(|h| h.kind == kind && h.name.eq_ignore_ascii_case(name))
+    }
+}
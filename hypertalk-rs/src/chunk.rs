@@ -0,0 +1,347 @@
+use std::ops::Range;
+
+use crate::ast::{ChunkKind, Preposition};
+
+//
+// Chunks of text: characters, words, items and lines, read and written. Lines end in a newline,
+// which is what `return` is here; items are split on the item delimiter, a comma unless a script
+// has set it to something else. Words are runs of anything but spaces, tabs and line ends.
+//
+// Writing to a chunk that isn't there yet pads the text out to it, the way HyperCard does:
+// putting something into item 5 of "a,b" gives "a,b,,,something".
+//
+
+/// A chunk with its numbers worked out; both count from 1 and `end` is at least `start`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkRange {
+    pub kind: ChunkKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+impl ChunkRange {
+    pub fn new(kind: ChunkKind, start: usize, end: usize) -> Self {
+        ChunkRange {
+            kind,
+            start,
+            end: end.max(start),
+        }
+    }
+}
+
+/// How many chunks of a kind there are in the text. A delimiter at the very end doesn't start
+/// another item or line.
+pub fn count(text: &str, kind: ChunkKind, item_delimiter: char) -> usize {
+    let mut pieces = pieces(text, kind, item_delimiter);
+    if matches!(kind, ChunkKind::Item | ChunkKind::Line) {
+        if let Some(last) = pieces.last() {
+            if last.is_empty() {
+                pieces.pop();
+            }
+        }
+    }
+    pieces.len()
+}
+
+/// The number `middle` stands for when there are `count` chunks. (`last` is `count`, and `any`
+/// is anything from 1 to `count`.)
+pub fn middle(count: usize) -> usize {
+    count / 2 + 1
+}
+
+/// Read a chunk of a chunk of … the text. `chunks` go from the outermost in, so
+/// `char 2 of word 3 of x` is `[word 3, char 2]`.
+pub fn get(text: &str, chunks: &[ChunkRange], item_delimiter: char) -> String {
+    let mut text = text;
+    for chunk in chunks {
+        text = match range(text, chunk, item_delimiter) {
+            Some(range) => &text[range],
+            None => return String::new(),
+        };
+    }
+    text.to_string()
+}
+
+/// Put `value` into, before or after a chunk of the text, padding the text out to the chunk if
+/// it needs it.
+pub fn put(
+    text: &str,
+    chunks: &[ChunkRange],
+    value: &str,
+    preposition: Preposition,
+    item_delimiter: char,
+) -> String {
+    let (chunk, rest) = match chunks.split_first() {
+        Some(a) => a,
+        None => {
+            return match preposition {
+                Preposition::Into => value.to_string(),
+                Preposition::Before => format!("{}{}", value, text),
+                Preposition::After => format!("{}{}", text, value),
+            }
+        }
+    };
+    let text = pad(text, chunk, item_delimiter);
+    let range = range(&text, chunk, item_delimiter).unwrap_or(text.len()..text.len());
+    let inner = put(
+        &text[range.clone()],
+        rest,
+        value,
+        preposition,
+        item_delimiter,
+    );
+    format!("{}{}{}", &text[..range.start], inner, &text[range.end..])
+}
+
+/// Take a chunk of a chunk of … the text out. A whole item or line goes with the delimiter
+/// after it, or the one before it if it's the last; a word goes with the spaces after it, or
+/// before it if it's the last.
+pub fn delete(text: &str, chunks: &[ChunkRange], item_delimiter: char) -> String {
+    let (chunk, rest) = match chunks.split_first() {
+        Some(a) => a,
+        None => return String::new(),
+    };
+    let range = match range(text, chunk, item_delimiter) {
+        Some(range) => range,
+        None => return text.to_string(),
+    };
+    if !rest.is_empty() {
+        let inner = delete(&text[range.clone()], rest, item_delimiter);
+        return format!("{}{}{}", &text[..range.start], inner, &text[range.end..]);
+    }
+    let (mut start, mut end) = (range.start, range.end);
+    match chunk.kind {
+        ChunkKind::Char => {}
+        ChunkKind::Word => {
+            end += text[end..].len() - text[end..].trim_start_matches(is_word_space).len();
+            if end == text.len() {
+                start = text[..start].trim_end_matches(is_word_space).len();
+            }
+        }
+        ChunkKind::Item | ChunkKind::Line => {
+            let delimiter = if chunk.kind == ChunkKind::Item {
+                item_delimiter
+            } else {
+                '\n'
+            };
+            if text[end..].starts_with(delimiter) {
+                end += delimiter.len_utf8();
+            } else if text[..start].ends_with(delimiter) {
+                start -= delimiter.len_utf8();
+            }
+        }
+    }
+    format!("{}{}", &text[..start], &text[end..])
+}
+
+/// Where a chunk of a chunk of … the text is in it, in bytes, or `None` if it isn't there.
+pub fn locate(text: &str, chunks: &[ChunkRange], item_delimiter: char) -> Option<Range<usize>> {
+    let mut found = 0..text.len();
+    for chunk in chunks {
+        let inner = range(&text[found.clone()], chunk, item_delimiter)?;
+        found = found.start + inner.start..found.start + inner.end;
+    }
+    Some(found)
+}
+
+// the text with enough delimiters added for the chunk to be there to write to. Characters and
+// words can't be padded; anything put past the end of those goes on the end.
+fn pad(text: &str, chunk: &ChunkRange, item_delimiter: char) -> String {
+    let delimiter = match chunk.kind {
+        ChunkKind::Item => item_delimiter,
+        ChunkKind::Line => '\n',
+        ChunkKind::Word => {
+            let mut text = text.to_string();
+            if chunk.start > count(&text, ChunkKind::Word, item_delimiter)
+                && !text.is_empty()
+                && !text.ends_with(is_word_space)
+            {
+                text.push(' ');
+            }
+            return text;
+        }
+        ChunkKind::Char => return text.to_string(),
+    };
+    // there's one more piece than there are delimiters.
+    let have = text.matches(delimiter).count() + 1;
+    let mut text = text.to_string();
+    for _ in have..chunk.start {
+        text.push(delimiter);
+    }
+    text
+}
+
+// where in the text the chunk is, or `None` if it starts past the end.
+fn range(text: &str, chunk: &ChunkRange, item_delimiter: char) -> Option<Range<usize>> {
+    if chunk.start == 0 {
+        return None;
+    }
+    let pieces = pieces(text, chunk.kind, item_delimiter);
+    let first = pieces.get(chunk.start - 1)?;
+    let last = &pieces[chunk.end.min(pieces.len()) - 1];
+    Some(first.start..last.end)
+}
+
+// every chunk of the kind, as where it is in the text. Items and lines include an empty one at
+// the end if the text ends in a delimiter, and there's always at least one.
+fn pieces(text: &str, kind: ChunkKind, item_delimiter: char) -> Vec<Range<usize>> {
+    match kind {
+        ChunkKind::Char => text
+            .char_indices()
+            .map(|(i, ch)| i..i + ch.len_utf8())
+            .collect(),
+        ChunkKind::Word => {
+            let mut words = Vec::new();
+            let mut start = None;
+            for (i, ch) in text.char_indices() {
+                match (is_word_space(ch), start) {
+                    (false, None) => start = Some(i),
+                    (true, Some(s)) => {
+                        words.push(s..i);
+                        start = None;
+                    }
+                    _ => {}
+                }
+            }
+            if let Some(s) = start {
+                words.push(s..text.len());
+            }
+            words
+        }
+        ChunkKind::Item | ChunkKind::Line => {
+            let delimiter = if kind == ChunkKind::Item {
+                item_delimiter
+            } else {
+                '\n'
+            };
+            let mut pieces = Vec::new();
+            let mut start = 0;
+            for (i, _) in text.match_indices(delimiter) {
+                pieces.push(start..i);
+                start = i + delimiter.len_utf8();
+            }
+            pieces.push(start..text.len());
+            pieces
+        }
+    }
+}
+
+fn is_word_space(ch: char) -> bool {
+    ch == ' ' || ch == '\t' || ch == '\n' || ch == '\r'
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(n: usize) -> ChunkRange {
+        ChunkRange::new(ChunkKind::Item, n, n)
+    }
+
+    fn line(n: usize) -> ChunkRange {
+        ChunkRange::new(ChunkKind::Line, n, n)
+    }
+
+    #[test]
+    fn words_are_split_on_any_space() {
+        let text = "one  two\tthree\nfour";
+        assert_eq!(count(text, ChunkKind::Word, ','), 4);
+        let word = |n| ChunkRange::new(ChunkKind::Word, n, n);
+        assert_eq!(get(text, &[word(3)], ','), "three");
+        assert_eq!(
+            get(text, &[ChunkRange::new(ChunkKind::Word, 2, 3)], ','),
+            "two\tthree"
+        );
+    }
+
+    #[test]
+    fn a_delimiter_at_the_end_starts_no_chunk() {
+        assert_eq!(count("a\nb\n", ChunkKind::Line, ','), 2);
+        assert_eq!(count("a,b,", ChunkKind::Item, ','), 2);
+        assert_eq!(count("", ChunkKind::Item, ','), 0);
+        assert_eq!(count("a;b", ChunkKind::Item, ';'), 2);
+    }
+
+    #[test]
+    fn chunks_of_chunks() {
+        let chars = ChunkRange::new(ChunkKind::Char, 2, 3);
+        assert_eq!(get("ab,cde\nfg", &[line(1), item(2), chars], ','), "de");
+    }
+
+    #[test]
+    fn getting_past_the_end_is_empty() {
+        assert_eq!(get("a,b", &[item(5)], ','), "");
+        assert_eq!(get("a,b", &[item(0)], ','), "");
+    }
+
+    #[test]
+    fn putting_past_the_end_pads_with_delimiters() {
+        assert_eq!(
+            put("a,b", &[item(5)], "e", Preposition::Into, ','),
+            "a,b,,,e"
+        );
+        assert_eq!(put("", &[line(3)], "c", Preposition::Into, ','), "\n\nc");
+    }
+
+    #[test]
+    fn words_and_chars_go_on_the_end() {
+        let word = ChunkRange::new(ChunkKind::Word, 4, 4);
+        assert_eq!(put("a b", &[word], "d", Preposition::Into, ','), "a b d");
+        let char = ChunkRange::new(ChunkKind::Char, 9, 9);
+        assert_eq!(put("ab", &[char], "c", Preposition::Into, ','), "abc");
+    }
+
+    #[test]
+    fn before_and_after_a_chunk() {
+        assert_eq!(
+            put("a,b", &[item(2)], "x", Preposition::Before, ','),
+            "a,xb"
+        );
+        assert_eq!(put("a,b", &[item(1)], "x", Preposition::After, ','), "ax,b");
+    }
+
+    #[test]
+    fn middle_counts_from_the_front() {
+        assert_eq!(middle(1), 1);
+        assert_eq!(middle(4), 3);
+        assert_eq!(middle(5), 3);
+    }
+
+    #[test]
+    fn deleting_a_line_takes_its_return() {
+        assert_eq!(delete("a\nb\nc", &[line(2)], ','), "a\nc");
+        assert_eq!(delete("a\nb\nc", &[line(1)], ','), "b\nc");
+    }
+
+    #[test]
+    fn deleting_the_last_line_takes_the_return_before_it() {
+        assert_eq!(delete("a\nb\nc", &[line(3)], ','), "a\nb");
+    }
+
+    #[test]
+    fn deleting_items_uses_the_delimiter() {
+        assert_eq!(delete("a,b,c", &[item(3)], ','), "a,b");
+        assert_eq!(delete("a;b;c", &[item(1)], ';'), "b;c");
+    }
+
+    #[test]
+    fn deleting_a_word_takes_the_spaces_after_it() {
+        let word = |n| ChunkRange::new(ChunkKind::Word, n, n);
+        assert_eq!(delete("one two  three", &[word(2)], ','), "one three");
+        assert_eq!(delete("one two  three", &[word(3)], ','), "one two");
+    }
+
+    #[test]
+    fn deleting_inside_a_chunk() {
+        let chars = ChunkRange::new(ChunkKind::Char, 1, 2);
+        assert_eq!(
+            delete("ab,cde\nfg", &[line(1), item(2), chars], ','),
+            "ab,e\nfg"
+        );
+    }
+
+    #[test]
+    fn deleting_past_the_end_changes_nothing() {
+        assert_eq!(delete("a,b", &[item(5)], ','), "a,b");
+    }
+}
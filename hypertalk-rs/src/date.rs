@@ -0,0 +1,345 @@
+use crate::ast::{DateFormat, Modifier};
+
+//
+// Dates and times the way HyperCard writes and reads them. A moment is a number of seconds
+// since the start of 1904 in local time, which is what `the seconds` is.
+//
+
+const MONTHS: [&str; 12] = [
+    "January",
+    "February",
+    "March",
+    "April",
+    "May",
+    "June",
+    "July",
+    "August",
+    "September",
+    "October",
+    "November",
+    "December",
+];
+
+const WEEKDAYS: [&str; 7] = [
+    "Sunday",
+    "Monday",
+    "Tuesday",
+    "Wednesday",
+    "Thursday",
+    "Friday",
+    "Saturday",
+];
+
+// days from the start of 1904 to the start of 1970.
+const DAYS_TO_1970: i64 = 24107;
+
+/// A moment broken up the way `dateItems` writes it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DateTime {
+    pub year: i64,
+    /// 1 to 12.
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: u32,
+    /// 1 for Sunday to 7 for Saturday.
+    pub weekday: u32,
+}
+
+impl DateTime {
+    pub fn from_seconds(seconds: i64) -> Self {
+        let days = seconds.div_euclid(86400);
+        let time = seconds.rem_euclid(86400) as u32;
+        let (year, month, day) = civil(days - DAYS_TO_1970);
+        DateTime {
+            year,
+            month,
+            day,
+            hour: time / 3600,
+            minute: time / 60 % 60,
+            second: time % 60,
+            // the first of January 1904 was a Friday.
+            weekday: ((days + 5).rem_euclid(7) + 1) as u32,
+        }
+    }
+}
+
+/// `the date`: `10/19/26`, or `Mon, Oct 19, 2026` abbreviated, or `Monday, October 19, 2026`
+/// long.
+pub fn date(seconds: i64, modifier: Option<Modifier>) -> String {
+    let d = DateTime::from_seconds(seconds);
+    let weekday = WEEKDAYS[d.weekday as usize - 1];
+    let month = MONTHS[d.month as usize - 1];
+    match modifier {
+        None | Some(Modifier::Short) => {
+            format!("{}/{}/{:02}", d.month, d.day, d.year.rem_euclid(100))
+        }
+        Some(Modifier::Abbreviated) => {
+            format!("{}, {} {}, {}", &weekday[..3], &month[..3], d.day, d.year)
+        }
+        Some(Modifier::Long) => format!("{}, {} {}, {}", weekday, month, d.day, d.year),
+    }
+}
+
+/// `the time`: `1:05 PM`, or `1:05:09 PM` long.
+pub fn time(seconds: i64, modifier: Option<Modifier>) -> String {
+    let d = DateTime::from_seconds(seconds);
+    let hour = match d.hour % 12 {
+        0 => 12,
+        hour => hour,
+    };
+    let noon = if d.hour < 12 { "AM" } else { "PM" };
+    match modifier {
+        Some(Modifier::Long) => format!("{}:{:02}:{:02} {}", hour, d.minute, d.second, noon),
+        _ => format!("{}:{:02} {}", hour, d.minute, noon),
+    }
+}
+
+/// `yyyy,m,d,h,m,s,weekday`.
+pub fn date_items(seconds: i64) -> String {
+    let d = DateTime::from_seconds(seconds);
+    format!(
+        "{},{},{},{},{},{},{}",
+        d.year, d.month, d.day, d.hour, d.minute, d.second, d.weekday
+    )
+}
+
+/// A moment written the way `convert` writes it.
+pub fn format(seconds: i64, format: DateFormat) -> String {
+    match format {
+        DateFormat::Seconds => seconds.to_string(),
+        DateFormat::DateItems => date_items(seconds),
+        DateFormat::Date(modifier) => date(seconds, modifier),
+        DateFormat::Time(modifier) => time(seconds, modifier),
+    }
+}
+
+/// Read a date, a time, or a date followed by a time, in any of the ways HyperCard writes
+/// them, or as date items. A time on its own is on the first day there is.
+pub fn parse(text: &str) -> Option<i64> {
+    let text = text.trim();
+    if let Some(seconds) = parse_date_items(text) {
+        return Some(seconds);
+    }
+    // the time is whatever's after the last word with a colon in it, along with AM or PM.
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let time_at = words
+        .iter()
+        .position(|w| w.contains(':'))
+        .unwrap_or(words.len());
+    let date_part = words[..time_at].join(" ");
+    let time_part = &words[time_at..];
+
+    let days = if date_part.is_empty() {
+        if time_part.is_empty() {
+            return None;
+        }
+        0
+    } else {
+        parse_date(&date_part)?
+    };
+    let time = if time_part.is_empty() {
+        0
+    } else {
+        parse_time(time_part)?
+    };
+    Some(days * 86400 + time)
+}
+
+// days since 1904 for `10/19/26` or `[Monday,] October 19, 2026`.
+fn parse_date(text: &str) -> Option<i64> {
+    let (year, month, day) = if text.contains('/') {
+        let parts: Vec<&str> = text.split('/').map(str::trim).collect();
+        match parts[..] {
+            [month, day, year] => (year.parse().ok()?, month.parse().ok()?, day.parse().ok()?),
+            _ => return None,
+        }
+    } else {
+        let mut words: Vec<&str> = text.split([' ', ',']).filter(|w| !w.is_empty()).collect();
+        if words.len() == 4 {
+            find_name(&WEEKDAYS, words[0])?;
+            words.remove(0);
+        }
+        match words[..] {
+            [month, day, year] => (
+                year.parse().ok()?,
+                find_name(&MONTHS, month)? as u32 + 1,
+                day.parse().ok()?,
+            ),
+            _ => return None,
+        }
+    };
+    let year = match year {
+        0..=39 => 2000 + year,
+        40..=99 => 1900 + year,
+        year => year,
+    };
+    if !(1..=12).contains(&month) || !(1..=days_in_month(year, month)).contains(&day) {
+        return None;
+    }
+    Some(days(year, month, day) + DAYS_TO_1970)
+}
+
+// seconds into the day for `1:05 PM` or `13:05:09`.
+fn parse_time(words: &[&str]) -> Option<i64> {
+    let (clock, noon) = match words {
+        [clock] => (*clock, None),
+        [clock, noon] => (*clock, Some(noon.to_uppercase())),
+        _ => return None,
+    };
+    let numbers: Option<Vec<u32>> = clock.split(':').map(|n| n.parse().ok()).collect();
+    let (hour, minute, second) = match numbers?[..] {
+        [hour, minute] => (hour, minute, 0),
+        [hour, minute, second] => (hour, minute, second),
+        _ => return None,
+    };
+    let hour = match noon.as_deref() {
+        None if hour < 24 => hour,
+        Some("AM") if (1..=12).contains(&hour) => hour % 12,
+        Some("PM") if (1..=12).contains(&hour) => hour % 12 + 12,
+        _ => return None,
+    };
+    if minute > 59 || second > 59 {
+        return None;
+    }
+    Some((hour * 3600 + minute * 60 + second) as i64)
+}
+
+fn parse_date_items(text: &str) -> Option<i64> {
+    let items: Option<Vec<i64>> = text.split(',').map(|n| n.trim().parse().ok()).collect();
+    match items?[..] {
+        [year, month, day, hour, minute, second, _] => {
+            // date items can be out of range, as `convert` allows: a 32nd day is the next
+            // month's first.
+            let days = days(year, 1, 1) + DAYS_TO_1970 + month_days(year, month) + day - 1;
+            Some(days * 86400 + hour * 3600 + minute * 60 + second)
+        }
+        _ => None,
+    }
+}
+
+// a full or three-letter name in a list, ignoring case.
+fn find_name(names: &[&str], word: &str) -> Option<usize> {
+    names.iter().position(|name| {
+        name.eq_ignore_ascii_case(word) || (word.len() == 3 && name[..3].eq_ignore_ascii_case(word))
+    })
+}
+
+// days from the start of a year to the start of a month, which can be past December.
+fn month_days(year: i64, month: i64) -> i64 {
+    let (year, month) = (
+        year + (month - 1).div_euclid(12),
+        (month - 1).rem_euclid(12) + 1,
+    );
+    days(year, month as u32, 1) - days(year, 1, 1)
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if year % 4 == 0 && (year % 100 != 0 || year % 400 == 0) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// days since 1970 for a date, and back.
+fn days(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = month as i64;
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + day as i64 - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+fn civil(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 1:05:09 PM on Monday, October 19, 2026.
+    const MOMENT: i64 = 3_875_259_909;
+
+    #[test]
+    fn dates() {
+        assert_eq!(date(MOMENT, None), "10/19/26");
+        assert_eq!(
+            date(MOMENT, Some(Modifier::Abbreviated)),
+            "Mon, Oct 19, 2026"
+        );
+        assert_eq!(
+            date(MOMENT, Some(Modifier::Long)),
+            "Monday, October 19, 2026"
+        );
+    }
+
+    #[test]
+    fn times() {
+        assert_eq!(time(MOMENT, None), "1:05 PM");
+        assert_eq!(time(MOMENT, Some(Modifier::Long)), "1:05:09 PM");
+        assert_eq!(time(0, None), "12:00 AM");
+    }
+
+    #[test]
+    fn the_first_moment_was_a_friday() {
+        assert_eq!(date(0, Some(Modifier::Long)), "Friday, January 1, 1904");
+    }
+
+    #[test]
+    fn date_items_of_a_moment() {
+        assert_eq!(date_items(MOMENT), "2026,10,19,13,5,9,2");
+    }
+
+    #[test]
+    fn leap_days_round_trip() {
+        let leap = parse("2/29/24").unwrap();
+        assert_eq!(date(leap, None), "2/29/24");
+        assert_eq!(date_items(leap), "2024,2,29,0,0,0,5");
+    }
+
+    #[test]
+    fn formats_for_convert() {
+        assert_eq!(format(MOMENT, DateFormat::Seconds), "3875259909");
+        assert_eq!(format(MOMENT, DateFormat::DateItems), "2026,10,19,13,5,9,2");
+        assert_eq!(
+            format(MOMENT, DateFormat::Time(Some(Modifier::Long))),
+            "1:05:09 PM"
+        );
+    }
+
+    #[test]
+    fn parses_what_it_writes() {
+        let midnight = MOMENT - (13 * 3600 + 5 * 60 + 9);
+        assert_eq!(parse("10/19/26"), Some(midnight));
+        assert_eq!(parse("Mon, Oct 19, 2026"), Some(midnight));
+        assert_eq!(parse("Monday, October 19, 2026 1:05:09 PM"), Some(MOMENT));
+        assert_eq!(parse("2026,10,19,13,5,9,2"), Some(MOMENT));
+    }
+
+    #[test]
+    fn a_time_on_its_own_is_on_the_first_day() {
+        assert_eq!(parse("1:05 PM"), Some(13 * 3600 + 5 * 60));
+    }
+
+    #[test]
+    fn does_not_parse_other_text() {
+        assert_eq!(parse("tomorrow"), None);
+        assert_eq!(parse(""), None);
+    }
+}
@@ -0,0 +1,44 @@
+use std::error::Error;
+use std::fmt::{self, Display};
+
+use crate::lexer::Span;
+
+/// Anything that can go wrong with a script, from reading it in to running it. The messages are
+/// HyperCard's wherever HyperCard has one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptError {
+    pub message: String,
+    /// Where in the script it went wrong, if it's down to a particular bit of it.
+    pub span: Option<Span>,
+}
+
+impl ScriptError {
+    pub fn new(message: impl Into<String>) -> Self {
+        ScriptError {
+            message: message.into(),
+            span: None,
+        }
+    }
+
+    pub fn at(message: impl Into<String>, span: Span) -> Self {
+        ScriptError {
+            message: message.into(),
+            span: Some(span),
+        }
+    }
+}
+
+impl Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.span {
+            Some(span) => write!(
+                f,
+                "{} (line {}, column {})",
+                self.message, span.line, span.column
+            ),
+            None => f.write_str(&self.message),
+        }
+    }
+}
+
+impl Error for ScriptError {}
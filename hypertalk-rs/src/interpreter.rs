@@ -0,0 +1,1228 @@
+use std::collections::{HashMap, HashSet};
+use std::rc::Rc;
+use std::thread;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use hc_decode::part::{ContentEntry, Part};
+use hc_decode::stack::Stack;
+use hc_render::transition::VisualEffect;
+
+use crate::ast::*;
+use crate::chunk::{self, ChunkRange};
+use crate::error::ScriptError;
+use crate::lexer::Span;
+use crate::object::{Found, ObjectId};
+use crate::property::{self, Properties};
+use crate::value::{self, DEFAULT_NUMBER_FORMAT};
+
+mod command;
+mod expression;
+mod function;
+mod go;
+mod properties;
+
+//
+// Runs scripts. The interpreter walks the parsed handlers directly, keeping a frame for each
+// handler that's running with its parameters and local variables. Everything outside the
+// language itself — the stack, the card being shown, commands it doesn't know — comes from the
+// host, so scripts can run without anything on screen.
+//
+
+// seconds from the start of 1904 to the start of 1970.
+const SECONDS_TO_1970: i64 = 2_082_844_800;
+
+// how deep handlers can call each other before it's taken to be runaway recursion.
+const MAX_DEPTH: usize = 256;
+
+// the messages HyperCard sends when things happen, which scripts can send too without anything
+// having to handle them.
+const SYSTEM_MESSAGES: [&str; 44] = [
+    "mouseDown",
+    "mouseStillDown",
+    "mouseUp",
+    "mouseEnter",
+    "mouseWithin",
+    "mouseLeave",
+    "keyDown",
+    "returnKey",
+    "enterKey",
+    "tabKey",
+    "arrowKey",
+    "functionKey",
+    "controlKey",
+    "commandKeyDown",
+    "returnInField",
+    "enterInField",
+    "openField",
+    "closeField",
+    "exitField",
+    "openCard",
+    "closeCard",
+    "openBackground",
+    "closeBackground",
+    "openStack",
+    "closeStack",
+    "resumeStack",
+    "suspendStack",
+    "resume",
+    "suspend",
+    "startUp",
+    "quit",
+    "idle",
+    "help",
+    "newCard",
+    "deleteCard",
+    "newBackground",
+    "deleteBackground",
+    "newButton",
+    "deleteButton",
+    "newField",
+    "deleteField",
+    "newStack",
+    "deleteStack",
+    "doMenu",
+];
+
+/// What a script runs against.
+pub trait Host {
+    fn stack(&self) -> &Stack;
+    fn stack_mut(&mut self) -> &mut Stack;
+    /// The stack's name, which `stack "name"` has to match.
+    fn stack_name(&self) -> &str;
+    /// The card being shown.
+    fn card(&self) -> u32;
+    /// The card shown before this one.
+    fn recent_card(&self) -> Option<u32> {
+        None
+    }
+    /// Show another card of the stack. `remember` is whether the card being left goes on the
+    /// list of recent cards, which it doesn't for `go back` or while `the lockRecent` is set.
+    /// The card is shown with the effects, one after another, if there are any. The
+    /// interpreter sends the messages that go with it, like `openCard`.
+    fn go(
+        &mut self,
+        card: u32,
+        remember: bool,
+        effects: &[VisualEffect],
+    ) -> Result<(), ScriptError>;
+    /// The card `go back` goes to, taking it off the list of recent cards.
+    fn back(&mut self) -> Option<u32> {
+        self.recent_card()
+    }
+    /// Whether there's a stack by a name or path that `go_stack` can open.
+    fn has_stack(&self, _name: &str) -> bool {
+        false
+    }
+    /// Open another stack in place of this one, by its name or path, such as Home for
+    /// `go home`. It's only asked to open stacks `has_stack` said there were.
+    fn go_stack(&mut self, _name: &str) -> Result<(), ScriptError> {
+        Ok(())
+    }
+    /// A message that went all the way along the message path without a handler taking it, for
+    /// the host to do what Home or HyperCard would with it, such as run an external command.
+    /// Returns whether the host knew it.
+    fn command(&mut self, _name: &str, _args: &[String]) -> Result<bool, ScriptError> {
+        Ok(false)
+    }
+    /// The same for functions, returning the function's value if the host knew it.
+    fn function(&mut self, _name: &str, _args: &[String]) -> Result<Option<String>, ScriptError> {
+        Ok(None)
+    }
+    /// The script of an object along the message path couldn't be parsed, so messages go past it
+    /// until it's changed.
+    fn script_error(&mut self, _object: ObjectId, _error: &ScriptError) {}
+    /// The time, in seconds since the start of 1904 in local time. Without a clock of the
+    /// host's own it's the system clock, in UTC.
+    fn seconds(&self) -> f64 {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default();
+        now.as_secs_f64() + SECONDS_TO_1970 as f64
+    }
+    /// Sixtieths of a second since some moment, such as when the host started.
+    fn ticks(&self) -> u64 {
+        (self.seconds() * 60.0) as u64
+    }
+    fn input(&self) -> Input {
+        Input::default()
+    }
+    /// `left,top,right,bottom` of the screen.
+    fn screen_rect(&self) -> (i32, i32, i32, i32) {
+        (0, 0, 512, 342)
+    }
+    /// The stacks that are open, for `the stacks`: their paths, the one in front first.
+    fn stacks(&self) -> Vec<String> {
+        vec![self.stack_name().to_string()]
+    }
+    /// The names of the windows that are open, for `the windows`, the one in front first.
+    fn windows(&self) -> Vec<String> {
+        vec!["card window".to_string()]
+    }
+    /// The version of the system the host runs on, for `the systemVersion`.
+    fn system_version(&self) -> String {
+        String::new()
+    }
+    /// How many bytes are free for `the diskSpace`, `the heapSpace` and `the stackSpace`.
+    /// Scripts ask before doing something big, so without knowing there's taken to be plenty.
+    fn free_space(&self, _space: Space) -> u64 {
+        16 * 1024 * 1024
+    }
+    /// A property of one of HyperCard's windows, such as `the loc of msg window`, if the host
+    /// has that window.
+    fn window_property(&self, _window: Window, _name: &str) -> Option<String> {
+        None
+    }
+    /// Set one, returning whether the host has that window and property.
+    fn set_window_property(
+        &mut self,
+        _window: Window,
+        _name: &str,
+        _value: &str,
+    ) -> Result<bool, ScriptError> {
+        Ok(false)
+    }
+    /// Show what's changed since `lock screen`, with the effects, one after another.
+    fn unlock_screen(&mut self, _effects: &[VisualEffect]) {}
+    fn show_menu_bar(&mut self, _visible: bool) {}
+    fn beep(&mut self) {}
+    /// Wait for a number of ticks. Without anything else to do meanwhile, this sleeps.
+    fn wait(&mut self, ticks: u64) {
+        thread::sleep(Duration::from_secs_f64(ticks as f64 / 60.0));
+    }
+    /// Select something, or nothing, as `select` says to.
+    fn select(&mut self, _selection: Selection) {}
+    /// Type text into whatever is selected, holding down the keys.
+    fn type_text(&mut self, _text: &str, _keys: Keys) {}
+    /// Drag the mouse from one point on the card to another with the tool that's chosen.
+    fn drag(&mut self, _from: (i32, i32), _to: (i32, i32), _keys: Keys) {}
+}
+
+/// What the free space `Host::free_space` is asked about is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Space {
+    Disk,
+    Heap,
+    Stack,
+}
+
+/// What `select` selects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Selection {
+    Nothing,
+    /// A button or field itself, as the button and field tools select them.
+    Object(ObjectId),
+    /// Characters of a field's text, counting from 0 with `end` past the last. An insertion
+    /// point is where `start` and `end` are the same.
+    Text {
+        field: ObjectId,
+        start: usize,
+        end: usize,
+    },
+}
+
+/// What the user is doing, for the functions that ask: where the mouse is, which keys are held
+/// down, and what was last clicked, selected or found.
+#[derive(Debug, Clone, Default)]
+pub struct Input {
+    pub mouse_loc: (i32, i32),
+    pub mouse_down: bool,
+    /// Whether the mouse has been clicked since a script last asked.
+    pub mouse_click: bool,
+    pub click_loc: (i32, i32),
+    /// The word clicked in a field, and the chunk and line it's in, such as
+    /// `char 5 to 9 of card field 1` and `line 2 of card field 1`.
+    pub click_text: String,
+    pub click_chunk: String,
+    pub click_line: String,
+    pub shift_key: bool,
+    pub option_key: bool,
+    pub command_key: bool,
+    pub selected_text: String,
+    pub selected_chunk: String,
+    pub selected_field: String,
+    pub selected_line: String,
+    /// Where the insertion point or selection starts, if there's one.
+    pub selected_loc: Option<(i32, i32)>,
+    pub found_text: String,
+    pub found_chunk: String,
+    pub found_field: String,
+    pub found_line: String,
+}
+
+pub struct Interpreter<H: Host> {
+    pub host: H,
+    /// Global variables, by lowercase name.
+    pub globals: HashMap<String, String>,
+    /// `the result`: what the last command left.
+    pub result: String,
+    pub number_format: String,
+    pub item_delimiter: char,
+    pub message_box: String,
+    pub properties: Properties,
+    /// The cards `push card` has pushed, the last one on top.
+    pub pushed: Vec<u32>,
+    /// Where `click at` last clicked, which `the clickLoc` gives in place of the host's until
+    /// the host clears it for a click of its own.
+    pub click_loc: Option<(i32, i32)>,
+    scripts: HashMap<ObjectId, Rc<Script>>,
+    frames: Vec<Frame>,
+    seed: u64,
+    // the effects `visual effect` has asked for, which the next card shown is shown with.
+    visual_effects: Vec<VisualEffect>,
+    // what the mouse and keyboard look like to scripts while `click at` runs, in place of the
+    // host's.
+    input: Option<Input>,
+}
+
+// a handler that's running.
+struct Frame {
+    me: ObjectId,
+    target: ObjectId,
+    /// The message or function the handler is for.
+    message: String,
+    params: Vec<String>,
+    /// Local variables, by lowercase name.
+    variables: HashMap<String, String>,
+    /// The names declared `global` in this handler so far.
+    globals: HashSet<String>,
+}
+
+// what running a statement leaves the handler to do next.
+enum Flow {
+    Continue,
+    NextRepeat,
+    ExitRepeat,
+    Exit,
+    Pass,
+    Return(String),
+}
+
+// why a handler stopped before its end.
+enum Stop {
+    Error(ScriptError),
+    /// `exit to HyperCard`, which stops every handler that's running.
+    Halt,
+}
+
+type Run<T> = Result<T, Stop>;
+
+impl From<ScriptError> for Stop {
+    fn from(error: ScriptError) -> Self {
+        Stop::Error(error)
+    }
+}
+
+impl Stop {
+    // say where an error happened, unless something closer to it already has.
+    fn at(self, span: Span) -> Self {
+        match self {
+            Stop::Error(mut error) => {
+                error.span.get_or_insert(span);
+                Stop::Error(error)
+            }
+            Stop::Halt => Stop::Halt,
+        }
+    }
+}
+
+impl<H: Host> Interpreter<H> {
+    pub fn new(host: H) -> Self {
+        // random numbers start from the clock, so they differ from run to run.
+        let seed = host.seconds().to_bits() ^ host.ticks().wrapping_mul(0x9e37_79b9_7f4a_7c15);
+        Interpreter {
+            host,
+            globals: HashMap::new(),
+            result: String::new(),
+            number_format: DEFAULT_NUMBER_FORMAT.to_string(),
+            item_delimiter: ',',
+            message_box: String::new(),
+            properties: Properties::default(),
+            pushed: Vec::new(),
+            click_loc: None,
+            scripts: HashMap::new(),
+            frames: Vec::new(),
+            seed: seed.max(1),
+            visual_effects: Vec::new(),
+            input: None,
+        }
+    }
+
+    /// Start random numbers from somewhere else, so runs can be repeated.
+    pub fn seed(&mut self, seed: u64) {
+        self.seed = seed.max(1);
+    }
+
+    /// Send a message to an object, the way HyperCard does when something happens to it: a
+    /// `mouseUp` to a button, `openCard` to a card and so on. The message goes along the message
+    /// path from that object, and the object is `the target` for every handler on the way.
+    /// Returns whether a handler took it without passing it on, so the host knows whether to do
+    /// what HyperCard would have.
+    pub fn send(
+        &mut self,
+        to: ObjectId,
+        message: &str,
+        args: &[String],
+    ) -> Result<bool, ScriptError> {
+        let handled = self.deliver(to, HandlerKind::Message, message, args);
+        let handled = self.finish(handled)?;
+        Ok(matches!(handled, Some(Some(_))))
+    }
+
+    /// Call a function handler, starting from an object's script and going along the message
+    /// path, returning its value if there's one.
+    pub fn call(
+        &mut self,
+        to: ObjectId,
+        function: &str,
+        args: &[String],
+    ) -> Result<Option<String>, ScriptError> {
+        let value = self.deliver(to, HandlerKind::Function, function, args);
+        Ok(self.finish(value)?.flatten())
+    }
+
+    /// Evaluate an expression as if it were in an object's script.
+    pub fn evaluate(&mut self, me: ObjectId, source: &str) -> Result<String, ScriptError> {
+        let expr = Expr::parse(source)?;
+        self.frames.push(Frame::new(me, me, "", &[]));
+        let value = self.eval(&expr);
+        self.frames.pop();
+        Ok(self.finish(value)?.unwrap_or_default())
+    }
+
+    // once nothing is running any more: `exit to HyperCard` isn't an error, and the number
+    // format, item delimiter and the properties HyperCard resets go back to what they were, as
+    // they do when HyperCard is idle.
+    fn finish<T>(&mut self, result: Run<T>) -> Result<Option<T>, ScriptError> {
+        if self.frames.is_empty() {
+            self.number_format = DEFAULT_NUMBER_FORMAT.to_string();
+            self.item_delimiter = ',';
+            self.visual_effects.clear();
+            self.properties.idle();
+        }
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(Stop::Halt) => Ok(None),
+            Err(Stop::Error(error)) => Err(error),
+        }
+    }
+
+    /// Forget the parsed script of an object, after its script has changed.
+    pub fn forget_script(&mut self, id: ObjectId) {
+        self.scripts.remove(&id);
+    }
+
+    // the object's script, parsed.
+    fn script(&mut self, id: ObjectId) -> Run<Rc<Script>> {
+        if let Some(script) = self.scripts.get(&id) {
+            return Ok(script.clone());
+        }
+        let source = match id.find(self.host.stack()) {
+            Some(Found::Stack(stack)) => &stack.script,
+            Some(Found::Background(background)) => &background.script,
+            Some(Found::Card(card)) => &card.script,
+            Some(Found::Part { part, .. }) => &part.script,
+            None => return Err(ScriptError::new("No such object.").into()),
+        };
+        let script = match Script::parse(source) {
+            Ok(script) => Rc::new(script),
+            Err(error) => {
+                self.host.script_error(id, &error);
+                Rc::new(Script::default())
+            }
+        };
+        self.scripts.insert(id, script.clone());
+        Ok(script)
+    }
+
+    // a message or function call sent to an object, which goes along the message path until a
+    // handler takes it: `None` if none did, or what the handler that did returned.
+    fn deliver(
+        &mut self,
+        to: ObjectId,
+        kind: HandlerKind,
+        name: &str,
+        args: &[String],
+    ) -> Run<Option<String>> {
+        for id in self.path(to) {
+            let script = self.script(id)?;
+            if let Some(handler) = script.handler(kind, name) {
+                if let Some(value) = self.run_handler(handler, id, to, args)? {
+                    return Ok(Some(value));
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    // the objects a message sent to an object goes through: a button or field, its card, the
+    // card's background, then the stack. A message sent to something that isn't on the card
+    // being shown or its background goes through that card and background too before it gets
+    // to the stack. Windows have no scripts, so a message sent to one goes to the card.
+    fn path(&self, from: ObjectId) -> Vec<ObjectId> {
+        if let ObjectId::Window(_) = from {
+            return self.path(ObjectId::Card(self.host.card()));
+        }
+        let stack = self.host.stack();
+        let background_of = |card: u32| {
+            stack
+                .card(card)
+                .map(|c| ObjectId::Background(c.background_id))
+        };
+        let mut path = vec![from];
+        match from {
+            ObjectId::Part { card, .. } => {
+                path.push(ObjectId::Card(card));
+                path.extend(background_of(card));
+            }
+            ObjectId::Card(card) => path.extend(background_of(card)),
+            ObjectId::Background(_) | ObjectId::Stack | ObjectId::Window(_) => {}
+        }
+        if from == ObjectId::Stack {
+            return path;
+        }
+        let card = self.host.card();
+        for shown in [Some(ObjectId::Card(card)), background_of(card)]
+            .into_iter()
+            .flatten()
+        {
+            if !path.contains(&shown) {
+                path.push(shown);
+            }
+        }
+        path.push(ObjectId::Stack);
+        path
+    }
+
+    fn run_handler(
+        &mut self,
+        handler: &Handler,
+        me: ObjectId,
+        target: ObjectId,
+        args: &[String],
+    ) -> Run<Option<String>> {
+        if self.frames.len() >= MAX_DEPTH {
+            return Err(ScriptError::at("Too much recursion", handler.span).into());
+        }
+        let mut frame = Frame::new(me, target, &handler.name, args);
+        for (i, param) in handler.params.iter().enumerate() {
+            let value = args.get(i).cloned().unwrap_or_default();
+            frame.variables.insert(param.to_lowercase(), value);
+        }
+        self.frames.push(frame);
+        let flow = self.block(&handler.body);
+        self.frames.pop();
+        match flow? {
+            Flow::Pass => Ok(None),
+            Flow::Return(value) => Ok(Some(value)),
+            _ => Ok(Some(String::new())),
+        }
+    }
+
+    fn block(&mut self, body: &[Statement]) -> Run<Flow> {
+        for statement in body {
+            match self
+                .statement(statement)
+                .map_err(|stop| stop.at(statement.span))?
+            {
+                Flow::Continue => {}
+                flow => return Ok(flow),
+            }
+        }
+        Ok(Flow::Continue)
+    }
+
+    fn statement(&mut self, statement: &Statement) -> Run<Flow> {
+        match &statement.kind {
+            StatementKind::Command(command) => {
+                self.result = self.command(command)?;
+                Ok(Flow::Continue)
+            }
+            StatementKind::If(If {
+                condition,
+                then,
+                otherwise,
+            }) => {
+                let condition = self.eval(condition)?;
+                if value::to_bool(&condition)? {
+                    self.block(then)
+                } else if let Some(otherwise) = otherwise {
+                    self.block(otherwise)
+                } else {
+                    Ok(Flow::Continue)
+                }
+            }
+            StatementKind::Repeat(repeat) => self.repeat(repeat),
+            StatementKind::NextRepeat => Ok(Flow::NextRepeat),
+            StatementKind::ExitRepeat => Ok(Flow::ExitRepeat),
+            StatementKind::Exit(_) => Ok(Flow::Exit),
+            StatementKind::ExitToHyperCard => Err(Stop::Halt),
+            StatementKind::Pass(_) => Ok(Flow::Pass),
+            StatementKind::Return(value) => {
+                let value = match value {
+                    Some(value) => self.eval(value)?,
+                    None => String::new(),
+                };
+                Ok(Flow::Return(value))
+            }
+            StatementKind::Global(names) => {
+                for name in names {
+                    let name = name.to_lowercase();
+                    self.globals.entry(name.clone()).or_default();
+                    self.frame().globals.insert(name);
+                }
+                Ok(Flow::Continue)
+            }
+        }
+    }
+
+    fn repeat(&mut self, repeat: &Repeat) -> Run<Flow> {
+        let mut count = 0i64;
+        let (times, end) = match &repeat.kind {
+            RepeatKind::Times(times) => (Some(value::to_integer(&self.eval(times)?)?), None),
+            RepeatKind::With { from, to, .. } => {
+                count = value::to_integer(&self.eval(from)?)?;
+                (None, Some(value::to_integer(&self.eval(to)?)?))
+            }
+            _ => (None, None),
+        };
+        let mut done = 0;
+        loop {
+            let go_on = match &repeat.kind {
+                RepeatKind::Forever => true,
+                RepeatKind::Times(_) => done < times.unwrap_or(0),
+                RepeatKind::While(condition) => value::to_bool(&self.eval(condition)?)?,
+                RepeatKind::Until(condition) => !value::to_bool(&self.eval(condition)?)?,
+                RepeatKind::With { variable, down, .. } => {
+                    let end = end.unwrap_or(0);
+                    let go_on = if *down { count >= end } else { count <= end };
+                    if go_on {
+                        self.set_variable(variable, count.to_string());
+                    }
+                    go_on
+                }
+            };
+            if !go_on {
+                return Ok(Flow::Continue);
+            }
+            match self.block(&repeat.body)? {
+                Flow::Continue | Flow::NextRepeat => {}
+                Flow::ExitRepeat => return Ok(Flow::Continue),
+                flow => return Ok(flow),
+            }
+            done += 1;
+            if let RepeatKind::With { down, .. } = &repeat.kind {
+                count += if *down { -1 } else { 1 };
+            }
+        }
+    }
+
+    // send a message from a script. One that nothing along the path takes goes to the host, which
+    // stands in for Home and HyperCard; a message HyperCard sends itself can go unhandled, but
+    // anything else is a command nobody knows.
+    fn message(&mut self, to: ObjectId, name: &str, args: &[String]) -> Run<String> {
+        if let Some(result) = self.deliver(to, HandlerKind::Message, name, args)? {
+            return Ok(result);
+        }
+        if self.host.command(name, args)?
+            || SYSTEM_MESSAGES.iter().any(|m| m.eq_ignore_ascii_case(name))
+        {
+            return Ok(String::new());
+        }
+        Err(ScriptError::new(format!("Can't understand \"{}\"", name)).into())
+    }
+
+    fn eval_all(&mut self, exprs: &[Expr]) -> Run<Vec<String>> {
+        exprs.iter().map(|expr| self.eval(expr)).collect()
+    }
+
+    // put a value into, before or after a container.
+    fn put(&mut self, value: &str, preposition: Preposition, container: &Container) -> Run<()> {
+        let (base, text, ranges) = self.chunks_of(container)?;
+        let text = chunk::put(&text, &ranges, value, preposition, self.item_delimiter);
+        self.set_container(base, text)
+    }
+
+    // what's in a container.
+    fn container_value(&mut self, container: &Container) -> Run<String> {
+        let (_, text, ranges) = self.chunks_of(container)?;
+        Ok(chunk::get(&text, &ranges, self.item_delimiter))
+    }
+
+    // the variable, field or message box a container is a chunk of, what's in it, and the
+    // chunks, which go from the outside in. `char 1 of word 2 of x` is held as char 1 of
+    // (word 2 of x), so the chunks are the other way round to how they're held.
+    fn chunks_of<'c>(
+        &mut self,
+        container: &'c Container,
+    ) -> Run<(&'c Container, String, Vec<ChunkRange>)> {
+        let mut chunks = Vec::new();
+        let mut base = container;
+        while let Container::Chunk { chunk, of } = base {
+            chunks.push(chunk);
+            base = of;
+        }
+        chunks.reverse();
+
+        let text = match base {
+            Container::Variable(name) => self.contents(name).unwrap_or_default(),
+            Container::Object(object) => {
+                let id = self.resolve(object)?;
+                self.object_value(id)?
+            }
+            Container::MessageBox => self.message_box.clone(),
+            Container::Chunk { .. } => unreachable!(),
+        };
+        let mut ranges = Vec::new();
+        let mut inner = text.clone();
+        for chunk in chunks {
+            let range = self.chunk_range(chunk, &inner)?;
+            inner = chunk::get(&inner, &[range], self.item_delimiter);
+            ranges.push(range);
+        }
+        Ok((base, text, ranges))
+    }
+
+    // replace what's in a variable, field or the message box.
+    fn set_container(&mut self, container: &Container, text: String) -> Run<()> {
+        match container {
+            Container::Variable(name) => self.set_variable(name, text),
+            Container::Object(object) => {
+                let id = self.resolve(object)?;
+                self.set_object_value(id, &text)?;
+            }
+            Container::MessageBox => self.message_box = text,
+            Container::Chunk { .. } => unreachable!(),
+        }
+        Ok(())
+    }
+
+    // the mouse and keyboard, as scripts see them.
+    fn input(&self) -> Input {
+        let mut input = self.input.clone().unwrap_or_else(|| self.host.input());
+        if let Some(at) = self.click_loc {
+            input.click_loc = at;
+        }
+        input
+    }
+
+    fn frame(&mut self) -> &mut Frame {
+        self.frames.last_mut().expect("no handler is running")
+    }
+
+    // a variable's value; one that hasn't been given one yet is its own name, the way
+    // HyperTalk takes unquoted words.
+    fn variable(&self, name: &str) -> String {
+        self.contents(name).unwrap_or_else(|| name.to_string())
+    }
+
+    // what's in a variable as a container, where one that hasn't been given a value is empty.
+    fn contents(&self, name: &str) -> Option<String> {
+        let key = name.to_lowercase();
+        let frame = self.frames.last();
+        let value = match frame {
+            Some(frame) if frame.globals.contains(&key) => self.globals.get(&key),
+            Some(frame) => frame.variables.get(&key),
+            None => None,
+        };
+        match value {
+            Some(value) => Some(value.clone()),
+            None if key == "it" => Some(String::new()),
+            None if key == "msg" || key == "message" => Some(self.message_box.clone()),
+            None => None,
+        }
+    }
+
+    fn set_variable(&mut self, name: &str, value: String) {
+        let key = name.to_lowercase();
+        if key == "msg" || key == "message" {
+            self.message_box = value;
+            return;
+        }
+        let frame = self.frames.last_mut().expect("no handler is running");
+        if frame.globals.contains(&key) {
+            self.globals.insert(key, value);
+        } else {
+            frame.variables.insert(key, value);
+        }
+    }
+
+    // what's in a field or button, with its lines ending in newlines.
+    fn object_value(&self, id: ObjectId) -> Run<String> {
+        match id.find(self.host.stack()) {
+            Some(Found::Part { part, layer, card }) => Ok(card
+                .contents_of(part, layer == Layer::Background)
+                .map(|c| c.text.replace('\r', "\n"))
+                .unwrap_or_default()),
+            Some(found) => Ok(property::name(found, self.host.stack_name(), None)),
+            None => Err(ScriptError::new("No such object.").into()),
+        }
+    }
+
+    fn set_object_value(&mut self, id: ObjectId, text: &str) -> Run<()> {
+        let (layer, card, id) = match id {
+            ObjectId::Part { layer, card, id } => (layer, card, id),
+            _ => return Err(ScriptError::new("Can't put into that").into()),
+        };
+        let text = text.replace('\n', "\r");
+        let stack = self.host.stack_mut();
+        let background_id = stack.card(card).map(|c| c.background_id);
+        let shared = match layer {
+            Layer::Card => true,
+            Layer::Background => background_id
+                .and_then(|b| stack.background(b))
+                .and_then(|b| Part::find(&b.parts, id))
+                .is_some_and(|p| p.shared_text),
+        };
+        let entries = match (layer, shared) {
+            (Layer::Card, _) => stack
+                .card_mut(card)
+                .and_then(|c| Part::find_mut(&mut c.parts, id))
+                .map(|p| &mut p.contents),
+            (Layer::Background, true) => background_id
+                .and_then(|b| stack.background_mut(b))
+                .and_then(|b| Part::find_mut(&mut b.parts, id))
+                .map(|p| &mut p.contents),
+            (Layer::Background, false) => stack.card_mut(card).map(|c| &mut c.background_contents),
+        };
+        let entries = entries.ok_or_else(|| ScriptError::new("No such object."))?;
+        // a card part's own entry is its first; a background part's entry on a card is the
+        // one with its ID.
+        let entry = match (layer, shared) {
+            (Layer::Background, false) => entries.iter_mut().find(|e| e.id == id),
+            _ => entries.first_mut(),
+        };
+        match entry {
+            Some(entry) => {
+                entry.text = text;
+                entry.styles = None;
+            }
+            None => entries.push(ContentEntry {
+                id,
+                card_part: layer == Layer::Card,
+                styles: None,
+                text,
+            }),
+        }
+        Ok(())
+    }
+}
+
+impl Frame {
+    fn new(me: ObjectId, target: ObjectId, message: &str, params: &[String]) -> Self {
+        Frame {
+            me,
+            target,
+            message: message.to_string(),
+            params: params.to_vec(),
+            variables: HashMap::new(),
+            globals: HashSet::new(),
+        }
+    }
+}
+
+// a random number from 1 to `n`, moving the seed on.
+fn random(seed: &mut u64, n: usize) -> usize {
+    // xorshift
+    *seed ^= *seed << 13;
+    *seed ^= *seed >> 7;
+    *seed ^= *seed << 17;
+    (*seed % n.max(1) as u64) as usize + 1
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use hc_decode::background::Background;
+    use hc_decode::card::Card;
+
+    use super::*;
+
+    // a host over a stack held in memory: cards 10 and 11 in one background and card 12 in
+    // another, with a button on card 10.
+    pub(crate) struct TestHost {
+        pub(crate) stack: Stack,
+        pub(crate) card: u32,
+        pub(crate) seconds: f64,
+        pub(crate) ticks: u64,
+        /// The commands nothing along the message path handled, and the stacks gone to.
+        pub(crate) commands: Vec<String>,
+        /// The effects cards were shown with.
+        pub(crate) effects: Vec<VisualEffect>,
+        /// The scripts that couldn't be parsed.
+        pub(crate) script_errors: Vec<(ObjectId, String)>,
+    }
+
+    impl Host for TestHost {
+        fn stack(&self) -> &Stack {
+            &self.stack
+        }
+
+        fn stack_mut(&mut self) -> &mut Stack {
+            &mut self.stack
+        }
+
+        fn stack_name(&self) -> &str {
+            "Test"
+        }
+
+        fn card(&self) -> u32 {
+            self.card
+        }
+
+        fn has_stack(&self, name: &str) -> bool {
+            name.eq_ignore_ascii_case("Home")
+        }
+
+        fn go_stack(&mut self, name: &str) -> Result<(), ScriptError> {
+            self.commands.push(format!("go stack {}", name));
+            Ok(())
+        }
+
+        fn command(&mut self, name: &str, args: &[String]) -> Result<bool, ScriptError> {
+            self.commands.push(format!("{} {}", name, args.join(",")));
+            Ok(name.eq_ignore_ascii_case("chime"))
+        }
+
+        fn go(
+            &mut self,
+            card: u32,
+            _remember: bool,
+            effects: &[VisualEffect],
+        ) -> Result<(), ScriptError> {
+            self.card = card;
+            self.effects.extend_from_slice(effects);
+            Ok(())
+        }
+
+        fn seconds(&self) -> f64 {
+            self.seconds
+        }
+
+        fn ticks(&self) -> u64 {
+            self.ticks
+        }
+
+        fn script_error(&mut self, object: ObjectId, error: &ScriptError) {
+            self.script_errors.push((object, error.to_string()));
+        }
+
+        fn wait(&mut self, _ticks: u64) {}
+    }
+
+    // an interpreter on card 10 of a stack whose stack script is the one given, with a clock
+    // that stands still at `MOMENT`.
+    pub(crate) fn interpreter(script: &str) -> Interpreter<TestHost> {
+        let stack = Stack {
+            script: script.replace('\n', "\r"),
+            backgrounds: vec![
+                Background {
+                    id: 1,
+                    name: "Front".to_string(),
+                    ..Default::default()
+                },
+                Background {
+                    id: 2,
+                    name: "Back".to_string(),
+                    ..Default::default()
+                },
+            ],
+            cards: vec![
+                Card {
+                    id: 10,
+                    background_id: 1,
+                    name: "One".to_string(),
+                    parts: vec![Part {
+                        id: 1,
+                        name: "OK".to_string(),
+                        ..Default::default()
+                    }],
+                    ..Default::default()
+                },
+                Card {
+                    id: 11,
+                    background_id: 1,
+                    ..Default::default()
+                },
+                Card {
+                    id: 12,
+                    background_id: 2,
+                    name: "End".to_string(),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        Interpreter::new(TestHost {
+            stack,
+            card: 10,
+            seconds: MOMENT as f64,
+            ticks: 600,
+            commands: Vec::new(),
+            effects: Vec::new(),
+            script_errors: Vec::new(),
+        })
+    }
+
+    /// 1:05:09 PM on Monday, October 19, 2026, which is when the test host's clock says it is.
+    pub(crate) const MOMENT: i64 = 3_875_259_909;
+
+    pub(crate) const BUTTON: ObjectId = ObjectId::Part {
+        layer: Layer::Card,
+        card: 10,
+        id: 1,
+    };
+
+    // give an object other than the stack a script.
+    pub(crate) fn set_script(interpreter: &mut Interpreter<TestHost>, id: ObjectId, script: &str) {
+        let stack = &mut interpreter.host.stack;
+        let slot = match id {
+            ObjectId::Background(id) => &mut stack.background_mut(id).unwrap().script,
+            ObjectId::Card(id) => &mut stack.card_mut(id).unwrap().script,
+            ObjectId::Part { card, id, .. } => {
+                let card = stack.card_mut(card).unwrap();
+                &mut Part::find_mut(&mut card.parts, id).unwrap().script
+            }
+            ObjectId::Stack => &mut stack.script,
+            ObjectId::Window(_) => panic!("a window has no script"),
+        };
+        *slot = script.replace('\n', "\r");
+        interpreter.forget_script(id);
+    }
+
+    fn strings(args: &[&str]) -> Vec<String> {
+        args.iter().map(|a| a.to_string()).collect()
+    }
+
+    // the message box after sending a message to the stack.
+    fn send(script: &str, message: &str, args: &[&str]) -> String {
+        let mut interpreter = interpreter(script);
+        interpreter
+            .send(ObjectId::Stack, message, &strings(args))
+            .unwrap();
+        interpreter.message_box
+    }
+
+    #[test]
+    fn params_are_bound_in_order() {
+        let script = "on greet who, greeting\n  put greeting && who\nend greet";
+        assert_eq!(send(script, "greet", &["Ann", "Hello"]), "Hello Ann");
+    }
+
+    #[test]
+    fn missing_params_are_empty() {
+        let script = "on greet who, greeting\n  put \"[\" & greeting & \"]\"\nend greet";
+        assert_eq!(send(script, "greet", &["Ann"]), "[]");
+    }
+
+    #[test]
+    fn param_functions() {
+        let script = "on m a\n  put param(0) & param(2) & the paramCount\nend m";
+        assert_eq!(send(script, "m", &["x", "y"]), "my2");
+    }
+
+    #[test]
+    fn get_puts_into_it() {
+        let script = "on test\n  get 2 + 3\n  put it * 2\nend test";
+        assert_eq!(send(script, "test", &[]), "10");
+    }
+
+    #[test]
+    fn unset_variables_are_their_names() {
+        let script = "on test\n  put hello\nend test";
+        assert_eq!(send(script, "test", &[]), "hello");
+    }
+
+    #[test]
+    fn globals_outlive_handlers() {
+        let script = "on a\n  global g\n  put 5 into g\nend a\non b\n  global g\n  put g\nend b";
+        let mut interpreter = interpreter(script);
+        interpreter.send(ObjectId::Stack, "a", &[]).unwrap();
+        assert_eq!(interpreter.globals["g"], "5");
+        interpreter.send(ObjectId::Stack, "b", &[]).unwrap();
+        assert_eq!(interpreter.message_box, "5");
+    }
+
+    #[test]
+    fn locals_are_not_globals() {
+        let script =
+            "on a\n  put 5 into g\n  b\nend a\non b\n  global g\n  put \"[\" & g & \"]\"\nend b";
+        assert_eq!(send(script, "a", &[]), "[]");
+    }
+
+    #[test]
+    fn return_sets_the_result() {
+        let script = "on a\n  b\n  put the result\nend a\non b\n  return \"oops\"\nend b";
+        assert_eq!(send(script, "a", &[]), "oops");
+    }
+
+    #[test]
+    fn functions_return_their_value() {
+        let script = "function double n\n  return n * 2\nend double";
+        let mut interpreter = interpreter(script);
+        let value = interpreter.call(ObjectId::Stack, "double", &strings(&["21"]));
+        assert_eq!(value.unwrap().as_deref(), Some("42"));
+    }
+
+    #[test]
+    fn number_format_applies_then_resets() {
+        let script = "on test\n  set numberFormat to \"0.00\"\n  put 1 / 3\nend test";
+        let mut interpreter = interpreter(script);
+        interpreter.send(ObjectId::Stack, "test", &[]).unwrap();
+        assert_eq!(interpreter.message_box, "0.33");
+        assert_eq!(interpreter.number_format, DEFAULT_NUMBER_FORMAT);
+    }
+
+    #[test]
+    fn exit_to_hypercard_stops_quietly() {
+        let script = "on a\n  b\n  put \"after\"\nend a\non b\n  exit to HyperCard\nend b";
+        assert_eq!(send(script, "a", &[]), "");
+    }
+
+    #[test]
+    fn unknown_commands_go_to_the_host() {
+        let script = "on a\n  chime 3\nend a\non b\n  frobnicate\nend b";
+        let mut interpreter = interpreter(script);
+        interpreter.send(ObjectId::Stack, "a", &[]).unwrap();
+        assert_eq!(interpreter.host.commands, ["chime 3"]);
+        let error = interpreter.send(ObjectId::Stack, "b", &[]).unwrap_err();
+        assert_eq!(error.message, "Can't understand \"frobnicate\"");
+    }
+
+    #[test]
+    fn unhandled_messages_are_not_errors() {
+        let mut interpreter = interpreter("");
+        assert!(!interpreter.send(ObjectId::Stack, "mouseUp", &[]).unwrap());
+    }
+
+    #[test]
+    fn evaluates_expressions() {
+        let mut interpreter = interpreter("");
+        assert_eq!(
+            interpreter.evaluate(ObjectId::Stack, "2 * 3 + 1").unwrap(),
+            "7"
+        );
+        assert_eq!(
+            interpreter.evaluate(ObjectId::Stack, "card 2").unwrap(),
+            "card id 11"
+        );
+    }
+
+    // a handler that adds a letter to the message box and passes the message on.
+    fn passes(letter: &str) -> String {
+        format!("on m\n  put \"{}\" after msg\n  pass m\nend m", letter)
+    }
+
+    #[test]
+    fn messages_go_from_a_part_to_the_stack() {
+        let mut interpreter = interpreter(&passes("s"));
+        set_script(&mut interpreter, BUTTON, &passes("p"));
+        set_script(&mut interpreter, ObjectId::Card(10), &passes("c"));
+        set_script(&mut interpreter, ObjectId::Background(1), &passes("b"));
+        assert!(!interpreter.send(BUTTON, "m", &[]).unwrap());
+        assert_eq!(interpreter.message_box, "pcbs");
+    }
+
+    #[test]
+    fn a_handler_that_does_not_pass_stops_the_message() {
+        let mut interpreter = interpreter(&passes("s"));
+        set_script(&mut interpreter, BUTTON, &passes("p"));
+        set_script(
+            &mut interpreter,
+            ObjectId::Card(10),
+            "on m\n  put \"c\" after msg\nend m",
+        );
+        assert!(interpreter.send(BUTTON, "m", &[]).unwrap());
+        assert_eq!(interpreter.message_box, "pc");
+    }
+
+    #[test]
+    fn messages_to_other_cards_go_through_the_shown_one() {
+        let mut interpreter = interpreter(&passes("s"));
+        set_script(&mut interpreter, ObjectId::Card(12), &passes("e"));
+        set_script(&mut interpreter, ObjectId::Background(2), &passes("k"));
+        set_script(&mut interpreter, ObjectId::Card(10), &passes("c"));
+        set_script(&mut interpreter, ObjectId::Background(1), &passes("b"));
+        interpreter.send(ObjectId::Card(12), "m", &[]).unwrap();
+        assert_eq!(interpreter.message_box, "ekcbs");
+    }
+
+    #[test]
+    fn the_shown_card_is_on_the_path_of_another_card_of_its_background() {
+        let mut interpreter = interpreter(&passes("s"));
+        set_script(&mut interpreter, ObjectId::Card(11), &passes("e"));
+        set_script(&mut interpreter, ObjectId::Card(10), &passes("c"));
+        set_script(&mut interpreter, ObjectId::Background(1), &passes("b"));
+        interpreter.send(ObjectId::Card(11), "m", &[]).unwrap();
+        assert_eq!(interpreter.message_box, "ebcs");
+    }
+
+    #[test]
+    fn a_script_that_does_not_parse_is_passed_over() {
+        let mut interpreter = interpreter(&passes("s"));
+        set_script(&mut interpreter, ObjectId::Card(10), "on m\n  put (\nend m");
+        set_script(&mut interpreter, ObjectId::Background(1), &passes("b"));
+        assert!(!interpreter.send(BUTTON, "m", &[]).unwrap());
+        interpreter.send(BUTTON, "m", &[]).unwrap();
+        assert_eq!(interpreter.message_box, "bsbs");
+        // it's reported the once, until the script changes.
+        let errors = &interpreter.host.script_errors;
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, ObjectId::Card(10));
+    }
+
+    #[test]
+    fn the_stack_script_is_not_on_the_cards_path_twice() {
+        let mut interpreter = interpreter(&passes("s"));
+        interpreter.send(ObjectId::Stack, "m", &[]).unwrap();
+        assert_eq!(interpreter.message_box, "s");
+    }
+
+    #[test]
+    fn the_target_is_where_the_message_started() {
+        let mut interpreter = interpreter("on m\n  put the target\nend m");
+        interpreter.send(BUTTON, "m", &[]).unwrap();
+        assert_eq!(interpreter.message_box, "card button \"OK\"");
+    }
+
+    #[test]
+    fn send_delivers_to_another_object() {
+        let mut interpreter = interpreter("on a\n  send \"b 4, 5\" to card id 11\nend a");
+        set_script(
+            &mut interpreter,
+            ObjectId::Card(11),
+            "on b x, y\n  put x + y\nend b",
+        );
+        interpreter.send(ObjectId::Stack, "a", &[]).unwrap();
+        assert_eq!(interpreter.message_box, "9");
+    }
+
+    #[test]
+    fn functions_are_found_along_the_path() {
+        let mut interpreter = interpreter("function twice n\n  return n * 2\nend twice");
+        set_script(
+            &mut interpreter,
+            BUTTON,
+            "on mouseUp\n  put twice(4)\nend mouseUp",
+        );
+        interpreter.send(BUTTON, "mouseUp", &[]).unwrap();
+        assert_eq!(interpreter.message_box, "8");
+    }
+
+    #[test]
+    fn counts_marked_cards() {
+        let mut interpreter = interpreter("");
+        for card in [10, 12] {
+            interpreter.host.stack.card_mut(card).unwrap().marked = true;
+        }
+        let mut count = |source| interpreter.evaluate(ObjectId::Stack, source).unwrap();
+        assert_eq!(count("the number of marked cards"), "2");
+        assert_eq!(count("the number of marked cards in bkgnd \"Front\""), "1");
+        assert_eq!(count("the number of cards in bkgnd \"Front\""), "2");
+    }
+
+    #[test]
+    fn only_cards_are_marked() {
+        let mut interpreter = interpreter("");
+        let counted = interpreter.evaluate(ObjectId::Stack, "the number of marked buttons");
+        assert!(counted.is_err());
+    }
+}
@@ -0,0 +1,720 @@
+use hc_decode::part::PartType;
+use hc_decode::stack::Stack;
+
+use crate::ast::*;
+use crate::chunk;
+use crate::date;
+use crate::error::ScriptError;
+use crate::object::{Found, ObjectId};
+use crate::property;
+use crate::value;
+
+use super::{Host, Interpreter, Run, Selection, Stop};
+
+//
+// HyperCard's commands. Those that change what's on screen or what the user sees, like `beep`,
+// `select` and `type`, are handed to the host; the rest work on the stack and the interpreter.
+//
+
+// the tools, in the order `choose tool 3` numbers them.
+const TOOLS: [&str; 18] = [
+    "browse",
+    "button",
+    "field",
+    "select",
+    "lasso",
+    "pencil",
+    "brush",
+    "eraser",
+    "line",
+    "spray",
+    "rectangle",
+    "round rect",
+    "bucket",
+    "oval",
+    "curve",
+    "text",
+    "regular polygon",
+    "polygon",
+];
+
+// other names for some of them.
+const TOOL_NAMES: [(&str, &str); 6] = [
+    ("spray can", "spray"),
+    ("rect", "rectangle"),
+    ("round rectangle", "round rect"),
+    ("reg poly", "regular polygon"),
+    ("reg polygon", "regular polygon"),
+    ("poly", "polygon"),
+];
+
+// a card has this flag set if it can't be deleted.
+const CANT_DELETE: u16 = 0x4000;
+
+impl<H: Host> Interpreter<H> {
+    // run a command, returning what it leaves in `the result`.
+    pub(super) fn command(&mut self, command: &Command) -> Run<String> {
+        match command {
+            Command::Put { value, into } => {
+                let value = self.eval(value)?;
+                match into {
+                    Some((preposition, container)) => self.put(&value, *preposition, container)?,
+                    None => self.message_box = value,
+                }
+            }
+            Command::Get(value) => {
+                let value = self.eval(value)?;
+                self.set_variable("it", value);
+            }
+            Command::Set { name, of, value } => {
+                let value = self.eval(value)?;
+                self.set_property(name, of.as_ref(), &value)?;
+            }
+            Command::Send { message, to } => {
+                let message = self.eval(message)?;
+                let to = match to {
+                    Some(to) => self.resolve(to)?,
+                    None => self.frame().me,
+                };
+                return match Command::parse(&message)? {
+                    Command::Message { name, args } => {
+                        let args = self.eval_all(&args)?;
+                        self.message(to, &name, &args)
+                    }
+                    // HyperCard's own commands, which go straight to HyperCard.
+                    command => self.command(&command),
+                };
+            }
+            Command::Go {
+                destination,
+                effect,
+            } => return self.go(destination, *effect),
+            Command::Arithmetic {
+                op,
+                value,
+                container,
+            } => {
+                let value = value::to_number(&self.eval(value)?)?;
+                let current = value::to_number(&self.container_value(container)?)?;
+                let n = match op {
+                    BinaryOp::Add => current + value,
+                    BinaryOp::Subtract => current - value,
+                    BinaryOp::Multiply => current * value,
+                    _ => current / value,
+                };
+                let n = self.number(n);
+                self.put(&n, Preposition::Into, container)?;
+            }
+            Command::Show { visible, what, at } => self.show(*visible, what, at.as_ref())?,
+            Command::ShowCards { count, marked } => {
+                let next = Object::Card {
+                    which: Which::Next,
+                    marked: *marked,
+                    of: None,
+                };
+                let times = match count {
+                    Some(count) => value::to_integer(&self.eval(count)?)?,
+                    None => {
+                        let cards = &self.host.stack().cards;
+                        cards.iter().filter(|c| !marked || c.marked).count() as i64
+                    }
+                };
+                for _ in 0..times {
+                    if let ObjectId::Card(card) = self.resolve(&next)? {
+                        let remember = !self.properties.lock_recent;
+                        self.go_to_card(card, remember, None)?;
+                    }
+                }
+            }
+            Command::Lock {
+                what,
+                locked,
+                effect,
+            } => match what {
+                Lockable::Screen => {
+                    self.properties.lock_screen = *locked;
+                    if !locked {
+                        let effects = self.effects(*effect);
+                        self.host.unlock_screen(&effects);
+                    }
+                }
+                Lockable::Messages => self.properties.lock_messages = *locked,
+                Lockable::Recent => self.properties.lock_recent = *locked,
+            },
+            Command::Push(card) => match self.resolve(card)? {
+                ObjectId::Card(card) => self.pushed.push(card),
+                _ => return Err(ScriptError::new("Can't push that").into()),
+            },
+            Command::Pop(into) => {
+                let Some(card) = self.pushed.pop() else {
+                    return Ok(String::new());
+                };
+                match into {
+                    Some((preposition, container)) => {
+                        let name = match self.host.stack().card(card) {
+                            Some(found) => property::name(
+                                Found::Card(found),
+                                self.host.stack_name(),
+                                Some(Modifier::Long),
+                            ),
+                            None => String::new(),
+                        };
+                        self.put(&name, *preposition, container)?;
+                    }
+                    None => {
+                        let remember = !self.properties.lock_recent;
+                        self.go_to_card(card, remember, None)?;
+                    }
+                }
+            }
+            Command::Beep(times) => {
+                let times = match times {
+                    Some(times) => value::to_integer(&self.eval(times)?)?,
+                    None => 1,
+                };
+                for _ in 0..times {
+                    self.host.beep();
+                }
+            }
+            Command::Wait(wait) => self.wait(wait)?,
+            Command::Do(source) => {
+                let source = self.eval(source)?;
+                // where in the value something went wrong means nothing in the script, so it's
+                // the `do` that's pointed to.
+                let unplaced = |stop| match stop {
+                    Stop::Error(mut error) => {
+                        error.span = None;
+                        Stop::Error(error)
+                    }
+                    stop => stop,
+                };
+                let statements = Statement::parse_all(&source).map_err(|e| unplaced(e.into()))?;
+                self.block(&statements).map_err(unplaced)?;
+                return Ok(self.result.clone());
+            }
+            Command::Delete(container) => self.delete(container)?,
+            Command::Select(selectable) => self.select(selectable)?,
+            Command::Type { text, keys } => {
+                let text = self.eval(text)?;
+                self.host.type_text(&text, *keys);
+            }
+            Command::Click { at, keys } => {
+                let at = self.point(at)?;
+                self.click(at, *keys)?;
+            }
+            Command::Drag { from, to, keys } => {
+                let (from, to) = (self.point(from)?, self.point(to)?);
+                self.host.drag(from, to, *keys);
+            }
+            Command::Choose(tool) => {
+                let tool = self.eval(tool)?;
+                let name = match value::parse_number(&tool) {
+                    Some(n) if n >= 1.0 => TOOLS.get(n as usize - 1).copied(),
+                    Some(_) => None,
+                    None => TOOLS
+                        .iter()
+                        .find(|t| t.eq_ignore_ascii_case(&tool))
+                        .copied()
+                        .or_else(|| {
+                            TOOL_NAMES
+                                .iter()
+                                .find(|(name, _)| name.eq_ignore_ascii_case(&tool))
+                                .map(|(_, t)| *t)
+                        }),
+                };
+                let name = name.ok_or_else(|| ScriptError::new("No such tool."))?;
+                self.properties.tool = name.to_string();
+            }
+            Command::Convert { container, to } => {
+                // a number is already seconds; anything else has to read as a date or time.
+                let text = self.container_value(container)?;
+                let seconds = match value::parse_number(&text) {
+                    Some(n) => Some(n as i64),
+                    None => date::parse(&text),
+                };
+                let Some(seconds) = seconds else {
+                    return Ok("Invalid date".to_string());
+                };
+                let text: Vec<String> = to.iter().map(|&f| date::format(seconds, f)).collect();
+                self.put(&text.join(" "), Preposition::Into, container)?;
+            }
+            Command::Visual(effect) => self.visual_effects.push(*effect),
+            Command::Mark { marked, cards } => self.mark(*marked, cards)?,
+            Command::Message { name, args } => {
+                let args = self.eval_all(args)?;
+                let me = self.frame().me;
+                return self.message(me, name, &args);
+            }
+            // the host can run it if it knows how, with the rest of the line as it's written.
+            Command::Unparsed { name, args } => {
+                let words: Vec<String> = args.iter().map(|t| t.kind.to_string()).collect();
+                self.host.command(name, &[words.join(" ")])?;
+            }
+        }
+        Ok(String::new())
+    }
+
+    fn show(&mut self, visible: bool, what: &Showable, at: Option<&Expr>) -> Run<()> {
+        let id = match what {
+            Showable::MenuBar => {
+                self.host.show_menu_bar(visible);
+                return Ok(());
+            }
+            Showable::Picture(object) => {
+                let id = self.resolve(object)?;
+                if !matches!(id, ObjectId::Card(_) | ObjectId::Background(_)) {
+                    return Err(ScriptError::new("Only cards and backgrounds have pictures").into());
+                }
+                self.set_object_property(id, "showPict", &value::from_bool(visible))?;
+                return Ok(());
+            }
+            Showable::Object(object) => self.resolve(object)?,
+        };
+        if !matches!(id, ObjectId::Part { .. } | ObjectId::Window(_)) {
+            let verb = if visible { "show" } else { "hide" };
+            return Err(ScriptError::new(format!("Can't {} that", verb)).into());
+        }
+        if let Some(at) = at {
+            let at = self.eval(at)?;
+            self.set_object_property(id, "loc", &at)?;
+        }
+        // a host without the window has nothing to show or hide.
+        self.set_object_property(id, "visible", &value::from_bool(visible))?;
+        Ok(())
+    }
+
+    fn wait(&mut self, wait: &Wait) -> Run<()> {
+        match wait {
+            Wait::For { time, seconds } => {
+                let time = value::to_number(&self.eval(time)?)?;
+                let ticks = if *seconds { time * 60.0 } else { time };
+                self.host.wait(ticks.round().max(0.0) as u64);
+            }
+            Wait::Until(condition) | Wait::While(condition) => {
+                let until = matches!(wait, Wait::Until(_));
+                while value::to_bool(&self.eval(condition)?)? != until {
+                    self.host.wait(1);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // take a chunk out of a container, or a button, field or card out of the stack.
+    fn delete(&mut self, container: &Container) -> Run<()> {
+        match container {
+            Container::Chunk { .. } => {
+                let (base, text, ranges) = self.chunks_of(container)?;
+                let text = chunk::delete(&text, &ranges, self.item_delimiter);
+                self.set_container(base, text)
+            }
+            Container::Object(object) => {
+                let id = self.resolve(object)?;
+                self.delete_object(id)
+            }
+            Container::Variable(_) | Container::MessageBox => {
+                Err(ScriptError::new("Can't delete that").into())
+            }
+        }
+    }
+
+    fn delete_object(&mut self, id: ObjectId) -> Run<()> {
+        match id {
+            part @ ObjectId::Part { layer, card, id } => {
+                let message = match part_kind(self.host.stack(), part) {
+                    Some(PartKind::Field) => "deleteField",
+                    _ => "deleteButton",
+                };
+                self.system_message(part, message)?;
+                let stack = self.host.stack_mut();
+                let background_id = stack.card(card).map(|c| c.background_id);
+                match layer {
+                    Layer::Card => {
+                        if let Some(card) = stack.card_mut(card) {
+                            card.parts.retain(|p| p.id != id);
+                        }
+                    }
+                    Layer::Background => {
+                        if let Some(background) =
+                            background_id.and_then(|b| stack.background_mut(b))
+                        {
+                            background.parts.retain(|p| p.id != id);
+                        }
+                        // its text on every card goes with it.
+                        for card in &mut stack.cards {
+                            if Some(card.background_id) == background_id {
+                                card.background_contents.retain(|c| c.id != id);
+                            }
+                        }
+                    }
+                }
+                self.forget_script(part);
+                Ok(())
+            }
+            ObjectId::Card(card) => {
+                let stack = self.host.stack();
+                let found = stack
+                    .card(card)
+                    .ok_or_else(|| ScriptError::new("No such card."))?;
+                if found.flags & CANT_DELETE != 0 {
+                    return Err(ScriptError::new("Can't delete that card").into());
+                }
+                if stack.cards.len() == 1 {
+                    return Err(ScriptError::new("Can't delete the last card").into());
+                }
+                let background_id = found.background_id;
+                self.system_message(id, "deleteCard")?;
+                // the card being shown is left for the next one first.
+                if self.host.card() == card {
+                    let index = self.host.stack().cards.iter().position(|c| c.id == card);
+                    let cards = &self.host.stack().cards;
+                    if let Some(next) = index.map(|i| cards[(i + 1) % cards.len()].id) {
+                        self.go_to_card(next, false, None)?;
+                    }
+                }
+                let stack = self.host.stack_mut();
+                stack.cards.retain(|c| c.id != card);
+                self.pushed.retain(|c| *c != card);
+                self.forget_script(id);
+                // a background goes when its last card does.
+                if !self
+                    .host
+                    .stack()
+                    .cards
+                    .iter()
+                    .any(|c| c.background_id == background_id)
+                {
+                    self.host
+                        .stack_mut()
+                        .backgrounds
+                        .retain(|b| b.id != background_id);
+                    self.forget_script(ObjectId::Background(background_id));
+                }
+                Ok(())
+            }
+            _ => Err(ScriptError::new("Can't delete that").into()),
+        }
+    }
+
+    fn select(&mut self, selectable: &Selectable) -> Run<()> {
+        let selection = match selectable {
+            Selectable::Empty => Selection::Nothing,
+            Selectable::Object(object) => {
+                let id = self.resolve(object)?;
+                // selecting a button or field chooses the tool for it, as it does in HyperCard.
+                self.properties.tool = match part_kind(self.host.stack(), id) {
+                    Some(PartKind::Button) => "button",
+                    Some(PartKind::Field) => "field",
+                    _ => return Err(ScriptError::new("Can't select that").into()),
+                }
+                .to_string();
+                Selection::Object(id)
+            }
+            Selectable::Text { preposition, of } => {
+                let (base, text, ranges) = self.chunks_of(of)?;
+                let field = match base {
+                    Container::Object(object) => self.resolve(object)?,
+                    _ => ObjectId::Stack,
+                };
+                if part_kind(self.host.stack(), field) != Some(PartKind::Field) {
+                    return Err(ScriptError::new("Can only select text in a field").into());
+                }
+                let bytes = chunk::locate(&text, &ranges, self.item_delimiter)
+                    .unwrap_or(text.len()..text.len());
+                let start = text[..bytes.start].chars().count();
+                let end = start + text[bytes].chars().count();
+                let (start, end) = match preposition {
+                    Preposition::Into => (start, end),
+                    Preposition::Before => (start, start),
+                    Preposition::After => (end, end),
+                };
+                Selection::Text { field, start, end }
+            }
+        };
+        self.host.select(selection);
+        Ok(())
+    }
+
+    // `mark` and `unmark`.
+    fn mark(&mut self, marked: bool, cards: &Markable) -> Run<()> {
+        let all: Vec<u32> = self.host.stack().cards.iter().map(|c| c.id).collect();
+        let ids = match cards {
+            Markable::Card(card) => match self.resolve(card)? {
+                ObjectId::Card(card) => vec![card],
+                _ => return Err(ScriptError::new("Can only mark cards").into()),
+            },
+            Markable::All => all,
+            Markable::Where(condition) => {
+                // the condition is about each card in turn, so each is made the card being shown
+                // while it's evaluated, without sending it any messages.
+                let shown = self.host.card();
+                let mut ids = Vec::new();
+                for card in all {
+                    self.host.go(card, false, &[])?;
+                    match self.eval(condition).and_then(|v| Ok(value::to_bool(&v)?)) {
+                        Ok(true) => ids.push(card),
+                        Ok(false) => {}
+                        Err(stop) => {
+                            self.host.go(shown, false, &[])?;
+                            return Err(stop);
+                        }
+                    }
+                }
+                self.host.go(shown, false, &[])?;
+                ids
+            }
+        };
+        for card in &mut self.host.stack_mut().cards {
+            if ids.contains(&card.id) {
+                card.marked = marked;
+            }
+        }
+        Ok(())
+    }
+
+    // `click at`: a `mouseDown` then a `mouseUp` to whatever's at the point on the card being
+    // shown, with the mouse there and the keys held down as far as scripts can tell.
+    fn click(&mut self, at: (i32, i32), keys: Keys) -> Run<()> {
+        let mut input = self.input();
+        input.mouse_loc = at;
+        input.click_loc = at;
+        input.shift_key |= keys.shift;
+        input.option_key |= keys.option;
+        input.command_key |= keys.command;
+        let target = self.part_at(at);
+        self.click_loc = Some(at);
+        let outer = self.input.replace(input);
+        let clicked = self
+            .message(target, "mouseDown", &[])
+            .and_then(|_| self.message(target, "mouseUp", &[]));
+        self.input = outer;
+        clicked.map(|_| ())
+    }
+
+    // the topmost visible button or field at a point on the card being shown, or else the card.
+    fn part_at(&self, (h, v): (i32, i32)) -> ObjectId {
+        let stack = self.host.stack();
+        let card = self.host.card();
+        let Some(found) = stack.card(card) else {
+            return ObjectId::Card(card);
+        };
+        let background = stack.background(found.background_id);
+        let layers = [
+            (Layer::Card, Some(&found.parts)),
+            (Layer::Background, background.map(|b| &b.parts)),
+        ];
+        for (layer, parts) in layers {
+            let hit = parts.into_iter().flatten().rev().find(|p| {
+                let (top, left, bottom, right) = p.position;
+                p.visible
+                    && (left as i32..right as i32).contains(&h)
+                    && (top as i32..bottom as i32).contains(&v)
+            });
+            if let Some(part) = hit {
+                return ObjectId::Part {
+                    layer,
+                    card,
+                    id: part.id,
+                };
+            }
+        }
+        ObjectId::Card(card)
+    }
+
+    fn point(&mut self, expr: &Expr) -> Run<(i32, i32)> {
+        let value = self.eval(expr)?;
+        match value::to_point(&value) {
+            Some((h, v)) => Ok((h as i32, v as i32)),
+            None => {
+                Err(ScriptError::new(format!("Expected a point but found \"{}\"", value)).into())
+            }
+        }
+    }
+}
+
+// whether an object is a button or a field, if it's either.
+fn part_kind(stack: &Stack, id: ObjectId) -> Option<PartKind> {
+    match id.find(stack)? {
+        Found::Part { part, .. } => match part.ty {
+            PartType::Button => Some(PartKind::Button),
+            PartType::Field => Some(PartKind::Field),
+            PartType::Unknown => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::tests::{interpreter, set_script, TestHost};
+    use crate::interpreter::Interpreter;
+    use crate::object::ObjectId;
+
+    // an interpreter that has run a handler with the given body.
+    fn run(body: &str) -> Interpreter<TestHost> {
+        let mut interpreter = interpreter(&format!("on test\n{}\nend test", body));
+        interpreter.send(ObjectId::Stack, "test", &[]).unwrap();
+        interpreter
+    }
+
+    // what the message box ends up with after running a handler.
+    fn put(body: &str) -> String {
+        run(body).message_box
+    }
+
+    #[test]
+    fn arithmetic_on_a_variable() {
+        assert_eq!(put("  put 6 into x\n  add 4 to x\n  put x"), "10");
+        assert_eq!(put("  put 6 into x\n  subtract 4 from x\n  put x"), "2");
+        assert_eq!(put("  put 6 into x\n  multiply x by 4\n  put x"), "24");
+        assert_eq!(put("  put 6 into x\n  divide x by 4\n  put x"), "1.5");
+    }
+
+    #[test]
+    fn arithmetic_on_a_chunk() {
+        assert_eq!(
+            put("  put \"1,2,3\" into x\n  add 5 to item 2 of x\n  put x"),
+            "1,7,3"
+        );
+    }
+
+    #[test]
+    fn a_variable_named_after_a_chunk() {
+        assert_eq!(
+            put("  put 1 into item\n  put item + 1 into item\n  put item"),
+            "2"
+        );
+    }
+
+    #[test]
+    fn arithmetic_needs_numbers() {
+        let mut interpreter = interpreter("on test\n  put \"a\" into x\n  add 1 to x\nend test");
+        assert!(interpreter.send(ObjectId::Stack, "test", &[]).is_err());
+    }
+
+    #[test]
+    fn deletes_a_chunk() {
+        assert_eq!(
+            put("  put \"a,b,c\" into x\n  delete item 2 of x\n  put x"),
+            "a,c"
+        );
+    }
+
+    #[test]
+    fn show_and_hide() {
+        let visible = |body| {
+            let mut interpreter = run(body);
+            interpreter
+                .evaluate(ObjectId::Stack, "the visible of btn 1")
+                .unwrap()
+        };
+        assert_eq!(visible("  show btn 1"), "true");
+        assert_eq!(visible("  show btn 1\n  hide btn 1"), "false");
+    }
+
+    #[test]
+    fn deletes_a_button() {
+        let mut interpreter = run("  delete btn 1");
+        let count = interpreter.evaluate(ObjectId::Stack, "the number of btns");
+        assert_eq!(count.unwrap(), "0");
+    }
+
+    #[test]
+    fn do_runs_a_value() {
+        assert_eq!(put("  do \"put 2 + 2\""), "4");
+    }
+
+    #[test]
+    fn show_cards_goes_through_them() {
+        assert_eq!(run("  show 2 cards").host.card, 12);
+    }
+
+    #[test]
+    fn choose_a_tool() {
+        let interpreter = run("  choose button tool");
+        assert_eq!(interpreter.properties.tool, "button");
+    }
+
+    #[test]
+    fn convert_writes_a_date_another_way() {
+        assert_eq!(
+            put("  put \"10/19/26 1:05 PM\" into x\n  convert x to dateItems\n  put x"),
+            "2026,10,19,13,5,0,2"
+        );
+        assert_eq!(
+            put("  put the seconds into x\n  convert x to long date and time\n  put x"),
+            "Monday, October 19, 2026 1:05 PM"
+        );
+        assert_eq!(
+            put("  put \"1904,1,32,0,0,0,1\" into x\n  convert x to seconds\n  put x"),
+            "2678400"
+        );
+    }
+
+    #[test]
+    fn convert_leaves_what_is_not_a_date() {
+        assert_eq!(
+            put("  put \"soon\" into x\n  convert x to date\n  put the result && x"),
+            "Invalid date soon"
+        );
+    }
+
+    #[test]
+    fn marks_and_unmarks_cards() {
+        let count = "\n  put the number of marked cards";
+        assert_eq!(put(&format!("  mark card 2{}", count)), "1");
+        assert_eq!(put(&format!("  mark card{}", count)), "1");
+        assert_eq!(put(&format!("  mark all cards{}", count)), "3");
+        assert_eq!(
+            put(&format!("  mark all cards\n  unmark card \"End\"{}", count)),
+            "2"
+        );
+    }
+
+    #[test]
+    fn marks_the_cards_a_condition_is_true_of() {
+        let interpreter = run("  mark cards where the short name of this bkgnd is \"Back\"");
+        let marked: Vec<u32> = interpreter
+            .host
+            .stack
+            .cards
+            .iter()
+            .filter(|c| c.marked)
+            .map(|c| c.id)
+            .collect();
+        assert_eq!(marked, [12]);
+        assert_eq!(interpreter.host.card, 10);
+    }
+
+    #[test]
+    fn commands_not_run_yet_go_to_the_host() {
+        let interpreter = run("  flash 2\n  palette \"Navigator\"");
+        assert_eq!(
+            interpreter.host.commands,
+            ["flash 2", "palette \"Navigator\""]
+        );
+    }
+
+    #[test]
+    fn click_sends_mouse_messages_to_the_part_there() {
+        let body = "  set the rect of btn 1 to 10,10,50,30
+  show btn 1
+  click at 20,20
+  click at 80,80";
+        let mut interpreter = interpreter(&format!("on test\n{}\nend test", body));
+        let notes = "on mouseUp\n  put the short name of the target & \";\" after msg\nend mouseUp";
+        set_script(&mut interpreter, ObjectId::Card(10), notes);
+        interpreter.send(ObjectId::Stack, "test", &[]).unwrap();
+        assert_eq!(interpreter.message_box, "OK;One;");
+    }
+
+    #[test]
+    fn the_click_loc_stays_where_click_at_clicked() {
+        let mut interpreter = run("  click at 310,20");
+        let click_loc =
+            |i: &mut Interpreter<TestHost>| i.evaluate(ObjectId::Stack, "the clickLoc").unwrap();
+        assert_eq!(click_loc(&mut interpreter), "310,20");
+        interpreter.click_loc = None;
+        assert_eq!(click_loc(&mut interpreter), "0,0");
+    }
+}
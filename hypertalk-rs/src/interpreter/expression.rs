@@ -0,0 +1,338 @@
+use crate::ast::*;
+use crate::chunk::{self, ChunkRange};
+use crate::date;
+use crate::error::ScriptError;
+use crate::object::{Evaluate, ObjectId, Resolver};
+use crate::property;
+use crate::value::{self, format_number};
+
+use super::{random, Frame, Host, Interpreter, Run};
+
+//
+// Working out the values of expressions.
+//
+
+// the values in an object reference once they've been worked out, for the resolver; `any` takes
+// its random number from the interpreter's.
+struct Literals<'a>(&'a mut u64);
+
+impl Evaluate for Literals<'_> {
+    fn evaluate(&mut self, expr: &Expr) -> Result<String, ScriptError> {
+        match expr {
+            Expr::String(value) => Ok(value.clone()),
+            _ => Err(ScriptError::new("Expected a value")),
+        }
+    }
+
+    fn random(&mut self, n: usize) -> usize {
+        random(self.0, n)
+    }
+}
+
+impl<H: Host> Interpreter<H> {
+    pub(super) fn eval(&mut self, expr: &Expr) -> Run<String> {
+        match expr {
+            Expr::Number(n) | Expr::String(n) => Ok(n.clone()),
+            Expr::Variable(name) => Ok(constant(name).unwrap_or_else(|| self.variable(name))),
+            Expr::Unary(UnaryOp::Negate, value) => {
+                let n = value::to_number(&self.eval(value)?)?;
+                Ok(self.number(-n))
+            }
+            Expr::Unary(UnaryOp::Not, value) => {
+                let b = value::to_bool(&self.eval(value)?)?;
+                Ok(value::from_bool(!b))
+            }
+            Expr::Binary(op, left, right) => self.binary(*op, left, right),
+            Expr::IsA { value, ty, negated } => {
+                let value = self.eval(value)?;
+                let is = match ty.to_lowercase().as_str() {
+                    "number" => value::is_number(&value),
+                    "integer" => value::parse_number(&value).is_some_and(|n| n.fract() == 0.0),
+                    "point" => value::to_point(&value).is_some(),
+                    "rect" | "rectangle" => value::to_rect(&value).is_some(),
+                    "logical" | "boolean" => value::to_bool(&value).is_ok(),
+                    "date" => date::parse(&value).is_some(),
+                    _ => {
+                        return Err(ScriptError::new(format!("Can't understand \"{}\"", ty)).into())
+                    }
+                };
+                Ok(value::from_bool(is != *negated))
+            }
+            Expr::Call { name, args } => {
+                let args = args
+                    .iter()
+                    .map(|arg| self.eval(arg))
+                    .collect::<Run<Vec<_>>>()?;
+                self.call_function(name, None, &args, true)
+            }
+            Expr::The { modifier, name, of } => {
+                let args = match of.as_deref() {
+                    Some(Expr::Object(object)) => {
+                        let id = self.resolve(object)?;
+                        if let Some(value) = self.get_property(id, name, *modifier)? {
+                            return Ok(value);
+                        }
+                        // not one of its properties, so a function of what's in it, as in
+                        // `the length of card field 1`.
+                        vec![self.object_value(id)?]
+                    }
+                    Some(of) => vec![self.eval(of)?],
+                    None => Vec::new(),
+                };
+                self.call_function(name, *modifier, &args, false)
+            }
+            Expr::Chunk { .. } => {
+                // `char 1 of word 2 of x` is char 1 of (word 2 of x): work out x, then take
+                // the chunks from the outside in.
+                let mut chunks = Vec::new();
+                let mut base = expr;
+                while let Expr::Chunk { chunk, of } = base {
+                    chunks.push(chunk);
+                    base = of;
+                }
+                let mut text = self.eval(base)?;
+                for chunk in chunks.into_iter().rev() {
+                    let range = self.chunk_range(chunk, &text)?;
+                    text = chunk::get(&text, &[range], self.item_delimiter);
+                }
+                Ok(text)
+            }
+            Expr::NumberOf { kind, of } => {
+                let text = self.eval(of)?;
+                Ok(chunk::count(&text, *kind, self.item_delimiter).to_string())
+            }
+            // `the target` is the object's name, where anything else is what's in it.
+            Expr::Object(object) if **object == Object::Target => {
+                let target = self.frame().target;
+                let found = target.find(self.host.stack());
+                let stack_name = self.host.stack_name();
+                Ok(found
+                    .map(|found| property::name(found, stack_name, None))
+                    .unwrap_or_default())
+            }
+            Expr::Object(object) => {
+                let id = self.resolve(object)?;
+                self.object_value(id)
+            }
+            Expr::NumberOfObjects { objects, of } => {
+                let of = match of {
+                    Some(of) => Some(self.literal_object(of)?),
+                    None => None,
+                };
+                let resolver = resolver(&self.host, &self.frames);
+                let count = resolver.count(*objects, of.as_ref(), &mut Literals(&mut self.seed))?;
+                Ok(count.to_string())
+            }
+        }
+    }
+
+    fn binary(&mut self, op: BinaryOp, left: &Expr, right: &Expr) -> Run<String> {
+        let left = self.eval(left)?;
+        // `and` and `or` don't look at the right if the left decides it.
+        match op {
+            BinaryOp::And if !value::to_bool(&left)? => return Ok(value::from_bool(false)),
+            BinaryOp::Or if value::to_bool(&left)? => return Ok(value::from_bool(true)),
+            _ => {}
+        }
+        let right = self.eval(right)?;
+        let arithmetic = |f: fn(f64, f64) -> f64| -> Run<f64> {
+            Ok(f(value::to_number(&left)?, value::to_number(&right)?))
+        };
+        Ok(match op {
+            BinaryOp::Power => self.number(arithmetic(f64::powf)?),
+            BinaryOp::Multiply => self.number(arithmetic(|a, b| a * b)?),
+            BinaryOp::Divide => self.number(arithmetic(|a, b| a / b)?),
+            BinaryOp::Div => self.number(arithmetic(|a, b| (a / b).trunc())?),
+            BinaryOp::Mod => self.number(arithmetic(|a, b| a % b)?),
+            BinaryOp::Add => self.number(arithmetic(|a, b| a + b)?),
+            BinaryOp::Subtract => self.number(arithmetic(|a, b| a - b)?),
+            BinaryOp::Concat => left + &right,
+            BinaryOp::ConcatSpace => left + " " + &right,
+            BinaryOp::Less => value::from_bool(value::compare(&left, &right).is_lt()),
+            BinaryOp::Greater => value::from_bool(value::compare(&left, &right).is_gt()),
+            BinaryOp::LessEqual => value::from_bool(value::compare(&left, &right).is_le()),
+            BinaryOp::GreaterEqual => value::from_bool(value::compare(&left, &right).is_ge()),
+            BinaryOp::IsIn => value::from_bool(value::contains(&right, &left)),
+            BinaryOp::IsNotIn => value::from_bool(!value::contains(&right, &left)),
+            BinaryOp::Contains => value::from_bool(value::contains(&left, &right)),
+            BinaryOp::IsWithin | BinaryOp::IsNotWithin => {
+                let within = match (value::to_point(&left), value::to_rect(&right)) {
+                    (Some((h, v)), Some((l, t, r, b))) => h >= l && h < r && v >= t && v < b,
+                    (None, _) => {
+                        return Err(ScriptError::new(format!(
+                            "Expected a point but found \"{}\"",
+                            left
+                        ))
+                        .into())
+                    }
+                    (_, None) => {
+                        return Err(ScriptError::new(format!(
+                            "Expected a rectangle but found \"{}\"",
+                            right
+                        ))
+                        .into())
+                    }
+                };
+                value::from_bool(within == (op == BinaryOp::IsWithin))
+            }
+            BinaryOp::Equal => value::from_bool(value::equal(&left, &right)),
+            BinaryOp::NotEqual => value::from_bool(!value::equal(&left, &right)),
+            BinaryOp::And | BinaryOp::Or => value::from_bool(value::to_bool(&right)?),
+        })
+    }
+
+    // a function: a handler for it if there is one (only for the `f(x)` form; `the f` always
+    // means the built-in one), then the built-in functions and global properties, then the
+    // host's.
+    fn call_function(
+        &mut self,
+        name: &str,
+        modifier: Option<Modifier>,
+        args: &[String],
+        handlers: bool,
+    ) -> Run<String> {
+        if handlers {
+            let me = self.frame().me;
+            if let Some(value) = self.deliver(me, HandlerKind::Function, name, args)? {
+                return Ok(value);
+            }
+        }
+        if let Some(value) = self.built_in_function(name, modifier, args)? {
+            return Ok(value);
+        }
+        if let Some(value) = self.properties.get(name) {
+            return Ok(value);
+        }
+        if let Some(value) = self.host.function(name, args)? {
+            return Ok(value);
+        }
+        if handlers {
+            Err(ScriptError::new(format!("Can't understand \"{}\"", name)).into())
+        } else {
+            Err(ScriptError::new("Can't get that property").into())
+        }
+    }
+
+    // a chunk with its positions worked out against the text it's a chunk of.
+    pub(super) fn chunk_range(&mut self, chunk: &Chunk, text: &str) -> Run<ChunkRange> {
+        let start = self.position(&chunk.start, chunk.kind, text)?;
+        let end = match &chunk.end {
+            Some(end) => self.position(end, chunk.kind, text)?,
+            None => start,
+        };
+        Ok(ChunkRange::new(chunk.kind, start, end))
+    }
+
+    fn position(&mut self, position: &Position, kind: ChunkKind, text: &str) -> Run<usize> {
+        let count = || chunk::count(text, kind, self.item_delimiter);
+        Ok(match position {
+            Position::Number(n) => {
+                let n = value::to_integer(&self.eval(n)?)?;
+                n.max(0) as usize
+            }
+            Position::Last => count(),
+            Position::Middle => chunk::middle(count()),
+            Position::Any => random(&mut self.seed, count()),
+        })
+    }
+
+    pub(super) fn resolve(&mut self, object: &Object) -> Run<ObjectId> {
+        let object = self.literal_object(object)?;
+        let resolver = resolver(&self.host, &self.frames);
+        Ok(resolver.resolve(&object, &mut Literals(&mut self.seed))?)
+    }
+
+    // the object reference with the values in it worked out.
+    fn literal_object(&mut self, object: &Object) -> Run<Object> {
+        Ok(match object {
+            Object::Me | Object::Target | Object::Window(_) => object.clone(),
+            Object::Part {
+                kind,
+                layer,
+                which,
+                of,
+            } => Object::Part {
+                kind: *kind,
+                layer: *layer,
+                which: self.literal_which(which)?,
+                of: self.literal_of(of)?,
+            },
+            Object::Card { which, marked, of } => Object::Card {
+                which: self.literal_which(which)?,
+                marked: *marked,
+                of: self.literal_of(of)?,
+            },
+            Object::Background { which, of } => Object::Background {
+                which: self.literal_which(which)?,
+                of: self.literal_of(of)?,
+            },
+            Object::Stack(which) => Object::Stack(self.literal_which(which)?),
+        })
+    }
+
+    fn literal_which(&mut self, which: &Which) -> Run<Which> {
+        Ok(match which {
+            Which::Value(value) => Which::Value(Expr::String(self.eval(value)?)),
+            Which::Id(id) => Which::Id(Expr::String(self.eval(id)?)),
+            Which::Position(Position::Number(n)) => {
+                Which::Position(Position::Number(Expr::String(self.eval(n)?)))
+            }
+            which => which.clone(),
+        })
+    }
+
+    fn literal_of(&mut self, of: &Option<Box<Object>>) -> Run<Option<Box<Object>>> {
+        Ok(match of {
+            Some(of) => Some(Box::new(self.literal_object(of)?)),
+            None => None,
+        })
+    }
+
+    // a number that came out of arithmetic, as `the numberFormat` says to write it.
+    pub(super) fn number(&self, n: f64) -> String {
+        format_number(n, &self.number_format)
+    }
+}
+
+// where the running handler is, for finding objects. (This takes the fields it needs rather than
+// the interpreter so the seed can be borrowed alongside it.)
+fn resolver<'a, H: Host>(host: &'a H, frames: &[Frame]) -> Resolver<'a> {
+    let frame = frames.last();
+    Resolver {
+        stack: host.stack(),
+        stack_name: host.stack_name(),
+        card: host.card(),
+        me: frame.map(|f| f.me),
+        target: frame.map(|f| f.target),
+        recent: host.recent_card(),
+    }
+}
+
+// the constants, which are never variables.
+fn constant(name: &str) -> Option<String> {
+    const NUMBERS: [&str; 11] = [
+        "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten",
+    ];
+    if let Some(n) = NUMBERS.iter().position(|w| w.eq_ignore_ascii_case(name)) {
+        return Some(n.to_string());
+    }
+    Some(
+        match name.to_lowercase().as_str() {
+            "empty" => "",
+            "true" => "true",
+            "false" => "false",
+            "return" | "linefeed" => "\n",
+            "space" => " ",
+            "tab" => "\t",
+            "quote" => "\"",
+            "comma" => ",",
+            "colon" => ":",
+            "formfeed" => "\x0c",
+            "pi" => "3.14159265358979323846",
+            "up" => "up",
+            "down" => "down",
+            _ => return None,
+        }
+        .to_string(),
+    )
+}
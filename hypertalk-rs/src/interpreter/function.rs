@@ -0,0 +1,321 @@
+use std::f64::consts::LN_2;
+
+use hc_decode::macroman::{char_to_macroman, macroman_to_char};
+
+use crate::ast::{Expr, Modifier};
+use crate::date;
+use crate::error::ScriptError;
+use crate::value;
+
+use super::{random, Host, Interpreter, Run, Space};
+
+//
+// HyperCard's own functions, which `the f`, `the f of x` and `f(x)` all call when no handler
+// takes `f(x)` first. What they know about the clock and the mouse and keyboard comes from the
+// host.
+//
+
+impl<H: Host> Interpreter<H> {
+    pub(super) fn built_in_function(
+        &mut self,
+        name: &str,
+        modifier: Option<Modifier>,
+        args: &[String],
+    ) -> Run<Option<String>> {
+        let arg = |i: usize| args.get(i).map_or("", String::as_str);
+        let number = |i: usize| value::to_number(arg(i));
+        let up_down = |down: bool| if down { "down" } else { "up" }.to_string();
+        let point = |(h, v): (i32, i32)| format!("{},{}", h, v);
+
+        Ok(Some(match name.to_lowercase().as_str() {
+            "param" => {
+                let frame = self.frame();
+                match value::to_integer(arg(0))? {
+                    0 => frame.message.clone(),
+                    n if n > 0 => frame
+                        .params
+                        .get(n as usize - 1)
+                        .cloned()
+                        .unwrap_or_default(),
+                    _ => String::new(),
+                }
+            }
+            "params" => {
+                let frame = self.frame();
+                let params: Vec<String> =
+                    frame.params.iter().map(|p| format!("\"{}\"", p)).collect();
+                if params.is_empty() {
+                    frame.message.clone()
+                } else {
+                    format!("{} {}", frame.message, params.join(","))
+                }
+            }
+            "paramcount" => self.frame().params.len().to_string(),
+            "result" => self.result.clone(),
+            "numberformat" => self.number_format.clone(),
+            "itemdelimiter" => self.item_delimiter.to_string(),
+            "value" => {
+                let expr = Expr::parse(arg(0))?;
+                self.eval(&expr)?
+            }
+
+            // text
+            "length" => arg(0).chars().count().to_string(),
+            "offset" => {
+                let (needle, haystack) = (arg(0).to_lowercase(), arg(1).to_lowercase());
+                match haystack.find(&needle) {
+                    Some(i) if !needle.is_empty() => {
+                        (haystack[..i].chars().count() + 1).to_string()
+                    }
+                    _ => "0".to_string(),
+                }
+            }
+            "chartonum" => match arg(0).chars().next() {
+                Some(c) => char_to_macroman(c).map_or(c as u32, u32::from).to_string(),
+                None => "0".to_string(),
+            },
+            "numtochar" => {
+                let n = value::to_integer(arg(0))?;
+                match n {
+                    0..=255 => macroman_to_char(n as u8).to_string(),
+                    n => char::from_u32(n as u32)
+                        .map(String::from)
+                        .unwrap_or_default(),
+                }
+            }
+
+            // arithmetic
+            "abs" => self.number(number(0)?.abs()),
+            "round" => self.number(number(0)?.round_ties_even()),
+            "trunc" => self.number(number(0)?.trunc()),
+            "sqrt" => self.number(number(0)?.sqrt()),
+            "sin" => self.number(number(0)?.sin()),
+            "cos" => self.number(number(0)?.cos()),
+            "tan" => self.number(number(0)?.tan()),
+            "atan" => self.number(number(0)?.atan()),
+            "exp" => self.number(number(0)?.exp()),
+            "exp1" => self.number(number(0)?.exp_m1()),
+            "exp2" => self.number(number(0)?.exp2()),
+            "ln" => self.number(number(0)?.ln()),
+            "ln1" => self.number(number(0)?.ln_1p()),
+            "log2" => self.number(number(0)?.ln() / LN_2),
+            "annuity" => {
+                let (rate, periods) = (number(0)?, number(1)?);
+                self.number(if rate == 0.0 {
+                    periods
+                } else {
+                    (1.0 - (1.0 + rate).powf(-periods)) / rate
+                })
+            }
+            "compound" => self.number((1.0 + number(0)?).powf(number(1)?)),
+            "average" | "max" | "min" => {
+                let numbers = list(args)?;
+                if numbers.is_empty() {
+                    return Err(ScriptError::new(format!(
+                        "Expected a list of numbers for {}",
+                        name
+                    ))
+                    .into());
+                }
+                let n = match name.to_lowercase().as_str() {
+                    "average" => numbers.iter().sum::<f64>() / numbers.len() as f64,
+                    "max" => numbers.iter().copied().fold(f64::MIN, f64::max),
+                    _ => numbers.iter().copied().fold(f64::MAX, f64::min),
+                };
+                self.number(n)
+            }
+            "random" => {
+                let n = value::to_integer(arg(0))?;
+                if n < 1 {
+                    return Err(ScriptError::new(format!(
+                        "Expected a positive number but found \"{}\"",
+                        arg(0)
+                    ))
+                    .into());
+                }
+                random(&mut self.seed, n as usize).to_string()
+            }
+
+            // the clock
+            "seconds" | "secs" => (self.host.seconds() as i64).to_string(),
+            "ticks" => self.host.ticks().to_string(),
+            "date" => date::date(self.host.seconds() as i64, modifier),
+            "time" => date::time(self.host.seconds() as i64, modifier),
+            "dateitems" => date::date_items(self.host.seconds() as i64),
+
+            // the mouse and keyboard
+            "mouse" => up_down(self.input().mouse_down),
+            "mouseclick" => value::from_bool(self.input().mouse_click),
+            "mouseloc" => point(self.input().mouse_loc),
+            "mouseh" => self.input().mouse_loc.0.to_string(),
+            "mousev" => self.input().mouse_loc.1.to_string(),
+            "clickloc" => point(self.input().click_loc),
+            "clickh" => self.input().click_loc.0.to_string(),
+            "clickv" => self.input().click_loc.1.to_string(),
+            "clicktext" => self.input().click_text,
+            "clickchunk" => self.input().click_chunk,
+            "clickline" => self.input().click_line,
+            "shiftkey" => up_down(self.input().shift_key),
+            "optionkey" => up_down(self.input().option_key),
+            "commandkey" | "cmdkey" => up_down(self.input().command_key),
+            "selectedtext" => self.input().selected_text,
+            "selectedchunk" => self.input().selected_chunk,
+            "selectedfield" => self.input().selected_field,
+            "selectedline" => self.input().selected_line,
+            "selectedloc" => self.input().selected_loc.map(point).unwrap_or_default(),
+            "foundtext" => self.input().found_text,
+            "foundchunk" => self.input().found_chunk,
+            "foundfield" => self.input().found_field,
+            "foundline" => self.input().found_line,
+
+            // HyperCard and the machine it's on
+            "screenrect" => {
+                let (left, top, right, bottom) = self.host.screen_rect();
+                format!("{},{},{},{}", left, top, right, bottom)
+            }
+            "stacks" => self.host.stacks().join("\n"),
+            "windows" => self.host.windows().join("\n"),
+            "menus" => MENUS.join("\n"),
+            "tool" => format!("{} tool", self.properties.tool),
+            "sound" => "done".to_string(),
+            "version" => match modifier {
+                Some(Modifier::Long) => "02418000".to_string(),
+                _ => "2.41".to_string(),
+            },
+            "systemversion" => self.host.system_version(),
+            "diskspace" => self.host.free_space(Space::Disk).to_string(),
+            "heapspace" => self.host.free_space(Space::Heap).to_string(),
+            "stackspace" => self.host.free_space(Space::Stack).to_string(),
+            "destination" | "voices" | "speech" | "programs" => String::new(),
+            _ => return Ok(None),
+        }))
+    }
+}
+
+// the menus in HyperCard's menu bar.
+const MENUS: [&str; 8] = [
+    "Apple", "File", "Edit", "Go", "Tools", "Objects", "Font", "Style",
+];
+
+// numbers given one by one or as lists separated by commas, or both: `max(1, 2)`, `max(x)`.
+fn list(args: &[String]) -> Result<Vec<f64>, ScriptError> {
+    args.iter()
+        .flat_map(|arg| arg.split(','))
+        .filter(|n| !n.trim().is_empty())
+        .map(value::to_number)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::tests::{interpreter, MOMENT};
+    use crate::interpreter::Interpreter;
+    use crate::object::ObjectId;
+
+    fn evaluate(source: &str) -> String {
+        interpreter("").evaluate(ObjectId::Stack, source).unwrap()
+    }
+
+    #[test]
+    fn the_clock_comes_from_the_host() {
+        assert_eq!(evaluate("the seconds"), MOMENT.to_string());
+        assert_eq!(evaluate("the ticks"), "600");
+        assert_eq!(evaluate("the long date"), "Monday, October 19, 2026");
+        assert_eq!(evaluate("the time"), "1:05 PM");
+        assert_eq!(evaluate("the dateItems"), "2026,10,19,13,5,9,2");
+    }
+
+    #[test]
+    fn random_numbers_start_from_the_clock() {
+        let rolls = |ticks| {
+            let mut host = interpreter("").host;
+            host.ticks = ticks;
+            let mut interpreter = Interpreter::new(host);
+            (0..5)
+                .map(|_| {
+                    interpreter
+                        .evaluate(ObjectId::Stack, "random(100)")
+                        .unwrap()
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(rolls(600), rolls(600));
+        assert_ne!(rolls(600), rolls(601));
+    }
+
+    #[test]
+    fn random_numbers_stay_in_range() {
+        let mut interpreter = interpreter("");
+        for _ in 0..50 {
+            let n = interpreter.evaluate(ObjectId::Stack, "random(6)").unwrap();
+            assert!((1..=6).contains(&n.parse::<i32>().unwrap()), "{}", n);
+        }
+    }
+
+    #[test]
+    fn a_seed_repeats_random_numbers() {
+        let rolls = |seed| {
+            let mut interpreter = interpreter("");
+            interpreter.seed(seed);
+            (0..5)
+                .map(|_| {
+                    interpreter
+                        .evaluate(ObjectId::Stack, "random(100)")
+                        .unwrap()
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(rolls(7), rolls(7));
+        assert_ne!(rolls(7), rolls(8));
+    }
+
+    #[test]
+    fn random_needs_a_positive_number() {
+        let error = interpreter("")
+            .evaluate(ObjectId::Stack, "random(0)")
+            .unwrap_err();
+        assert_eq!(error.message, "Expected a positive number but found \"0\"");
+    }
+
+    #[test]
+    fn round_goes_to_even() {
+        assert_eq!(evaluate("round(2.5)"), "2");
+        assert_eq!(evaluate("round(3.5)"), "4");
+        assert_eq!(evaluate("trunc(-3.7)"), "-3");
+    }
+
+    #[test]
+    fn lists_of_numbers() {
+        assert_eq!(evaluate("max(1, 5, 3)"), "5");
+        assert_eq!(evaluate("min(\"4,2\", 3)"), "2");
+        assert_eq!(evaluate("average(1, 2, 3, 4)"), "2.5");
+    }
+
+    #[test]
+    fn financial_functions() {
+        assert_eq!(evaluate("annuity(0, 5)"), "5");
+        assert_eq!(evaluate("compound(0.1, 2)"), "1.21");
+    }
+
+    #[test]
+    fn text_functions() {
+        assert_eq!(evaluate("length(\"hello\")"), "5");
+        assert_eq!(evaluate("offset(\"LL\", \"hello\")"), "3");
+        assert_eq!(evaluate("offset(\"z\", \"hello\")"), "0");
+        assert_eq!(evaluate("charToNum(\"A\")"), "65");
+        assert_eq!(evaluate("numToChar(142)"), "é");
+    }
+
+    #[test]
+    fn the_machine_comes_from_the_host() {
+        assert_eq!(evaluate("the stacks"), "Test");
+        assert_eq!(evaluate("the windows"), "card window");
+        assert_eq!(evaluate("the systemVersion"), "");
+        assert_eq!(evaluate("the diskSpace"), "16777216");
+    }
+
+    #[test]
+    fn value_evaluates_its_argument() {
+        assert_eq!(evaluate("value(\"2 + 3\")"), "5");
+    }
+}
@@ -0,0 +1,295 @@
+use std::mem;
+
+use hc_render::transition::VisualEffect;
+
+use crate::ast::{Destination, Object, Which};
+use crate::object::ObjectId;
+
+use super::{Host, Interpreter, Run, Stop};
+
+//
+// Going from card to card and stack to stack, with the messages HyperCard sends on the way:
+// `closeCard` to the card being left and `openCard` to the one gone to, along with
+// `closeBackground` and `openBackground` when the background changes and `closeStack` and
+// `openStack` when the stack does. None of them are sent while `the lockMessages` is set.
+//
+
+impl<H: Host> Interpreter<H> {
+    // `go`, returning what it leaves in `the result`: empty, or why it couldn't go.
+    pub(super) fn go(
+        &mut self,
+        destination: &Destination,
+        effect: Option<VisualEffect>,
+    ) -> Run<String> {
+        let object = match destination {
+            Destination::Back => {
+                if let Some(card) = self.host.back() {
+                    self.go_to_card(card, false, effect)?;
+                }
+                return Ok(String::new());
+            }
+            Destination::Home => return self.go_to_stack("Home", None, effect),
+            Destination::Object(object) => object,
+        };
+        if let Some(name) = self.other_stack(object)? {
+            let card = match object {
+                Object::Stack(_) => None,
+                object => Some(object),
+            };
+            return self.go_to_stack(&name, card, effect);
+        }
+        let card = match self.resolve(object) {
+            Ok(ObjectId::Card(card)) => card,
+            // a background's first card, unless this card is already one of its cards.
+            Ok(ObjectId::Background(background)) => {
+                let stack = self.host.stack();
+                let shown = stack.card(self.host.card());
+                if shown.is_some_and(|c| c.background_id == background) {
+                    return Ok(String::new());
+                }
+                match stack.cards.iter().find(|c| c.background_id == background) {
+                    Some(card) => card.id,
+                    None => return Ok("No such card.".to_string()),
+                }
+            }
+            Ok(_) => return Ok(String::new()),
+            // HyperCard leaves not finding where to go in the result rather than stopping.
+            Err(Stop::Error(error)) if error.message.starts_with("No such") => {
+                return Ok(error.message)
+            }
+            Err(stop) => return Err(stop),
+        };
+        let remember = !self.properties.lock_recent;
+        self.go_to_card(card, remember, effect)?;
+        Ok(String::new())
+    }
+
+    // show another card of this stack, with the effects `visual effect` asked for and then
+    // the one given.
+    pub(super) fn go_to_card(
+        &mut self,
+        card: u32,
+        remember: bool,
+        effect: Option<VisualEffect>,
+    ) -> Run<()> {
+        let from = self.host.card();
+        if card == from {
+            return Ok(());
+        }
+        let stack = self.host.stack();
+        let background_of = |card: u32| stack.card(card).map(|c| c.background_id);
+        let new_background = background_of(from) != background_of(card);
+
+        self.system_message(ObjectId::Card(from), "closeCard")?;
+        if new_background {
+            self.system_message(ObjectId::Card(from), "closeBackground")?;
+        }
+        let effects = self.effects(effect);
+        self.host.go(card, remember, &effects)?;
+        if new_background {
+            self.system_message(ObjectId::Card(card), "openBackground")?;
+        }
+        self.system_message(ObjectId::Card(card), "openCard")
+    }
+
+    // open another stack, going to a card in it if there's one to go to.
+    fn go_to_stack(
+        &mut self,
+        name: &str,
+        card: Option<&Object>,
+        effect: Option<VisualEffect>,
+    ) -> Run<String> {
+        if !self.host.has_stack(name) {
+            return Ok("No such stack.".to_string());
+        }
+        let from = ObjectId::Card(self.host.card());
+        for message in ["closeCard", "closeBackground", "closeStack"] {
+            self.system_message(from, message)?;
+        }
+        self.host.go_stack(name)?;
+        self.scripts.clear();
+        self.pushed.clear();
+        // the effects go with the card in the other stack; the host opens a stack as it will.
+        let effects = self.effects(effect);
+        if let Some(card) = card {
+            if let ObjectId::Card(card) = self.resolve(card)? {
+                let remember = !self.properties.lock_recent;
+                self.host.go(card, remember, &effects)?;
+            }
+        }
+        let to = ObjectId::Card(self.host.card());
+        for message in ["openStack", "openBackground", "openCard"] {
+            self.system_message(to, message)?;
+        }
+        Ok(String::new())
+    }
+
+    // the name of the stack a card, background or stack reference is in, if that isn't this
+    // one.
+    fn other_stack(&mut self, object: &Object) -> Run<Option<String>> {
+        let which = match object {
+            Object::Stack(which) => which,
+            Object::Card { of: Some(of), .. } | Object::Background { of: Some(of), .. } => {
+                return self.other_stack(of)
+            }
+            _ => return Ok(None),
+        };
+        let name = match which {
+            Which::Value(name) => self.eval(name)?,
+            _ => return Ok(None),
+        };
+        // a path names a stack too; it's the last part that says which one.
+        let last = name.rsplit([':', '/']).next().unwrap_or_default();
+        if last.eq_ignore_ascii_case(self.host.stack_name()) {
+            Ok(None)
+        } else {
+            Ok(Some(name))
+        }
+    }
+
+    // the effects to show the next card with, which are then used up.
+    pub(super) fn effects(&mut self, effect: Option<VisualEffect>) -> Vec<VisualEffect> {
+        let mut effects = mem::take(&mut self.visual_effects);
+        effects.extend(effect);
+        effects
+    }
+
+    // one of HyperCard's own messages, such as `openCard`, unless messages are locked.
+    pub(super) fn system_message(&mut self, to: ObjectId, message: &str) -> Run<()> {
+        if self.properties.lock_messages {
+            return Ok(());
+        }
+        self.message(to, message, &[])?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hc_render::transition::{Effect, Speed, VisualEffect};
+
+    use crate::interpreter::tests::{interpreter, TestHost};
+    use crate::interpreter::Interpreter;
+    use crate::object::ObjectId;
+
+    // a stack script that notes the messages going from card to card and stack to stack send.
+    const NOTES: &str = "on openCard\n  put \"o\" after msg\nend openCard
+on closeCard\n  put \"c\" after msg\nend closeCard
+on openBackground\n  put \"O\" after msg\nend openBackground
+on closeBackground\n  put \"C\" after msg\nend closeBackground
+on closeStack\n  put \"S\" after msg\nend closeStack";
+
+    // an interpreter that has run a handler with the given body, with the stack script noting
+    // the messages sent on the way.
+    fn run(body: &str) -> Interpreter<TestHost> {
+        let script = format!("{}\non test\n{}\nend test", NOTES, body);
+        let mut interpreter = interpreter(&script);
+        interpreter.send(ObjectId::Stack, "test", &[]).unwrap();
+        interpreter
+    }
+
+    #[test]
+    fn goes_to_a_card() {
+        let interpreter = run("  go to card 2");
+        assert_eq!(interpreter.host.card, 11);
+        assert_eq!(interpreter.message_box, "co");
+    }
+
+    #[test]
+    fn a_new_background_is_closed_and_opened() {
+        let interpreter = run("  go to card \"End\"");
+        assert_eq!(interpreter.host.card, 12);
+        assert_eq!(interpreter.message_box, "cCOo");
+    }
+
+    #[test]
+    fn going_to_the_shown_card_sends_nothing() {
+        let interpreter = run("  go to card 1");
+        assert_eq!(interpreter.message_box, "");
+    }
+
+    #[test]
+    fn prev_wraps_around() {
+        let interpreter = run("  go prev card");
+        assert_eq!(interpreter.host.card, 12);
+    }
+
+    #[test]
+    fn a_background_goes_to_its_first_card() {
+        let interpreter = run("  go to bkgnd \"Back\"");
+        assert_eq!(interpreter.host.card, 12);
+    }
+
+    #[test]
+    fn nowhere_to_go_is_left_in_the_result() {
+        let interpreter = run("  go to card 9");
+        assert_eq!(interpreter.host.card, 10);
+        assert_eq!(interpreter.result, "No such card.");
+    }
+
+    #[test]
+    fn locked_messages_are_not_sent() {
+        let interpreter = run("  lock messages\n  go to card 3");
+        assert_eq!(interpreter.host.card, 12);
+        assert_eq!(interpreter.message_box, "");
+    }
+
+    #[test]
+    fn push_and_pop() {
+        let interpreter = run("  push this card\n  go to card 3\n  pop card");
+        assert_eq!(interpreter.host.card, 10);
+    }
+
+    #[test]
+    fn pop_into_a_container() {
+        let interpreter = run("  push card 2\n  pop card into x\n  put x");
+        assert_eq!(interpreter.message_box, "card id 11 of stack \"Test\"");
+    }
+
+    #[test]
+    fn a_stack_there_is_not_is_not_left() {
+        let interpreter = run("  go to stack \"Nowhere\"");
+        assert_eq!(interpreter.result, "No such stack.");
+        assert_eq!(interpreter.message_box, "");
+        assert!(interpreter.host.commands.is_empty());
+    }
+
+    #[test]
+    fn going_home_closes_this_stack() {
+        let interpreter = run("  go home");
+        assert_eq!(interpreter.result, "");
+        // the test host stays on the same stack, so its script gets the opening messages too.
+        assert_eq!(interpreter.message_box, "cCSOo");
+        assert_eq!(interpreter.host.commands, ["go stack Home", "openStack "]);
+    }
+
+    #[test]
+    fn the_card_is_shown_with_the_effects_asked_for() {
+        let interpreter =
+            run("  visual effect dissolve\n  go to card 2 with visual effect wipe left slowly");
+        let wipe = VisualEffect {
+            speed: Speed::Slow,
+            ..VisualEffect::new(Effect::WipeLeft)
+        };
+        assert_eq!(
+            interpreter.host.effects,
+            [VisualEffect::new(Effect::Dissolve), wipe]
+        );
+    }
+
+    #[test]
+    fn effects_are_used_up_by_going() {
+        let interpreter = run("  visual effect dissolve\n  go to card 2\n  go to card 3");
+        assert_eq!(
+            interpreter.host.effects,
+            [VisualEffect::new(Effect::Dissolve)]
+        );
+    }
+
+    #[test]
+    fn effects_not_used_are_forgotten_when_scripts_finish() {
+        let mut interpreter = run("  visual effect dissolve\n  go to card 9");
+        interpreter.send(ObjectId::Stack, "test", &[]).unwrap();
+        assert!(interpreter.host.effects.is_empty());
+    }
+}
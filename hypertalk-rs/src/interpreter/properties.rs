@@ -0,0 +1,221 @@
+use crate::ast::{Expr, Modifier, Window};
+use crate::error::ScriptError;
+use crate::object::ObjectId;
+use crate::property;
+
+use super::{Host, Interpreter, Run};
+
+//
+// Reading and writing properties: an object's go to the stack, a window's to the host, and the
+// global ones are kept here.
+//
+
+impl<H: Host> Interpreter<H> {
+    // `None` if the object hasn't a property by that name, so it can be a function of what's in
+    // the object instead, as in `the length of card field 1`.
+    pub(super) fn get_property(
+        &mut self,
+        id: ObjectId,
+        name: &str,
+        modifier: Option<Modifier>,
+    ) -> Run<Option<String>> {
+        if let ObjectId::Window(window) = id {
+            let value = self.host.window_property(window, name);
+            return Ok(match (value, window) {
+                (None, Window::Card) => property::card_window(self.host.stack(), name),
+                (value, _) => value,
+            });
+        }
+        let (stack, stack_name) = (self.host.stack(), self.host.stack_name());
+        Ok(property::get(stack, stack_name, id, name, modifier)?)
+    }
+
+    pub(super) fn set_property(&mut self, name: &str, of: Option<&Expr>, value: &str) -> Run<()> {
+        let set = match of {
+            None => self.set_global_property(name, value)?,
+            Some(Expr::Object(object)) => {
+                let id = self.resolve(object)?;
+                self.set_object_property(id, name, value)?
+            }
+            Some(_) => false,
+        };
+        if set {
+            Ok(())
+        } else {
+            Err(ScriptError::new("Can't set that property").into())
+        }
+    }
+
+    // returns whether the object or window has a property by that name.
+    pub(super) fn set_object_property(
+        &mut self,
+        id: ObjectId,
+        name: &str,
+        value: &str,
+    ) -> Run<bool> {
+        if let ObjectId::Window(window) = id {
+            return Ok(self.host.set_window_property(window, name, value)?);
+        }
+        let set = property::set(self.host.stack_mut(), id, name, value)?;
+        if name.eq_ignore_ascii_case("script") {
+            self.forget_script(id);
+        }
+        Ok(set)
+    }
+
+    fn set_global_property(&mut self, name: &str, value: &str) -> Run<bool> {
+        if name.eq_ignore_ascii_case("numberFormat") {
+            self.number_format = value.to_string();
+            return Ok(true);
+        }
+        if name.eq_ignore_ascii_case("itemDelimiter") {
+            let mut chars = value.chars();
+            self.item_delimiter = match (chars.next(), chars.next()) {
+                (Some(delimiter), None) => delimiter,
+                _ => return Err(ScriptError::new("The itemDelimiter must be one character").into()),
+            };
+            return Ok(true);
+        }
+        Ok(self.properties.set(name, value)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::interpreter::tests::{interpreter, TestHost};
+    use crate::interpreter::Interpreter;
+    use crate::object::ObjectId;
+
+    // an interpreter that has run a handler with the given body.
+    fn run(body: &str) -> Interpreter<TestHost> {
+        let mut interpreter = interpreter(&format!("on test\n{}\nend test", body));
+        interpreter.send(ObjectId::Stack, "test", &[]).unwrap();
+        interpreter
+    }
+
+    fn evaluate(interpreter: &mut Interpreter<TestHost>, source: &str) -> String {
+        interpreter.evaluate(ObjectId::Stack, source).unwrap()
+    }
+
+    fn error(body: &str) -> String {
+        let mut interpreter = interpreter(&format!("on test\n{}\nend test", body));
+        let error = interpreter.send(ObjectId::Stack, "test", &[]).unwrap_err();
+        error.message
+    }
+
+    #[test]
+    fn names() {
+        let mut interpreter = interpreter("");
+        assert_eq!(
+            evaluate(&mut interpreter, "the name of card 1"),
+            "card \"One\""
+        );
+        assert_eq!(
+            evaluate(&mut interpreter, "the short name of card 1"),
+            "One"
+        );
+        assert_eq!(
+            evaluate(&mut interpreter, "the name of card 2"),
+            "card id 11"
+        );
+        assert_eq!(
+            evaluate(&mut interpreter, "the name of btn 1"),
+            "card button \"OK\""
+        );
+        assert_eq!(
+            evaluate(&mut interpreter, "the short name of this stack"),
+            "Test"
+        );
+    }
+
+    #[test]
+    fn ids_and_numbers() {
+        let mut interpreter = interpreter("");
+        assert_eq!(
+            evaluate(&mut interpreter, "the id of card \"End\""),
+            "card id 12"
+        );
+        assert_eq!(
+            evaluate(&mut interpreter, "the short id of card \"End\""),
+            "12"
+        );
+        assert_eq!(evaluate(&mut interpreter, "the number of card id 11"), "2");
+    }
+
+    #[test]
+    fn sets_a_name() {
+        let mut interpreter = run("  set the name of btn 1 to \"Go\"");
+        assert_eq!(evaluate(&mut interpreter, "the short name of btn 1"), "Go");
+    }
+
+    #[test]
+    fn rects_and_what_follows_from_them() {
+        let mut interpreter = run("  set the rect of btn 1 to 10,20,110,40");
+        assert_eq!(evaluate(&mut interpreter, "the loc of btn 1"), "60,30");
+        assert_eq!(evaluate(&mut interpreter, "the width of btn 1"), "100");
+        assert_eq!(evaluate(&mut interpreter, "the bottom of btn 1"), "40");
+    }
+
+    #[test]
+    fn part_flags() {
+        let mut interpreter = run("  set the hilite of btn 1 to true");
+        assert_eq!(evaluate(&mut interpreter, "the hilite of btn 1"), "true");
+        assert_eq!(evaluate(&mut interpreter, "the visible of btn 1"), "false");
+    }
+
+    #[test]
+    fn card_flags() {
+        let mut interpreter = run("  set the cantDelete of card 2 to true");
+        assert_eq!(
+            evaluate(&mut interpreter, "the cantDelete of card 2"),
+            "true"
+        );
+        assert_eq!(
+            evaluate(&mut interpreter, "the cantDelete of card 1"),
+            "false"
+        );
+    }
+
+    #[test]
+    fn ids_cannot_be_set() {
+        assert_eq!(
+            error("  set the id of card 1 to 3"),
+            "Can't set that property"
+        );
+    }
+
+    #[test]
+    fn setting_a_script_replaces_its_handlers() {
+        let body =
+            "  set the script of card 2 to \"on m\" & return & \"put 7\" & return & \"end m\"
+  send \"m\" to card 2";
+        assert_eq!(run(body).message_box, "7");
+    }
+
+    #[test]
+    fn global_properties() {
+        let mut interpreter =
+            run("  set the userLevel to 2\n  set the cursor to watch\n  put the cursor");
+        assert_eq!(evaluate(&mut interpreter, "the userLevel"), "2");
+        assert_eq!(interpreter.message_box, "watch");
+    }
+
+    #[test]
+    fn the_cursor_goes_back_when_scripts_finish() {
+        let interpreter = run("  set the cursor to watch");
+        assert_eq!(interpreter.properties.cursor, "hand");
+    }
+
+    #[test]
+    fn global_properties_check_their_values() {
+        assert_eq!(
+            error("  set the userLevel to 9"),
+            "Expected a number from 1 to 5 but found \"9\""
+        );
+        assert_eq!(error("  set the cursor to spinner"), "No such cursor.");
+        assert_eq!(
+            error("  set the itemDelimiter to \"ab\""),
+            "The itemDelimiter must be one character"
+        );
+    }
+}
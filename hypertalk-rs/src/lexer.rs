@@ -0,0 +1,482 @@
+use std::fmt::{self, Display};
+
+use crate::error::ScriptError;
+
+//
+// Turns script text into tokens. Scripts come out of the stack already converted from MacRoman,
+// so `≠`, `≤`, `≥` and `¬` are the Unicode characters here. Lines can end in a return (as they do
+// in stacks) or a newline. A `¬` at the end of a line joins it to the next, and `--` starts a
+// comment that runs to the end of the line.
+//
+
+/// Where a token is in the script. Lines and columns count from 1, columns in characters; `start`
+/// and `end` are byte offsets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Span {
+    pub line: usize,
+    pub column: usize,
+    pub start: usize,
+    pub end: usize,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub span: Span,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    Identifier(String),
+    Keyword(Keyword),
+    /// A number as it was written; HyperTalk keeps `1.50` as `1.50` until it does arithmetic
+    /// with it.
+    Number(String),
+    /// A quoted string, without the quotes.
+    String(String),
+    Operator(Operator),
+    LineEnd,
+}
+
+/// Words that are part of the language itself and can't be used as variable names.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Keyword {
+    On,
+    End,
+    Function,
+    If,
+    Then,
+    Else,
+    Repeat,
+    Next,
+    Exit,
+    Pass,
+    Return,
+    Global,
+    The,
+    Of,
+    And,
+    Or,
+    Not,
+    Div,
+    Mod,
+    Is,
+    Contains,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    Plus,
+    Minus,
+    Times,
+    Divide,
+    Power,
+    Concat,
+    ConcatSpace,
+    Equal,
+    /// `<>` or `≠`.
+    NotEqual,
+    Less,
+    Greater,
+    /// `<=` or `≤`.
+    LessEqual,
+    /// `>=` or `≥`.
+    GreaterEqual,
+    LeftParen,
+    RightParen,
+    Comma,
+}
+
+impl Keyword {
+    pub fn from_word(word: &str) -> Option<Self> {
+        Some(match word.to_lowercase().as_str() {
+            "on" => Keyword::On,
+            "end" => Keyword::End,
+            "function" => Keyword::Function,
+            "if" => Keyword::If,
+            "then" => Keyword::Then,
+            "else" => Keyword::Else,
+            "repeat" => Keyword::Repeat,
+            "next" => Keyword::Next,
+            "exit" => Keyword::Exit,
+            "pass" => Keyword::Pass,
+            "return" => Keyword::Return,
+            "global" => Keyword::Global,
+            "the" => Keyword::The,
+            "of" => Keyword::Of,
+            "and" => Keyword::And,
+            "or" => Keyword::Or,
+            "not" => Keyword::Not,
+            "div" => Keyword::Div,
+            "mod" => Keyword::Mod,
+            "is" => Keyword::Is,
+            "contains" => Keyword::Contains,
+            _ => return None,
+        })
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Keyword::On => "on",
+            Keyword::End => "end",
+            Keyword::Function => "function",
+            Keyword::If => "if",
+            Keyword::Then => "then",
+            Keyword::Else => "else",
+            Keyword::Repeat => "repeat",
+            Keyword::Next => "next",
+            Keyword::Exit => "exit",
+            Keyword::Pass => "pass",
+            Keyword::Return => "return",
+            Keyword::Global => "global",
+            Keyword::The => "the",
+            Keyword::Of => "of",
+            Keyword::And => "and",
+            Keyword::Or => "or",
+            Keyword::Not => "not",
+            Keyword::Div => "div",
+            Keyword::Mod => "mod",
+            Keyword::Is => "is",
+            Keyword::Contains => "contains",
+        }
+    }
+}
+
+impl Operator {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Operator::Plus => "+",
+            Operator::Minus => "-",
+            Operator::Times => "*",
+            Operator::Divide => "/",
+            Operator::Power => "^",
+            Operator::Concat => "&",
+            Operator::ConcatSpace => "&&",
+            Operator::Equal => "=",
+            Operator::NotEqual => "<>",
+            Operator::Less => "<",
+            Operator::Greater => ">",
+            Operator::LessEqual => "<=",
+            Operator::GreaterEqual => ">=",
+            Operator::LeftParen => "(",
+            Operator::RightParen => ")",
+            Operator::Comma => ",",
+        }
+    }
+}
+
+impl Token {
+    /// Whether this is the given word, as an identifier or a keyword, ignoring case.
+    pub fn is_word(&self, word: &str) -> bool {
+        match &self.kind {
+            TokenKind::Identifier(a) => a.eq_ignore_ascii_case(word),
+            TokenKind::Keyword(a) => a.as_str().eq_ignore_ascii_case(word),
+            _ => false,
+        }
+    }
+
+    pub fn is_keyword(&self, keyword: Keyword) -> bool {
+        self.kind == TokenKind::Keyword(keyword)
+    }
+
+    pub fn is_operator(&self, operator: Operator) -> bool {
+        self.kind == TokenKind::Operator(operator)
+    }
+}
+
+impl Display for TokenKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenKind::Identifier(a) | TokenKind::Number(a) => f.write_str(a),
+            TokenKind::Keyword(a) => f.write_str(a.as_str()),
+            TokenKind::String(a) => write!(f, "\"{}\"", a),
+            TokenKind::Operator(a) => f.write_str(a.as_str()),
+            TokenKind::LineEnd => f.write_str("end of line"),
+        }
+    }
+}
+
+/// Split a script into tokens.
+pub fn tokenize(source: &str) -> Result<Vec<Token>, ScriptError> {
+    let mut lexer = Lexer {
+        source,
+        chars: source.char_indices().peekable(),
+        line: 1,
+        column: 1,
+        tokens: Vec::new(),
+    };
+    lexer.run()?;
+    Ok(lexer.tokens)
+}
+
+struct Lexer<'a> {
+    source: &'a str,
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+    line: usize,
+    column: usize,
+    tokens: Vec<Token>,
+}
+
+impl<'a> Lexer<'a> {
+    fn run(&mut self) -> Result<(), ScriptError> {
+        while let Some(&(start, ch)) = self.chars.peek() {
+            let span = Span {
+                line: self.line,
+                column: self.column,
+                start,
+                end: start,
+            };
+            match ch {
+                ' ' | '\t' => {
+                    self.bump();
+                }
+                '\r' | '\n' => {
+                    self.line_end();
+                    self.push(TokenKind::LineEnd, span);
+                }
+                '¬' => {
+                    self.bump();
+                    self.skip_while(|c| c == ' ' || c == '\t');
+                    match self.chars.peek() {
+                        Some(&(_, '\r')) | Some(&(_, '\n')) => self.line_end(),
+                        None => {}
+                        Some(_) => {
+                            return Err(ScriptError::at("Expected end of line after ¬", span))
+                        }
+                    }
+                }
+                '-' if self.source[start..].starts_with("--") => {
+                    self.skip_while(|c| c != '\r' && c != '\n');
+                }
+                '"' => {
+                    self.bump();
+                    let text = self.take_while(|c| c != '"' && c != '\r' && c != '\n');
+                    match self.chars.peek() {
+                        Some(&(_, '"')) => {
+                            self.bump();
+                        }
+                        _ => {
+                            return Err(ScriptError::at("Can't find the end of this string", span))
+                        }
+                    }
+                    self.push(TokenKind::String(text), span);
+                }
+                '0'..='9' | '.' => {
+                    let text = self.take_while(|c| c.is_ascii_digit());
+                    let text = match self.chars.peek() {
+                        Some(&(_, '.')) => {
+                            self.bump();
+                            text + "." + &self.take_while(|c| c.is_ascii_digit())
+                        }
+                        _ => text,
+                    };
+                    if text == "." {
+                        return Err(ScriptError::at("Unexpected character .", span));
+                    }
+                    self.push(TokenKind::Number(text), span);
+                }
+                c if c.is_alphabetic() || c == '_' => {
+                    let word = self.take_while(|c| c.is_alphanumeric() || c == '_');
+                    let kind = match Keyword::from_word(&word) {
+                        Some(keyword) => TokenKind::Keyword(keyword),
+                        None => TokenKind::Identifier(word),
+                    };
+                    self.push(kind, span);
+                }
+                _ => {
+                    self.bump();
+                    let next = self.chars.peek().map(|&(_, c)| c);
+                    let (operator, long) = match (ch, next) {
+                        ('&', Some('&')) => (Operator::ConcatSpace, true),
+                        ('&', _) => (Operator::Concat, false),
+                        ('<', Some('>')) => (Operator::NotEqual, true),
+                        ('<', Some('=')) => (Operator::LessEqual, true),
+                        ('<', _) => (Operator::Less, false),
+                        ('>', Some('=')) => (Operator::GreaterEqual, true),
+                        ('>', _) => (Operator::Greater, false),
+                        ('≠', _) => (Operator::NotEqual, false),
+                        ('≤', _) => (Operator::LessEqual, false),
+                        ('≥', _) => (Operator::GreaterEqual, false),
+                        ('=', _) => (Operator::Equal, false),
+                        ('+', _) => (Operator::Plus, false),
+                        ('-', _) => (Operator::Minus, false),
+                        ('*', _) => (Operator::Times, false),
+                        ('/', _) => (Operator::Divide, false),
+                        ('^', _) => (Operator::Power, false),
+                        ('(', _) => (Operator::LeftParen, false),
+                        (')', _) => (Operator::RightParen, false),
+                        (',', _) => (Operator::Comma, false),
+                        _ => {
+                            return Err(ScriptError::at(
+                                format!("Unexpected character {}", ch),
+                                span,
+                            ))
+                        }
+                    };
+                    if long {
+                        self.bump();
+                    }
+                    self.push(TokenKind::Operator(operator), span);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let (_, ch) = self.chars.next()?;
+        self.column += 1;
+        Some(ch)
+    }
+
+    // step over a line ending, treating a return followed by a newline as one.
+    fn line_end(&mut self) {
+        if self.bump() == Some('\r') {
+            if let Some(&(_, '\n')) = self.chars.peek() {
+                self.chars.next();
+            }
+        }
+        self.line += 1;
+        self.column = 1;
+    }
+
+    fn take_while(&mut self, f: impl Fn(char) -> bool) -> String {
+        let mut text = String::new();
+        while let Some(&(_, ch)) = self.chars.peek() {
+            if !f(ch) {
+                break;
+            }
+            text.push(ch);
+            self.bump();
+        }
+        text
+    }
+
+    fn skip_while(&mut self, f: impl Fn(char) -> bool) {
+        self.take_while(f);
+    }
+
+    fn push(&mut self, kind: TokenKind, mut span: Span) {
+        span.end = self
+            .chars
+            .peek()
+            .map(|&(i, _)| i)
+            .unwrap_or(self.source.len());
+        self.tokens.push(Token { kind, span });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(source: &str) -> Vec<TokenKind> {
+        tokenize(source)
+            .unwrap()
+            .into_iter()
+            .map(|t| t.kind)
+            .collect()
+    }
+
+    fn word(word: &str) -> TokenKind {
+        TokenKind::Identifier(word.to_string())
+    }
+
+    fn number(number: &str) -> TokenKind {
+        TokenKind::Number(number.to_string())
+    }
+
+    #[test]
+    fn operators_need_no_spaces() {
+        assert_eq!(
+            kinds("x-1&&\"a\""),
+            [
+                word("x"),
+                TokenKind::Operator(Operator::Minus),
+                number("1"),
+                TokenKind::Operator(Operator::ConcatSpace),
+                TokenKind::String("a".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn numbers_keep_how_they_were_written() {
+        assert_eq!(
+            kinds("1.50 .5 007"),
+            [number("1.50"), number(".5"), number("007")]
+        );
+    }
+
+    #[test]
+    fn keywords_ignore_case() {
+        assert_eq!(
+            kinds("On mouseUp THE"),
+            [
+                TokenKind::Keyword(Keyword::On),
+                word("mouseUp"),
+                TokenKind::Keyword(Keyword::The),
+            ]
+        );
+    }
+
+    #[test]
+    fn mac_comparison_characters() {
+        assert_eq!(kinds("a ≠ b ≤ c ≥ d"), kinds("a <> b <= c >= d"));
+    }
+
+    #[test]
+    fn every_kind_of_line_end() {
+        for source in ["beep\rbeep", "beep\nbeep", "beep\r\nbeep"] {
+            assert_eq!(
+                kinds(source),
+                [word("beep"), TokenKind::LineEnd, word("beep")],
+                "{:?}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    fn continued_lines_are_one_line() {
+        assert_eq!(kinds("put 1 + ¬\r  2"), kinds("put 1 + 2"));
+        // spaces can follow the `¬`, but nothing else can.
+        assert_eq!(kinds("put 1 ¬  \n+ 2"), kinds("put 1 + 2"));
+        let error = tokenize("put 1 ¬ 2").unwrap_err();
+        assert_eq!(error.message, "Expected end of line after ¬");
+    }
+
+    #[test]
+    fn comments_run_to_the_end_of_the_line() {
+        assert_eq!(kinds("put x -- put y\rbeep"), kinds("put x\rbeep"));
+        assert_eq!(kinds("put 3--2"), kinds("put 3"));
+        // but not inside a string.
+        assert_eq!(kinds("\"--\""), [TokenKind::String("--".to_string())]);
+    }
+
+    #[test]
+    fn spans_count_lines_and_characters() {
+        let tokens = tokenize("put ≠ ¬\r  x\rbeep").unwrap();
+        let at = |i: usize| (tokens[i].span.line, tokens[i].span.column);
+        assert_eq!(at(1), (1, 5));
+        // the continued line is still line 2.
+        assert_eq!(at(2), (2, 3));
+        assert_eq!(at(4), (3, 1));
+        // `≠` is one character but three bytes.
+        assert_eq!((tokens[1].span.start, tokens[1].span.end), (4, 7));
+    }
+
+    #[test]
+    fn unterminated_string() {
+        let error = tokenize("put \"a\rb\"").unwrap_err();
+        assert_eq!(error.message, "Can't find the end of this string");
+        assert_eq!(error.span.map(|s| (s.line, s.column)), Some((1, 5)));
+    }
+
+    #[test]
+    fn unexpected_character() {
+        let error = tokenize("put 1 # 2").unwrap_err();
+        assert_eq!(error.message, "Unexpected character #");
+        assert_eq!(error.span.map(|s| s.column), Some(7));
+    }
+}
@@ -0,0 +1,672 @@
+use hc_decode::background::Background;
+use hc_decode::card::Card;
+use hc_decode::part::{Part, PartType};
+use hc_decode::stack::Stack;
+
+use crate::ast::{Expr, Layer, Object, Objects, PartKind, Position, Which, Window};
+use crate::chunk;
+use crate::error::ScriptError;
+
+//
+// Finds the objects scripts refer to in a stack. The numbers and names in a reference are
+// expressions, so working those out is left to whatever is running the script.
+//
+
+/// An object in the stack, by ID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ObjectId {
+    Stack,
+    Background(u32),
+    Card(u32),
+    /// A button or field. `card` is the card it's on; for a background part, that's the card
+    /// whose contents it has.
+    Part {
+        layer: Layer,
+        card: u32,
+        id: u16,
+    },
+    /// One of HyperCard's windows, which aren't in the stack.
+    Window(Window),
+}
+
+/// An object looked up in the stack.
+#[derive(Debug, Clone, Copy)]
+pub enum Found<'a> {
+    Stack(&'a Stack),
+    Background(&'a Background),
+    Card(&'a Card),
+    Part {
+        part: &'a Part,
+        layer: Layer,
+        card: &'a Card,
+    },
+}
+
+/// What the resolver needs from whatever is running the script.
+pub trait Evaluate {
+    fn evaluate(&mut self, expr: &Expr) -> Result<String, ScriptError>;
+    /// A random number from 1 to `n`, for `any`.
+    fn random(&mut self, n: usize) -> usize;
+}
+
+/// Where a script is running, which is what `this card`, `me` and the rest are relative to.
+pub struct Resolver<'a> {
+    pub stack: &'a Stack,
+    /// The stack's name, for `stack "name"`.
+    pub stack_name: &'a str,
+    /// The card being shown.
+    pub card: u32,
+    pub me: Option<ObjectId>,
+    pub target: Option<ObjectId>,
+    /// The card shown before this one.
+    pub recent: Option<u32>,
+}
+
+impl ObjectId {
+    pub fn find<'a>(&self, stack: &'a Stack) -> Option<Found<'a>> {
+        Some(match *self {
+            ObjectId::Stack => Found::Stack(stack),
+            ObjectId::Background(id) => Found::Background(stack.background(id)?),
+            ObjectId::Card(id) => Found::Card(stack.card(id)?),
+            ObjectId::Part { layer, card, id } => {
+                let card = stack.card(card)?;
+                let parts = match layer {
+                    Layer::Card => &card.parts,
+                    Layer::Background => &stack.background(card.background_id)?.parts,
+                };
+                Found::Part {
+                    part: Part::find(parts, id)?,
+                    layer,
+                    card,
+                }
+            }
+            ObjectId::Window(_) => return None,
+        })
+    }
+}
+
+impl<'a> Resolver<'a> {
+    pub fn resolve(
+        &self,
+        object: &Object,
+        eval: &mut dyn Evaluate,
+    ) -> Result<ObjectId, ScriptError> {
+        match object {
+            Object::Me => self.me.ok_or_else(|| ScriptError::new("No such object.")),
+            Object::Target => self
+                .target
+                .ok_or_else(|| ScriptError::new("No such object.")),
+            Object::Stack(which) => {
+                self.stack_named(which, eval)?;
+                Ok(ObjectId::Stack)
+            }
+            Object::Window(window) => Ok(ObjectId::Window(*window)),
+            Object::Background { which, of } => {
+                if let Some(of) = of {
+                    self.in_this_stack(of, eval)?;
+                }
+                let current = self.stack.card(self.card).and_then(|c| {
+                    self.stack
+                        .backgrounds
+                        .iter()
+                        .position(|b| b.id == c.background_id)
+                });
+                let backgrounds = &self.stack.backgrounds;
+                let index = self.pick(backgrounds, which, current, |b| &b.name, |b| b.id, eval)?;
+                match index {
+                    Some(i) => Ok(ObjectId::Background(backgrounds[i].id)),
+                    None => Err(ScriptError::new("No such background.")),
+                }
+            }
+            Object::Card { which, marked, of } => {
+                if let Which::Recent = which {
+                    return self
+                        .recent
+                        .map(ObjectId::Card)
+                        .ok_or_else(|| ScriptError::new("No such card."));
+                }
+                let mut cards = self.cards(of.as_deref(), eval)?;
+                if *marked {
+                    // the next or previous marked card is counted from this card, whether or
+                    // not it's marked itself.
+                    if let Which::Next | Which::Previous = which {
+                        let len = cards.len();
+                        // where to count forwards and backwards from.
+                        let (forward, backward) = match cards.iter().position(|c| c.id == self.card)
+                        {
+                            Some(i) => (i, i),
+                            // this card isn't one of them, like `next marked card of bkgnd 2`
+                            // from another background, so go by where it is in the stack.
+                            None => {
+                                let in_stack =
+                                    |id| self.stack.cards.iter().position(|c| c.id == id);
+                                let here = in_stack(self.card)
+                                    .ok_or_else(|| ScriptError::new("No such card."))?;
+                                let after = cards
+                                    .iter()
+                                    .position(|c| in_stack(c.id) > Some(here))
+                                    .unwrap_or(len);
+                                (after + len - 1, after)
+                            }
+                        };
+                        let next = matches!(which, Which::Next);
+                        return (1..=len)
+                            .map(|step| {
+                                if next {
+                                    (forward + step) % len
+                                } else {
+                                    (backward + len * 2 - step) % len
+                                }
+                            })
+                            .find(|&i| cards[i].marked)
+                            .map(|i| ObjectId::Card(cards[i].id))
+                            .ok_or_else(|| ScriptError::new("No such card."));
+                    }
+                    cards.retain(|c| c.marked);
+                }
+                let current = cards.iter().position(|c| c.id == self.card);
+                match self.pick(&cards, which, current, |c| &c.name, |c| c.id, eval)? {
+                    Some(i) => Ok(ObjectId::Card(cards[i].id)),
+                    None => Err(ScriptError::new("No such card.")),
+                }
+            }
+            Object::Part {
+                kind,
+                layer,
+                which,
+                of,
+            } => {
+                let card = self.part_card(of.as_deref(), eval)?;
+                let layer = layer.unwrap_or(default_layer(*kind));
+                let parts = self.parts(card, layer, *kind)?;
+                let index = self.pick(&parts, which, None, |p| &p.name, |p| p.id as u32, eval)?;
+                match index {
+                    Some(i) => Ok(ObjectId::Part {
+                        layer,
+                        card: card.id,
+                        id: parts[i].id,
+                    }),
+                    None => Err(ScriptError::new(format!("No such {}.", part_word(*kind)))),
+                }
+            }
+        }
+    }
+
+    /// How many of a kind of object there are, for `the number of`.
+    pub fn count(
+        &self,
+        objects: Objects,
+        of: Option<&Object>,
+        eval: &mut dyn Evaluate,
+    ) -> Result<usize, ScriptError> {
+        match objects {
+            Objects::Cards { marked } => {
+                let cards = self.cards(of, eval)?;
+                Ok(cards.iter().filter(|c| c.marked || !marked).count())
+            }
+            Objects::Backgrounds => {
+                if let Some(of) = of {
+                    self.in_this_stack(of, eval)?;
+                }
+                Ok(self.stack.backgrounds.len())
+            }
+            Objects::Parts { kind, layer } => {
+                let card = self.part_card(of, eval)?;
+                Ok(self
+                    .parts(card, layer.unwrap_or(default_layer(kind)), kind)?
+                    .len())
+            }
+        }
+    }
+
+    // the cards a card reference picks from: all of them, or a background's.
+    fn cards(
+        &self,
+        of: Option<&Object>,
+        eval: &mut dyn Evaluate,
+    ) -> Result<Vec<&'a Card>, ScriptError> {
+        let background = match of.map(|of| self.resolve(of, eval)).transpose()? {
+            None | Some(ObjectId::Stack) => None,
+            Some(ObjectId::Background(id)) => Some(id),
+            Some(_) => return Err(ScriptError::new("No such card.")),
+        };
+        Ok(self
+            .stack
+            .cards
+            .iter()
+            .filter(|c| background.is_none_or(|id| c.background_id == id))
+            .collect())
+    }
+
+    // the card a part is on: the one it says, or this one.
+    fn part_card(
+        &self,
+        of: Option<&Object>,
+        eval: &mut dyn Evaluate,
+    ) -> Result<&'a Card, ScriptError> {
+        let id = match of.map(|of| self.resolve(of, eval)).transpose()? {
+            None => self.card,
+            Some(ObjectId::Card(id)) => id,
+            Some(_) => return Err(ScriptError::new("No such card.")),
+        };
+        self.stack
+            .card(id)
+            .ok_or_else(|| ScriptError::new("No such card."))
+    }
+
+    // the buttons, fields or both in one layer of a card, in order.
+    fn parts(
+        &self,
+        card: &'a Card,
+        layer: Layer,
+        kind: PartKind,
+    ) -> Result<Vec<&'a Part>, ScriptError> {
+        let parts = match layer {
+            Layer::Card => &card.parts,
+            Layer::Background => match self.stack.background(card.background_id) {
+                Some(background) => &background.parts,
+                None => return Err(ScriptError::new("No such background.")),
+            },
+        };
+        Ok(parts
+            .iter()
+            .filter(|p| match kind {
+                PartKind::Button => matches!(p.ty, PartType::Button),
+                PartKind::Field => matches!(p.ty, PartType::Field),
+                PartKind::Part => true,
+            })
+            .collect())
+    }
+
+    // checks that a `stack` reference is this stack, the only one there is.
+    fn stack_named(&self, which: &Which, eval: &mut dyn Evaluate) -> Result<(), ScriptError> {
+        let found = match which {
+            Which::This => true,
+            Which::Value(name) => {
+                let name = eval.evaluate(name)?;
+                // a path names a stack too; it's the last part that has to match.
+                let name = name.rsplit([':', '/']).next().unwrap_or_default();
+                name.eq_ignore_ascii_case(self.stack_name)
+            }
+            _ => false,
+        };
+        if found {
+            Ok(())
+        } else {
+            Err(ScriptError::new("No such stack."))
+        }
+    }
+
+    fn in_this_stack(&self, of: &Object, eval: &mut dyn Evaluate) -> Result<(), ScriptError> {
+        match self.resolve(of, eval)? {
+            ObjectId::Stack => Ok(()),
+            _ => Err(ScriptError::new("No such stack.")),
+        }
+    }
+
+    // which one of a list of objects a reference means, counting from 1 and going by number, name
+    // or ID. `current` is where `this`, `next` and `prev` are, if anywhere.
+    fn pick<T>(
+        &self,
+        list: &[T],
+        which: &Which,
+        current: Option<usize>,
+        name: impl Fn(&T) -> &str,
+        id: impl Fn(&T) -> u32,
+        eval: &mut dyn Evaluate,
+    ) -> Result<Option<usize>, ScriptError> {
+        let len = list.len();
+        let number = match which {
+            Which::Value(value) => {
+                let value = eval.evaluate(value)?;
+                match whole_number(&value) {
+                    Some(n) => n,
+                    None => {
+                        return Ok(list
+                            .iter()
+                            .position(|a| name(a).eq_ignore_ascii_case(&value)))
+                    }
+                }
+            }
+            Which::Id(value) => {
+                let value = eval.evaluate(value)?;
+                return Ok(whole_number(&value)
+                    .and_then(|n| list.iter().position(|a| id(a) as usize == n)));
+            }
+            Which::Position(Position::Number(n)) => {
+                let n = eval.evaluate(n)?;
+                whole_number(&n).ok_or_else(|| ScriptError::new("Expected a number here."))?
+            }
+            Which::Position(Position::Last) => len,
+            Which::Position(Position::Middle) => chunk::middle(len),
+            Which::Position(Position::Any) if len > 0 => eval.random(len),
+            Which::Position(Position::Any) => 0,
+            Which::This => return Ok(current),
+            Which::Next => return Ok(current.map(|i| (i + 1) % len)),
+            Which::Previous => return Ok(current.map(|i| (i + len - 1) % len)),
+            Which::Recent => return Ok(None),
+        };
+        Ok((1..=len).contains(&number).then(|| number - 1))
+    }
+}
+
+fn default_layer(kind: PartKind) -> Layer {
+    match kind {
+        PartKind::Field => Layer::Background,
+        PartKind::Button | PartKind::Part => Layer::Card,
+    }
+}
+
+fn part_word(kind: PartKind) -> &'static str {
+    match kind {
+        PartKind::Button => "button",
+        PartKind::Field => "field",
+        PartKind::Part => "part",
+    }
+}
+
+fn whole_number(value: &str) -> Option<usize> {
+    let n: f64 = value.trim().parse().ok()?;
+    (n.is_finite() && n >= 0.0 && n.fract() == 0.0).then_some(n as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // numbers and strings are their own values, and `any` always picks the last one.
+    struct Literal;
+
+    impl Evaluate for Literal {
+        fn evaluate(&mut self, expr: &Expr) -> Result<String, ScriptError> {
+            match expr {
+                Expr::Number(s) | Expr::String(s) => Ok(s.clone()),
+                _ => Err(ScriptError::new("Not a literal.")),
+            }
+        }
+
+        fn random(&mut self, n: usize) -> usize {
+            n
+        }
+    }
+
+    fn part(id: u16, ty: PartType, name: &str) -> Part {
+        Part {
+            id,
+            ty,
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    fn card(id: u32, background_id: u32, name: &str) -> Card {
+        Card {
+            id,
+            background_id,
+            name: name.to_string(),
+            ..Default::default()
+        }
+    }
+
+    // two backgrounds; the first has cards 10 and 11, the second card 12.
+    fn stack() -> Stack {
+        let mut index = card(10, 1, "Index");
+        index.parts = vec![
+            part(1, PartType::Button, "OK"),
+            part(2, PartType::Field, "Notes"),
+            part(3, PartType::Button, "Cancel"),
+        ];
+        Stack {
+            backgrounds: vec![
+                Background {
+                    id: 1,
+                    name: "Front".to_string(),
+                    parts: vec![part(4, PartType::Field, "Title")],
+                    ..Default::default()
+                },
+                Background {
+                    id: 2,
+                    name: "Back".to_string(),
+                    ..Default::default()
+                },
+            ],
+            cards: vec![index, card(11, 1, ""), card(12, 2, "End")],
+            ..Default::default()
+        }
+    }
+
+    fn resolver(stack: &Stack, card: u32) -> Resolver<'_> {
+        Resolver {
+            stack,
+            stack_name: "Home",
+            card,
+            me: None,
+            target: None,
+            recent: None,
+        }
+    }
+
+    fn number(n: u32) -> Expr {
+        Expr::Number(n.to_string())
+    }
+
+    fn string(s: &str) -> Expr {
+        Expr::String(s.to_string())
+    }
+
+    fn card_ref(which: Which) -> Object {
+        Object::Card {
+            which,
+            marked: false,
+            of: None,
+        }
+    }
+
+    fn button(layer: Option<Layer>, which: Which) -> Object {
+        Object::Part {
+            kind: PartKind::Button,
+            layer,
+            which,
+            of: None,
+        }
+    }
+
+    fn field(layer: Option<Layer>, which: Which) -> Object {
+        Object::Part {
+            kind: PartKind::Field,
+            layer,
+            which,
+            of: None,
+        }
+    }
+
+    fn resolve(card: u32, object: Object) -> Result<ObjectId, ScriptError> {
+        let stack = stack();
+        resolver(&stack, card).resolve(&object, &mut Literal)
+    }
+
+    #[test]
+    fn cards_by_number_name_and_id() {
+        let by_number = card_ref(Which::Value(number(2)));
+        assert_eq!(resolve(10, by_number).unwrap(), ObjectId::Card(11));
+        let by_name = card_ref(Which::Value(string("end")));
+        assert_eq!(resolve(10, by_name).unwrap(), ObjectId::Card(12));
+        let by_id = card_ref(Which::Id(number(12)));
+        assert_eq!(resolve(10, by_id).unwrap(), ObjectId::Card(12));
+    }
+
+    #[test]
+    fn next_and_previous_wrap_around() {
+        assert_eq!(
+            resolve(12, card_ref(Which::Next)).unwrap(),
+            ObjectId::Card(10)
+        );
+        assert_eq!(
+            resolve(10, card_ref(Which::Previous)).unwrap(),
+            ObjectId::Card(12)
+        );
+        assert_eq!(
+            resolve(11, card_ref(Which::This)).unwrap(),
+            ObjectId::Card(11)
+        );
+    }
+
+    #[test]
+    fn last_middle_and_any() {
+        let last = card_ref(Which::Position(Position::Last));
+        assert_eq!(resolve(10, last).unwrap(), ObjectId::Card(12));
+        let middle = card_ref(Which::Position(Position::Middle));
+        assert_eq!(resolve(10, middle).unwrap(), ObjectId::Card(11));
+        let any = card_ref(Which::Position(Position::Any));
+        assert_eq!(resolve(10, any).unwrap(), ObjectId::Card(12));
+    }
+
+    #[test]
+    fn cards_of_a_background_count_within_it() {
+        let first = Object::Card {
+            which: Which::Value(number(1)),
+            marked: false,
+            of: Some(Box::new(Object::Background {
+                which: Which::Value(string("Back")),
+                of: None,
+            })),
+        };
+        assert_eq!(resolve(10, first).unwrap(), ObjectId::Card(12));
+    }
+
+    #[test]
+    fn marked_cards_of_another_background_go_by_stack_order() {
+        let mut stack = stack();
+        for card in &mut stack.cards[..2] {
+            card.marked = true;
+        }
+        let marked = |which| Object::Card {
+            which,
+            marked: true,
+            of: Some(Box::new(Object::Background {
+                which: Which::Value(string("Front")),
+                of: None,
+            })),
+        };
+        let resolve = |card, object| resolver(&stack, card).resolve(&object, &mut Literal);
+        // card 12 is on the other background, after both of them.
+        assert_eq!(
+            resolve(12, marked(Which::Next)).unwrap(),
+            ObjectId::Card(10)
+        );
+        assert_eq!(
+            resolve(12, marked(Which::Previous)).unwrap(),
+            ObjectId::Card(11)
+        );
+        assert_eq!(
+            resolve(10, marked(Which::Next)).unwrap(),
+            ObjectId::Card(11)
+        );
+    }
+
+    #[test]
+    fn this_background_is_the_shown_cards() {
+        let this = Object::Background {
+            which: Which::This,
+            of: None,
+        };
+        assert_eq!(resolve(12, this).unwrap(), ObjectId::Background(2));
+    }
+
+    #[test]
+    fn buttons_count_only_buttons() {
+        assert_eq!(
+            resolve(10, button(None, Which::Value(number(2)))).unwrap(),
+            ObjectId::Part {
+                layer: Layer::Card,
+                card: 10,
+                id: 3
+            }
+        );
+    }
+
+    #[test]
+    fn fields_are_background_fields_unless_it_says_card() {
+        assert_eq!(
+            resolve(10, field(None, Which::Value(number(1)))).unwrap(),
+            ObjectId::Part {
+                layer: Layer::Background,
+                card: 10,
+                id: 4
+            }
+        );
+        assert_eq!(
+            resolve(10, field(Some(Layer::Card), Which::Value(number(1)))).unwrap(),
+            ObjectId::Part {
+                layer: Layer::Card,
+                card: 10,
+                id: 2
+            }
+        );
+    }
+
+    #[test]
+    fn parts_of_another_card() {
+        let object = Object::Part {
+            kind: PartKind::Field,
+            layer: None,
+            which: Which::Value(string("title")),
+            of: Some(Box::new(card_ref(Which::Id(number(11))))),
+        };
+        assert_eq!(
+            resolve(10, object).unwrap(),
+            ObjectId::Part {
+                layer: Layer::Background,
+                card: 11,
+                id: 4
+            }
+        );
+    }
+
+    #[test]
+    fn stacks_go_by_the_last_part_of_the_path() {
+        let stack = Object::Stack(Which::Value(string("Disk:Stacks:home")));
+        assert_eq!(resolve(10, stack).unwrap(), ObjectId::Stack);
+        let other = Object::Stack(Which::Value(string("Addresses")));
+        assert_eq!(
+            resolve(10, other).unwrap_err().to_string(),
+            "No such stack."
+        );
+    }
+
+    #[test]
+    fn missing_objects_are_errors() {
+        let card = card_ref(Which::Value(number(4)));
+        assert_eq!(resolve(10, card).unwrap_err().to_string(), "No such card.");
+        let button = button(None, Which::Value(string("Help")));
+        assert_eq!(
+            resolve(10, button).unwrap_err().to_string(),
+            "No such button."
+        );
+        let recent = card_ref(Which::Recent);
+        assert_eq!(
+            resolve(10, recent).unwrap_err().to_string(),
+            "No such card."
+        );
+    }
+
+    #[test]
+    fn counts_objects() {
+        let stack = stack();
+        let resolver = resolver(&stack, 10);
+        let count = |objects, of: Option<&Object>| resolver.count(objects, of, &mut Literal);
+        assert_eq!(count(Objects::Cards { marked: false }, None).unwrap(), 3);
+        assert_eq!(count(Objects::Backgrounds, None).unwrap(), 2);
+        let buttons = Objects::Parts {
+            kind: PartKind::Button,
+            layer: None,
+        };
+        assert_eq!(count(buttons, None).unwrap(), 2);
+        let fields = Objects::Parts {
+            kind: PartKind::Field,
+            layer: None,
+        };
+        let end = card_ref(Which::Id(number(12)));
+        assert_eq!(count(fields, Some(&end)).unwrap(), 0);
+    }
+}
@@ -0,0 +1,599 @@
+use crate::ast::*;
+use crate::error::ScriptError;
+use crate::lexer::{tokenize, Keyword, Operator, Span, Token, TokenKind};
+
+mod command;
+mod expression;
+mod object;
+
+//
+// Parses the tokens of a script into handlers and their statements. Anything outside a handler
+// is ignored, the way HyperCard ignores it.
+//
+
+impl Script {
+    pub fn parse(source: &str) -> Result<Script, ScriptError> {
+        let mut parser = Parser::new(tokenize(source)?);
+        parser.script()
+    }
+}
+
+impl Statement {
+    /// Parse statements that aren't in a handler, as `do` needs to.
+    pub fn parse_all(source: &str) -> Result<Vec<Statement>, ScriptError> {
+        let mut parser = Parser::new(tokenize(source)?);
+        let statements = parser.block()?;
+        match parser.peek() {
+            None => Ok(statements),
+            Some(_) => Err(parser.expected("a statement")),
+        }
+    }
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    pos: usize,
+    // whether `name of x` can be read as `the name of x`. It can't in a chunk or object number,
+    // where `line n of x` is line n of x.
+    bare_of: bool,
+}
+
+impl Parser {
+    fn new(tokens: Vec<Token>) -> Self {
+        Parser {
+            tokens,
+            pos: 0,
+            bare_of: true,
+        }
+    }
+
+    fn script(&mut self) -> Result<Script, ScriptError> {
+        let mut handlers = Vec::new();
+        loop {
+            self.skip_line_ends();
+            match self.peek() {
+                None => break,
+                Some(t) if t.is_keyword(Keyword::On) => {
+                    handlers.push(self.handler(HandlerKind::Message)?)
+                }
+                Some(t) if t.is_keyword(Keyword::Function) => {
+                    handlers.push(self.handler(HandlerKind::Function)?)
+                }
+                Some(_) => self.skip_line(),
+            }
+        }
+        Ok(Script { handlers })
+    }
+
+    fn handler(&mut self, kind: HandlerKind) -> Result<Handler, ScriptError> {
+        let span = self.span();
+        self.pos += 1;
+        let name = self.name()?;
+        let mut params = Vec::new();
+        if !self.at_line_end() {
+            params.push(self.name()?);
+            while self.eat_operator(Operator::Comma) {
+                params.push(self.name()?);
+            }
+        }
+        self.expect_line_end()?;
+
+        let body = self.block()?;
+        self.expect_end(&name)?;
+        Ok(Handler {
+            kind,
+            name,
+            params,
+            body,
+            span,
+        })
+    }
+
+    // statements up to an `end` or `else`, which is left for the caller.
+    fn block(&mut self) -> Result<Vec<Statement>, ScriptError> {
+        let mut body = Vec::new();
+        loop {
+            self.skip_line_ends();
+            match self.peek() {
+                None => return Ok(body),
+                Some(t)
+                    if t.is_keyword(Keyword::End)
+                        || t.is_keyword(Keyword::Else)
+                        || t.is_keyword(Keyword::On)
+                        || t.is_keyword(Keyword::Function) =>
+                {
+                    return Ok(body)
+                }
+                Some(_) => {}
+            }
+            body.push(self.statement()?);
+            self.expect_line_end()?;
+        }
+    }
+
+    fn statement(&mut self) -> Result<Statement, ScriptError> {
+        let span = self.span();
+        let token = match self.peek() {
+            Some(t) => t.clone(),
+            None => return Err(self.error("Expected a statement")),
+        };
+        let kind = match &token.kind {
+            TokenKind::Keyword(Keyword::If) => StatementKind::If(self.if_statement()?),
+            TokenKind::Keyword(Keyword::Repeat) => StatementKind::Repeat(self.repeat()?),
+            TokenKind::Keyword(Keyword::Next) => {
+                self.pos += 1;
+                self.expect_word("repeat")?;
+                StatementKind::NextRepeat
+            }
+            TokenKind::Keyword(Keyword::Exit) => {
+                self.pos += 1;
+                if self.eat_word("repeat") {
+                    StatementKind::ExitRepeat
+                } else if self.eat_word("to") {
+                    self.expect_word("HyperCard")?;
+                    StatementKind::ExitToHyperCard
+                } else {
+                    StatementKind::Exit(self.name()?)
+                }
+            }
+            TokenKind::Keyword(Keyword::Pass) => {
+                self.pos += 1;
+                StatementKind::Pass(self.name()?)
+            }
+            TokenKind::Keyword(Keyword::Return) => {
+                self.pos += 1;
+                if self.at_statement_end() {
+                    StatementKind::Return(None)
+                } else {
+                    StatementKind::Return(Some(self.expression()?))
+                }
+            }
+            TokenKind::Keyword(Keyword::Global) => {
+                self.pos += 1;
+                let mut names = vec![self.name()?];
+                while self.eat_operator(Operator::Comma) {
+                    names.push(self.name()?);
+                }
+                StatementKind::Global(names)
+            }
+            TokenKind::Identifier(name) => {
+                self.pos += 1;
+                StatementKind::Command(self.command(name)?)
+            }
+            kind => {
+                return Err(ScriptError::at(
+                    format!("Can't understand \"{}\"", kind),
+                    span,
+                ))
+            }
+        };
+        Ok(Statement { kind, span })
+    }
+
+    fn if_statement(&mut self) -> Result<If, ScriptError> {
+        self.pos += 1;
+        // the condition can go on to the next line, as long as that line starts with `then`.
+        let condition = self.expression()?;
+        if self.at_line_end() {
+            self.skip_line_ends();
+        }
+        if !self.eat_keyword(Keyword::Then) {
+            return Err(self.expected("\"then\""));
+        }
+
+        if self.at_line_end() {
+            let then = self.block()?;
+            let otherwise = if self.eat_keyword(Keyword::Else) {
+                Some(self.else_branch()?)
+            } else {
+                self.expect_end("if")?;
+                None
+            };
+            return Ok(If {
+                condition,
+                then,
+                otherwise,
+            });
+        }
+
+        let then = vec![self.statement()?];
+        // an else can follow on the same line or start the next one.
+        let otherwise = if self.eat_keyword(Keyword::Else) || self.else_on_next_line() {
+            Some(self.else_branch()?)
+        } else {
+            None
+        };
+        Ok(If {
+            condition,
+            then,
+            otherwise,
+        })
+    }
+
+    fn else_branch(&mut self) -> Result<Vec<Statement>, ScriptError> {
+        if self.at_line_end() {
+            let body = self.block()?;
+            self.expect_end("if")?;
+            Ok(body)
+        } else {
+            Ok(vec![self.statement()?])
+        }
+    }
+
+    // whether the next line starts with `else`, in which case it's been stepped over.
+    fn else_on_next_line(&mut self) -> bool {
+        let mut pos = self.pos;
+        while matches!(self.tokens.get(pos), Some(t) if t.kind == TokenKind::LineEnd) {
+            pos += 1;
+        }
+        if pos > self.pos && matches!(self.tokens.get(pos), Some(t) if t.is_keyword(Keyword::Else))
+        {
+            self.pos = pos + 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn repeat(&mut self) -> Result<Repeat, ScriptError> {
+        self.pos += 1;
+        let kind = if self.at_line_end() || self.eat_word("forever") {
+            RepeatKind::Forever
+        } else if self.eat_word("while") {
+            RepeatKind::While(self.expression()?)
+        } else if self.eat_word("until") {
+            RepeatKind::Until(self.expression()?)
+        } else if self.eat_word("with") {
+            let variable = self.name()?;
+            if !self.eat_operator(Operator::Equal) {
+                return Err(self.expected("\"=\""));
+            }
+            let from = self.expression()?;
+            let down = self.eat_word("down");
+            self.expect_word("to")?;
+            let to = self.expression()?;
+            RepeatKind::With {
+                variable,
+                from,
+                to,
+                down,
+            }
+        } else {
+            self.eat_word("for");
+            let count = self.expression()?;
+            self.eat_word("times");
+            RepeatKind::Times(count)
+        };
+        self.expect_line_end()?;
+        let body = self.block()?;
+        self.expect_end("repeat")?;
+        Ok(Repeat { kind, body })
+    }
+
+    // `end <name>`, where the name is the handler's or the structure's.
+    fn expect_end(&mut self, name: &str) -> Result<(), ScriptError> {
+        let found = self.pos;
+        if self.eat_keyword(Keyword::End) && self.eat_word(name) {
+            return Ok(());
+        }
+        self.pos = found;
+        match self.peek() {
+            Some(t) if t.is_keyword(Keyword::Else) => {
+                Err(ScriptError::at("Found \"else\" without \"if\"", t.span))
+            }
+            Some(t) if t.is_keyword(Keyword::End) => {
+                let found = match self.tokens.get(self.pos + 1) {
+                    Some(next) if next.kind != TokenKind::LineEnd => format!("end {}", next.kind),
+                    _ => "end".to_string(),
+                };
+                Err(ScriptError::at(
+                    format!("Expected \"end {}\" but found \"{}\"", name, found),
+                    t.span,
+                ))
+            }
+            _ => Err(self.expected(&format!("\"end {}\"", name))),
+        }
+    }
+
+    // the tokens up to the end of the statement: the end of the line, or an `else`.
+    fn rest_of_statement(&mut self) -> Vec<Token> {
+        let mut tokens = Vec::new();
+        while let Some(t) = self.peek() {
+            if t.kind == TokenKind::LineEnd || t.is_keyword(Keyword::Else) {
+                break;
+            }
+            tokens.push(t.clone());
+            self.pos += 1;
+        }
+        tokens
+    }
+
+    fn name(&mut self) -> Result<String, ScriptError> {
+        match self.peek() {
+            Some(Token {
+                kind: TokenKind::Identifier(name),
+                ..
+            }) => {
+                let name = name.clone();
+                self.pos += 1;
+                Ok(name)
+            }
+            _ => Err(self.expected("a name")),
+        }
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn at_line_end(&self) -> bool {
+        matches!(
+            self.peek(),
+            None | Some(Token {
+                kind: TokenKind::LineEnd,
+                ..
+            })
+        )
+    }
+
+    fn at_statement_end(&self) -> bool {
+        self.at_line_end() || matches!(self.peek(), Some(t) if t.is_keyword(Keyword::Else))
+    }
+
+    fn skip_line_ends(&mut self) {
+        while matches!(self.peek(), Some(t) if t.kind == TokenKind::LineEnd) {
+            self.pos += 1;
+        }
+    }
+
+    fn skip_line(&mut self) {
+        while let Some(t) = self.peek() {
+            let line_end = t.kind == TokenKind::LineEnd;
+            self.pos += 1;
+            if line_end {
+                break;
+            }
+        }
+    }
+
+    fn expect_line_end(&mut self) -> Result<(), ScriptError> {
+        match self.peek() {
+            None => Ok(()),
+            Some(t) if t.kind == TokenKind::LineEnd => {
+                self.pos += 1;
+                Ok(())
+            }
+            Some(_) => Err(self.expected("end of line")),
+        }
+    }
+
+    fn eat_keyword(&mut self, keyword: Keyword) -> bool {
+        let found = matches!(self.peek(), Some(t) if t.is_keyword(keyword));
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn eat_word(&mut self, word: &str) -> bool {
+        let found = matches!(self.peek(), Some(t) if t.is_word(word));
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn eat_operator(&mut self, operator: Operator) -> bool {
+        let found = matches!(self.peek(), Some(t) if t.is_operator(operator));
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    fn expect_word(&mut self, word: &str) -> Result<(), ScriptError> {
+        if self.eat_word(word) {
+            Ok(())
+        } else {
+            Err(self.expected(&format!("\"{}\"", word)))
+        }
+    }
+
+    // where the parser is, for errors; the end of the last token if it's run out.
+    fn span(&self) -> Span {
+        match self.peek() {
+            Some(t) => t.span,
+            None => self
+                .tokens
+                .last()
+                .map(|t| Span {
+                    start: t.span.end,
+                    ..t.span
+                })
+                .unwrap_or_default(),
+        }
+    }
+
+    fn error(&self, message: &str) -> ScriptError {
+        ScriptError::at(message, self.span())
+    }
+
+    fn expected(&self, what: &str) -> ScriptError {
+        let found = match self.peek() {
+            Some(t) if t.kind == TokenKind::LineEnd => "end of line".to_string(),
+            Some(t) => format!("\"{}\"", t.kind),
+            None => "end of script".to_string(),
+        };
+        ScriptError::at(
+            format!("Expected {} but found {}", what, found),
+            self.span(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the statements of a handler with the given body.
+    fn body(source: &str) -> Vec<Statement> {
+        let script = Script::parse(&format!("on test\r{}\rend test", source)).unwrap();
+        script.handlers[0].body.clone()
+    }
+
+    fn command_name(statement: &Statement) -> &str {
+        match &statement.kind {
+            StatementKind::Command(command) => match command {
+                Command::Put { .. } => "put",
+                Command::Message { name, .. } | Command::Unparsed { name, .. } => name,
+                command => panic!("unexpected command {:?}", command),
+            },
+            kind => panic!("expected a command, found {:?}", kind),
+        }
+    }
+
+    #[test]
+    fn handlers_and_their_params() {
+        let script = Script::parse(
+            "-- outside any handler\r\
+             on mouseUp a, b\r  beep\rend mouseUp\r\
+             this line is ignored\r\
+             function double x\r  return x * 2\rend double",
+        )
+        .unwrap();
+        let [up, double] = &script.handlers[..] else {
+            panic!("expected two handlers, found {:?}", script.handlers);
+        };
+        assert_eq!(
+            (up.kind, up.name.as_str()),
+            (HandlerKind::Message, "mouseUp")
+        );
+        assert_eq!(up.params, ["a", "b"]);
+        assert_eq!((double.kind, double.span.line), (HandlerKind::Function, 6));
+        assert!(script.handler(HandlerKind::Message, "MOUSEUP").is_some());
+        assert!(script.handler(HandlerKind::Function, "mouseUp").is_none());
+    }
+
+    #[test]
+    fn convert_to_more_than_one_format() {
+        let command = Command::parse("convert x to abbr date and long time").unwrap();
+        let Command::Convert { container, to } = command else {
+            panic!("expected convert, found {:?}", command);
+        };
+        assert_eq!(container, Container::Variable("x".to_string()));
+        assert_eq!(
+            to,
+            [
+                DateFormat::Date(Some(Modifier::Abbreviated)),
+                DateFormat::Time(Some(Modifier::Long)),
+            ]
+        );
+        assert!(Command::parse("convert x to tomorrow").is_err());
+    }
+
+    #[test]
+    fn visual_effects_have_to_be_known() {
+        let error = Command::parse("visual effect sideways").unwrap_err();
+        assert_eq!(error.message, "Expected a visual effect");
+        assert!(Command::parse("go next with visual effect sideways").is_err());
+        assert!(Command::parse("unlock screen with visual iris open to black").is_ok());
+    }
+
+    #[test]
+    fn single_line_if_else() {
+        let statements = body("if x then greet else put 1");
+        let StatementKind::If(If {
+            then, otherwise, ..
+        }) = &statements[0].kind
+        else {
+            panic!("expected an if, found {:?}", statements[0]);
+        };
+        assert_eq!(command_name(&then[0]), "greet");
+        assert_eq!(command_name(&otherwise.as_ref().unwrap()[0]), "put");
+    }
+
+    #[test]
+    fn else_on_the_next_line_belongs_to_the_if() {
+        let statements = body("if x then beep\r\r  else put 1\rbeep");
+        assert_eq!(statements.len(), 2);
+        assert!(matches!(
+            &statements[0].kind,
+            StatementKind::If(If {
+                otherwise: Some(_),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn else_if_chains_nest() {
+        let statements = body("if x then\rbeep\relse if y then\rput 1\relse\rput 2\rend if");
+        let StatementKind::If(If {
+            otherwise: Some(otherwise),
+            ..
+        }) = &statements[0].kind
+        else {
+            panic!("expected an if with an else, found {:?}", statements[0]);
+        };
+        assert!(matches!(
+            &otherwise[..],
+            [Statement {
+                kind: StatementKind::If(_),
+                ..
+            }]
+        ));
+    }
+
+    #[test]
+    fn repeat_forms() {
+        let kind = |source: &str| match &body(source)[0].kind {
+            StatementKind::Repeat(repeat) => repeat.kind.clone(),
+            kind => panic!("expected a repeat, found {:?}", kind),
+        };
+        assert!(matches!(kind("repeat\rend repeat"), RepeatKind::Forever));
+        assert!(matches!(
+            kind("repeat for 3 times\rend repeat"),
+            RepeatKind::Times(_)
+        ));
+        assert!(matches!(
+            kind("repeat until x\rend repeat"),
+            RepeatKind::Until(_)
+        ));
+        assert!(matches!(
+            kind("repeat with i = 3 down to 1\rend repeat"),
+            RepeatKind::With { down: true, .. }
+        ));
+    }
+
+    #[test]
+    fn exit_pass_return_and_global() {
+        let statements = body("exit to HyperCard\rpass test\rreturn\rglobal a, b");
+        assert!(matches!(statements[0].kind, StatementKind::ExitToHyperCard));
+        assert!(matches!(&statements[1].kind, StatementKind::Pass(name) if name == "test"));
+        assert!(matches!(statements[2].kind, StatementKind::Return(None)));
+        assert!(
+            matches!(&statements[3].kind, StatementKind::Global(names) if names == &["a", "b"])
+        );
+    }
+
+    #[test]
+    fn mismatched_end() {
+        let error = Script::parse("on a\rbeep\rend b").unwrap_err();
+        assert_eq!(error.message, "Expected \"end a\" but found \"end b\"");
+        assert_eq!(error.span.map(|s| s.line), Some(3));
+    }
+
+    #[test]
+    fn missing_end_if() {
+        let error = Script::parse("on a\rif x then\rbeep\rend a").unwrap_err();
+        assert_eq!(error.message, "Expected \"end if\" but found \"end a\"");
+        assert_eq!(error.span.map(|s| s.line), Some(4));
+    }
+
+    #[test]
+    fn else_without_if() {
+        let error = Script::parse("on a\relse\rend a").unwrap_err();
+        assert_eq!(error.message, "Found \"else\" without \"if\"");
+        assert_eq!(error.span.map(|s| s.line), Some(2));
+    }
+}
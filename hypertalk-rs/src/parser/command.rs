@@ -0,0 +1,535 @@
+use hc_render::transition::VisualEffect;
+
+use crate::ast::{
+    BinaryOp, Command, Container, DateFormat, Destination, Expr, Keys, Lockable, Markable, Object,
+    Preposition, Selectable, Showable, Wait, Which, Window,
+};
+use crate::error::ScriptError;
+use crate::lexer::{tokenize, Keyword, Operator, Span, TokenKind};
+
+use super::expression::modifier;
+use super::Parser;
+
+//
+// Commands. HyperCard's own commands each have their own syntax; any other command is a message
+// with its arguments separated by commas, except the ones of HyperCard's that aren't run yet,
+// which are kept as they were written.
+//
+
+// HyperCard's commands that aren't run yet.
+const UNPARSED: [&str; 22] = [
+    "answer", "ask", "close", "create", "dial", "disable", "doMenu", "edit", "enable", "find",
+    "flash", "mark", "open", "play", "print", "read", "reset", "sort", "unmark", "visual", "write",
+    "palette",
+];
+
+impl Command {
+    /// Parse a command on its own, as `send` needs to.
+    pub fn parse(source: &str) -> Result<Command, ScriptError> {
+        let mut parser = Parser::new(tokenize(source)?);
+        parser.skip_line_ends();
+        let name = match parser.peek().map(|t| &t.kind) {
+            Some(TokenKind::Identifier(name)) => name.clone(),
+            _ => return Err(parser.expected("a command")),
+        };
+        parser.pos += 1;
+        let command = parser.command(&name)?;
+        parser.skip_line_ends();
+        match parser.peek() {
+            None => Ok(command),
+            Some(_) => Err(parser.expected("end of command")),
+        }
+    }
+}
+
+impl Parser {
+    // after the command's name.
+    pub(super) fn command(&mut self, name: &str) -> Result<Command, ScriptError> {
+        if name.eq_ignore_ascii_case("put") {
+            let value = self.expression()?;
+            let into = if self.at_statement_end() {
+                None
+            } else {
+                Some((self.preposition()?, self.container()?))
+            };
+            Ok(Command::Put { value, into })
+        } else if name.eq_ignore_ascii_case("get") {
+            Ok(Command::Get(self.expression()?))
+        } else if name.eq_ignore_ascii_case("set") {
+            self.eat_keyword(Keyword::The);
+            let name = self.name()?;
+            let of = if self.eat_keyword(Keyword::Of) {
+                Some(self.expression()?)
+            } else {
+                None
+            };
+            self.expect_word("to")?;
+            let value = self.list()?;
+            Ok(Command::Set { name, of, value })
+        } else if name.eq_ignore_ascii_case("send") {
+            let message = self.expression()?;
+            let to = if self.eat_word("to") {
+                let span = self.span();
+                match self.expression()? {
+                    Expr::Object(object) => Some(*object),
+                    _ => return Err(ScriptError::at("Expected an object to send to", span)),
+                }
+            } else {
+                None
+            };
+            Ok(Command::Send { message, to })
+        } else if name.eq_ignore_ascii_case("go") {
+            let destination = self.destination()?;
+            let effect = self.visual_effect()?;
+            Ok(Command::Go {
+                destination,
+                effect,
+            })
+        } else if let Some(op) = arithmetic(name) {
+            self.arithmetic(op)
+        } else if name.eq_ignore_ascii_case("show") || name.eq_ignore_ascii_case("hide") {
+            self.show(name.eq_ignore_ascii_case("show"))
+        } else if name.eq_ignore_ascii_case("lock") || name.eq_ignore_ascii_case("unlock") {
+            let what = if self.eat_word("screen") {
+                Lockable::Screen
+            } else if self.eat_word("messages") {
+                Lockable::Messages
+            } else if self.eat_word("recent") {
+                Lockable::Recent
+            } else {
+                return Err(self.expected("\"screen\", \"messages\" or \"recent\""));
+            };
+            let effect = self.visual_effect()?;
+            Ok(Command::Lock {
+                what,
+                locked: name.eq_ignore_ascii_case("lock"),
+                effect,
+            })
+        } else if name.eq_ignore_ascii_case("push") {
+            let card = if self.at_object() {
+                self.object()?
+            } else {
+                if !self.eat_word("card") {
+                    self.eat_word("cd");
+                }
+                Object::Card {
+                    which: Which::This,
+                    marked: false,
+                    of: None,
+                }
+            };
+            Ok(Command::Push(card))
+        } else if name.eq_ignore_ascii_case("pop") {
+            if !self.eat_word("card") && !self.eat_word("cd") {
+                return Err(self.expected("\"card\""));
+            }
+            let into = if self.at_statement_end() {
+                None
+            } else {
+                Some((self.preposition()?, self.container()?))
+            };
+            Ok(Command::Pop(into))
+        } else if name.eq_ignore_ascii_case("beep") {
+            let times = if self.at_statement_end() {
+                None
+            } else {
+                Some(self.expression()?)
+            };
+            Ok(Command::Beep(times))
+        } else if name.eq_ignore_ascii_case("wait") {
+            let wait = if self.eat_word("until") {
+                Wait::Until(self.expression()?)
+            } else if self.eat_word("while") {
+                Wait::While(self.expression()?)
+            } else {
+                self.eat_word("for");
+                let time = self.expression()?;
+                let seconds = ["seconds", "second", "secs", "sec"]
+                    .iter()
+                    .any(|unit| self.eat_word(unit));
+                if !seconds && !self.eat_word("ticks") {
+                    self.eat_word("tick");
+                }
+                Wait::For { time, seconds }
+            };
+            Ok(Command::Wait(wait))
+        } else if name.eq_ignore_ascii_case("do") {
+            Ok(Command::Do(self.expression()?))
+        } else if name.eq_ignore_ascii_case("delete") {
+            Ok(Command::Delete(self.container()?))
+        } else if name.eq_ignore_ascii_case("select") {
+            self.select()
+        } else if name.eq_ignore_ascii_case("type") {
+            let text = self.expression()?;
+            let keys = self.keys()?;
+            Ok(Command::Type { text, keys })
+        } else if name.eq_ignore_ascii_case("click") {
+            self.expect_word("at")?;
+            let at = self.list()?;
+            let keys = self.keys()?;
+            Ok(Command::Click { at, keys })
+        } else if name.eq_ignore_ascii_case("drag") {
+            self.expect_word("from")?;
+            let from = self.list()?;
+            self.expect_word("to")?;
+            let to = self.list()?;
+            let keys = self.keys()?;
+            Ok(Command::Drag { from, to, keys })
+        } else if name.eq_ignore_ascii_case("choose") {
+            // `choose tool 3`, or the tool's name, which can be more than one word, then `tool`.
+            if self.eat_word("tool") {
+                return Ok(Command::Choose(self.expression()?));
+            }
+            let mut words = Vec::new();
+            while !self.eat_word("tool") {
+                match self.peek().map(|t| &t.kind) {
+                    Some(TokenKind::Identifier(word)) => words.push(word.clone()),
+                    _ => return Err(self.expected("\"tool\"")),
+                }
+                self.pos += 1;
+            }
+            Ok(Command::Choose(Expr::String(words.join(" "))))
+        } else if name.eq_ignore_ascii_case("convert") {
+            let container = self.container()?;
+            self.expect_word("to")?;
+            let mut to = vec![self.date_format()?];
+            while self.eat_keyword(Keyword::And) {
+                to.push(self.date_format()?);
+            }
+            Ok(Command::Convert { container, to })
+        } else if name.eq_ignore_ascii_case("visual") {
+            Ok(Command::Visual(self.effect()?))
+        } else if name.eq_ignore_ascii_case("mark") || name.eq_ignore_ascii_case("unmark") {
+            let cards = if self.eat_word("all") {
+                if !self.eat_word("cards") && !self.eat_word("cds") {
+                    return Err(self.expected("\"cards\""));
+                }
+                Markable::All
+            } else if self.eat_word("cards") || self.eat_word("cds") {
+                self.expect_word("where")?;
+                Markable::Where(self.expression()?)
+            } else if self.at_object() {
+                Markable::Card(self.object()?)
+            } else {
+                if !self.eat_word("card") {
+                    self.eat_word("cd");
+                }
+                Markable::Card(Object::Card {
+                    which: Which::This,
+                    marked: false,
+                    of: None,
+                })
+            };
+            Ok(Command::Mark {
+                marked: name.eq_ignore_ascii_case("mark"),
+                cards,
+            })
+        } else if UNPARSED.iter().any(|c| c.eq_ignore_ascii_case(name)) {
+            Ok(Command::Unparsed {
+                name: name.to_string(),
+                args: self.rest_of_statement(),
+            })
+        } else {
+            let mut args = Vec::new();
+            if !self.at_statement_end() {
+                args.push(self.expression()?);
+                while self.eat_operator(Operator::Comma) {
+                    args.push(self.expression()?);
+                }
+            }
+            Ok(Command::Message {
+                name: name.to_string(),
+                args,
+            })
+        }
+    }
+
+    fn preposition(&mut self) -> Result<Preposition, ScriptError> {
+        if self.eat_word("into") {
+            Ok(Preposition::Into)
+        } else if self.eat_word("before") {
+            Ok(Preposition::Before)
+        } else if self.eat_word("after") {
+            Ok(Preposition::After)
+        } else {
+            Err(self.expected("\"into\", \"before\" or \"after\""))
+        }
+    }
+
+    // values separated by commas, which make one list: `set the loc of btn 1 to 100, 200`,
+    // `click at 10, 20`.
+    fn list(&mut self) -> Result<Expr, ScriptError> {
+        let mut value = self.expression()?;
+        while self.eat_operator(Operator::Comma) {
+            let comma = Expr::Binary(BinaryOp::Concat, Box::new(value), Box::new(comma()));
+            value = Expr::Binary(
+                BinaryOp::Concat,
+                Box::new(comma),
+                Box::new(self.expression()?),
+            );
+        }
+        Ok(value)
+    }
+
+    // after `go`: `[to] card 3`, `next`, `back`, `home`, `stack "Home"` or a stack's name.
+    fn destination(&mut self) -> Result<Destination, ScriptError> {
+        self.eat_word("to");
+        if self.eat_word("back") {
+            return Ok(Destination::Back);
+        }
+        if self.eat_word("home") {
+            return Ok(Destination::Home);
+        }
+        self.eat_keyword(Keyword::The);
+        if let Some(card) = self.bare_card() {
+            return Ok(Destination::Object(card));
+        }
+        let span = self.span();
+        if self.at_object() {
+            return match self.object()? {
+                object @ (Object::Card { .. } | Object::Background { .. } | Object::Stack(_)) => {
+                    Ok(Destination::Object(object))
+                }
+                _ => Err(ScriptError::at(
+                    "Expected a card, background or stack to go to",
+                    span,
+                )),
+            };
+        }
+        let name = self.without_bare_of(Self::primary)?;
+        Ok(Destination::Object(Object::Stack(Which::Value(name))))
+    }
+
+    // `with visual effect dissolve` after `go` or `unlock screen`.
+    fn visual_effect(&mut self) -> Result<Option<VisualEffect>, ScriptError> {
+        let start = self.pos;
+        if self.eat_word("with") && self.eat_word("visual") {
+            Ok(Some(self.effect()?))
+        } else {
+            self.pos = start;
+            Ok(None)
+        }
+    }
+
+    // the rest of the line after `visual`, such as `effect iris open slowly to black`.
+    fn effect(&mut self) -> Result<VisualEffect, ScriptError> {
+        let span = self.span();
+        let words: Vec<String> = self
+            .rest_of_statement()
+            .iter()
+            .map(|t| t.kind.to_string())
+            .collect();
+        VisualEffect::parse(&words.join(" "))
+            .ok_or_else(|| ScriptError::at("Expected a visual effect", span))
+    }
+
+    // after `add`, `subtract`, `multiply` or `divide`.
+    fn arithmetic(&mut self, op: BinaryOp) -> Result<Command, ScriptError> {
+        let (value, container) = match op {
+            BinaryOp::Add | BinaryOp::Subtract => {
+                let value = self.expression()?;
+                self.expect_word(if op == BinaryOp::Add { "to" } else { "from" })?;
+                (value, self.container()?)
+            }
+            _ => {
+                let container = self.container()?;
+                self.expect_word("by")?;
+                (self.expression()?, container)
+            }
+        };
+        Ok(Command::Arithmetic {
+            op,
+            value,
+            container,
+        })
+    }
+
+    // after `show` or `hide`.
+    fn show(&mut self, visible: bool) -> Result<Command, ScriptError> {
+        let this = |which| Object::Card {
+            which,
+            marked: false,
+            of: None,
+        };
+        let what = if self.eat_word("menuBar") {
+            Showable::MenuBar
+        } else if self.eat_word("picture") || self.eat_word("pict") {
+            if !self.eat_keyword(Keyword::Of) {
+                return Err(self.expected("\"of\""));
+            }
+            Showable::Picture(self.object()?)
+        } else if self.picture_of(&["card", "cd"]) {
+            Showable::Picture(this(Which::This))
+        } else if self.picture_of(&["background", "bkgnd", "bkgd", "bg"]) {
+            Showable::Picture(Object::Background {
+                which: Which::This,
+                of: None,
+            })
+        } else if self.at_object() {
+            Showable::Object(self.object()?)
+        } else if (self.eat_word("msg") || self.eat_word("message")) && self.at_statement_end() {
+            Showable::Object(Object::Window(Window::Message))
+        } else if visible {
+            // `show 5 cards`, `show all cards`, `show marked cards`, `show cards`.
+            let marked = self.eat_word("marked");
+            let count = if marked || self.eat_word("all") || self.at_cards() {
+                None
+            } else {
+                Some(self.expression()?)
+            };
+            if !self.eat_word("cards") && !self.eat_word("cds") {
+                return Err(self.expected("\"cards\""));
+            }
+            return Ok(Command::ShowCards { count, marked });
+        } else {
+            return Err(self.expected("an object"));
+        };
+        let at = if visible && self.eat_word("at") {
+            Some(self.list()?)
+        } else {
+            None
+        };
+        Ok(Command::Show { visible, what, at })
+    }
+
+    // `card picture` or `bkgnd picture`, which is stepped over if it's there.
+    fn picture_of(&mut self, nouns: &[&str]) -> bool {
+        let at = |n: usize| self.tokens.get(self.pos + n);
+        let found = matches!(at(0), Some(t) if nouns.iter().any(|w| t.is_word(w)))
+            && matches!(at(1), Some(t) if t.is_word("picture") || t.is_word("pict"));
+        if found {
+            self.pos += 2;
+        }
+        found
+    }
+
+    fn at_cards(&self) -> bool {
+        matches!(self.peek(), Some(t) if t.is_word("cards") || t.is_word("cds"))
+    }
+
+    // after `select`.
+    fn select(&mut self) -> Result<Command, ScriptError> {
+        if self.eat_word("empty") {
+            return Ok(Command::Select(Selectable::Empty));
+        }
+        let preposition = if self.eat_word("before") {
+            Some(Preposition::Before)
+        } else if self.eat_word("after") {
+            Some(Preposition::After)
+        } else {
+            None
+        };
+        // `text of fld 1`: all of the field's text.
+        let start = self.pos;
+        if self.eat_word("text") && self.eat_keyword(Keyword::Of) && self.at_object() {
+            return Ok(Command::Select(Selectable::Text {
+                preposition: preposition.unwrap_or(Preposition::Into),
+                of: Container::Object(self.object()?),
+            }));
+        }
+        self.pos = start;
+        let span = self.span();
+        let selectable = match (self.expression()?, preposition) {
+            (Expr::Object(object), None) => Selectable::Object(*object),
+            (expr @ (Expr::Chunk { .. } | Expr::Object(_)), preposition) => Selectable::Text {
+                preposition: preposition.unwrap_or(Preposition::Into),
+                of: container(expr, span)?,
+            },
+            _ => {
+                return Err(ScriptError::at(
+                    "Expected a button, field or chunk of a field to select",
+                    span,
+                ))
+            }
+        };
+        Ok(Command::Select(selectable))
+    }
+
+    // `with shiftKey, optionKey, commandKey` after `type`, `click` or `drag`.
+    fn keys(&mut self) -> Result<Keys, ScriptError> {
+        let mut keys = Keys::default();
+        if !self.eat_word("with") {
+            return Ok(keys);
+        }
+        loop {
+            if self.eat_word("shiftKey") {
+                keys.shift = true;
+            } else if self.eat_word("optionKey") {
+                keys.option = true;
+            } else if self.eat_word("commandKey") || self.eat_word("cmdKey") {
+                keys.command = true;
+            } else {
+                return Err(self.expected("\"shiftKey\", \"optionKey\" or \"commandKey\""));
+            }
+            if !self.eat_operator(Operator::Comma) {
+                return Ok(keys);
+            }
+        }
+    }
+
+    // `seconds`, `dateItems`, or a date or time with its modifier, as `convert` converts to.
+    fn date_format(&mut self) -> Result<DateFormat, ScriptError> {
+        if self.eat_word("seconds") || self.eat_word("secs") {
+            return Ok(DateFormat::Seconds);
+        }
+        if self.eat_word("dateItems") {
+            return Ok(DateFormat::DateItems);
+        }
+        let modifier = self.peek().and_then(modifier);
+        if modifier.is_some() {
+            self.pos += 1;
+        }
+        if self.eat_word("date") {
+            Ok(DateFormat::Date(modifier))
+        } else if self.eat_word("time") {
+            Ok(DateFormat::Time(modifier))
+        } else {
+            Err(self.expected("\"seconds\", \"dateItems\", \"date\" or \"time\""))
+        }
+    }
+
+    // where `put` puts something: a variable, a field, the message box or a chunk of one.
+    fn container(&mut self) -> Result<Container, ScriptError> {
+        let span = self.span();
+        if self.eat_word("msg") || self.eat_word("message") {
+            if !self.eat_word("box") {
+                self.eat_word("window");
+            }
+            return Ok(Container::MessageBox);
+        }
+        let expr = self.expression()?;
+        container(expr, span)
+    }
+}
+
+fn container(expr: Expr, span: Span) -> Result<Container, ScriptError> {
+    match expr {
+        Expr::Variable(name) => Ok(Container::Variable(name)),
+        Expr::Object(object) => Ok(Container::Object(*object)),
+        Expr::Chunk { chunk, of } => Ok(Container::Chunk {
+            chunk: *chunk,
+            of: Box::new(container(*of, span)?),
+        }),
+        _ => Err(ScriptError::at(
+            "Expected a variable, field or chunk to put into",
+            span,
+        )),
+    }
+}
+
+fn comma() -> Expr {
+    Expr::String(",".to_string())
+}
+
+// the operator `add`, `subtract`, `multiply` or `divide` does.
+fn arithmetic(command: &str) -> Option<BinaryOp> {
+    [
+        ("add", BinaryOp::Add),
+        ("subtract", BinaryOp::Subtract),
+        ("multiply", BinaryOp::Multiply),
+        ("divide", BinaryOp::Divide),
+    ]
+    .into_iter()
+    .find(|(name, _)| name.eq_ignore_ascii_case(command))
+    .map(|(_, op)| op)
+}
@@ -0,0 +1,626 @@
+use crate::ast::{BinaryOp, Chunk, ChunkKind, Expr, Modifier, Object, Position, UnaryOp};
+use crate::error::ScriptError;
+use crate::lexer::{tokenize, Keyword, Operator, Token, TokenKind};
+
+use super::Parser;
+
+//
+// Expressions, parsed by precedence from the loosest (`or`) down to the tightest (a single value
+// or a parenthesised expression). Operators of the same precedence go left to right.
+//
+
+impl Expr {
+    /// Parse an expression on its own, as `value` and `do` need to.
+    pub fn parse(source: &str) -> Result<Expr, ScriptError> {
+        let mut parser = Parser::new(tokenize(source)?);
+        parser.skip_line_ends();
+        let expr = parser.expression()?;
+        parser.skip_line_ends();
+        match parser.peek() {
+            None => Ok(expr),
+            Some(_) => Err(parser.expected("end of expression")),
+        }
+    }
+}
+
+impl Parser {
+    pub(super) fn expression(&mut self) -> Result<Expr, ScriptError> {
+        self.or()
+    }
+
+    fn or(&mut self) -> Result<Expr, ScriptError> {
+        let mut left = self.and()?;
+        while self.eat_keyword(Keyword::Or) {
+            left = binary(BinaryOp::Or, left, self.and()?);
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expr, ScriptError> {
+        let mut left = self.equality()?;
+        while self.eat_keyword(Keyword::And) {
+            left = binary(BinaryOp::And, left, self.equality()?);
+        }
+        Ok(left)
+    }
+
+    fn equality(&mut self) -> Result<Expr, ScriptError> {
+        let mut left = self.comparison()?;
+        loop {
+            let op = if self.eat_operator(Operator::Equal) {
+                BinaryOp::Equal
+            } else if self.eat_operator(Operator::NotEqual) {
+                BinaryOp::NotEqual
+            } else if self.eat_keyword(Keyword::Is) {
+                if self.eat_keyword(Keyword::Not) {
+                    BinaryOp::NotEqual
+                } else {
+                    BinaryOp::Equal
+                }
+            } else {
+                break;
+            };
+            left = binary(op, left, self.comparison()?);
+        }
+        Ok(left)
+    }
+
+    fn comparison(&mut self) -> Result<Expr, ScriptError> {
+        let mut left = self.concatenation()?;
+        loop {
+            let op = if self.eat_operator(Operator::Less) {
+                BinaryOp::Less
+            } else if self.eat_operator(Operator::Greater) {
+                BinaryOp::Greater
+            } else if self.eat_operator(Operator::LessEqual) {
+                BinaryOp::LessEqual
+            } else if self.eat_operator(Operator::GreaterEqual) {
+                BinaryOp::GreaterEqual
+            } else if self.eat_keyword(Keyword::Contains) {
+                BinaryOp::Contains
+            } else if self.at_comparing_is() {
+                self.pos += 1;
+                let negated = self.eat_keyword(Keyword::Not);
+                if self.eat_word("in") {
+                    if negated {
+                        BinaryOp::IsNotIn
+                    } else {
+                        BinaryOp::IsIn
+                    }
+                } else if self.eat_word("within") {
+                    if negated {
+                        BinaryOp::IsNotWithin
+                    } else {
+                        BinaryOp::IsWithin
+                    }
+                } else {
+                    // `is a` or `is an`, then the type.
+                    self.pos += 1;
+                    let ty = self.name()?;
+                    left = Expr::IsA {
+                        value: Box::new(left),
+                        ty,
+                        negated,
+                    };
+                    continue;
+                }
+            } else {
+                break;
+            };
+            left = binary(op, left, self.concatenation()?);
+        }
+        Ok(left)
+    }
+
+    // whether we're at an `is` that goes with `in`, `within` or `a`, rather than an `is` that
+    // means `=`.
+    fn at_comparing_is(&self) -> bool {
+        let at = |n: usize| self.tokens.get(self.pos + n);
+        if !matches!(at(0), Some(t) if t.is_keyword(Keyword::Is)) {
+            return false;
+        }
+        let next = match at(1) {
+            Some(t) if t.is_keyword(Keyword::Not) => at(2),
+            t => t,
+        };
+        matches!(next, Some(t) if ["in", "within", "a", "an"].iter().any(|w| t.is_word(w)))
+    }
+
+    fn concatenation(&mut self) -> Result<Expr, ScriptError> {
+        let mut left = self.additive()?;
+        loop {
+            let op = if self.eat_operator(Operator::Concat) {
+                BinaryOp::Concat
+            } else if self.eat_operator(Operator::ConcatSpace) {
+                BinaryOp::ConcatSpace
+            } else {
+                break;
+            };
+            left = binary(op, left, self.additive()?);
+        }
+        Ok(left)
+    }
+
+    fn additive(&mut self) -> Result<Expr, ScriptError> {
+        let mut left = self.multiplicative()?;
+        loop {
+            let op = if self.eat_operator(Operator::Plus) {
+                BinaryOp::Add
+            } else if self.eat_operator(Operator::Minus) {
+                BinaryOp::Subtract
+            } else {
+                break;
+            };
+            left = binary(op, left, self.multiplicative()?);
+        }
+        Ok(left)
+    }
+
+    fn multiplicative(&mut self) -> Result<Expr, ScriptError> {
+        let mut left = self.power()?;
+        loop {
+            let op = if self.eat_operator(Operator::Times) {
+                BinaryOp::Multiply
+            } else if self.eat_operator(Operator::Divide) {
+                BinaryOp::Divide
+            } else if self.eat_keyword(Keyword::Div) {
+                BinaryOp::Div
+            } else if self.eat_keyword(Keyword::Mod) {
+                BinaryOp::Mod
+            } else {
+                break;
+            };
+            left = binary(op, left, self.power()?);
+        }
+        Ok(left)
+    }
+
+    fn power(&mut self) -> Result<Expr, ScriptError> {
+        let mut left = self.unary()?;
+        while self.eat_operator(Operator::Power) {
+            left = binary(BinaryOp::Power, left, self.unary()?);
+        }
+        Ok(left)
+    }
+
+    fn unary(&mut self) -> Result<Expr, ScriptError> {
+        if self.eat_operator(Operator::Minus) {
+            Ok(Expr::Unary(UnaryOp::Negate, Box::new(self.unary()?)))
+        } else if self.eat_keyword(Keyword::Not) {
+            Ok(Expr::Unary(UnaryOp::Not, Box::new(self.unary()?)))
+        } else {
+            self.primary()
+        }
+    }
+
+    pub(super) fn primary(&mut self) -> Result<Expr, ScriptError> {
+        let token = match self.peek() {
+            Some(t) => t.clone(),
+            None => return Err(self.expected("an expression")),
+        };
+        match token.kind {
+            TokenKind::Number(n) => {
+                self.pos += 1;
+                Ok(Expr::Number(n))
+            }
+            TokenKind::String(s) => {
+                self.pos += 1;
+                Ok(Expr::String(s))
+            }
+            TokenKind::Operator(Operator::LeftParen) => {
+                self.pos += 1;
+                let bare_of = std::mem::replace(&mut self.bare_of, true);
+                let expr = self.expression();
+                self.bare_of = bare_of;
+                let expr = expr?;
+                if !self.eat_operator(Operator::RightParen) {
+                    return Err(self.expected("\")\""));
+                }
+                Ok(expr)
+            }
+            TokenKind::Keyword(Keyword::The) => {
+                self.pos += 1;
+                self.the()
+            }
+            // `return` is also the constant for a return character.
+            TokenKind::Keyword(Keyword::Return) => {
+                self.pos += 1;
+                Ok(Expr::Variable("return".to_string()))
+            }
+            // `short name of x` without the `the`.
+            TokenKind::Identifier(_) if self.at_modifier() => self.the(),
+            TokenKind::Identifier(_) if self.at_chunk() => self.chunk(),
+            TokenKind::Identifier(_) if self.at_number_of() => self.number_of(),
+            TokenKind::Identifier(_) | TokenKind::Keyword(Keyword::Next) if self.at_object() => {
+                Ok(Expr::Object(Box::new(self.object()?)))
+            }
+            TokenKind::Identifier(name) => {
+                self.pos += 1;
+                if self.eat_operator(Operator::LeftParen) {
+                    Ok(Expr::Call {
+                        name,
+                        args: self.arguments()?,
+                    })
+                } else if self.bare_of && self.eat_keyword(Keyword::Of) {
+                    Ok(Expr::The {
+                        modifier: None,
+                        name,
+                        of: Some(Box::new(self.unary()?)),
+                    })
+                } else {
+                    Ok(Expr::Variable(name))
+                }
+            }
+            _ => Err(self.expected("an expression")),
+        }
+    }
+
+    // after `the`: an optional modifier, the name, and what it's of.
+    fn the(&mut self) -> Result<Expr, ScriptError> {
+        if self.at_chunk() {
+            return self.chunk();
+        }
+        if self.at_number_of() {
+            return self.number_of();
+        }
+        if self.at_object() {
+            return Ok(Expr::Object(Box::new(self.object()?)));
+        }
+        if self.eat_word("target") {
+            return Ok(Expr::Object(Box::new(Object::Target)));
+        }
+        let modifier = match self.peek() {
+            Some(t) if is_identifier(self.tokens.get(self.pos + 1)) => modifier(t),
+            _ => None,
+        };
+        if modifier.is_some() {
+            self.pos += 1;
+        }
+        let name = self.name()?;
+        let of = if self.eat_keyword(Keyword::Of) {
+            Some(Box::new(self.unary()?))
+        } else {
+            None
+        };
+        Ok(Expr::The { modifier, name, of })
+    }
+
+    // whether this is a modifier, a name and `of`.
+    fn at_modifier(&self) -> bool {
+        let at = |n: usize| self.tokens.get(self.pos + n);
+        matches!(at(0), Some(t) if modifier(t).is_some())
+            && is_identifier(at(1))
+            && matches!(at(2), Some(t) if t.is_keyword(Keyword::Of))
+    }
+
+    // whether a chunk starts here: a chunk word and its number, or an ordinal and a chunk word.
+    fn at_chunk(&mut self) -> bool {
+        let at = |n: usize| self.tokens.get(self.pos + n);
+        let starts = match (at(0), at(1)) {
+            (Some(t), Some(next)) if chunk_kind(t, false).is_some() => !matches!(
+                next.kind,
+                TokenKind::LineEnd
+                    | TokenKind::Keyword(Keyword::Of)
+                    | TokenKind::Operator(Operator::Comma | Operator::RightParen)
+            ),
+            (Some(t), Some(next)) => ordinal(t).is_some() && chunk_kind(next, false).is_some(),
+            _ => false,
+        };
+        // `item + 1` is the variable `item`, so it's only a chunk if the position is followed by
+        // `of`.
+        let start = self.pos;
+        let chunk = starts && self.chunk_head().is_ok();
+        self.pos = start;
+        chunk
+    }
+
+    fn chunk(&mut self) -> Result<Expr, ScriptError> {
+        let (kind, start, end) = self.chunk_head()?;
+        Ok(Expr::Chunk {
+            chunk: Box::new(Chunk { kind, start, end }),
+            of: Box::new(self.unary()?),
+        })
+    }
+
+    // `item 3 to 5 of`, up to what the chunk is of.
+    fn chunk_head(&mut self) -> Result<(ChunkKind, Position, Option<Position>), ScriptError> {
+        let ordinal = self.peek().and_then(ordinal);
+        let (kind, start) = match ordinal {
+            Some(position) => {
+                self.pos += 1;
+                let kind = self.peek().and_then(|t| chunk_kind(t, false));
+                self.pos += 1;
+                (kind, position)
+            }
+            None => {
+                let kind = self.peek().and_then(|t| chunk_kind(t, false));
+                self.pos += 1;
+                (kind, self.position()?)
+            }
+        };
+        let kind = kind.ok_or_else(|| self.expected("a chunk"))?;
+        let end = if self.eat_word("to") {
+            Some(self.position()?)
+        } else {
+            None
+        };
+        if !self.eat_keyword(Keyword::Of) && !self.eat_word("in") {
+            return Err(self.expected("\"of\""));
+        }
+        Ok((kind, start, end))
+    }
+
+    fn position(&mut self) -> Result<Position, ScriptError> {
+        match self.peek().and_then(ordinal) {
+            Some(position) => {
+                self.pos += 1;
+                Ok(position)
+            }
+            None => Ok(Position::Number(self.without_bare_of(Self::additive)?)),
+        }
+    }
+
+    // parse something with `bare_of` off, for the number in `line n of x`.
+    pub(super) fn without_bare_of<T>(
+        &mut self,
+        f: impl FnOnce(&mut Self) -> Result<T, ScriptError>,
+    ) -> Result<T, ScriptError> {
+        let bare_of = std::mem::replace(&mut self.bare_of, false);
+        let result = f(self);
+        self.bare_of = bare_of;
+        result
+    }
+
+    // `number of words in x` or `number of cards`, with or without the `the`.
+    fn at_number_of(&self) -> bool {
+        let at = |n: usize| self.tokens.get(self.pos + n);
+        matches!(at(0), Some(t) if t.is_word("number"))
+            && matches!(at(1), Some(t) if t.is_keyword(Keyword::Of))
+            && (matches!(at(2), Some(t) if chunk_kind(t, true).is_some()) || self.at_objects(2))
+    }
+
+    fn number_of(&mut self) -> Result<Expr, ScriptError> {
+        self.pos += 2;
+        if self.at_objects(0) {
+            return self.number_of_objects();
+        }
+        let kind = self.peek().and_then(|t| chunk_kind(t, true));
+        self.pos += 1;
+        let kind = kind.ok_or_else(|| self.expected("a chunk"))?;
+        if !self.eat_word("in") && !self.eat_keyword(Keyword::Of) {
+            return Err(self.expected("\"in\""));
+        }
+        Ok(Expr::NumberOf {
+            kind,
+            of: Box::new(self.unary()?),
+        })
+    }
+
+    // the arguments of a call, after the opening parenthesis.
+    fn arguments(&mut self) -> Result<Vec<Expr>, ScriptError> {
+        let mut args = Vec::new();
+        if self.eat_operator(Operator::RightParen) {
+            return Ok(args);
+        }
+        loop {
+            args.push(self.expression()?);
+            if self.eat_operator(Operator::RightParen) {
+                return Ok(args);
+            }
+            if !self.eat_operator(Operator::Comma) {
+                return Err(self.expected("\",\" or \")\""));
+            }
+        }
+    }
+}
+
+fn binary(op: BinaryOp, left: Expr, right: Expr) -> Expr {
+    Expr::Binary(op, Box::new(left), Box::new(right))
+}
+
+fn is_identifier(token: Option<&Token>) -> bool {
+    matches!(
+        token,
+        Some(Token {
+            kind: TokenKind::Identifier(_),
+            ..
+        })
+    )
+}
+
+pub(super) fn modifier(token: &Token) -> Option<Modifier> {
+    if token.is_word("long") {
+        Some(Modifier::Long)
+    } else if token.is_word("short") {
+        Some(Modifier::Short)
+    } else if token.is_word("abbreviated") || token.is_word("abbrev") || token.is_word("abbr") {
+        Some(Modifier::Abbreviated)
+    } else {
+        None
+    }
+}
+
+fn chunk_kind(token: &Token, plural: bool) -> Option<ChunkKind> {
+    let words: &[(&str, ChunkKind)] = if plural {
+        &[
+            ("chars", ChunkKind::Char),
+            ("characters", ChunkKind::Char),
+            ("words", ChunkKind::Word),
+            ("items", ChunkKind::Item),
+            ("lines", ChunkKind::Line),
+        ]
+    } else {
+        &[
+            ("char", ChunkKind::Char),
+            ("character", ChunkKind::Char),
+            ("word", ChunkKind::Word),
+            ("item", ChunkKind::Item),
+            ("line", ChunkKind::Line),
+        ]
+    };
+    words
+        .iter()
+        .find(|(word, _)| token.is_word(word))
+        .map(|(_, kind)| *kind)
+}
+
+pub(super) fn ordinal(token: &Token) -> Option<Position> {
+    const NUMBERS: [&str; 10] = [
+        "first", "second", "third", "fourth", "fifth", "sixth", "seventh", "eighth", "ninth",
+        "tenth",
+    ];
+    if let Some(n) = NUMBERS.iter().position(|word| token.is_word(word)) {
+        return Some(Position::Number(Expr::Number((n + 1).to_string())));
+    }
+    if token.is_word("last") {
+        Some(Position::Last)
+    } else if token.is_word("middle") || token.is_word("mid") {
+        Some(Position::Middle)
+    } else if token.is_word("any") {
+        Some(Position::Any)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // an expression with each operation and its operands in parentheses.
+    fn tree(expr: &Expr) -> String {
+        match expr {
+            Expr::Number(a) | Expr::Variable(a) => a.clone(),
+            Expr::Unary(op, a) => format!("({:?} {})", op, tree(a)),
+            Expr::Binary(op, a, b) => format!("({:?} {} {})", op, tree(a), tree(b)),
+            Expr::Call { name, args } => {
+                let args: Vec<String> = args.iter().map(tree).collect();
+                format!("({} {})", name, args.join(" "))
+            }
+            expr => format!("{:?}", expr),
+        }
+    }
+
+    fn parse(source: &str) -> String {
+        tree(&Expr::parse(source).unwrap())
+    }
+
+    #[test]
+    fn multiplication_before_addition() {
+        assert_eq!(parse("1 + 2 * 3"), "(Add 1 (Multiply 2 3))");
+        assert_eq!(parse("(1 + 2) * 3"), "(Multiply (Add 1 2) 3)");
+    }
+
+    #[test]
+    fn operators_of_a_level_go_left_to_right() {
+        assert_eq!(parse("1 - 2 - 3"), "(Subtract (Subtract 1 2) 3)");
+        // `^` too, unlike in most languages.
+        assert_eq!(parse("2^3^2"), "(Power (Power 2 3) 2)");
+        assert_eq!(parse("7 div 2 mod 3 / 4"), "(Divide (Mod (Div 7 2) 3) 4)");
+    }
+
+    #[test]
+    fn minus_sign_binds_tightest() {
+        assert_eq!(parse("-2^2"), "(Power (Negate 2) 2)");
+    }
+
+    #[test]
+    fn concatenation_comes_after_arithmetic() {
+        assert_eq!(parse("a & 1 + 2"), "(Concat a (Add 1 2))");
+        assert_eq!(parse("a & b < c"), "(Less (Concat a b) c)");
+    }
+
+    #[test]
+    fn equality_comes_after_comparison() {
+        assert_eq!(parse("a ≤ b ≠ c"), "(NotEqual (LessEqual a b) c)");
+        assert_eq!(parse("a = b and c or d"), "(Or (And (Equal a b) c) d)");
+        assert_eq!(parse("not a = b"), "(Equal (Not a) b)");
+    }
+
+    #[test]
+    fn is_is_equals() {
+        assert_eq!(parse("a is b"), "(Equal a b)");
+        assert_eq!(parse("a is not b"), "(NotEqual a b)");
+        assert_eq!(parse("a is not in b & c"), "(IsNotIn a (Concat b c))");
+        assert_eq!(parse("p is within r"), "(IsWithin p r)");
+    }
+
+    #[test]
+    fn is_a() {
+        let expr = Expr::parse("x + 1 is not an integer").unwrap();
+        let Expr::IsA { value, ty, negated } = expr else {
+            panic!("expected `is a`, found {:?}", expr);
+        };
+        assert_eq!(
+            (tree(&value), ty.as_str(), negated),
+            ("(Add x 1)".to_string(), "integer", true)
+        );
+    }
+
+    #[test]
+    fn function_calls() {
+        assert_eq!(parse("max(1, 2 + 3)"), "(max 1 (Add 2 3))");
+        assert_eq!(parse("f()"), "(f )");
+    }
+
+    #[test]
+    fn the_with_and_without_of() {
+        let the = |source: &str| match Expr::parse(source).unwrap() {
+            Expr::The { modifier, name, of } => (modifier, name, of.map(|of| tree(&of))),
+            expr => panic!("expected `the`, found {:?}", expr),
+        };
+        assert_eq!(
+            the("the long date"),
+            (Some(Modifier::Long), "date".to_string(), None)
+        );
+        assert_eq!(
+            the("length of x"),
+            (None, "length".to_string(), Some("x".to_string()))
+        );
+        // `of` takes only what's right after it.
+        assert_eq!(parse("the length of x & y"), parse("(the length of x) & y"));
+    }
+
+    #[test]
+    fn errors() {
+        let error = |source: &str| Expr::parse(source).unwrap_err().message;
+        assert_eq!(
+            error("1 +"),
+            "Expected an expression but found end of script"
+        );
+        assert_eq!(error("(1 + 2"), "Expected \")\" but found end of script");
+        assert_eq!(error("max(1 2)"), "Expected \",\" or \")\" but found \"2\"");
+        assert_eq!(error("1 2"), "Expected end of expression but found \"2\"");
+    }
+
+    #[test]
+    fn chunks_go_from_the_outside_in() {
+        let expr = Expr::parse("char 2 to 3 of last word of x").unwrap();
+        let Expr::Chunk { chunk, of } = expr else {
+            panic!("expected a chunk, found {:?}", expr);
+        };
+        assert_eq!(chunk.kind, ChunkKind::Char);
+        assert!(chunk.end.is_some());
+        assert!(matches!(
+            *of,
+            Expr::Chunk { ref chunk, .. } if chunk.kind == ChunkKind::Word && chunk.start == Position::Last
+        ));
+    }
+
+    #[test]
+    fn a_chunk_word_without_of_is_a_variable() {
+        assert_eq!(parse("item + 1"), "(Add item 1)");
+        assert_eq!(
+            parse("line * (word - 2)"),
+            "(Multiply line (Subtract word 2))"
+        );
+        assert!(matches!(
+            Expr::parse("item x + 1 of y").unwrap(),
+            Expr::Chunk { .. }
+        ));
+        assert!(matches!(
+            Expr::parse("the second line in y").unwrap(),
+            Expr::Chunk { .. }
+        ));
+    }
+}
@@ -0,0 +1,299 @@
+use crate::ast::{Expr, Layer, Object, Objects, PartKind, Which, Window};
+use crate::error::ScriptError;
+use crate::lexer::{Keyword, Operator, Token, TokenKind};
+
+use super::expression::ordinal;
+use super::Parser;
+
+//
+// References to objects: `btn 3`, `cd fld "Name" of card 2`, `bkgnd id 12`, `this card`,
+// `card "Index" of stack "Home"`, `me` and `card window`. The number or name after the type is a
+// single value, so `cd fld x & y` is the field's text followed by y.
+//
+
+// the words before `window` that name one.
+const WINDOWS: [(&str, Window); 7] = [
+    ("card", Window::Card),
+    ("cd", Window::Card),
+    ("message", Window::Message),
+    ("msg", Window::Message),
+    ("tool", Window::Tool),
+    ("pattern", Window::Pattern),
+    ("scroll", Window::Scroll),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Noun {
+    Part(PartKind),
+    Card,
+    Background,
+    Stack,
+}
+
+// words that can follow a type word without being its number or name, as in `put x into card`.
+const NOT_VALUES: [&str; 10] = [
+    "into", "before", "after", "to", "in", "from", "by", "with", "window", "picture",
+];
+
+impl Parser {
+    // whether an object reference starts here.
+    pub(super) fn at_object(&self) -> bool {
+        let at = |n: usize| self.tokens.get(self.pos + n);
+        let token = match at(0) {
+            Some(t) => t,
+            None => return false,
+        };
+        if token.is_word("me") || self.window().is_some() {
+            return true;
+        }
+        if relative(token).is_some() {
+            return match at(1) {
+                Some(t) if t.is_word("marked") => {
+                    matches!(at(2).and_then(|t| noun(t, false)), Some(Noun::Card))
+                }
+                Some(t) => noun(t, false).is_some(),
+                None => false,
+            };
+        }
+        match noun(token, false) {
+            Some(Noun::Card | Noun::Background)
+                if matches!(at(1).and_then(|t| noun(t, false)), Some(Noun::Part(_))) =>
+            {
+                true
+            }
+            Some(_) => takes_value(at(1)),
+            None => false,
+        }
+    }
+
+    pub(super) fn object(&mut self) -> Result<Object, ScriptError> {
+        if self.eat_word("me") {
+            return Ok(Object::Me);
+        }
+        if let Some(window) = self.window() {
+            self.pos += 2;
+            return Ok(Object::Window(window));
+        }
+        let relative = self.peek().and_then(relative);
+        if relative.is_some() {
+            self.pos += 1;
+        }
+        let marked = relative.is_some() && self.eat_word("marked");
+
+        let mut kind = match self.peek().and_then(|t| noun(t, false)) {
+            Some(kind) => kind,
+            None => return Err(self.expected("an object")),
+        };
+        self.pos += 1;
+        // `cd btn`, `bkgnd fld`.
+        let mut layer = None;
+        if let Some(Noun::Part(part)) = self.peek().and_then(|t| noun(t, false)) {
+            layer = match kind {
+                Noun::Card => Some(Layer::Card),
+                Noun::Background => Some(Layer::Background),
+                _ => None,
+            };
+            if layer.is_some() {
+                self.pos += 1;
+                kind = Noun::Part(part);
+            }
+        }
+
+        let which = match relative {
+            Some(which) => which,
+            None if self.eat_word("id") => Which::Id(self.object_value()?),
+            None => Which::Value(self.object_value()?),
+        };
+        let of = if kind != Noun::Stack && self.eat_of_object() {
+            Some(Box::new(self.object()?))
+        } else {
+            None
+        };
+        Ok(match kind {
+            Noun::Part(kind) => Object::Part {
+                kind,
+                layer,
+                which,
+                of,
+            },
+            Noun::Card => Object::Card { which, marked, of },
+            Noun::Background => Object::Background { which, of },
+            Noun::Stack => Object::Stack(which),
+        })
+    }
+
+    // `next`, `prev`, `first` and the rest without a type word after them, which `go` takes to
+    // be cards.
+    pub(super) fn bare_card(&mut self) -> Option<Object> {
+        let which = self.peek().and_then(relative)?;
+        if self.at_object() {
+            return None;
+        }
+        self.pos += 1;
+        Some(Object::Card {
+            which,
+            marked: false,
+            of: None,
+        })
+    }
+
+    // the window named by the next two words, if they name one.
+    fn window(&self) -> Option<Window> {
+        let first = self.peek()?;
+        let second = self.tokens.get(self.pos + 1)?;
+        let window = if second.is_word("window") {
+            WINDOWS
+                .iter()
+                .find(|(word, _)| first.is_word(word))
+                .map(|(_, window)| *window)?
+        } else if second.is_word("box") && (first.is_word("msg") || first.is_word("message")) {
+            Window::Message
+        } else if second.is_word("watcher") && first.is_word("message") {
+            Window::MessageWatcher
+        } else if second.is_word("watcher") && first.is_word("variable") {
+            Window::VariableWatcher
+        } else {
+            return None;
+        };
+        Some(window)
+    }
+
+    fn object_value(&mut self) -> Result<Expr, ScriptError> {
+        self.without_bare_of(Self::primary)
+    }
+
+    // an `of` with an object after it, which is stepped over if it's there.
+    fn eat_of_object(&mut self) -> bool {
+        let start = self.pos;
+        if self.eat_keyword(Keyword::Of) && self.at_object() {
+            return true;
+        }
+        self.pos = start;
+        false
+    }
+
+    // whether `n` tokens on is something `the number of` counts: `cards`, `bkgnds`, `btns`,
+    // `cd flds` and so on.
+    pub(super) fn at_objects(&self, n: usize) -> bool {
+        let at = |i: usize| self.tokens.get(self.pos + n + i);
+        if matches!(at(0), Some(t) if t.is_word("marked")) {
+            return matches!(at(1).and_then(|t| noun(t, true)), Some(Noun::Card));
+        }
+        match at(0).and_then(|t| noun(t, true)) {
+            Some(_) => true,
+            None => {
+                matches!(
+                    at(0).and_then(|t| noun(t, false)),
+                    Some(Noun::Card | Noun::Background)
+                ) && matches!(at(1).and_then(|t| noun(t, true)), Some(Noun::Part(_)))
+            }
+        }
+    }
+
+    // after `number of`.
+    pub(super) fn number_of_objects(&mut self) -> Result<Expr, ScriptError> {
+        if self.eat_word("marked") {
+            if !matches!(self.peek().and_then(|t| noun(t, true)), Some(Noun::Card)) {
+                return Err(self.expected("cards"));
+            }
+            self.pos += 1;
+            let of = self.objects_of()?;
+            let objects = Objects::Cards { marked: true };
+            return Ok(Expr::NumberOfObjects { objects, of });
+        }
+        let layer = match self.peek().and_then(|t| noun(t, false)) {
+            Some(Noun::Card) => Some(Layer::Card),
+            Some(Noun::Background) => Some(Layer::Background),
+            _ => None,
+        };
+        if layer.is_some() {
+            self.pos += 1;
+        }
+        let objects = match self.peek().and_then(|t| noun(t, true)) {
+            Some(Noun::Part(kind)) => Objects::Parts { kind, layer },
+            Some(Noun::Card) => Objects::Cards { marked: false },
+            Some(Noun::Background) => Objects::Backgrounds,
+            _ => return Err(self.expected("buttons, fields, cards or backgrounds")),
+        };
+        self.pos += 1;
+        let of = self.objects_of()?;
+        Ok(Expr::NumberOfObjects { objects, of })
+    }
+
+    // what objects are counted in, after `number of cards` and the rest: `in bkgnd 2` or
+    // `of this stack`, if that follows.
+    fn objects_of(&mut self) -> Result<Option<Box<Object>>, ScriptError> {
+        let start = self.pos;
+        if (self.eat_word("in") || self.eat_keyword(Keyword::Of)) && self.at_object() {
+            Ok(Some(Box::new(self.object()?)))
+        } else {
+            self.pos = start;
+            Ok(None)
+        }
+    }
+}
+
+// `this`, `next`, `prev` and `recent`, and the ordinals.
+fn relative(token: &Token) -> Option<Which> {
+    if token.is_word("this") {
+        Some(Which::This)
+    } else if token.is_keyword(Keyword::Next) {
+        Some(Which::Next)
+    } else if token.is_word("prev") || token.is_word("previous") {
+        Some(Which::Previous)
+    } else if token.is_word("recent") {
+        Some(Which::Recent)
+    } else {
+        ordinal(token).map(Which::Position)
+    }
+}
+
+fn noun(token: &Token, plural: bool) -> Option<Noun> {
+    let words: &[(&str, Noun)] = if plural {
+        &[
+            ("buttons", Noun::Part(PartKind::Button)),
+            ("btns", Noun::Part(PartKind::Button)),
+            ("fields", Noun::Part(PartKind::Field)),
+            ("flds", Noun::Part(PartKind::Field)),
+            ("parts", Noun::Part(PartKind::Part)),
+            ("cards", Noun::Card),
+            ("cds", Noun::Card),
+            ("backgrounds", Noun::Background),
+            ("bkgnds", Noun::Background),
+            ("bkgds", Noun::Background),
+            ("bgs", Noun::Background),
+        ]
+    } else {
+        &[
+            ("button", Noun::Part(PartKind::Button)),
+            ("btn", Noun::Part(PartKind::Button)),
+            ("field", Noun::Part(PartKind::Field)),
+            ("fld", Noun::Part(PartKind::Field)),
+            ("part", Noun::Part(PartKind::Part)),
+            ("card", Noun::Card),
+            ("cd", Noun::Card),
+            ("background", Noun::Background),
+            ("bkgnd", Noun::Background),
+            ("bkgd", Noun::Background),
+            ("bg", Noun::Background),
+            ("stack", Noun::Stack),
+        ]
+    };
+    words
+        .iter()
+        .find(|(word, _)| token.is_word(word))
+        .map(|(_, noun)| *noun)
+}
+
+// whether a token can be the number or name after a type word.
+fn takes_value(token: Option<&Token>) -> bool {
+    match token.map(|t| &t.kind) {
+        Some(
+            TokenKind::Number(_) | TokenKind::String(_) | TokenKind::Operator(Operator::LeftParen),
+        ) => true,
+        Some(TokenKind::Identifier(word)) => {
+            !NOT_VALUES.iter().any(|w| w.eq_ignore_ascii_case(word))
+        }
+        _ => false,
+    }
+}
@@ -0,0 +1,897 @@
+use hc_decode::font::Font;
+use hc_decode::part::{Part, PartStyle, PartType, TextAlignment};
+use hc_decode::stack::Stack;
+
+use crate::ast::{Layer, Modifier};
+use crate::error::ScriptError;
+use crate::object::{Found, ObjectId};
+use crate::value;
+
+//
+// Properties: what `the name of btn 1`, `the rect of cd fld 2` and the rest read and write on
+// the decoded stack, and HyperCard's global properties such as `the userLevel`. Property names
+// are matched ignoring case.
+//
+
+/// HyperCard's global properties, apart from `the numberFormat` and `the itemDelimiter`, which
+/// the interpreter keeps itself.
+#[derive(Debug, Clone)]
+pub struct Properties {
+    /// 1 (browsing) to 5 (scripting).
+    pub user_level: u8,
+    /// Whether the screen is left as it is until a script unlocks it.
+    pub lock_screen: bool,
+    /// Whether HyperCard's own messages, like `openCard`, aren't sent.
+    pub lock_messages: bool,
+    /// Whether the cards a script goes to are left out of `recent card`.
+    pub lock_recent: bool,
+    pub cursor: String,
+    /// Whether a locked stack can still be changed, without it being saved.
+    pub user_modify: bool,
+    pub blind_typing: bool,
+    pub power_keys: bool,
+    pub text_arrows: bool,
+    /// How fast `drag` moves the mouse, in pixels a second; 0 is as fast as it can.
+    pub drag_speed: u32,
+    /// Whether painting and new objects go on the background.
+    pub edit_bkgnd: bool,
+    pub language: String,
+    /// Ticks to wait between lines when tracing a script.
+    pub trace_delay: u32,
+    /// The tool `choose` chose last, such as `browse` or `round rect`.
+    pub tool: String,
+}
+
+// the cursors `set the cursor to` knows by name.
+const CURSORS: [&str; 8] = [
+    "arrow", "busy", "cross", "hand", "iBeam", "none", "plus", "watch",
+];
+
+// text styles, by their bit in a part's text style.
+const TEXT_STYLES: [(&str, u8); 8] = [
+    ("bold", 0x01),
+    ("italic", 0x02),
+    ("underline", 0x04),
+    ("outline", 0x08),
+    ("shadow", 0x10),
+    ("condense", 0x20),
+    ("extend", 0x40),
+    ("group", 0x80),
+];
+
+// card and background flags, by their bit. Showing the picture is kept the other way round.
+const CARD_FLAGS: [(&str, u16); 3] = [
+    ("cantDelete", 0x4000),
+    ("showPict", 0x2000),
+    ("dontSearch", 0x0800),
+];
+
+// the properties of a card or background that are the stack's size, since they're all as big as
+// the stack.
+const CARD_SIZE: [&str; 4] = ["rect", "rectangle", "width", "height"];
+
+impl Default for Properties {
+    fn default() -> Self {
+        Properties {
+            user_level: 5,
+            lock_screen: false,
+            lock_messages: false,
+            lock_recent: false,
+            cursor: "hand".to_string(),
+            user_modify: false,
+            blind_typing: false,
+            power_keys: false,
+            text_arrows: true,
+            drag_speed: 0,
+            edit_bkgnd: false,
+            language: "English".to_string(),
+            trace_delay: 0,
+            tool: "browse".to_string(),
+        }
+    }
+}
+
+impl Properties {
+    /// A global property, if it's one of these.
+    pub fn get(&self, name: &str) -> Option<String> {
+        Some(match name.to_lowercase().as_str() {
+            "userlevel" => self.user_level.to_string(),
+            "lockscreen" => value::from_bool(self.lock_screen),
+            "lockmessages" => value::from_bool(self.lock_messages),
+            "lockrecent" => value::from_bool(self.lock_recent),
+            "cursor" => self.cursor.clone(),
+            "usermodify" => value::from_bool(self.user_modify),
+            "blindtyping" => value::from_bool(self.blind_typing),
+            "powerkeys" => value::from_bool(self.power_keys),
+            "textarrows" => value::from_bool(self.text_arrows),
+            "dragspeed" => self.drag_speed.to_string(),
+            "editbkgnd" => value::from_bool(self.edit_bkgnd),
+            "language" => self.language.clone(),
+            "tracedelay" => self.trace_delay.to_string(),
+            _ => return None,
+        })
+    }
+
+    /// Set a global property, returning whether it's one of these.
+    pub fn set(&mut self, name: &str, value: &str) -> Result<bool, ScriptError> {
+        match name.to_lowercase().as_str() {
+            "userlevel" => {
+                self.user_level = match value::to_integer(value)? {
+                    n @ 1..=5 => n as u8,
+                    _ => {
+                        return Err(ScriptError::new(format!(
+                            "Expected a number from 1 to 5 but found \"{}\"",
+                            value
+                        )))
+                    }
+                }
+            }
+            "lockscreen" => self.lock_screen = value::to_bool(value)?,
+            "lockmessages" => self.lock_messages = value::to_bool(value)?,
+            "lockrecent" => self.lock_recent = value::to_bool(value)?,
+            "cursor" => {
+                let value = value.trim();
+                // a cursor can be a resource ID too.
+                let known = CURSORS.iter().find(|c| c.eq_ignore_ascii_case(value));
+                self.cursor = match known {
+                    Some(cursor) => cursor.to_string(),
+                    None if value::is_number(value) => value.to_string(),
+                    None => return Err(ScriptError::new("No such cursor.")),
+                };
+            }
+            "usermodify" => self.user_modify = value::to_bool(value)?,
+            "blindtyping" => self.blind_typing = value::to_bool(value)?,
+            "powerkeys" => self.power_keys = value::to_bool(value)?,
+            "textarrows" => self.text_arrows = value::to_bool(value)?,
+            "dragspeed" => self.drag_speed = whole(value)?,
+            "editbkgnd" => self.edit_bkgnd = value::to_bool(value)?,
+            "language" => self.language = value.to_string(),
+            "tracedelay" => self.trace_delay = whole(value)?,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// What HyperCard puts back once no script is running.
+    pub fn idle(&mut self) {
+        self.lock_screen = false;
+        self.lock_messages = false;
+        self.lock_recent = false;
+        self.cursor = Properties::default().cursor;
+    }
+}
+
+/// An object's property, or `None` if it doesn't have one by that name.
+pub fn get(
+    stack: &Stack,
+    stack_name: &str,
+    id: ObjectId,
+    name: &str,
+    modifier: Option<Modifier>,
+) -> Result<Option<String>, ScriptError> {
+    let found = id
+        .find(stack)
+        .ok_or_else(|| ScriptError::new("No such object."))?;
+    let lower = name.to_lowercase();
+    match lower.as_str() {
+        "name" => return Ok(Some(self::name(found, stack_name, modifier))),
+        "id" => return Ok(object_id(found, stack_name, modifier)),
+        "number" => return Ok(number(stack, found).map(|n| n.to_string())),
+        "script" => {
+            return Ok(Some(match found {
+                Found::Stack(stack) => stack.script.clone(),
+                Found::Background(background) => background.script.clone(),
+                Found::Card(card) => card.script.clone(),
+                Found::Part { part, .. } => part.script.clone(),
+            }))
+        }
+        _ => {}
+    }
+
+    let flags = match found {
+        Found::Background(background) => background.flags,
+        Found::Card(card) => card.flags,
+        Found::Stack(stack) => {
+            let (width, height) = stack.size;
+            let rect = (0, 0, width as i32, height as i32);
+            return Ok(match lower.as_str() {
+                "version" => Some(
+                    [
+                        &stack.version.0,
+                        &stack.version.1,
+                        &stack.version.2,
+                        &stack.version.3,
+                    ]
+                    .map(|v| format!("{:08x}", v.whole()))
+                    .join(","),
+                ),
+                _ => rect_property(rect, &lower),
+            });
+        }
+        Found::Part { part, .. } => return Ok(part_property(stack, part, &lower)),
+    };
+    if let Found::Card(_) | Found::Background(_) = found {
+        if CARD_SIZE.contains(&lower.as_str()) {
+            let (width, height) = stack.size;
+            return Ok(rect_property((0, 0, width as i32, height as i32), &lower));
+        }
+    }
+    if let Found::Card(card) = found {
+        if lower == "owner" {
+            let background = stack.background(card.background_id);
+            return Ok(background.map(|b| self::name(Found::Background(b), stack_name, modifier)));
+        }
+        if lower == "marked" {
+            return Ok(Some(value::from_bool(card.marked)));
+        }
+    }
+    Ok(CARD_FLAGS
+        .iter()
+        .find(|(flag, _)| flag.eq_ignore_ascii_case(name))
+        .map(|(flag, bit)| {
+            let set = flags & bit != 0;
+            value::from_bool(if *flag == "showPict" { !set } else { set })
+        }))
+}
+
+/// Set an object's property, returning whether it has one by that name.
+pub fn set(stack: &mut Stack, id: ObjectId, name: &str, value: &str) -> Result<bool, ScriptError> {
+    let lower = name.to_lowercase();
+    if lower == "id" || lower == "number" {
+        return Err(ScriptError::new("Can't set that property"));
+    }
+    if let ObjectId::Card(_) | ObjectId::Background(_) = id {
+        if let Some(size) = card_size(stack.size, &lower, value)? {
+            stack.size = size;
+            return Ok(true);
+        }
+    }
+    let (flags, object_name, script) = match id {
+        ObjectId::Part { layer, card, id } => {
+            let background_id = stack.card(card).map(|c| c.background_id);
+            // the parts and the font table are borrowed together, since setting the text font
+            // can add to the table.
+            let parts = match layer {
+                Layer::Card => stack
+                    .cards
+                    .iter_mut()
+                    .find(|c| c.id == card)
+                    .map(|c| &mut c.parts),
+                Layer::Background => stack
+                    .backgrounds
+                    .iter_mut()
+                    .find(|b| Some(b.id) == background_id)
+                    .map(|b| &mut b.parts),
+            };
+            let part = parts
+                .and_then(|parts| Part::find_mut(parts, id))
+                .ok_or_else(|| ScriptError::new("No such object."))?;
+            return set_part_property(&mut stack.fonts, part, &lower, value);
+        }
+        ObjectId::Card(id) => {
+            let card = stack
+                .card_mut(id)
+                .ok_or_else(|| ScriptError::new("No such card."))?;
+            if lower == "marked" {
+                card.marked = value::to_bool(value)?;
+                return Ok(true);
+            }
+            (&mut card.flags, &mut card.name, &mut card.script)
+        }
+        ObjectId::Background(id) => {
+            let background = stack
+                .background_mut(id)
+                .ok_or_else(|| ScriptError::new("No such background."))?;
+            (
+                &mut background.flags,
+                &mut background.name,
+                &mut background.script,
+            )
+        }
+        ObjectId::Stack => {
+            return match lower.as_str() {
+                "script" => {
+                    stack.script = value.to_string();
+                    Ok(true)
+                }
+                _ => Ok(false),
+            }
+        }
+        ObjectId::Window(_) => return Ok(false),
+    };
+    match lower.as_str() {
+        "name" => *object_name = value.to_string(),
+        "script" => *script = value.to_string(),
+        _ => {
+            let Some((flag, bit)) = CARD_FLAGS
+                .iter()
+                .find(|(flag, _)| flag.eq_ignore_ascii_case(name))
+            else {
+                return Ok(false);
+            };
+            let on = value::to_bool(value)? != (*flag == "showPict");
+            if on {
+                *flags |= bit;
+            } else {
+                *flags &= !bit;
+            }
+        }
+    }
+    Ok(true)
+}
+
+/// A property of the card window, going by where the stack was last shown.
+pub fn card_window(stack: &Stack, name: &str) -> Option<String> {
+    let name = name.to_lowercase();
+    if name == "visible" {
+        return Some(value::from_bool(true));
+    }
+    let (top, left, bottom, right) = stack.card_window_coords;
+    let rect = if bottom > top && right > left {
+        (left as i32, top as i32, right as i32, bottom as i32)
+    } else {
+        (0, 0, stack.size.0 as i32, stack.size.1 as i32)
+    };
+    rect_property(rect, &name)
+}
+
+/// An object's name as HyperCard writes it: `card "Index"`, or `card id 12` if it hasn't one.
+/// The short name is just the name, and the long name says where the object is.
+pub fn name(found: Found, stack_name: &str, modifier: Option<Modifier>) -> String {
+    let (kind, name, id) = match found {
+        Found::Stack(_) => {
+            return match modifier {
+                Some(Modifier::Short) => stack_name.to_string(),
+                _ => format!("stack \"{}\"", stack_name),
+            }
+        }
+        Found::Background(b) => ("bkgnd", &b.name, b.id),
+        Found::Card(c) => ("card", &c.name, c.id),
+        Found::Part { part, layer, .. } => (part_kind(part, layer), &part.name, part.id as u32),
+    };
+    let name = match (modifier, name.is_empty()) {
+        (_, true) => format!("{} id {}", kind, id),
+        (Some(Modifier::Short), false) => name.clone(),
+        (_, false) => format!("{} \"{}\"", kind, name),
+    };
+    match modifier {
+        Some(Modifier::Long) => format!("{}{}", name, long_suffix(found, stack_name)),
+        _ => name,
+    }
+}
+
+// `the id of`: a part's is a number and a card's or background's says what it is, unless it's
+// the short ID. The long ID says where the object is.
+fn object_id(found: Found, stack_name: &str, modifier: Option<Modifier>) -> Option<String> {
+    let (kind, id) = match found {
+        Found::Stack(_) => return None,
+        Found::Background(b) => ("bkgnd", b.id),
+        Found::Card(c) => ("card", c.id),
+        Found::Part { part, layer, .. } => (part_kind(part, layer), part.id as u32),
+    };
+    Some(match (modifier, found) {
+        (Some(Modifier::Long), _) => {
+            format!("{} id {}{}", kind, id, long_suffix(found, stack_name))
+        }
+        (Some(Modifier::Short), _) | (_, Found::Part { .. }) => id.to_string(),
+        _ => format!("{} id {}", kind, id),
+    })
+}
+
+// ` of card id 12 of stack "Home"` and so on, for long names and IDs.
+fn long_suffix(found: Found, stack_name: &str) -> String {
+    let card = match found {
+        Found::Part { card, .. } => format!(" of card id {}", card.id),
+        _ => String::new(),
+    };
+    format!("{} of stack \"{}\"", card, stack_name)
+}
+
+fn part_kind(part: &Part, layer: Layer) -> &'static str {
+    match (layer, &part.ty) {
+        (Layer::Card, PartType::Field) => "card field",
+        (Layer::Background, PartType::Field) => "bkgnd field",
+        (Layer::Background, _) => "bkgnd button",
+        (Layer::Card, _) => "card button",
+    }
+}
+
+// where an object comes in its stack, or among the buttons or fields in its layer.
+fn number(stack: &Stack, found: Found) -> Option<usize> {
+    let index = match found {
+        Found::Stack(_) => return None,
+        Found::Background(b) => stack.backgrounds.iter().position(|o| o.id == b.id),
+        Found::Card(c) => stack.cards.iter().position(|o| o.id == c.id),
+        Found::Part { part, layer, card } => {
+            let parts = match layer {
+                Layer::Card => &card.parts,
+                Layer::Background => &stack.background(card.background_id)?.parts,
+            };
+            parts
+                .iter()
+                .filter(|p| std::mem::discriminant(&p.ty) == std::mem::discriminant(&part.ty))
+                .position(|p| p.id == part.id)
+        }
+    };
+    index.map(|i| i + 1)
+}
+
+fn part_property(stack: &Stack, part: &Part, name: &str) -> Option<String> {
+    if let Some(flag) = flag(part, name) {
+        return Some(value::from_bool(flag));
+    }
+    let is_field = matches!(part.ty, PartType::Field);
+    Some(match name {
+        "style" => style_name(&part.style).to_string(),
+        "textalign" => match part.text_alignment {
+            TextAlignment::Center | TextAlignment::ForceCenterAlign => "center",
+            TextAlignment::Right | TextAlignment::ForceRightAlign => "right",
+            _ => "left",
+        }
+        .to_string(),
+        "textfont" => stack
+            .font(part.font_id)
+            .map_or_else(|| part.font_id.to_string(), |f| f.name.clone()),
+        "textsize" => part.font_size.to_string(),
+        "textheight" => part.line_height.to_string(),
+        "textstyle" => text_style(part.text_style),
+        "icon" if !is_field => part.icon_id.to_string(),
+        "titlewidth" if !is_field => part.title_width.to_string(),
+        "scroll" if is_field => part.scroll.to_string(),
+        _ => return rect_property(part_rect(part), name),
+    })
+}
+
+fn set_part_property(
+    fonts: &mut Vec<Font>,
+    part: &mut Part,
+    name: &str,
+    value: &str,
+) -> Result<bool, ScriptError> {
+    if let Some(flag) = flag_mut(part, name) {
+        *flag = value::to_bool(value)?;
+        return Ok(true);
+    }
+    let is_field = matches!(part.ty, PartType::Field);
+    match name {
+        "name" => part.name = value.to_string(),
+        "script" => part.script = value.to_string(),
+        "style" => part.style = part_style(value, is_field)?,
+        "textalign" => {
+            part.text_alignment = match value.trim().to_lowercase().as_str() {
+                "left" => TextAlignment::Left,
+                "center" => TextAlignment::Center,
+                "right" => TextAlignment::Right,
+                _ => {
+                    return Err(ScriptError::new(format!(
+                        "Expected left, center or right but found \"{}\"",
+                        value
+                    )))
+                }
+            }
+        }
+        "textfont" => part.font_id = font_id(fonts, value.trim()),
+        "textsize" => part.font_size = positive(value)?,
+        "textheight" => part.line_height = positive(value)?,
+        "textstyle" => part.text_style = parse_text_style(value)?,
+        "icon" if !is_field => {
+            let icon = value::to_integer(value)?;
+            part.icon_id = i16::try_from(icon).map_err(|_| {
+                ScriptError::new(format!("Expected an icon but found \"{}\"", value))
+            })?;
+        }
+        "titlewidth" if !is_field => part.title_width = whole(value)?,
+        "scroll" if is_field => part.scroll = whole(value)?,
+        _ => match set_rect_property(part_rect(part), name, value)? {
+            Some((left, top, right, bottom)) => {
+                let (left, right) = clamp_side(left, right);
+                let (top, bottom) = clamp_side(top, bottom);
+                part.position = (top, left, bottom, right);
+            }
+            None => return Ok(false),
+        },
+    }
+    Ok(true)
+}
+
+// the part properties that are true or false, if the part has the one asked for.
+fn flag(part: &Part, name: &str) -> Option<bool> {
+    let button = matches!(part.ty, PartType::Button);
+    let field = matches!(part.ty, PartType::Field);
+    Some(match name {
+        "visible" => part.visible,
+        "enabled" if button => part.enabled,
+        "showname" if button => part.show_name,
+        "hilite" if button => part.hilite,
+        "autohilite" if button => part.auto_hilite,
+        "locktext" if field => part.lock_text,
+        "dontwrap" if field => part.dont_wrap,
+        "dontsearch" if field => part.dont_search,
+        "sharedtext" if field => part.shared_text,
+        "fixedlineheight" if field => part.fixed_line_height,
+        "autotab" if field => part.auto_tab,
+        "autoselect" if field => part.auto_select,
+        "showlines" if field => part.show_lines,
+        "widemargins" if field => part.wide_margins,
+        "multiplelines" if field => part.multiple_lines,
+        _ => return None,
+    })
+}
+
+fn flag_mut<'a>(part: &'a mut Part, name: &str) -> Option<&'a mut bool> {
+    flag(part, name)?;
+    Some(match name {
+        "visible" => &mut part.visible,
+        "enabled" => &mut part.enabled,
+        "showname" => &mut part.show_name,
+        "hilite" => &mut part.hilite,
+        "autohilite" => &mut part.auto_hilite,
+        "locktext" => &mut part.lock_text,
+        "dontwrap" => &mut part.dont_wrap,
+        "dontsearch" => &mut part.dont_search,
+        "sharedtext" => &mut part.shared_text,
+        "fixedlineheight" => &mut part.fixed_line_height,
+        "autotab" => &mut part.auto_tab,
+        "autoselect" => &mut part.auto_select,
+        "showlines" => &mut part.show_lines,
+        "widemargins" => &mut part.wide_margins,
+        _ => &mut part.multiple_lines,
+    })
+}
+
+// `left,top,right,bottom`.
+fn part_rect(part: &Part) -> (i32, i32, i32, i32) {
+    let (top, left, bottom, right) = part.position;
+    (left as i32, top as i32, right as i32, bottom as i32)
+}
+
+// the properties that come from where an object is: its rect, loc, width and so on.
+fn rect_property(rect: (i32, i32, i32, i32), name: &str) -> Option<String> {
+    let (left, top, right, bottom) = rect;
+    Some(match name {
+        "rect" | "rectangle" => format!("{},{},{},{}", left, top, right, bottom),
+        "loc" | "location" => format!("{},{}", (left + right) / 2, (top + bottom) / 2),
+        "topleft" => format!("{},{}", left, top),
+        "bottomright" | "botright" => format!("{},{}", right, bottom),
+        "left" => left.to_string(),
+        "top" => top.to_string(),
+        "right" => right.to_string(),
+        "bottom" => bottom.to_string(),
+        "width" => (right - left).to_string(),
+        "height" => (bottom - top).to_string(),
+        _ => return None,
+    })
+}
+
+// an object's rect after setting one of those. Setting a side or a corner moves the object;
+// setting its width or height keeps it where its center is.
+fn set_rect_property(
+    rect: (i32, i32, i32, i32),
+    name: &str,
+    value: &str,
+) -> Result<Option<(i32, i32, i32, i32)>, ScriptError> {
+    let (left, top, right, bottom) = rect;
+    let (width, height) = (right - left, bottom - top);
+    let point = || {
+        value::to_point(value)
+            .map(|(h, v)| (h as i32, v as i32))
+            .ok_or_else(|| ScriptError::new(format!("Expected a point but found \"{}\"", value)))
+    };
+    let number = || value::to_integer(value).map(|n| n as i32);
+    Ok(Some(match name {
+        "rect" | "rectangle" => {
+            let (left, top, right, bottom) = value::to_rect(value).ok_or_else(|| {
+                ScriptError::new(format!("Expected a rectangle but found \"{}\"", value))
+            })?;
+            (left as i32, top as i32, right as i32, bottom as i32)
+        }
+        "loc" | "location" => {
+            let (h, v) = point()?;
+            let (left, top) = (h - width / 2, v - height / 2);
+            (left, top, left + width, top + height)
+        }
+        "topleft" => {
+            let (h, v) = point()?;
+            (h, v, h + width, v + height)
+        }
+        "bottomright" | "botright" => {
+            let (h, v) = point()?;
+            (h - width, v - height, h, v)
+        }
+        "left" => {
+            let left = number()?;
+            (left, top, left + width, bottom)
+        }
+        "top" => {
+            let top = number()?;
+            (left, top, right, top + height)
+        }
+        "right" => {
+            let right = number()?;
+            (right - width, top, right, bottom)
+        }
+        "bottom" => {
+            let bottom = number()?;
+            (left, bottom - height, right, bottom)
+        }
+        "width" => {
+            let width = number()?;
+            let h = (left + right) / 2;
+            (h - width / 2, top, h - width / 2 + width, bottom)
+        }
+        "height" => {
+            let height = number()?;
+            let v = (top + bottom) / 2;
+            (left, v - height / 2, right, v - height / 2 + height)
+        }
+        _ => return Ok(None),
+    }))
+}
+
+// one side of a part's rect moved back inside the coordinates a part can have, keeping its size.
+fn clamp_side(low: i32, high: i32) -> (u16, u16) {
+    let max = u16::MAX as i32;
+    let size = (high - low).clamp(0, max);
+    let low = low.clamp(0, max - size);
+    (low as u16, (low + size) as u16)
+}
+
+// the stack's size after setting the rect, width or height of a card or background, which are
+// all as big as the stack.
+fn card_size(size: (u16, u16), name: &str, value: &str) -> Result<Option<(u16, u16)>, ScriptError> {
+    Ok(Some(match name {
+        "rect" | "rectangle" => {
+            let side = |n: i64| u16::try_from(n).ok().filter(|n| *n > 0);
+            value::to_rect(value)
+                .and_then(|(left, top, right, bottom)| {
+                    Some((side(right - left)?, side(bottom - top)?))
+                })
+                .ok_or_else(|| {
+                    ScriptError::new(format!("Expected a rectangle but found \"{}\"", value))
+                })?
+        }
+        "width" => (positive(value)?, size.1),
+        "height" => (size.0, positive(value)?),
+        _ => return Ok(None),
+    }))
+}
+
+fn style_name(style: &PartStyle) -> &'static str {
+    match style {
+        PartStyle::Transparent => "transparent",
+        PartStyle::Opaque => "opaque",
+        PartStyle::Rectangle => "rectangle",
+        PartStyle::RoundRectangle => "roundRect",
+        PartStyle::Shadow => "shadow",
+        PartStyle::Checkbox => "checkBox",
+        PartStyle::Radio => "radioButton",
+        PartStyle::Scrolling => "scrolling",
+        PartStyle::Standard => "standard",
+        PartStyle::Default => "default",
+        PartStyle::Oval => "oval",
+        PartStyle::Popup => "popup",
+        PartStyle::Unknown => "",
+    }
+}
+
+fn part_style(value: &str, is_field: bool) -> Result<PartStyle, ScriptError> {
+    let style = match value.trim().to_lowercase().as_str() {
+        "transparent" => PartStyle::Transparent,
+        "opaque" => PartStyle::Opaque,
+        "rectangle" => PartStyle::Rectangle,
+        "shadow" => PartStyle::Shadow,
+        "scrolling" if is_field => PartStyle::Scrolling,
+        "roundrect" if !is_field => PartStyle::RoundRectangle,
+        "checkbox" if !is_field => PartStyle::Checkbox,
+        "radiobutton" if !is_field => PartStyle::Radio,
+        "standard" if !is_field => PartStyle::Standard,
+        "default" if !is_field => PartStyle::Default,
+        "oval" if !is_field => PartStyle::Oval,
+        "popup" if !is_field => PartStyle::Popup,
+        _ => {
+            let kind = if is_field { "field" } else { "button" };
+            return Err(ScriptError::new(format!(
+                "Expected a {} style but found \"{}\"",
+                kind, value
+            )));
+        }
+    };
+    Ok(style)
+}
+
+fn text_style(style: u8) -> String {
+    let names: Vec<&str> = TEXT_STYLES
+        .iter()
+        .filter(|(_, bit)| style & bit != 0)
+        .map(|(name, _)| *name)
+        .collect();
+    if names.is_empty() {
+        "plain".to_string()
+    } else {
+        names.join(",")
+    }
+}
+
+fn parse_text_style(value: &str) -> Result<u8, ScriptError> {
+    let mut style = 0;
+    for name in value.split(',').map(str::trim) {
+        if name.eq_ignore_ascii_case("plain") {
+            continue;
+        }
+        match TEXT_STYLES
+            .iter()
+            .find(|(style, _)| style.eq_ignore_ascii_case(name))
+        {
+            Some((_, bit)) => style |= bit,
+            None => {
+                return Err(ScriptError::new(format!(
+                    "Expected a text style but found \"{}\"",
+                    name
+                )))
+            }
+        }
+    }
+    Ok(style)
+}
+
+// the ID of a font in the stack's font table, adding it if it isn't there.
+fn font_id(fonts: &mut Vec<Font>, name: &str) -> u16 {
+    if let Some(font) = fonts.iter().find(|f| f.name.eq_ignore_ascii_case(name)) {
+        return font.id;
+    }
+    let id = fonts.iter().map(|f| f.id + 1).max().unwrap_or(0);
+    fonts.push(Font {
+        id,
+        name: name.to_string(),
+    });
+    id
+}
+
+fn whole<T: TryFrom<i64>>(value: &str) -> Result<T, ScriptError> {
+    T::try_from(value::to_integer(value)?).map_err(|_| {
+        ScriptError::new(format!(
+            "Expected a positive number but found \"{}\"",
+            value
+        ))
+    })
+}
+
+fn positive(value: &str) -> Result<u16, ScriptError> {
+    match value::to_integer(value)? {
+        n @ 1..=0xffff => Ok(n as u16),
+        _ => Err(ScriptError::new(format!(
+            "Expected a positive number but found \"{}\"",
+            value
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use hc_decode::background::Background;
+    use hc_decode::card::Card;
+
+    use super::*;
+
+    const CARD: ObjectId = ObjectId::Card(10);
+    const BACKGROUND: ObjectId = ObjectId::Background(1);
+    const BUTTON: ObjectId = ObjectId::Part {
+        layer: Layer::Card,
+        card: 10,
+        id: 1,
+    };
+    const FIELD: ObjectId = ObjectId::Part {
+        layer: Layer::Card,
+        card: 10,
+        id: 2,
+    };
+
+    // a 512 by 342 stack with one card, which has a 95 by 22 button at 105,12 and a field.
+    fn stack() -> Stack {
+        Stack {
+            size: (512, 342),
+            backgrounds: vec![Background {
+                id: 1,
+                ..Default::default()
+            }],
+            cards: vec![Card {
+                id: 10,
+                background_id: 1,
+                parts: vec![
+                    Part {
+                        id: 1,
+                        position: (12, 105, 34, 200),
+                        ..Default::default()
+                    },
+                    Part {
+                        id: 2,
+                        ty: PartType::Field,
+                        ..Default::default()
+                    },
+                ],
+                ..Default::default()
+            }],
+            ..Default::default()
+        }
+    }
+
+    fn property(stack: &Stack, id: ObjectId, name: &str) -> String {
+        get(stack, "Test", id, name, None).unwrap().unwrap()
+    }
+
+    // the button's rect after setting one of its properties.
+    fn button_rect(name: &str, value: &str) -> String {
+        let mut stack = stack();
+        assert!(set(&mut stack, BUTTON, name, value).unwrap());
+        property(&stack, BUTTON, "rect")
+    }
+
+    fn set_error(id: ObjectId, name: &str, value: &str) -> String {
+        set(&mut stack(), id, name, value).unwrap_err().message
+    }
+
+    #[test]
+    fn cards_are_the_size_of_the_stack() {
+        let stack = stack();
+        assert_eq!(property(&stack, CARD, "rect"), "0,0,512,342");
+        assert_eq!(property(&stack, BACKGROUND, "width"), "512");
+    }
+
+    #[test]
+    fn sizing_a_card_sizes_the_stack() {
+        let mut stack = stack();
+        assert!(set(&mut stack, CARD, "width", "448").unwrap());
+        assert!(set(&mut stack, BACKGROUND, "height", "504").unwrap());
+        assert_eq!(stack.size, (448, 504));
+        assert_eq!(property(&stack, BACKGROUND, "rect"), "0,0,448,504");
+    }
+
+    #[test]
+    fn a_card_rect_keeps_it_at_the_top_left() {
+        let mut stack = stack();
+        assert!(set(&mut stack, CARD, "rect", "10,10,650,490").unwrap());
+        assert_eq!(property(&stack, CARD, "rect"), "0,0,640,480");
+    }
+
+    #[test]
+    fn card_sizes_are_checked() {
+        assert!(set_error(CARD, "rect", "0,0,0,342").starts_with("Expected a rectangle"));
+        assert!(set_error(CARD, "rect", "0,0,70000,342").starts_with("Expected a rectangle"));
+        assert!(set_error(CARD, "width", "0").starts_with("Expected a positive number"));
+        assert!(set_error(CARD, "height", "70000").starts_with("Expected a positive number"));
+    }
+
+    #[test]
+    fn moving_a_part_keeps_its_size() {
+        assert_eq!(button_rect("loc", "200,100"), "153,89,248,111");
+        assert_eq!(button_rect("topLeft", "0,0"), "0,0,95,22");
+    }
+
+    #[test]
+    fn a_part_kept_on_the_card_keeps_its_size() {
+        assert_eq!(button_rect("loc", "10,20"), "0,9,95,31");
+        assert_eq!(button_rect("topLeft", "-50,-50"), "0,0,95,22");
+        assert_eq!(button_rect("right", "70000"), "65440,12,65535,34");
+    }
+
+    #[test]
+    fn title_width_and_scroll() {
+        let mut stack = stack();
+        assert!(set(&mut stack, BUTTON, "titleWidth", "40").unwrap());
+        assert!(set(&mut stack, FIELD, "scroll", "65535").unwrap());
+        assert_eq!(property(&stack, BUTTON, "titleWidth"), "40");
+        assert_eq!(property(&stack, FIELD, "scroll"), "65535");
+    }
+
+    #[test]
+    fn title_width_and_scroll_are_checked() {
+        assert_eq!(
+            set_error(BUTTON, "titleWidth", "-1"),
+            "Expected a positive number but found \"-1\""
+        );
+        assert_eq!(
+            set_error(FIELD, "scroll", "65536"),
+            "Expected a positive number but found \"65536\""
+        );
+    }
+}
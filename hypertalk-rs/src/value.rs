@@ -0,0 +1,164 @@
+use std::cmp::Ordering;
+
+use crate::error::ScriptError;
+
+//
+// Every value in HyperTalk is a string. A value is taken as a number, or as true or false, when
+// something needs it to be, and numbers that come out of arithmetic are written the way
+// `the numberFormat` says.
+//
+
+/// `the numberFormat` until a script sets it: as many decimal places as needed, up to six.
+pub const DEFAULT_NUMBER_FORMAT: &str = "0.######";
+
+/// The value as a number, if it is one. Spaces around it are fine.
+pub fn parse_number(value: &str) -> Option<f64> {
+    let text = value.trim();
+    let digits = text.strip_prefix(['-', '+']).unwrap_or(text);
+    let (mantissa, exponent) = match digits.find(['e', 'E']) {
+        Some(i) => (&digits[..i], Some(&digits[i + 1..])),
+        None => (digits, None),
+    };
+    let (whole, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    let all_digits = |s: &str| s.chars().all(|c| c.is_ascii_digit());
+    if whole.len() + fraction.len() == 0 || !all_digits(whole) || !all_digits(fraction) {
+        return None;
+    }
+    if let Some(exponent) = exponent {
+        let exponent = exponent.strip_prefix(['-', '+']).unwrap_or(exponent);
+        if exponent.is_empty() || !all_digits(exponent) {
+            return None;
+        }
+    }
+    text.parse().ok()
+}
+
+pub fn is_number(value: &str) -> bool {
+    parse_number(value).is_some()
+}
+
+/// The value as a number for arithmetic. Empty counts as zero.
+pub fn to_number(value: &str) -> Result<f64, ScriptError> {
+    if value.trim().is_empty() {
+        return Ok(0.0);
+    }
+    parse_number(value)
+        .ok_or_else(|| ScriptError::new(format!("Expected a number but found \"{}\"", value)))
+}
+
+/// The value as a whole number, for counts and chunk positions.
+pub fn to_integer(value: &str) -> Result<i64, ScriptError> {
+    let n = to_number(value)?;
+    if n.fract() != 0.0 {
+        return Err(ScriptError::new(format!(
+            "Expected an integer but found \"{}\"",
+            value
+        )));
+    }
+    Ok(n as i64)
+}
+
+pub fn to_bool(value: &str) -> Result<bool, ScriptError> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("true") {
+        Ok(true)
+    } else if value.eq_ignore_ascii_case("false") {
+        Ok(false)
+    } else {
+        Err(ScriptError::new(format!(
+            "Expected true or false but found \"{}\"",
+            value
+        )))
+    }
+}
+
+pub fn from_bool(b: bool) -> String {
+    if b { "true" } else { "false" }.to_string()
+}
+
+/// Write a number the way a `numberFormat` says: `0` for a digit that's always written, `#` for
+/// a decimal place that's only written if it isn't a trailing zero.
+pub fn format_number(n: f64, number_format: &str) -> String {
+    if n.is_nan() {
+        return "NAN".to_string();
+    }
+    if n.is_infinite() {
+        return if n > 0.0 { "INF" } else { "-INF" }.to_string();
+    }
+    let (whole, fraction) = number_format.split_once('.').unwrap_or((number_format, ""));
+    let min_whole = whole.chars().filter(|c| *c == '0').count();
+    let min_places = fraction.chars().filter(|c| *c == '0').count();
+    let max_places = fraction
+        .chars()
+        .filter(|c| *c == '0' || *c == '#')
+        .count()
+        .max(min_places);
+
+    let mut text = format!("{:.*}", max_places, n.abs());
+    if let Some(point) = text.find('.') {
+        let mut places = text.len() - point - 1;
+        while places > min_places && text.ends_with('0') {
+            text.pop();
+            places -= 1;
+        }
+        if places == 0 {
+            text.pop();
+        }
+    }
+    let whole_digits = text.find('.').unwrap_or(text.len());
+    if whole_digits < min_whole {
+        text.insert_str(0, &"0".repeat(min_whole - whole_digits));
+    } else if min_whole == 0 && text.starts_with("0.") {
+        text.remove(0);
+    }
+    if n < 0.0 && text.chars().any(|c| c.is_ascii_digit() && c != '0') {
+        text.insert(0, '-');
+    }
+    text
+}
+
+/// Compare two values: as numbers if they both are, and otherwise as text, ignoring case.
+pub fn compare(a: &str, b: &str) -> Ordering {
+    match (parse_number(a), parse_number(b)) {
+        (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+        _ => a.to_lowercase().cmp(&b.to_lowercase()),
+    }
+}
+
+pub fn equal(a: &str, b: &str) -> bool {
+    compare(a, b) == Ordering::Equal
+}
+
+/// Whether `b` is in `a`, ignoring case.
+pub fn contains(a: &str, b: &str) -> bool {
+    a.to_lowercase().contains(&b.to_lowercase())
+}
+
+/// `h,v`.
+pub fn to_point(value: &str) -> Option<(i64, i64)> {
+    match integers(value)[..] {
+        [h, v] => Some((h, v)),
+        _ => None,
+    }
+}
+
+/// `left,top,right,bottom`.
+pub fn to_rect(value: &str) -> Option<(i64, i64, i64, i64)> {
+    match integers(value)[..] {
+        [left, top, right, bottom] => Some((left, top, right, bottom)),
+        _ => None,
+    }
+}
+
+// the whole numbers in a list separated by commas; empty if any of them isn't one.
+fn integers(value: &str) -> Vec<i64> {
+    let numbers: Option<Vec<i64>> = value
+        .split(',')
+        .map(|n| {
+            parse_number(n)
+                .filter(|n| n.fract() == 0.0)
+                .map(|n| n as i64)
+        })
+        .collect();
+    numbers.unwrap_or_default()
+}
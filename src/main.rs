@@ -1,34 +1,229 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use hypertalk::Script;
-use std::{error::Error, fs::File, io::Read, path::Path};
+use std::{
+    error::Error,
+    path::{Path, PathBuf},
+};
 
-use hc_decode::{stack::Stack, Block};
+use hc_decode::stack::Stack;
+use hc_render::contact_sheet::{self, ContactSheetOptions};
+use hc_render::pdf::{self, PdfLayout, PdfOptions};
+use hc_render::table::{self, FieldTable, TableFormat};
+use hc_render::text::Fonts;
+use hc_render::{site, svg};
 
 #[derive(Parser, Debug)]
 struct Args {
     #[arg(short, long)]
     path: String,
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// Render every card of the stack into one labelled grid image
+    ContactSheet {
+        /// Where to write the sheet
+        #[arg(short, long)]
+        output: PathBuf,
+        /// How many cards go across each row
+        #[arg(long, default_value_t = 4)]
+        columns: u32,
+        /// Width of each card's thumbnail in pixels
+        #[arg(long, default_value_t = 128)]
+        width: u32,
+        /// Also write each card's thumbnail into this directory
+        #[arg(long)]
+        thumbnails: Option<PathBuf>,
+    },
+    /// Export the stack as a static website with a page for every card
+    Site {
+        /// Directory to write the site into
+        #[arg(short, long)]
+        output: PathBuf,
+    },
+    /// Print the stack to a PDF, a card to a page, several to a page or as a report
+    Pdf {
+        /// Where to write the PDF
+        #[arg(short, long)]
+        output: PathBuf,
+        /// How many cards go across each page
+        #[arg(long, default_value_t = 1)]
+        columns: u32,
+        /// How many rows of cards go on each page
+        #[arg(long, default_value_t = 1)]
+        rows: u32,
+        /// Print a report with the named report template instead of the cards
+        #[arg(long)]
+        report: Option<String>,
+    },
+    /// Write out everything decoded from the stack as JSON or RON
+    #[cfg(feature = "dump")]
+    Dump {
+        /// Where to write it; standard output if left out
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// json or ron; worked out from the output's extension if it's left out
+        #[arg(long)]
+        format: Option<String>,
+        /// Write pictures as PNG files into this directory instead of inline
+        #[arg(long)]
+        bitmaps: Option<PathBuf>,
+    },
+    /// Export the text of the background fields, a column per field and a row per card
+    Data {
+        /// Where to write the data. With --background this is the file, otherwise a directory
+        /// with a file for each background
+        #[arg(short, long)]
+        output: PathBuf,
+        /// csv, jsonl or sqlite; worked out from the output's extension if it's left out
+        #[arg(long)]
+        format: Option<String>,
+        /// Only export the background with this ID
+        #[arg(long)]
+        background: Option<u32>,
+    },
+    /// Export every card of the stack as an SVG file
+    Svg {
+        /// Directory to write the files into
+        #[arg(short, long)]
+        output: PathBuf,
+    },
 }
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn Error>> {
     let args = Args::parse();
-    println!("{}", &args.path);
-    match Stack::from_path(&Path::new(&args.path)) {
-        Ok(stack) => {
-            let mas_script = Script::parse(stack.script);
-            println!(
-                "{}",
-                format!("{:?}", mas_script.commands).replace("),", "),\n")
-            );
-            for card in stack.cards {
-                let script = Script::parse(card.script);
-                println!("{}", format!("{:?}", script.commands).replace("),", "),\n"));
+    match Stack::from_path(Path::new(&args.path)) {
+        Ok(stack) => match args.command {
+            Some(Command::ContactSheet {
+                output,
+                columns,
+                width,
+                thumbnails,
+            }) => {
+                let options = ContactSheetOptions {
+                    columns,
+                    thumbnail_width: width,
+                };
+                contact_sheet::contact_sheet(&stack, &options).save(&output)?;
+                if let Some(dir) = thumbnails {
+                    for path in contact_sheet::write_thumbnails(&stack, width, &dir)? {
+                        println!("{}", path.display());
+                    }
+                }
             }
-        }
+            Some(Command::Site { output }) => {
+                site::export_site(&stack, &output)?;
+                println!("{}", output.join("index.html").display());
+            }
+            Some(Command::Pdf {
+                output,
+                columns,
+                rows,
+                report,
+            }) => {
+                let mut options = PdfOptions::for_stack(&stack);
+                if columns > 1 || rows > 1 {
+                    options.layout = PdfLayout::CardsPerPage { columns, rows };
+                }
+                if let Some(name) = report {
+                    match stack
+                        .report_templates
+                        .iter()
+                        .find(|template| template.name.eq_ignore_ascii_case(&name))
+                    {
+                        Some(template) => options.layout = PdfLayout::Report(template.id),
+                        None => return Err(format!("no report template named {}", name).into()),
+                    }
+                }
+                pdf::export_pdf(&stack, &options, &output)?;
+                println!("{}", output.display());
+            }
+            #[cfg(feature = "dump")]
+            Some(Command::Dump {
+                output,
+                format,
+                bitmaps,
+            }) => {
+                let format = format.or_else(|| {
+                    output
+                        .as_ref()
+                        .and_then(|path| path.extension())
+                        .map(|ext| ext.to_string_lossy().to_lowercase())
+                });
+                let serialize = || -> Result<String, Box<dyn Error>> {
+                    match format.as_deref() {
+                        Some("json") | None => Ok(serde_json::to_string_pretty(&stack)?),
+                        Some("ron") => Ok(ron::ser::to_string_pretty(
+                            &stack,
+                            ron::ser::PrettyConfig::default(),
+                        )?),
+                        Some(other) => Err(format!("unknown format {}", other).into()),
+                    }
+                };
+                let dump = match bitmaps {
+                    Some(dir) => {
+                        std::fs::create_dir_all(&dir)?;
+                        hc_decode::serialize::with_bitmap_dir(&dir, serialize)?
+                    }
+                    None => serialize()?,
+                };
+                match output {
+                    Some(path) => std::fs::write(path, dump)?,
+                    None => println!("{}", dump),
+                }
+            }
+            Some(Command::Data {
+                output,
+                format,
+                background,
+            }) => {
+                let format = match format.as_deref() {
+                    Some("csv") => TableFormat::Csv,
+                    Some("jsonl") | Some("json") => TableFormat::JsonLines,
+                    Some("sqlite") => TableFormat::Sqlite,
+                    Some(other) => return Err(format!("unknown format {}", other).into()),
+                    None => TableFormat::from_extension(&output).unwrap_or(TableFormat::Csv),
+                };
+                match background {
+                    Some(id) => match FieldTable::for_background(&stack, id) {
+                        Some(data) => data.write(format, &output)?,
+                        None => return Err(format!("no background with ID {}", id).into()),
+                    },
+                    None => table::export_tables(&stack, format, &output)?,
+                }
+                println!("{}", output.display());
+            }
+            Some(Command::Svg { output }) => {
+                std::fs::create_dir_all(&output)?;
+                let fonts = Fonts::for_stack(&stack);
+                for (number, card) in stack.cards.iter().enumerate() {
+                    let path = output.join(format!("card-{}-{}.svg", number + 1, card.id));
+                    std::fs::write(&path, svg::card_to_svg_with_fonts(&stack, &fonts, card))?;
+                    println!("{}", path.display());
+                }
+            }
+            None => print_scripts(stack),
+        },
         Err(err) => {
-            println!("{}", err);
+            eprintln!("{}", err);
         }
     }
     Ok(())
 }
+
+fn print_scripts(stack: Stack) {
+    let scripts = std::iter::once(stack.script).chain(stack.cards.into_iter().map(|c| c.script));
+    for script in scripts {
+        match Script::parse(&script) {
+            Ok(script) => {
+                for handler in script.handlers {
+                    println!("{:#?}", handler);
+                }
+            }
+            Err(err) => println!("{}", err),
+        }
+    }
+}
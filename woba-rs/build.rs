@@ -1,4 +1,3 @@
-
 fn main() {
     println!("cargo:rustc-flags=-l dylib=stdc++");
     // Use the `cc` crate to build a C file and statically link it.
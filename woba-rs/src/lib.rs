@@ -1,122 +1,145 @@
 use eyre::eyre;
-use image::io::Reader as ImageReader;
-use image::GrayImage;
+use image::{GrayImage, Luma};
 use std::error::Error;
 use std::ffi;
-use std::io::Cursor;
 
-#[derive(Debug)]
+// the decoder's picture, which only it knows the layout of.
 #[repr(C)]
-pub struct picture<'a> {
-    width: ffi::c_int,
-    height: ffi::c_int,
-    depth: ffi::c_int,
-    greyscalemask: ffi::c_int,
-
-    rowlength: ffi::c_int,
-    maskrowlength: ffi::c_int,
-    bitmaplength: ffi::c_int,
-    bitmap: &'a ffi::CStr,
-    masklength: ffi::c_int,
-    masklength_redundant: ffi::c_int,
-    mask: &'a ffi::CStr,
+struct RawPicture {
+    _private: [u8; 0],
 }
 
 /// WOBA-formatted picture
-impl<'a> picture<'a> {
+#[derive(Debug)]
+pub struct picture {
+    raw: *mut RawPicture,
+}
+
+impl Drop for picture {
+    fn drop(&mut self) {
+        unsafe { free_picture(self.raw) }
+    }
+}
+
+impl picture {
     /// The width
     pub fn width(&self) -> usize {
-        *&self.width as usize
+        unsafe { picture_width(self.raw) as usize }
     }
     /// The height
     pub fn height(&self) -> usize {
-        *&self.height as usize
+        unsafe { picture_height(self.raw) as usize }
     }
     /// The depth
     pub fn depth(&self) -> usize {
-        *&self.depth as usize
+        unsafe { picture_depth(self.raw) as usize }
     }
     /// The greyscale-mask
     pub fn greyscalemask(&self) -> bool {
-        *&self.greyscalemask == 1
-    }
-    pub fn as_image(self) -> Result<GrayImage, Box<dyn Error>> {
-        let bpbm = self.bitmap_pbm();
-        let mpbm = self.mask_pbm();
-        let blpbm = self.blank_pbm();
-        let image = match mpbm {
-            Some(a) => ImageReader::new(Cursor::new([bpbm, a].concat()))
-                .with_guessed_format()?
-                .decode()?,
-            None => ImageReader::new(Cursor::new([bpbm, blpbm].concat()))
-                .with_guessed_format()?
-                .decode()?,
-        };
-        Ok(image.as_luma8().unwrap().clone())
+        unsafe { picture_greyscale_mask(self.raw) != 0 }
     }
-
-    fn bitmap_raw(&self) -> &[u8] {
-        &self.bitmap.to_bytes()[..self.bitmaplength as usize - 1]
+    /// The picture itself; black pixels are 0, white pixels are 255.
+    pub fn as_image(&self) -> Result<GrayImage, Box<dyn Error>> {
+        let (raw, rowlength) = self.raw_bits(picture_bitmap);
+        self.to_gray(raw, rowlength, 0, 255)
     }
-
-    fn blank_pbm(&self) -> Vec<u8> {
-        // ...why is this a thing again?
-        format!("P4 {} {} ", &self.width(), &self.height())
-            .as_bytes()
-            .to_vec()
+    /// The mask; opaque pixels are 255, transparent pixels are 0.
+    pub fn as_mask(&self) -> Result<GrayImage, Box<dyn Error>> {
+        let (raw, rowlength) = self.raw_bits(picture_mask);
+        self.to_gray(raw, rowlength, 255, 0)
     }
 
-    fn bitmap_pbm(&self) -> Vec<u8> {
-        let j = format!("P4 {} {} ", &self.width(), &self.height());
-        [j.as_bytes(), (&self).bitmap_raw()].concat()
-    }
-    fn mask_raw(&self) -> Option<&[u8]> {
-        // redundany check: check if the struct above read the correct mask length by seeing if it read it twice
-        if self.masklength != self.masklength_redundant {
-            // this means the program is reading invalid memory, which indicates that there is no mask.
-            None
-        } else {
-            Some(&self.mask.to_bytes()[..self.masklength as usize - 1])
+    // expand a 1-bit buffer into a grayscale image, giving set bits the value `on`. an empty
+    // buffer, where nothing was decoded, is all `off`.
+    fn to_gray(
+        &self,
+        raw: &[u8],
+        rowlength: usize,
+        on: u8,
+        off: u8,
+    ) -> Result<GrayImage, Box<dyn Error>> {
+        let (width, height) = (self.width(), self.height());
+        if raw.is_empty() {
+            return Ok(GrayImage::from_pixel(
+                width as u32,
+                height as u32,
+                Luma([off]),
+            ));
         }
+        if rowlength < width.div_ceil(8) || raw.len() < rowlength * height {
+            return Err(eyre!(
+                "{} bytes is too short for a {}x{} picture with {} bytes a row",
+                raw.len(),
+                width,
+                height,
+                rowlength
+            )
+            .into());
+        }
+        Ok(GrayImage::from_fn(width as u32, height as u32, |x, y| {
+            let byte = raw[y as usize * rowlength + (x as usize / 8)];
+            if byte & (0x80 >> (x % 8)) != 0 {
+                Luma([on])
+            } else {
+                Luma([off])
+            }
+        }))
     }
 
-    fn mask_pbm(&self) -> Option<Vec<u8>> {
-        match (&self).mask_raw() {
-            Some(a) => {
-                let j = format!("P4 {} {} ", &self.width(), &self.height());
-                Some([j.as_bytes(), a].concat())
-            }
-            None => None,
+    // the bitmap or the mask, as `picture_bitmap` or `picture_mask` gives it, along with how
+    // many bytes a row takes.
+    fn raw_bits(&self, get: BitsFn) -> (&[u8], usize) {
+        let (mut length, mut rowlength) = (0, 0);
+        let bits = unsafe { get(self.raw, &mut length, &mut rowlength) };
+        if length <= 0 || bits.is_null() {
+            return (&[], 0);
         }
+        let raw = unsafe { std::slice::from_raw_parts(bits as *const u8, length as usize) };
+        (raw, rowlength.max(0) as usize)
     }
 }
 
+type BitsFn =
+    unsafe extern "C" fn(*mut RawPicture, *mut ffi::c_int, *mut ffi::c_int) -> *const ffi::c_char;
+
 #[link(name = "stackimport")]
 extern "C" {
-    fn new_picture_with_params<'a>(
+    fn new_picture_with_params(
         w: ffi::c_int,
         h: ffi::c_int,
         d: ffi::c_int,
         greymask: ffi::c_int,
-    ) -> picture<'a>;
-    fn woba_decode(p: &picture, woba: &ffi::CStr);
-    fn get_error_str<'a>() -> &'a ffi::CStr;
+    ) -> *mut RawPicture;
+    fn free_picture(p: *mut RawPicture);
+    fn picture_width(p: *mut RawPicture) -> ffi::c_int;
+    fn picture_height(p: *mut RawPicture) -> ffi::c_int;
+    fn picture_depth(p: *mut RawPicture) -> ffi::c_int;
+    fn picture_greyscale_mask(p: *mut RawPicture) -> ffi::c_int;
+    fn picture_bitmap(
+        p: *mut RawPicture,
+        length: *mut ffi::c_int,
+        rowlength: *mut ffi::c_int,
+    ) -> *const ffi::c_char;
+    fn picture_mask(
+        p: *mut RawPicture,
+        length: *mut ffi::c_int,
+        rowlength: *mut ffi::c_int,
+    ) -> *const ffi::c_char;
+    fn woba_decode(p: *mut RawPicture, woba: *const ffi::c_char, length: usize) -> *mut RawPicture;
+    fn get_error_str() -> *const ffi::c_char;
 }
 
 pub fn decode(b: &[u8]) -> Result<picture, eyre::ErrReport> {
-    let p: picture;
+    // the picture is freed when it's dropped, whether or not it decoded.
+    let p = picture {
+        raw: unsafe { new_picture_with_params(0, 0, 0, 0) },
+    };
     unsafe {
-        p = new_picture_with_params(0, 0, 0, 0);
-
-        // printing empty strings here lessens the chance of the function segfaulting by an extreme margin.
-        // this should not make sense but remember we're dealing with code written in C++ nothing makes sense in this language.
-        println!(" ");
-        woba_decode(&p, &ffi::CStr::from_bytes_with_nul_unchecked(b));
-        println!(" ");
+        let res = woba_decode(p.raw, b.as_ptr() as *const ffi::c_char, b.len());
 
-        let err = get_error_str();
-        if err.to_bytes().len() > 1 && err.to_bytes().len() < i32::MAX as usize {
-            return Err(eyre!(err.to_str()?));
+        if res.is_null() {
+            let err = ffi::CStr::from_ptr(get_error_str());
+            return Err(eyre!(err.to_str()?.to_string()));
         }
     };
     Ok(p)
@@ -82,6 +82,8 @@ picture::picture(void)
 	bitmaplength = 0;
 	masklength = 0;
 	masklength_redundant = 0;
+	bitmap = NULL;
+	mask = NULL;
 }
 
 picture::picture(int w, int h, int d, bool greymask)
@@ -103,22 +105,14 @@ picture::picture(int w, int h, int d, bool greymask)
 
 picture::~picture(void)
 {
-	if (bitmaplength) {
-		delete [] bitmap;
-	}
-	if (masklength) {
-		delete [] mask;
-	}
+	delete [] bitmap;
+	delete [] mask;
 }
 
 void picture::reinit(int w, int h, int d, bool greymask)
 {
-	if (bitmaplength) {
-		delete [] bitmap;
-	}
-	if (masklength) {
-		delete [] mask;
-	}
+	delete [] bitmap;
+	delete [] mask;
 	width = w;
 	height = h;
 	depth = d;
@@ -140,6 +134,10 @@ int picture::gdepth(void) { return depth; }
 int picture::gmaskdepth(void) { return greyscalemask?8:1; }
 int picture::bitmapsize(void) { return bitmaplength; }
 int picture::masksize(void) { return masklength; }
+int picture::growlength(void) { return rowlength; }
+int picture::gmaskrowlength(void) { return maskrowlength; }
+const char * picture::gbitmap(void) { return bitmap; }
+const char * picture::gmask(void) { return mask; }
 
 int picture::coordbyteoffset(int x, int y)
 {
@@ -492,8 +490,8 @@ void picture::bread(fstream fp)
 	int stuff[8];
 	fp.read(reinterpret_cast<char *>(stuff), 32);
 	if (stuff[0] == 0x12AAB175) {
-		if (bitmaplength) { delete [] bitmap; }
-		if (masklength) { delete [] mask; }
+		delete [] bitmap;
+		delete [] mask;
 		width = stuff[2];
 		height = stuff[3];
 		depth = stuff[4];
@@ -590,8 +588,8 @@ void picture::readfile(char * fn)
 	int stuff[8];
 	fp.read(reinterpret_cast<char *>(stuff), 32);
 	if (stuff[0] == 0x12AAB175) {
-		if (bitmaplength) { delete [] bitmap; }
-		if (masklength) { delete [] mask; }
+		delete [] bitmap;
+		delete [] mask;
 		width = stuff[2];
 		height = stuff[3];
 		depth = stuff[4];
@@ -610,7 +608,28 @@ void picture::readfile(char * fn)
 }
 
 extern "C" {
-	picture new_picture_with_params(int w, int h, int d, int greymask) {
-		return picture(w,h,d,greymask == 1);
+	picture * new_picture_with_params(int w, int h, int d, int greymask) {
+		return new picture(w,h,d,greymask == 1);
+	}
+
+	void free_picture(picture * p) {
+		delete p;
+	}
+
+	int picture_width(picture * p) { return p->gwidth(); }
+	int picture_height(picture * p) { return p->gheight(); }
+	int picture_depth(picture * p) { return p->gdepth(); }
+	int picture_greyscale_mask(picture * p) { return p->gmaskdepth() == 8; }
+
+	const char * picture_bitmap(picture * p, int * length, int * rowlength) {
+		*length = p->bitmapsize();
+		*rowlength = p->growlength();
+		return p->gbitmap();
+	}
+
+	const char * picture_mask(picture * p, int * length, int * rowlength) {
+		*length = p->masksize();
+		*rowlength = p->gmaskrowlength();
+		return p->gmask();
 	}
 }
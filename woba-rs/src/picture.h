@@ -56,6 +56,10 @@ class picture
 	int gmaskdepth(void);
 	int bitmapsize(void);
 	int masksize(void);
+	int growlength(void);
+	int gmaskrowlength(void);
+	const char * gbitmap(void);
+	const char * gmask(void);
 	
 	int coordbyteoffset(int, int);
 	unsigned int coordbitmask(int, int);
@@ -125,7 +129,14 @@ class picture
 };
 
 extern "C" {
-	picture new_picture_with_params(int w, int h, int d, int greymask);
+	picture * new_picture_with_params(int w, int h, int d, int greymask);
+	void free_picture(picture * p);
+	int picture_width(picture * p);
+	int picture_height(picture * p);
+	int picture_depth(picture * p);
+	int picture_greyscale_mask(picture * p);
+	const char * picture_bitmap(picture * p, int * length, int * rowlength);
+	const char * picture_mask(picture * p, int * length, int * rowlength);
 }
 
 #endif
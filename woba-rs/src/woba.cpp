@@ -43,6 +43,7 @@
 #include <exception>
 #include <typeinfo>
 #include <stdexcept>
+#include <string>
 
 
 using namespace std;
@@ -55,7 +56,7 @@ inline int __min(int x, int y)
 
 
 
-void woba_decode_cxx(picture & p, char * woba)
+void woba_decode_cxx(picture & p, const char * woba, size_t length)
 {	
 	#if DEBUGOUTPUT
 	std::cout << "===== NEXT BMAP =====" << endl;
@@ -106,6 +107,11 @@ void woba_decode_cxx(picture & p, char * woba)
 		#define MASK_START	52
 		#define INT16_AT(woba,pos)	ntohs(*(u_int16_t*)(woba+pos))
 		#define INT32_AT(woba,pos)	ntohl(*(u_int32_t*)(woba+pos))
+		/* the data has to have n more bytes at i */
+		#define NEED(n)	if( (size_t)i + (size_t)(n) > length ) throw std::out_of_range("WOBA data ends early")
+		
+		if( length < MASK_START )
+			throw std::out_of_range("WOBA data is too short for its header");
 		
 		totalRectTop = INT16_AT(woba,12);
 		totalRectLeft = INT16_AT(woba,14);
@@ -165,6 +171,7 @@ void woba_decode_cxx(picture & p, char * woba)
 			
 			while( j < maskDataLength )
 			{
+				NEED(1);
 				opcode = (unsigned char)woba[i];
 				#if DEBUGOUTPUT
 				std::cout << "Opcode: " << __hex(opcode) << endl;
@@ -187,6 +194,7 @@ void woba_decode_cxx(picture & p, char * woba)
 					
 					if( numberOfDataBytes )
 					{
+						NEED(numberOfDataBytes);
 						operandata.memcpy( 0, woba, i, numberOfDataBytes );
 						i += numberOfDataBytes; j += numberOfDataBytes;
 					}
@@ -213,6 +221,7 @@ void woba_decode_cxx(picture & p, char * woba)
 					#endif
 					if (numberOfDataBytes)
 					{
+						NEED(numberOfDataBytes);
 						operandata.memcpy( 0, woba, i, numberOfDataBytes );
 						i += numberOfDataBytes; j += numberOfDataBytes;
 					}
@@ -257,6 +266,7 @@ void woba_decode_cxx(picture & p, char * woba)
 							x = 0;
 							while( repeat )
 							{
+								NEED(rowwidth);
 								p.maskmemcopyin(woba+i, bx8, y, rowwidth);
 								y++;
 								repeat--;
@@ -288,6 +298,7 @@ void woba_decode_cxx(picture & p, char * woba)
 							break;
 							
 						case 0x83: /* pattern */
+							NEED(1);
 							operand = (unsigned char)woba[i];
 							#if DEBUGOUTPUT
 							std::cout << "patt: " << __hex(operand) << endl;
@@ -463,6 +474,7 @@ void woba_decode_cxx(picture & p, char * woba)
 			
 			while( j < pictureDataLength )
 			{
+				NEED(1);
 				opcode = (unsigned char)woba[i];
 				#if DEBUGOUTPUT
 				std::cout << "Opcode: " << __hex(opcode) << endl;
@@ -484,6 +496,7 @@ void woba_decode_cxx(picture & p, char * woba)
 					
 					if( numberOfDataBytes )
 					{
+						NEED(numberOfDataBytes);
 						operandata.memcpy( 0, woba,i, numberOfDataBytes );
 						i += numberOfDataBytes; j += numberOfDataBytes;
 					}
@@ -509,6 +522,7 @@ void woba_decode_cxx(picture & p, char * woba)
 					#endif
 					if( numberOfDataBytes )
 					{
+						NEED(numberOfDataBytes);
 						operandata.memcpy( 0, woba, i, numberOfDataBytes );
 						i += numberOfDataBytes; j += numberOfDataBytes;
 					}
@@ -552,6 +566,7 @@ void woba_decode_cxx(picture & p, char * woba)
 							x = 0;
 							while( repeat )
 							{
+								NEED(rowwidth);
 								p.memcopyin( woba+i, bx8, y, rowwidth );
 								y++;
 								repeat--;
@@ -583,6 +598,7 @@ void woba_decode_cxx(picture & p, char * woba)
 							break;
 							
 						case 0x83: /* pattern */
+							NEED(1);
 							operand = (unsigned char)woba[i];
 							#if DEBUGOUTPUT
 							std::cout << "patt: " << __hex(operand) << endl;
@@ -717,16 +733,21 @@ void woba_decode_cxx(picture & p, char * woba)
 }
 
 extern "C" {
-	char * error_str = " ";
+	char * error_str = (char*)" ";
+	static std::string error_message;
 
 	char * get_error_str() {
 		return error_str;
 	}
 
-	picture * woba_decode(picture & p, char * woba) {
+	picture * woba_decode(picture * p, const char * woba, size_t length) {
 		try {
-			woba_decode_cxx(p, woba);
-			return &p;
+			woba_decode_cxx(*p, woba, length);
+			return p;
+		} catch(const std::exception & e) {
+			error_message = e.what();
+			error_str = (char*)error_message.c_str();
+			return nullptr;
 		} catch(...) {
 			std::exception_ptr p = std::current_exception();
 			error_str = (char*)(p ? p.__cxa_exception_type()->name() : "null");
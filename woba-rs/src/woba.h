@@ -36,7 +36,7 @@
 using namespace std;
 
 extern "C" {
-    picture * woba_decode(picture & p, char * woba);
+    picture * woba_decode(picture * p, const char * woba, size_t length);
 }
 
 #endif